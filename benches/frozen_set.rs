@@ -0,0 +1,58 @@
+use radix_spline::FrozenSet;
+use rand::{distributions::Uniform, Rng};
+use std::collections::BTreeSet;
+use std::hint::black_box;
+use std::time::Instant;
+
+const NUM_KEYS: usize = 10_000_000;
+const NUM_LOOKUPS: usize = 1_000_000;
+
+fn random_keys(len: usize) -> Vec<u64> {
+    rand::thread_rng()
+        .sample_iter(&Uniform::from(0..u64::MAX / 2))
+        .take(len)
+        .collect()
+}
+
+fn measure(name: &str, f: impl FnOnce() -> usize) {
+    let start = Instant::now();
+    let result = black_box(f());
+    println!("{}: {} us ({})", name, start.elapsed().as_micros(), result);
+}
+
+/// Point lookups of present keys, and the intersection of a small set with a large one.
+fn main() {
+    let keys = random_keys(NUM_KEYS);
+    let frozen: FrozenSet = keys.iter().copied().collect();
+    let btree: BTreeSet<u64> = keys.iter().copied().collect();
+    let lookups: Vec<u64> = keys
+        .iter()
+        .step_by(NUM_KEYS / NUM_LOOKUPS)
+        .copied()
+        .collect();
+
+    measure("FrozenSet contains", || {
+        lookups.iter().filter(|key| frozen.contains(key)).count()
+    });
+    measure("BTreeSet contains", || {
+        lookups.iter().filter(|key| btree.contains(key)).count()
+    });
+
+    // half of the small side is present in the large one
+    let mut small = random_keys(1000);
+    small.extend(keys.iter().step_by(NUM_KEYS / 1000).copied());
+    let small_frozen: FrozenSet = small.iter().copied().collect();
+    let small_btree: BTreeSet<u64> = small.iter().copied().collect();
+    for _ in 0..2 {
+        measure("FrozenSet skewed intersection (x100)", || {
+            (0..100)
+                .map(|_| small_frozen.intersection(&frozen).count())
+                .sum()
+        });
+        measure("BTreeSet skewed intersection (x100)", || {
+            (0..100)
+                .map(|_| small_btree.intersection(&btree).count())
+                .sum()
+        });
+    }
+}
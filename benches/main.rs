@@ -1,6 +1,14 @@
+use radix_spline::datagen::{sample_probes, uniform_keys, ProbeSampling};
+use radix_spline::testing::{
+    adversarial, count_mismatches, time_lookups, time_point_scans, Adversarial, LookupRun,
+};
 use radix_spline::GreedySplineCorridor;
+use radix_spline::MaxError;
+use radix_spline::RadixBits;
 use radix_spline::RadixSpline;
-use rand::{distributions::Uniform, Rng};
+use radix_spline::SearchStrategy;
+use radix_spline::TableLayout;
+use std::hint::black_box;
 use std::process::exit;
 use std::time::Instant;
 
@@ -22,20 +30,21 @@ fn load_data(path: &str) -> Vec<u64> {
     data
 }
 
+const SEED: u64 = 42;
+
+/// The probes are drawn from the sorted keys at uniform positions, rather than taken from the unsorted prefix.
 fn random_data_keys() -> (Vec<u64>, Vec<u64>) {
-    let range = Uniform::from(0..100000000);
-    let mut data: Vec<u64> = rand::thread_rng()
-        .sample_iter(&range)
-        .take(10000000)
-        .collect();
-    let mut keys = vec![];
-    keys.extend_from_slice(&data[0..100000]);
-    data.sort_unstable();
+    let data = uniform_keys(10000000, 100000000, SEED);
+    let keys = sample_probes(&data, 100000, ProbeSampling::Positions, SEED);
     (data, keys)
 }
 
+/// Every lookup is checked by a counted comparison rather than an assertion, so a broken index fails the run
+/// (exit code 1) whatever the panic and debug-assertion settings. `--no-verify` skips the checks for pure-latency runs.
 fn main() {
-    let args: Vec<String> = std::env::args().collect();
+    let mut args: Vec<String> = std::env::args().collect();
+    let verify = !args.iter().any(|arg| arg == "--no-verify");
+    args.retain(|arg| arg != "--no-verify");
     let data;
     let keys;
     dbg!(&args);
@@ -47,50 +56,222 @@ fn main() {
         data = load_data(&args[1]);
         keys = load_data(&args[2]);
     } else {
-        println!("Usage: {} <path> <path>", args[0]);
+        println!("Usage: {} [--no-verify] <path> <path>", args[0]);
         exit(0);
     }
-    bench(&data, &keys);
+    let mismatches = [
+        bench(&data, &keys, verify),
+        bench_sparse_build(verify),
+        bench_linear(verify),
+        bench_table_layouts(verify),
+        bench_retune(&data, &keys, verify),
+        bench_search_strategies(&data, &keys, verify),
+        bench_search_batch(&data, verify),
+        bench_point_scans(),
+    ]
+    .iter()
+    .sum::<usize>();
+    if !verify {
+        println!("Verification: skipped (--no-verify)");
+    } else if mismatches > 0 {
+        println!("Verification: FAILED, {} mismatched lookups", mismatches);
+        exit(1);
+    } else {
+        println!("Verification: all lookups correct");
+    }
 }
 
-fn bench(data: &Vec<u64>, keys: &Vec<u64>) {
-    let spline = GreedySplineCorridor::default(data);
-    let radix_spline = RadixSpline::default(data);
-    let mut binary_total = 0;
-    let mut spline_total = 0;
-    let mut radix_spline_total = 0;
-    for key in keys {
-        let start = Instant::now();
-        if let Ok(idx) = data.binary_search(key) {
-            assert_eq!(&data[idx], key);
-        } else {
-            panic!("Error when binary search");
-        }
-        let elapsed = start.elapsed();
-        binary_total += elapsed.as_nanos();
+/// The mismatches of a verified run, and 0 otherwise.
+fn mismatches(run: LookupRun) -> usize {
+    run.mismatches.unwrap_or(0)
+}
 
-        let start = Instant::now();
-        if let Some(idx) = spline.search(*key) {
-            assert_eq!(&data[idx], key);
-        } else {
-            panic!("Error when spiline search");
+/// The mean data comparisons per lookup of each search strategy, counted rather than timed,
+/// on the fixture distributions and the benchmark data.
+fn bench_search_strategies(data: &[u64], keys: &[u64], verify: bool) -> usize {
+    let fixtures = Adversarial::ALL.map(|kind| {
+        let data = adversarial(kind);
+        let keys = sample_probes(&data, 100000, ProbeSampling::Positions, SEED);
+        (format!("{:?}", kind), data, keys)
+    });
+    let bench_data = ("Benchmark".to_string(), data.to_vec(), keys.to_vec());
+    let mut total = 0;
+    for (name, data, keys) in fixtures.into_iter().chain([bench_data]) {
+        for strategy in [SearchStrategy::Window, SearchStrategy::BiasedProbe] {
+            let radix_spline = RadixSpline::new(&data, RadixBits::DEFAULT, MaxError::DEFAULT)
+                .with_search_strategy(strategy);
+            let comparisons: usize = keys
+                .iter()
+                .map(|&key| radix_spline.search_counted(key).1)
+                .sum();
+            let run = time_lookups(&radix_spline, &data, &keys, verify);
+            println!(
+                "{} Radix Spline Search ({:?}): {:.2} comparisons, {} ns",
+                name,
+                strategy,
+                comparisons as f64 / keys.len() as f64,
+                run.nanos_per_lookup
+            );
+            total += mismatches(run);
         }
-        let elapsed = start.elapsed();
-        spline_total += elapsed.as_nanos();
+    }
+    total
+}
+
+/// `search_batch_into` over a million unsorted probes, which it searches in sorted order, against one `search` per key.
+fn bench_search_batch(data: &[u64], verify: bool) -> usize {
+    let radix_spline = RadixSpline::new(data, RadixBits::DEFAULT, MaxError::DEFAULT);
+    let keys = sample_probes(data, 1000000, ProbeSampling::Positions, SEED);
+    let start = Instant::now();
+    for key in &keys {
+        black_box(radix_spline.search(black_box(*key)));
+    }
+    let single_nanos = start.elapsed().as_nanos() / keys.len() as u128;
+    let mut out = Vec::new();
+    let start = Instant::now();
+    radix_spline.search_batch_into(black_box(&keys), &mut out);
+    let batch_nanos = start.elapsed().as_nanos() / keys.len() as u128;
+    println!(
+        "Radix Spline Batch Search: {} ns, one by one: {} ns",
+        batch_nanos, single_nanos
+    );
+    if !verify {
+        return 0;
+    }
+    keys.iter()
+        .zip(&out)
+        .filter(|&(&key, &found)| {
+            let first = data.partition_point(|&x| x < key);
+            found != (data.get(first) == Some(&key)).then_some(first)
+        })
+        .count()
+}
+
+/// The scan for the first spline point not less than a key over a bucket of 256 points, one point at a time
+/// and a chunk at a time (the `simd` feature). It has no lookups to verify.
+fn bench_point_scans() -> usize {
+    let (scalar_nanos, chunked_nanos) = time_point_scans(256, 1000000);
+    println!(
+        "Spline Point Scan (256 points): {:.1} ns one by one, {:.1} ns chunked",
+        scalar_nanos, chunked_nanos
+    );
+    0
+}
+
+/// Changing the radix bits of a built index only rebuilds the table, instead of another pass over the data.
+fn bench_retune(data: &[u64], keys: &[u64], verify: bool) -> usize {
+    let radix_spline = RadixSpline::new(data, RadixBits::DEFAULT, MaxError::DEFAULT);
+    let bits = RadixBits::new(22);
+    let start = Instant::now();
+    let built = RadixSpline::new(data, bits, MaxError::DEFAULT);
+    println!("Radix Spline Build: {} ms", start.elapsed().as_millis());
+    let start = Instant::now();
+    let retuned = radix_spline.with_radix_bits(bits).unwrap();
+    println!(
+        "Radix Spline Retune: {} ms, {}",
+        start.elapsed().as_millis(),
+        retuned
+    );
+    black_box(built);
+    mismatches(time_lookups(&retuned, data, keys, verify))
+}
+
+/// Clustered keys, where the run-length table saves memory but costs a binary search per lookup.
+fn bench_table_layouts(verify: bool) -> usize {
+    let data: Vec<u64> = (0..10000000u64)
+        .map(|i| (i % 1000) * 3 + (i / 1000) * (1 << 40))
+        .collect();
+    let keys = sample_probes(&data, 100000, ProbeSampling::Positions, SEED);
+    let mut total = 0;
+    for layout in [TableLayout::Flat, TableLayout::RunLength] {
+        let radix_spline = RadixSpline::new(&data, RadixBits::DEFAULT, MaxError::DEFAULT)
+            .with_table_layout(layout);
+        let run = time_lookups(&radix_spline, &data, &keys, verify);
+        println!(
+            "Clustered Radix Spline Search ({:?} table, {} bytes): {} ns",
+            layout,
+            radix_spline.table_bytes(),
+            run.nanos_per_lookup
+        );
+        total += mismatches(run);
+    }
+    total
+}
 
-        let start = Instant::now();
-        if let Some(idx) = radix_spline.search(*key) {
-            assert_eq!(&data[idx], key);
-        } else {
-            panic!("Error when radix spline searching!");
+/// An arithmetic progression, where `search` interpolates the exact position without a binary search.
+fn bench_linear(verify: bool) -> usize {
+    let data: Vec<u64> = (0..10000000).map(|i| i * 7).collect();
+    let keys = sample_probes(&data, 100000, ProbeSampling::Positions, SEED);
+    let radix_spline = RadixSpline::new(&data, RadixBits::DEFAULT, MaxError::DEFAULT);
+    let run = time_lookups(&radix_spline, &data, &keys, verify);
+    println!("Linear Radix Spline Search: {} ns", run.nanos_per_lookup);
+    mismatches(run)
+}
+
+/// Few spline points far apart, so nearly every point jumps over a huge run of radix buckets.
+fn bench_sparse_build(verify: bool) -> usize {
+    let data: Vec<u64> = (0..64).map(|i| i * (u64::MAX / 64)).collect();
+    let rounds = 20;
+    let mut total = 0;
+    let start = Instant::now();
+    for _ in 0..rounds {
+        let radix_spline = RadixSpline::new(&data, RadixBits::new(24), MaxError::new(0));
+        if verify {
+            total += count_mismatches(&radix_spline, &data, &data);
         }
-        let elapsed = start.elapsed();
-        radix_spline_total += elapsed.as_nanos();
     }
-    println!("Binary Search: {} ns", binary_total / keys.len() as u128);
-    println!("Spline Search: {} ns", spline_total / keys.len() as u128);
+    println!(
+        "Sparse Radix Spline Build: {} us",
+        start.elapsed().as_micros() / rounds
+    );
+    total
+}
+
+fn bench(data: &[u64], keys: &[u64], verify: bool) -> usize {
+    let spline = GreedySplineCorridor::new(data, MaxError::DEFAULT);
+    let radix_spline = RadixSpline::new(data, RadixBits::DEFAULT, MaxError::DEFAULT);
+    println!(
+        "Radix Spline: {:?} table, {} bytes",
+        radix_spline.table_layout(),
+        radix_spline.table_bytes()
+    );
+    // the binary search is the reference of the checks, so it is only timed
+    let start = Instant::now();
+    for key in keys {
+        black_box(data.binary_search(black_box(key)).ok());
+    }
+    let binary_nanos = start.elapsed().as_nanos() / keys.len() as u128;
+    let spline_run = time_lookups(&spline, data, keys, verify);
+    let radix_spline_run = time_lookups(&radix_spline, data, keys, verify);
+    println!("Binary Search: {} ns", binary_nanos);
+    println!("Spline Search: {} ns", spline_run.nanos_per_lookup);
     println!(
         "Radix Spline Search: {} ns",
-        radix_spline_total / keys.len() as u128
+        radix_spline_run.nanos_per_lookup
     );
+    // the final binary search takes about log2 of the window
+    let window_total: usize = keys
+        .iter()
+        .map(|&key| {
+            let bound = radix_spline.search_bound(key);
+            bound.stop - bound.start
+        })
+        .sum();
+    println!(
+        "Radix Spline Window: {:.1} keys per lookup, at most {}",
+        window_total as f64 / keys.len() as f64,
+        2 * MaxError::DEFAULT.get() + 1
+    );
+    // each key once, whatever its duplicates, and keys within the domain that miss
+    let mut total = mismatches(spline_run) + mismatches(radix_spline_run);
+    for sampling in [ProbeSampling::DistinctKeys, ProbeSampling::AbsentKeys] {
+        let probes = sample_probes(data, keys.len(), sampling, SEED);
+        let run = time_lookups(&radix_spline, data, &probes, verify);
+        println!(
+            "Radix Spline Search ({:?}): {} ns",
+            sampling, run.nanos_per_lookup
+        );
+        total += mismatches(run);
+    }
+    total
 }
@@ -0,0 +1,82 @@
+use radix_spline::{BuildScratch, MaxError, RadixBits, RadixSpline};
+use rand::{distributions::Uniform, Rng};
+use rayon::prelude::*;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+/// Counts the allocations of all threads.
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+const NUM_SHARDS: usize = 5000;
+
+fn shards() -> Vec<Vec<u64>> {
+    let mut rng = rand::thread_rng();
+    (0..NUM_SHARDS)
+        .map(|_| {
+            let len = rng.gen_range(1000..20000);
+            let mut shard: Vec<u64> = (&mut rng)
+                .sample_iter(&Uniform::from(0..u64::MAX / 2))
+                .take(len)
+                .collect();
+            shard.sort_unstable();
+            shard
+        })
+        .collect()
+}
+
+fn measure<'a>(name: &str, build: impl FnOnce() -> Vec<RadixSpline<'a>>) {
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let start = Instant::now();
+    let indexes = build();
+    let elapsed = start.elapsed();
+    assert_eq!(indexes.len(), NUM_SHARDS);
+    println!(
+        "{} ({} shards): {} ms, {} allocations",
+        name,
+        NUM_SHARDS,
+        elapsed.as_millis(),
+        ALLOCATIONS.load(Ordering::Relaxed) - allocations
+    );
+}
+
+fn main() {
+    let shards = shards();
+    let slices: Vec<&[u64]> = shards.iter().map(|shard| &shard[..]).collect();
+    let (bits, error) = (RadixBits::new(12), MaxError::new(32));
+    for _ in 0..2 {
+        measure("Parallel Build without Scratch", || {
+            // a fresh scratch per shard is the same as `RadixSpline::new`
+            slices
+                .par_iter()
+                .map(|shard| {
+                    RadixSpline::new_with_scratch(shard, bits, error, &mut BuildScratch::new())
+                })
+                .collect()
+        });
+        measure("Parallel Build with Scratch", || {
+            RadixSpline::build_shards(&slices, bits, error)
+        });
+    }
+}
@@ -0,0 +1,35 @@
+//! An LSM-style read path: several sorted runs, each indexed by a radix spline.
+//!
+//! cargo run --example lsm_read_path
+
+use radix_spline::{MultiRunReader, SortedRun};
+
+const TOMBSTONE: &str = "<deleted>";
+
+fn main() {
+    let mut reader = MultiRunReader::new(TOMBSTONE);
+
+    // the oldest run, e.g., from the last compaction
+    let keys: Vec<u64> = (0..100000).map(|i| i * 10).collect();
+    let values = vec!["v1"; keys.len()];
+    reader.push_run(SortedRun::new(keys, values));
+
+    // a newer run overwrites some keys
+    let keys: Vec<u64> = (0..1000).map(|i| i * 100).collect();
+    let values = vec!["v2"; keys.len()];
+    reader.push_run(SortedRun::new(keys, values));
+
+    // the newest run (a flushed memtable) deletes a few keys
+    let keys: Vec<u64> = vec![20, 30, 1000];
+    let values = vec![TOMBSTONE; keys.len()];
+    reader.push_run(SortedRun::new(keys, values));
+
+    for key in [10, 20, 100, 1000, 1010, 15] {
+        println!("get({}) = {:?}", key, reader.get(key));
+    }
+
+    println!("range(0, 120):");
+    for (key, value) in reader.range(0, 120) {
+        println!("  {} => {}", key, value);
+    }
+}
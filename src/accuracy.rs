@@ -0,0 +1,154 @@
+//! # Accuracy study
+//! With the `accuracy-study` feature, every interpolated prediction of `search` is computed three ways:
+//! the integer arithmetic of `interpolate` (a floor of the exact value), the exact value rounded to nearest in `u128`,
+//! and `f64`. The discrepancies are aggregated per index, and `accuracy_report` summarizes them.
+//! It is an experiment, so it costs a binary search over all keys per lookup.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::common::{Key, Point};
+use crate::radix::RadixSpline;
+
+/// The discrepancies are counted by distance, with the last bucket for 3 or more positions.
+const BUCKETS: usize = 4;
+
+/// The counters behind `AccuracyReport`, shared by concurrent searches.
+#[derive(Debug, Default)]
+pub(crate) struct AccuracyStats {
+    predictions: AtomicU64,
+    integer_diffs: [AtomicU64; BUCKETS],
+    float_diffs: [AtomicU64; BUCKETS],
+    integer_misses: AtomicU64,
+    exact_misses: AtomicU64,
+}
+
+/// A summary of the interpolated predictions of `search` since the index was built.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct AccuracyReport {
+    /// the interpolated predictions, i.e., lookups which did not hit a spline point
+    pub predictions: u64,
+    /// `integer_diffs[d]` predictions are `d` positions away from the exact prediction rounded to nearest (`d >= 3` in the last)
+    pub integer_diffs: [u64; BUCKETS],
+    /// the same for the `f64` prediction rounded to nearest
+    pub float_diffs: [u64; BUCKETS],
+    /// present keys with no occurrence within the window around the integer prediction
+    pub integer_misses: u64,
+    /// present keys with no occurrence within the same window around the exact prediction
+    pub exact_misses: u64,
+}
+
+impl AccuracyReport {
+    /// The fraction of predictions where the integer arithmetic moves the prediction by at least one position.
+    pub fn integer_changed_fraction(&self) -> f64 {
+        let changed: u64 = self.integer_diffs[1..].iter().sum();
+        changed as f64 / self.predictions.max(1) as f64
+    }
+
+    /// The misses of the integer arithmetic which the exact one would have found.
+    pub fn attributable_misses(&self) -> u64 {
+        self.integer_misses.saturating_sub(self.exact_misses)
+    }
+}
+
+/// The exact prediction rounded to nearest, and the `f64` one.
+/// The exact one drops the low bits of key gaps beyond 64 bits, as `interpolate`.
+fn exact_and_float<K: Key>(start: Point<K>, end: Point<K>, key: K) -> (usize, usize) {
+    let dx = key.offset_from(start.key());
+    let dy = (end.position() - start.position()) as u128;
+    let span = end.key().offset_from(start.key());
+    let shift = (128 - span.leading_zeros()).saturating_sub(64);
+    let (scaled_dx, scaled_span) = (dx >> shift, span >> shift);
+    let exact = start.position() + ((scaled_dx * dy + scaled_span / 2) / scaled_span) as usize;
+    let float = start.position() as f64 + dx as f64 * dy as f64 / span as f64;
+    (exact, float.round() as usize)
+}
+
+impl AccuracyStats {
+    fn record(diffs: &[AtomicU64; BUCKETS], diff: usize) {
+        diffs[diff.min(BUCKETS - 1)].fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+impl<'a, K: Key> RadixSpline<'a, K> {
+    /// Record the interpolated `predicted` position of `key` in the segment from `start` to `end`, with a window of `max_error`.
+    pub(crate) fn record_prediction(
+        &self,
+        start: Point<K>,
+        end: Point<K>,
+        key: K,
+        predicted: usize,
+        max_error: usize,
+    ) {
+        let stats = self.accuracy_stats();
+        let (exact, float) = exact_and_float(start, end, key);
+        stats.predictions.fetch_add(1, Ordering::Relaxed);
+        AccuracyStats::record(&stats.integer_diffs, predicted.abs_diff(exact));
+        AccuracyStats::record(&stats.float_diffs, float.abs_diff(exact));
+
+        let data = self.data();
+        let first = data.partition_point(|&x| x < key);
+        let last = data.partition_point(|&x| x <= key);
+        if first == last {
+            return;
+        }
+        // whether the window around `center` holds some occurrence of `key`
+        let holds = |center: usize| {
+            center.saturating_sub(max_error) < last && first <= center.saturating_add(max_error)
+        };
+        if !holds(predicted) {
+            stats.integer_misses.fetch_add(1, Ordering::Relaxed);
+        }
+        if !holds(exact) {
+            stats.exact_misses.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Summarize the predictions of `search` so far.
+    pub fn accuracy_report(&self) -> AccuracyReport {
+        let stats = self.accuracy_stats();
+        let load = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+        AccuracyReport {
+            predictions: load(&stats.predictions),
+            integer_diffs: stats.integer_diffs.each_ref().map(load),
+            float_diffs: stats.float_diffs.each_ref().map(load),
+            integer_misses: load(&stats.integer_misses),
+            exact_misses: load(&stats.exact_misses),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::testing::{adversarial, Adversarial};
+    use crate::{MaxError, RadixBits, RadixSpline};
+
+    /// The findings on the fixture datasets: the integer arithmetic is off the rounded exact prediction by at most one position,
+    /// and never misses a present key. Rounding would miss keys of the staircase, as the segment errors are measured
+    /// with the floor, so the floor stays.
+    #[test]
+    fn fixtures() {
+        for kind in Adversarial::ALL {
+            let data = adversarial(kind);
+            for (num_radix_bits, max_error) in [(0, 0), (8, 4), (18, 32)] {
+                let radix_spline = RadixSpline::new(
+                    &data,
+                    RadixBits::new(num_radix_bits),
+                    MaxError::new(max_error),
+                );
+                for &key in &data {
+                    assert!(radix_spline.search(key).is_some());
+                }
+                let report = radix_spline.accuracy_report();
+                assert_eq!(report.integer_diffs[2..], [0, 0], "{:?}", kind);
+                assert_eq!(report.integer_misses, 0, "{:?}", kind);
+                assert_eq!(report.attributable_misses(), 0, "{:?}", kind);
+                assert_eq!(report.integer_diffs.iter().sum::<u64>(), report.predictions);
+                assert_eq!(report.float_diffs[0], report.predictions, "{:?}", kind);
+                if kind == Adversarial::Staircase && max_error > 0 {
+                    assert!(report.integer_changed_fraction() > 0.0);
+                    assert!(report.exact_misses > 0);
+                }
+            }
+        }
+    }
+}
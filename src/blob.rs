@@ -0,0 +1,855 @@
+//! # A single-file index blob
+//! The header, keys, spline points and radix table are laid out in one file, so a pre-built index ships as a single artifact.
+//! With the `mmap` feature, `RadixSpline::open_mmap` searches the keys directly in the mapped file.
+//!
+//! The layout is little-endian `u64` words:
+//! `MAGIC`, `VERSION`, the bitmap of required features, the number of sections, then the sections.
+//! A section is its tag, its length in words and its payload. The tag of a section is the bit of its feature,
+//! so a reader skips a section it does not know unless the header marks its feature as required,
+//! and then it fails with an error naming the bit. This lets a newer writer add optional structures that older readers ignore.
+//!
+//! The sections of this version:
+//! - `PARAMS`: `shift_radix_bits`, `max_error`
+//! - `KEYS`: the keys
+//! - `FINGERPRINT` (optional): the 128-bit XXH3 hash of the little-endian key bytes, with the `fingerprint` feature
+//! - `POINTS`: the points as `(key, position)` pairs
+//! - `TABLE`: the radix table
+//! - `SEGMENTS` (optional): the error and the bias of each spline segment, one word per point, see `segment_words`
+//!
+//! All sections are whole words, so the keys stay 8-byte aligned in a mapped file, and one file with
+//! `write_self_contained` is all an index needs.
+//!
+//! The spline points are always checked against the keys when opening. With a fingerprint, all keys are checked as well,
+//! e.g., when the blob travelled through untrusted storage. The segment errors are measured over the keys
+//! only when a blob of an older writer has no `SEGMENTS` section, so opening takes no pass over the keys otherwise;
+//! `verify` checks them against the keys.
+
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use crate::common::{Point, Points, Table};
+use crate::radix::RadixSpline;
+
+const MAGIC: u64 = u64::from_le_bytes(*b"RSPLINE\0");
+const VERSION: u64 = 2;
+const HEADER_WORDS: usize = 4;
+
+// the section tags, which are also the feature bits
+const PARAMS: usize = 0;
+const KEYS: usize = 1;
+const POINTS: usize = 2;
+const TABLE: usize = 3;
+const FINGERPRINT: usize = 4;
+const SEGMENTS: usize = 5;
+const NUM_KNOWN_SECTIONS: usize = 6;
+/// The features a reader needs.
+const REQUIRED_FEATURES: u64 = 1 << PARAMS | 1 << KEYS | 1 << POINTS | 1 << TABLE;
+/// The features this version reads.
+const KNOWN_FEATURES: u64 = if cfg!(feature = "fingerprint") {
+    REQUIRED_FEATURES | 1 << SEGMENTS | 1 << FINGERPRINT
+} else {
+    REQUIRED_FEATURES | 1 << SEGMENTS
+};
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn write_words(writer: &mut impl Write, words: impl IntoIterator<Item = u64>) -> io::Result<()> {
+    for word in words {
+        writer.write_all(&word.to_le_bytes())?;
+    }
+    Ok(())
+}
+
+fn write_section<I>(writer: &mut impl Write, tag: usize, payload: I) -> io::Result<()>
+where
+    I: IntoIterator<Item = u64>,
+    I::IntoIter: ExactSizeIterator,
+{
+    let payload = payload.into_iter();
+    write_words(writer, [tag as u64, payload.len() as u64])?;
+    write_words(writer, payload)
+}
+
+/// Pass the little-endian bytes of `keys` to `f`, in chunks.
+fn for_each_key_bytes(keys: &[u64], mut f: impl FnMut(&[u8]) -> io::Result<()>) -> io::Result<()> {
+    const CHUNK: usize = 512;
+    let mut buffer = [0u8; CHUNK * 8];
+    for chunk in keys.chunks(CHUNK) {
+        let bytes = &mut buffer[..chunk.len() * 8];
+        for (word, key) in bytes.chunks_exact_mut(8).zip(chunk) {
+            word.copy_from_slice(&key.to_le_bytes());
+        }
+        f(bytes)?;
+    }
+    Ok(())
+}
+
+#[cfg(feature = "fingerprint")]
+fn fingerprint_words(hasher: &xxhash_rust::xxh3::Xxh3) -> [u64; 2] {
+    let hash = hasher.digest128();
+    [hash as u64, (hash >> 64) as u64]
+}
+
+/// Split the sections after the header by their tags, skipping the ones this version does not know.
+fn split_sections(words: &[u64]) -> io::Result<[Option<&[u64]>; NUM_KNOWN_SECTIONS]> {
+    let header = words
+        .get(..HEADER_WORDS)
+        .ok_or_else(|| invalid("truncated header"))?;
+    if header[0] != MAGIC || header[1] != VERSION {
+        return Err(invalid("not a radix spline blob"));
+    }
+    let unknown = header[2] & !KNOWN_FEATURES;
+    if unknown != 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!(
+                "the blob requires the unknown feature bit {}",
+                unknown.trailing_zeros()
+            ),
+        ));
+    }
+    let num_sections = usize::try_from(header[3]).map_err(|_| invalid("bad header"))?;
+
+    let mut sections = [None; NUM_KNOWN_SECTIONS];
+    let mut rest = &words[HEADER_WORDS..];
+    for _ in 0..num_sections {
+        let (&[tag, length], tail) = rest
+            .split_first_chunk()
+            .ok_or_else(|| invalid("truncated section"))?;
+        let length = usize::try_from(length)
+            .ok()
+            .filter(|&length| length <= tail.len())
+            .ok_or_else(|| invalid("truncated section"))?;
+        if tag >= u64::BITS as u64 {
+            return Err(invalid("bad section tag"));
+        }
+        let (payload, tail) = tail.split_at(length);
+        rest = tail;
+        // an unknown tag is an optional feature of a newer writer
+        if KNOWN_FEATURES & 1 << tag == 0 {
+            continue;
+        }
+        if let Some(section) = sections.get_mut(tag as usize) {
+            if section.replace(payload).is_some() {
+                return Err(invalid("duplicate section"));
+            }
+        }
+    }
+    if !rest.is_empty() {
+        return Err(invalid("bad blob length"));
+    }
+    Ok(sections)
+}
+
+impl<'a> RadixSpline<'a> {
+    /// Write the index and its keys as a single blob, which `from_blob` or `open_mmap` reads back.
+    /// With the `fingerprint` feature, the keys are hashed in the same pass that writes them.
+    pub fn write_blob(&self, mut writer: impl Write) -> io::Result<()> {
+        let num_sections = KNOWN_FEATURES.count_ones() as u64;
+        let header = [MAGIC, VERSION, REQUIRED_FEATURES, num_sections];
+        write_words(&mut writer, header)?;
+        let params = [self.shift_radix_bits() as u64, self.max_error() as u64];
+        write_section(&mut writer, PARAMS, params)?;
+
+        let keys = self.data();
+        write_words(&mut writer, [KEYS as u64, keys.len() as u64])?;
+        #[cfg(feature = "fingerprint")]
+        let mut hasher = xxhash_rust::xxh3::Xxh3::new();
+        for_each_key_bytes(keys, |bytes| {
+            #[cfg(feature = "fingerprint")]
+            hasher.update(bytes);
+            writer.write_all(bytes)
+        })?;
+        #[cfg(feature = "fingerprint")]
+        write_section(&mut writer, FINGERPRINT, fingerprint_words(&hasher))?;
+
+        let points: Vec<u64> = self
+            .points()
+            .iter()
+            .flat_map(|p| [p.key(), p.position() as u64])
+            .collect();
+        write_section(&mut writer, POINTS, points)?;
+        write_section(&mut writer, TABLE, self.table().iter().map(|&t| t as u64))?;
+        write_section(&mut writer, SEGMENTS, self.segment_words())
+    }
+
+    /// The blob of `write_blob` in memory. It depends only on the keys and the parameters, e.g., not on how many threads built the index.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![];
+        self.write_blob(&mut bytes)
+            .expect("writing to a `Vec` does not fail");
+        bytes
+    }
+
+    /// Write the blob to a new file at `path`, with the keys and the model in one self-contained file
+    /// which `open_mmap` serves without a separate data file. The file is synced before returning.
+    pub fn write_self_contained(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_blob(&mut writer)?;
+        writer
+            .into_inner()
+            .map_err(|error| error.into_error())?
+            .sync_all()
+    }
+
+    /// Open a blob written by `write_blob`, viewed as `u64` words, e.g., a mapped file. The keys are searched in place.
+    /// The spline points and the radix table are small, and they are checked against the keys when opening.
+    /// With the `fingerprint` feature, all keys are checked against the fingerprint of the blob if it has one.
+    /// Sections of optional features this version does not know are skipped.
+    pub fn from_blob(words: &'a [u64]) -> io::Result<Self> {
+        RadixSpline::read_blob(words, true)
+    }
+
+    /// The same as `from_blob`, without hashing the keys to check the fingerprint, e.g., for trusted local files.
+    pub fn from_blob_unverified(words: &'a [u64]) -> io::Result<Self> {
+        RadixSpline::read_blob(words, false)
+    }
+
+    fn read_blob(words: &'a [u64], verify_fingerprint: bool) -> io::Result<Self> {
+        if cfg!(target_endian = "big") {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "blobs are little-endian",
+            ));
+        }
+        let [Some(params), Some(keys), Some(points), Some(table), fingerprint, segments] =
+            split_sections(words)?
+        else {
+            return Err(invalid("missing section"));
+        };
+        #[cfg(feature = "fingerprint")]
+        if let (Some(fingerprint), true) = (fingerprint, verify_fingerprint) {
+            let mut hasher = xxhash_rust::xxh3::Xxh3::new();
+            for_each_key_bytes(keys, |bytes| {
+                hasher.update(bytes);
+                Ok(())
+            })?;
+            if fingerprint != fingerprint_words(&hasher) {
+                return Err(invalid("the keys do not match the fingerprint"));
+            }
+        }
+        #[cfg(not(feature = "fingerprint"))]
+        let _ = (fingerprint, verify_fingerprint);
+        let len = |word: u64| usize::try_from(word).map_err(|_| invalid("bad header"));
+        let &[shift_radix_bits, max_error] = params else {
+            return Err(invalid("bad params section"));
+        };
+        let shift_radix_bits =
+            u32::try_from(shift_radix_bits).map_err(|_| invalid("bad header"))?;
+        let max_error = len(max_error)?;
+        if points.len() % 2 != 0 {
+            return Err(invalid("bad points section"));
+        }
+        let num_points = points.len() / 2;
+
+        let points: Points = points
+            .chunks_exact(2)
+            .map(|p| {
+                let position = len(p[1])?;
+                #[cfg(feature = "compact-table")]
+                if position > u32::MAX as usize {
+                    return Err(invalid("position overflows the compact table"));
+                }
+                Ok(Point::new(p[0], position))
+            })
+            .collect::<io::Result<_>>()?;
+        let table: Table = table
+            .iter()
+            .map(|&t| {
+                let t = len(t)?;
+                if t > num_points {
+                    return Err(invalid("table entry out of range"));
+                }
+                Ok(crate::common::narrow(t))
+            })
+            .collect::<io::Result<_>>()?;
+
+        RadixSpline::from_parts(
+            Cow::Borrowed(keys),
+            shift_radix_bits,
+            max_error,
+            points,
+            table,
+            segments,
+        )
+        .map_err(invalid)
+    }
+}
+
+#[cfg(feature = "mmap")]
+pub use mmap::{MmapKeys, MmappedIndex};
+
+#[cfg(feature = "mmap")]
+mod mmap {
+    use std::fs::File;
+    use std::io;
+    use std::ops::{Deref, Range};
+    use std::path::Path;
+
+    use memmap2::{Mmap, MmapOptions};
+
+    use super::invalid;
+    use crate::params::{MaxError, RadixBits};
+    use crate::radix::RadixSpline;
+
+    /// An index opened from a mapped blob. Its keys are searched in the mapped memory, without a copy.
+    pub struct MmappedIndex {
+        // declared first, so it is dropped before the mapping it borrows
+        index: RadixSpline<'static>,
+        _mmap: Mmap,
+    }
+
+    impl MmappedIndex {
+        /// The same as `RadixSpline::open_mmap`, e.g., for a file of `write_self_contained`.
+        /// A truncated or corrupted file fails here rather than at query time.
+        ///
+        /// # Safety
+        /// The file must not be modified while it is mapped, see `memmap2::Mmap::map`.
+        pub unsafe fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+            RadixSpline::open_mmap(path)
+        }
+
+        /// The index, borrowing the mapping. It is not a `Deref`, which could only hand out the unbounded `RadixSpline<'static>`.
+        pub fn index(&self) -> &RadixSpline<'_> {
+            &self.index
+        }
+
+        /// See `RadixSpline::search`.
+        pub fn search(&self, key: u64) -> Option<usize> {
+            self.index.search(key)
+        }
+
+        /// See `RadixSpline::positions_of`.
+        pub fn positions_of(&self, range: (u64, u64)) -> Range<usize> {
+            self.index.positions_of(range)
+        }
+    }
+
+    /// The keys of a SOSD file, i.e., the number of keys then the keys, as little-endian `u64`,
+    /// which derefs to `&[u64]`, e.g., to pass to `RadixSpline::new`. The keys are read in the mapped file
+    /// without a copy, unless they are not 8-byte aligned in memory or the target is big-endian:
+    /// then they are copied once into memory, see `is_mapped`.
+    pub struct MmapKeys {
+        keys: Keys,
+    }
+
+    enum Keys {
+        /// The keys start right after the count, at byte 8 of the mapping.
+        Mapped {
+            mmap: Mmap,
+            len: usize,
+        },
+        Copied(Vec<u64>),
+    }
+
+    impl MmapKeys {
+        /// Map the SOSD file at `path`. A file whose length does not match its count fails with `InvalidData`.
+        ///
+        /// # Safety
+        /// The file must not be modified while it is mapped, see `memmap2::Mmap::map`.
+        pub unsafe fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+            MmapKeys::open_at(path, 0)
+        }
+
+        /// Map the SOSD file that starts at byte `offset` of the file at `path` and runs to its end,
+        /// e.g., keys embedded in a larger file. With an `offset` that is not a multiple of 8,
+        /// the keys are not aligned in the mapping, so they are copied.
+        ///
+        /// # Safety
+        /// The file must not be modified while it is mapped, see `memmap2::Mmap::map`.
+        pub unsafe fn open_at(path: impl AsRef<Path>, offset: u64) -> io::Result<Self> {
+            let file = File::open(path)?;
+            if file.metadata()?.len() < offset {
+                return Err(invalid("the offset is past the end of the file"));
+            }
+            let mmap = MmapOptions::new().offset(offset).map(&file)?;
+            let count = mmap
+                .get(..8)
+                .map(|count| u64::from_le_bytes(count.try_into().unwrap()))
+                .ok_or_else(|| invalid("missing key count"))?;
+            let len = usize::try_from(count)
+                .ok()
+                .filter(|&len| {
+                    len.checked_mul(8).and_then(|b| b.checked_add(8)) == Some(mmap.len())
+                })
+                .ok_or_else(|| invalid("the file length does not match the key count"))?;
+            let payload = &mmap[8..];
+            let keys =
+                if cfg!(target_endian = "little") && payload.as_ptr().cast::<u64>().is_aligned() {
+                    Keys::Mapped { mmap, len }
+                } else {
+                    let keys = payload
+                        .chunks_exact(8)
+                        .map(|key| u64::from_le_bytes(key.try_into().unwrap()))
+                        .collect();
+                    Keys::Copied(keys)
+                };
+            Ok(MmapKeys { keys })
+        }
+
+        /// Whether the keys are read in the mapped file, rather than copied.
+        pub fn is_mapped(&self) -> bool {
+            matches!(self.keys, Keys::Mapped { .. })
+        }
+
+        pub fn keys(&self) -> &[u64] {
+            match &self.keys {
+                Keys::Mapped { mmap, len } => {
+                    // SAFETY: `open_at` checked that the `len` keys after the count are in the mapping and aligned
+                    unsafe { std::slice::from_raw_parts(mmap[8..].as_ptr().cast::<u64>(), *len) }
+                }
+                Keys::Copied(keys) => keys,
+            }
+        }
+    }
+
+    impl Deref for MmapKeys {
+        type Target = [u64];
+
+        fn deref(&self) -> &[u64] {
+            self.keys()
+        }
+    }
+
+    impl AsRef<[u64]> for MmapKeys {
+        fn as_ref(&self) -> &[u64] {
+            self.keys()
+        }
+    }
+
+    impl<'a> RadixSpline<'a> {
+        /// Map a blob written by `write_blob`, and search its keys in place, see `from_blob`.
+        ///
+        /// # Safety
+        /// The file must not be modified while it is mapped, see `memmap2::Mmap::map`.
+        pub unsafe fn open_mmap(path: impl AsRef<Path>) -> io::Result<MmappedIndex> {
+            RadixSpline::map_blob(path.as_ref(), true)
+        }
+
+        /// The same as `open_mmap`, without hashing the keys to check the fingerprint, see `from_blob_unverified`.
+        ///
+        /// # Safety
+        /// The file must not be modified while it is mapped, see `memmap2::Mmap::map`.
+        pub unsafe fn open_mmap_unverified(path: impl AsRef<Path>) -> io::Result<MmappedIndex> {
+            RadixSpline::map_blob(path.as_ref(), false)
+        }
+
+        unsafe fn map_blob(path: &Path, verify_fingerprint: bool) -> io::Result<MmappedIndex> {
+            let file = File::open(path)?;
+            let mmap = Mmap::map(&file)?;
+            // a mapping is page-aligned, so the words are aligned
+            let (prefix, words, suffix) = mmap.align_to::<u64>();
+            if !prefix.is_empty() || !suffix.is_empty() {
+                return Err(invalid("bad blob length"));
+            }
+            // SAFETY: the words live as long as the mapping, which `MmappedIndex` keeps until the index is dropped
+            let words: &'static [u64] = std::slice::from_raw_parts(words.as_ptr(), words.len());
+            let index = RadixSpline::read_blob(words, verify_fingerprint)?;
+            Ok(MmappedIndex { index, _mmap: mmap })
+        }
+
+        /// Map a file of sorted little-endian `u64` keys and nothing else, and build an index over them in place,
+        /// e.g., a key file too large to copy into memory. The keys are only read sequentially while building,
+        /// so besides the pages the OS caches, the resident memory is the model of `size_in_bytes()`.
+        /// Unsorted keys or a length which is not a whole number of keys fail with `InvalidData`.
+        ///
+        /// # Safety
+        /// The file must not be modified while it is mapped, see `memmap2::Mmap::map`.
+        pub unsafe fn build_from_file(
+            path: impl AsRef<Path>,
+            num_radix_bits: RadixBits,
+            max_error: MaxError,
+        ) -> io::Result<MmappedIndex> {
+            let file = File::open(path)?;
+            let mmap = Mmap::map(&file)?;
+            #[cfg(unix)]
+            let _ = mmap.advise(memmap2::Advice::Sequential);
+            let (prefix, keys, suffix) = mmap.align_to::<u64>();
+            if cfg!(target_endian = "big") || !prefix.is_empty() || !suffix.is_empty() {
+                return Err(invalid("bad key file length"));
+            }
+            // SAFETY: as in `map_blob`
+            let keys: &'static [u64] = std::slice::from_raw_parts(keys.as_ptr(), keys.len());
+            let index = RadixSpline::try_new(keys, num_radix_bits, max_error)
+                .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+            Ok(MmappedIndex { index, _mmap: mmap })
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{MaxError, RadixBits};
+
+    fn blob(data: &[u64]) -> Vec<u64> {
+        RadixSpline::new(data, RadixBits::new(18), MaxError::new(8))
+            .to_bytes()
+            .chunks_exact(8)
+            .map(|w| u64::from_le_bytes(w.try_into().unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn parse_blob() {
+        let data: Vec<u64> = (0..10000).map(|i| i * i).collect();
+        let words = blob(&data);
+        let index = RadixSpline::from_blob(&words).unwrap();
+        for (i, &key) in data.iter().enumerate() {
+            assert_eq!(index.search(key), Some(i));
+        }
+        assert_eq!(index.search(3), None);
+    }
+
+    #[test]
+    fn corrupted_blob() {
+        let data: Vec<u64> = (0..10000).map(|i| i * i).collect();
+        let words = blob(&data);
+
+        assert!(RadixSpline::from_blob(&words[..words.len() - 1]).is_err());
+        assert!(RadixSpline::from_blob(&words[..3]).is_err());
+        let mut bad = words.clone();
+        bad[0] = 0;
+        assert!(RadixSpline::from_blob(&bad).is_err());
+        // the first key moved from under the first spline point
+        let mut bad = words.clone();
+        bad[FIRST_KEY] += 1;
+        assert!(RadixSpline::from_blob(&bad).is_err());
+        // a section without its payload
+        let mut bad = words.clone();
+        bad[HEADER_WORDS + 1] = u64::MAX;
+        assert!(RadixSpline::from_blob(&bad).is_err());
+        // a missing section
+        let mut bad = words.clone();
+        bad[3] -= 1;
+        assert!(RadixSpline::from_blob(&bad).is_err());
+        // a table entry, before the segments section
+        let mut bad = words.clone();
+        let num_points = RadixSpline::from_blob(&words).unwrap().num_spline_points();
+        let last = bad.len() - num_points - 3;
+        bad[last] = 0;
+        assert!(RadixSpline::from_blob(&bad).is_err());
+    }
+
+    #[test]
+    fn persisted_segments() {
+        let data: Vec<u64> = (0..10000u64).flat_map(|i| [i * i; 2]).collect();
+        let index = RadixSpline::new(&data, RadixBits::new(18), MaxError::new(8));
+        let words = blob(&data);
+        let num_points = index.num_spline_points();
+        let segments = words.len() - num_points;
+        assert_eq!(
+            words[segments - 2..segments],
+            [SEGMENTS as u64, num_points as u64]
+        );
+        let opened = RadixSpline::from_blob(&words).unwrap();
+        assert!(opened.segment_words().eq(index.segment_words()));
+
+        // the errors are taken from the blob rather than measured: a saturated error is kept, and only widens the window
+        let mut widened = words.clone();
+        widened[segments + 1] = u64::from(u8::MAX);
+        let opened = RadixSpline::from_blob(&widened).unwrap();
+        assert_eq!(opened.segment_words().nth(1), Some(u64::from(u8::MAX)));
+        assert_eq!(opened.search(data[100]), Some(100));
+        assert!(opened.verify().is_ok());
+
+        // a blob of an older writer has them measured over the keys
+        let older = without_section(&words, SEGMENTS as u64);
+        let opened = RadixSpline::from_blob(&older).unwrap();
+        assert!(opened.segment_words().eq(index.segment_words()));
+
+        // an error below the actual one is not caught when opening, but by `verify`
+        let mut narrowed = words.clone();
+        let (location, _) = index
+            .segment_words()
+            .enumerate()
+            .find(|&(_, word)| word as u8 > 0)
+            .unwrap();
+        narrowed[segments + location] = 0;
+        assert!(!RadixSpline::from_blob(&narrowed).unwrap().verify().is_ok());
+
+        let mut bad = words.clone();
+        bad[segments] = 1 << 16;
+        assert!(RadixSpline::from_blob(&bad).is_err());
+        let mut bad = without_section(&words, SEGMENTS as u64);
+        bad[3] += 1;
+        bad.extend([SEGMENTS as u64, 1, 0]);
+        assert!(RadixSpline::from_blob(&bad).is_err());
+    }
+
+    /// Append a section, as a newer writer would.
+    fn with_section(mut words: Vec<u64>, tag: u64, payload: &[u64], required: bool) -> Vec<u64> {
+        words[3] += 1;
+        if required {
+            words[2] |= 1 << tag;
+        }
+        words.extend([tag, payload.len() as u64]);
+        words.extend_from_slice(payload);
+        words
+    }
+
+    #[test]
+    fn optional_section() {
+        let data: Vec<u64> = (0..10000).map(|i| i * 3).collect();
+        let words = with_section(blob(&data), 40, &[1, 2, 3], false);
+        let index = RadixSpline::from_blob(&words).unwrap();
+        for (i, &key) in data.iter().enumerate() {
+            assert_eq!(index.search(key), Some(i));
+        }
+        let words = with_section(words, 41, &[], false);
+        assert!(RadixSpline::from_blob(&words).is_ok());
+    }
+
+    #[test]
+    fn required_section() {
+        let data: Vec<u64> = (0..10000).map(|i| i * 3).collect();
+        let words = with_section(blob(&data), 40, &[1, 2, 3], true);
+        let Err(error) = RadixSpline::from_blob(&words) else {
+            panic!("a required section of an unknown feature was skipped");
+        };
+        assert_eq!(error.kind(), std::io::ErrorKind::Unsupported);
+        assert_eq!(
+            error.to_string(),
+            "the blob requires the unknown feature bit 40"
+        );
+        // a tag is a feature bit
+        let words = with_section(blob(&data), 64, &[], false);
+        assert!(RadixSpline::from_blob(&words).is_err());
+    }
+
+    /// Remove the section with `tag`, as an older writer would have written the blob.
+    fn without_section(words: &[u64], tag: u64) -> Vec<u64> {
+        let mut result = words[..HEADER_WORDS].to_vec();
+        let mut rest = &words[HEADER_WORDS..];
+        while let [section_tag, length, ..] = *rest {
+            let (section, tail) = rest.split_at(2 + length as usize);
+            if section_tag == tag {
+                result[3] -= 1;
+            } else {
+                result.extend_from_slice(section);
+            }
+            rest = tail;
+        }
+        result
+    }
+
+    /// The position of the first key in a blob, after the params section and the keys tag and length.
+    const FIRST_KEY: usize = HEADER_WORDS + 4 + 2;
+
+    #[cfg(feature = "fingerprint")]
+    #[test]
+    fn fingerprint() {
+        let data: Vec<u64> = (0..10000).map(|i| i * 3).collect();
+        let words = blob(&data);
+        assert!(RadixSpline::from_blob(&words).is_ok());
+
+        // a key between the spline points, which the point checks cannot see
+        let mut tampered = words.clone();
+        tampered[FIRST_KEY + 5000] += 1;
+        let Err(error) = RadixSpline::from_blob(&tampered) else {
+            panic!("a tampered key was not detected");
+        };
+        assert_eq!(error.to_string(), "the keys do not match the fingerprint");
+        assert!(RadixSpline::from_blob_unverified(&tampered).is_ok());
+    }
+
+    #[test]
+    fn missing_fingerprint() {
+        let data: Vec<u64> = (0..10000).map(|i| i * 3).collect();
+        let words = without_section(&blob(&data), FINGERPRINT as u64);
+        let index = RadixSpline::from_blob(&words).unwrap();
+        assert_eq!(index.search(data[5000]), Some(5000));
+
+        // only the spline points are checked
+        let mut tampered = words.clone();
+        tampered[FIRST_KEY + 5000] += 1;
+        assert!(RadixSpline::from_blob(&tampered).is_ok());
+        let mut tampered = words.clone();
+        tampered[FIRST_KEY] += 1;
+        assert!(RadixSpline::from_blob(&tampered).is_err());
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn open_mmap() {
+        use rand::{distributions::Uniform, Rng};
+
+        let mut data: Vec<u64> = rand::thread_rng()
+            .sample_iter(&Uniform::from(0..u64::MAX))
+            .take(100000)
+            .collect();
+        data.sort_unstable();
+        let path = std::env::temp_dir().join(format!("radix-spline-{}.blob", std::process::id()));
+        let file = std::fs::File::create(&path).unwrap();
+        RadixSpline::new(&data, RadixBits::new(18), MaxError::new(8))
+            .write_blob(std::io::BufWriter::new(file))
+            .unwrap();
+
+        let mapped = unsafe { RadixSpline::open_mmap(&path) }.unwrap();
+        let index = mapped.index();
+        assert_eq!(index.data().len(), data.len());
+        for (i, &key) in data.iter().enumerate() {
+            assert_eq!(index.search(key), Some(i));
+        }
+        drop(mapped);
+        let mapped = unsafe { RadixSpline::open_mmap_unverified(&path) }.unwrap();
+        assert_eq!(mapped.index().search(data[7]), Some(7));
+        drop(mapped);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    /// A multi-megabyte key file builds the same index as the keys in memory.
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn build_from_file() {
+        use rand::Rng;
+
+        let mut rng = rand::thread_rng();
+        let mut data: Vec<u64> = (0..500_000).map(|_| rng.gen_range(0..1 << 50)).collect();
+        data.sort_unstable();
+        let path = std::env::temp_dir().join(format!("radix-spline-{}.keys", std::process::id()));
+        let bytes: Vec<u8> = data.iter().flat_map(|key| key.to_le_bytes()).collect();
+        std::fs::write(&path, &bytes).unwrap();
+
+        let (bits, error) = (RadixBits::new(18), MaxError::new(32));
+        let index = unsafe { RadixSpline::build_from_file(&path, bits, error) }.unwrap();
+        let expected = RadixSpline::new(&data, bits, error);
+        assert_eq!(index.index().to_bytes(), expected.to_bytes());
+        assert_eq!(index.index().size_in_bytes(), expected.size_in_bytes());
+        for _ in 0..10_000 {
+            let key = if rng.gen() {
+                data[rng.gen_range(0..data.len())]
+            } else {
+                rng.gen_range(0..1 << 50)
+            };
+            assert_eq!(index.search(key), expected.search(key), "{}", key);
+        }
+        drop(index);
+
+        for bad in [
+            &bytes[..bytes.len() - 3],
+            &[],
+            &[1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+        ] {
+            std::fs::write(&path, bad).unwrap();
+            let error = unsafe { RadixSpline::build_from_file(&path, bits, error) }.err();
+            assert_eq!(
+                error.map(|error| error.kind()),
+                Some(io::ErrorKind::InvalidData)
+            );
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    /// A SOSD key file is searched in place, or copied when its keys are not aligned.
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn mmap_keys() {
+        use rand::Rng;
+
+        let mut rng = rand::thread_rng();
+        let mut data: Vec<u64> = (0..200_000).map(|_| rng.gen_range(0..1 << 40)).collect();
+        data.sort_unstable();
+        let path = std::env::temp_dir().join(format!("radix-spline-{}.sosd", std::process::id()));
+        let sosd: Vec<u8> = std::iter::once(data.len() as u64)
+            .chain(data.iter().copied())
+            .flat_map(|word| word.to_le_bytes())
+            .collect();
+        let mut check = |keys: &MmapKeys| {
+            assert_eq!(&keys[..], &data[..]);
+            let index = RadixSpline::new(keys, RadixBits::new(18), MaxError::new(32));
+            for _ in 0..10_000 {
+                let key = data[rng.gen_range(0..data.len())];
+                assert_eq!(index.search(key), Some(data.partition_point(|&x| x < key)));
+            }
+            assert_eq!(index.search(1 << 40), None);
+        };
+
+        std::fs::write(&path, &sosd).unwrap();
+        let keys = unsafe { MmapKeys::open(&path) }.unwrap();
+        assert_eq!(keys.is_mapped(), cfg!(target_endian = "little"));
+        check(&keys);
+        drop(keys);
+
+        // after 8 bytes of something else the keys are still aligned, after 3 they are copied
+        for (offset, mapped) in [(8, true), (3, false)] {
+            let mut bytes = vec![0xAB; offset];
+            bytes.extend(&sosd);
+            std::fs::write(&path, &bytes).unwrap();
+            let keys = unsafe { MmapKeys::open_at(&path, offset as u64) }.unwrap();
+            assert_eq!(keys.is_mapped(), mapped && cfg!(target_endian = "little"));
+            check(&keys);
+        }
+
+        for bad in [
+            &sosd[..sosd.len() - 8],
+            &sosd[..sosd.len() - 1],
+            &sosd[..5],
+            &[],
+        ] {
+            std::fs::write(&path, bad).unwrap();
+            let error = unsafe { MmapKeys::open(&path) }.err();
+            assert_eq!(
+                error.map(|error| error.kind()),
+                Some(io::ErrorKind::InvalidData)
+            );
+        }
+        std::fs::write(&path, 0u64.to_le_bytes()).unwrap();
+        assert!(unsafe { MmapKeys::open(&path) }.unwrap().is_empty());
+        assert!(unsafe { MmapKeys::open_at(&path, 9) }.is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn self_contained() {
+        let data: Vec<u64> = (0..200_000u64).map(|i| i * i / 3).collect();
+        let path = std::env::temp_dir().join(format!("radix-spline-{}.rsi", std::process::id()));
+        RadixSpline::new(&data, RadixBits::new(18), MaxError::new(16))
+            .write_self_contained(&path)
+            .unwrap();
+
+        let index = unsafe { MmappedIndex::open(&path) }.unwrap();
+        for (i, &key) in data.iter().enumerate().step_by(3) {
+            assert_eq!(
+                index.search(key),
+                Some(data.partition_point(|&x| x < key)),
+                "{}",
+                i
+            );
+        }
+        assert_eq!(index.search(2), None);
+        assert_eq!(index.positions_of((10, 1000)), 6..55);
+        assert_eq!(index.positions_of((data[100], data[199])), 100..200);
+        drop(index);
+
+        // truncated anywhere, or with trailing bytes, the file fails to open
+        let bytes = std::fs::read(&path).unwrap();
+        let keys_end = (FIRST_KEY + data.len()) * 8;
+        for len in [
+            0,
+            7,
+            8,
+            40,
+            FIRST_KEY * 8,
+            keys_end / 2,
+            keys_end,
+            bytes.len() - 8,
+            bytes.len() - 1,
+        ] {
+            std::fs::write(&path, &bytes[..len]).unwrap();
+            assert!(
+                unsafe { MmappedIndex::open(&path) }.is_err(),
+                "truncated to {}",
+                len
+            );
+        }
+        std::fs::write(&path, [&bytes[..], &[0; 8]].concat()).unwrap();
+        assert!(unsafe { MmappedIndex::open(&path) }.is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
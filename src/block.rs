@@ -0,0 +1,109 @@
+//! # Radix spline over fixed-size blocks
+//! For block-based storage, the spline maps a key to the block containing it rather than to an element position.
+
+use std::ops::Range;
+
+use crate::params::{MaxError, RadixBits};
+use crate::radix::RadixSpline;
+
+/// `BlockIndex` learns the first key of each block of `block_size` keys.
+/// `search` returns a block, and only reads the first and last keys of the candidate blocks to verify it.
+pub struct BlockIndex<'a> {
+    data: &'a [u64],
+    block_size: usize,
+    index: RadixSpline<'static>, // built over the first key of each block
+}
+
+impl<'a> BlockIndex<'a> {
+    /// `data` is sorted and non-empty, and `block_size` is positive. The last block may be shorter.
+    pub fn new(
+        data: &'a [u64],
+        block_size: usize,
+        num_radix_bits: RadixBits,
+        max_error: MaxError,
+    ) -> Self {
+        assert!(block_size > 0);
+        let first_keys: Vec<u64> = data.iter().step_by(block_size).copied().collect();
+        BlockIndex {
+            data,
+            block_size,
+            index: RadixSpline::from_vec(first_keys, num_radix_bits.get(), max_error.get()),
+        }
+    }
+
+    /// search a given `key`, returning the first block whose key range `[first, last]` contains it.
+    /// The key itself may still be absent from the block.
+    pub fn search(&self, key: u64) -> Option<usize> {
+        // the first block starting at or after `key`
+        let block = self.index.lower_bound_position(key);
+        if block > 0 && self.last_key(block - 1) >= key {
+            return Some(block - 1);
+        }
+        if block < self.num_blocks() && self.data[block * self.block_size] == key {
+            return Some(block);
+        }
+        None
+    }
+
+    /// The positions of `block` in the data.
+    pub fn block_range(&self, block: usize) -> Range<usize> {
+        let start = block.saturating_mul(self.block_size).min(self.data.len());
+        start..start.saturating_add(self.block_size).min(self.data.len())
+    }
+
+    pub fn num_blocks(&self) -> usize {
+        self.data.len().div_ceil(self.block_size)
+    }
+
+    pub fn block_size(&self) -> usize {
+        self.block_size
+    }
+
+    fn last_key(&self, block: usize) -> u64 {
+        self.data[self.block_range(block).end - 1]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn search() {
+        use rand::{distributions::Uniform, Rng};
+
+        let mut data: Vec<u64> = rand::thread_rng()
+            .sample_iter(&Uniform::from(0..1000000))
+            .take(10000)
+            .collect();
+        data.sort_unstable();
+
+        for block_size in [7, 64, 4096, 20000] {
+            let blocks =
+                RadixSpline::block_index(&data, block_size, RadixBits::new(18), MaxError::new(4));
+            for key in data.iter().copied().chain([0, 500000, 999999]) {
+                // the first block whose key range contains `key`
+                let expected = (0..blocks.num_blocks()).find(|&block| {
+                    let range = blocks.block_range(block);
+                    data[range.start] <= key && key <= data[range.end - 1]
+                });
+                assert_eq!(blocks.search(key), expected, "{}", key);
+            }
+        }
+    }
+
+    #[test]
+    fn duplicates_across_blocks() {
+        let data: Vec<u64> = vec![1, 2, 5, 5, 5, 5, 5, 9];
+        let blocks = BlockIndex::new(&data, 3, RadixBits::new(18), MaxError::new(0));
+
+        assert_eq!(blocks.num_blocks(), 3);
+        assert_eq!(blocks.search(5), Some(0));
+        assert_eq!(blocks.search(9), Some(2));
+        assert_eq!(blocks.search(3), Some(0));
+        assert_eq!(blocks.search(6), Some(2));
+        assert_eq!(blocks.search(0), None);
+        assert_eq!(blocks.search(10), None);
+        assert_eq!(blocks.block_range(2), 6..8);
+    }
+}
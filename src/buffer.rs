@@ -0,0 +1,266 @@
+//! # Storage in an allocator of the caller
+//! `Vec<T, A>` needs the unstable `allocator_api`, so the storage of an index built by `new_in` is a `RawBuffer`,
+//! a growable buffer of `Copy` values which allocates, grows and frees through a `GlobalAlloc` of the caller,
+//! e.g., an arena used for accounting. Every other index keeps its usual `Vec` (or inline) storage, and `Buffer`
+//! holds either, derefing to a slice, so the lookups do not care where the memory is.
+
+use std::alloc::{handle_alloc_error, GlobalAlloc, Layout};
+use std::ops::{Deref, DerefMut};
+use std::ptr::NonNull;
+use std::sync::Arc;
+
+/// The allocator of a `RawBuffer`, shared by all buffers of an index and their clones.
+pub(crate) type SharedAlloc = Arc<dyn GlobalAlloc + Send + Sync>;
+
+/// The allocation of a `RawBuffer` failed, or its size overflowed.
+#[derive(Debug)]
+pub(crate) struct AllocError;
+
+/// A growable buffer of `Copy` values in a `SharedAlloc`, which frees its memory there when dropped.
+pub(crate) struct RawBuffer<T> {
+    ptr: NonNull<T>, // dangling while `capacity` is 0
+    len: usize,
+    capacity: usize,
+    alloc: SharedAlloc,
+}
+
+// SAFETY: a `RawBuffer` owns its values as a `Vec` does, and the allocator is `Send + Sync`
+unsafe impl<T: Send> Send for RawBuffer<T> {}
+unsafe impl<T: Sync> Sync for RawBuffer<T> {}
+
+impl<T: Copy> RawBuffer<T> {
+    pub(crate) fn new_in(alloc: SharedAlloc) -> Self {
+        assert!(size_of::<T>() > 0, "zero-sized values need no buffer");
+        RawBuffer {
+            ptr: NonNull::dangling(),
+            len: 0,
+            capacity: 0,
+            alloc,
+        }
+    }
+
+    /// `len` copies of `value`, in exactly `len` slots.
+    pub(crate) fn try_filled_in(
+        alloc: SharedAlloc,
+        len: usize,
+        value: T,
+    ) -> Result<Self, AllocError> {
+        let mut buffer: RawBuffer<T> = RawBuffer::new_in(alloc);
+        buffer.try_reserve_exact(len)?;
+        for i in 0..len {
+            // SAFETY: `i` is within the capacity just reserved
+            unsafe { buffer.ptr.as_ptr().add(i).write(value) };
+        }
+        buffer.len = len;
+        Ok(buffer)
+    }
+
+    /// The same as `try_filled_in`, but aborting on an allocation failure, as `Vec` does.
+    pub(crate) fn filled_in(alloc: SharedAlloc, len: usize, value: T) -> Self {
+        RawBuffer::try_filled_in(alloc, len, value).unwrap_or_else(|_| alloc_failed::<T>(len))
+    }
+
+    pub(crate) fn try_reserve_exact(&mut self, additional: usize) -> Result<(), AllocError> {
+        let required = self.len.checked_add(additional).ok_or(AllocError)?;
+        if required > self.capacity {
+            self.grow_to(required)?;
+        }
+        Ok(())
+    }
+
+    fn grow_to(&mut self, capacity: usize) -> Result<(), AllocError> {
+        let layout = Layout::array::<T>(capacity).map_err(|_| AllocError)?;
+        // SAFETY: `layout` has a non-zero size, and a non-empty buffer was allocated with `old` by `alloc`
+        let ptr = unsafe {
+            if self.capacity == 0 {
+                self.alloc.alloc(layout)
+            } else {
+                let old = Layout::array::<T>(self.capacity).expect("allocated before");
+                self.alloc
+                    .realloc(self.ptr.as_ptr().cast(), old, layout.size())
+            }
+        };
+        self.ptr = NonNull::new(ptr.cast()).ok_or(AllocError)?;
+        self.capacity = capacity;
+        Ok(())
+    }
+
+    pub(crate) fn push(&mut self, value: T) {
+        if self.len == self.capacity {
+            let capacity = (self.capacity * 2).max(4);
+            if self.grow_to(capacity).is_err() {
+                alloc_failed::<T>(capacity);
+            }
+        }
+        // SAFETY: `len` is within the capacity
+        unsafe { self.ptr.as_ptr().add(self.len).write(value) };
+        self.len += 1;
+    }
+}
+
+fn alloc_failed<T>(capacity: usize) -> ! {
+    handle_alloc_error(Layout::array::<T>(capacity).unwrap_or(Layout::new::<T>()))
+}
+
+impl<T> Deref for RawBuffer<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        // SAFETY: the first `len` values are initialized, and the pointer is dangling but aligned if `len` is 0
+        unsafe { std::slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl<T> DerefMut for RawBuffer<T> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        // SAFETY: as in `deref`, and the buffer is borrowed mutably
+        unsafe { std::slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl<T: Copy> Clone for RawBuffer<T> {
+    /// A copy in the same allocator.
+    fn clone(&self) -> Self {
+        let mut buffer = RawBuffer::new_in(self.alloc.clone());
+        if buffer.try_reserve_exact(self.len).is_err() {
+            alloc_failed::<T>(self.len);
+        }
+        // SAFETY: the new buffer holds `len` slots, and does not overlap with `self`
+        unsafe { std::ptr::copy_nonoverlapping(self.ptr.as_ptr(), buffer.ptr.as_ptr(), self.len) };
+        buffer.len = self.len;
+        buffer
+    }
+}
+
+impl<T> Drop for RawBuffer<T> {
+    fn drop(&mut self) {
+        if self.capacity > 0 {
+            let layout = Layout::array::<T>(self.capacity).expect("allocated before");
+            // SAFETY: the memory was allocated by `alloc` with `layout`, and the values need no drop
+            unsafe { self.alloc.dealloc(self.ptr.as_ptr().cast(), layout) };
+        }
+    }
+}
+
+/// The storage of an index: the usual `V` (a `Vec` or an inline vector of `T`), or a `RawBuffer` of `new_in`.
+pub(crate) enum Buffer<V, T> {
+    Heap(V),
+    Raw(RawBuffer<T>),
+}
+
+impl<V: Clone, T: Copy> Clone for Buffer<V, T> {
+    fn clone(&self) -> Self {
+        match self {
+            Buffer::Heap(heap) => Buffer::Heap(heap.clone()),
+            Buffer::Raw(raw) => Buffer::Raw(raw.clone()),
+        }
+    }
+}
+
+impl<V, T> Buffer<V, T> {
+    /// `len` copies of `value`, in the allocator of `self`.
+    pub(crate) fn filled_like<W: FromIterator<U>, U: Copy>(
+        &self,
+        len: usize,
+        value: U,
+    ) -> Buffer<W, U> {
+        match self {
+            Buffer::Heap(_) => Buffer::Heap(std::iter::repeat_n(value, len).collect()),
+            Buffer::Raw(raw) => Buffer::Raw(RawBuffer::filled_in(raw.alloc.clone(), len, value)),
+        }
+    }
+}
+
+impl<V, T> From<V> for Buffer<V, T> {
+    fn from(heap: V) -> Self {
+        Buffer::Heap(heap)
+    }
+}
+
+impl<V: Deref<Target = [T]>, T> Deref for Buffer<V, T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        match self {
+            Buffer::Heap(heap) => heap,
+            Buffer::Raw(raw) => raw,
+        }
+    }
+}
+
+impl<V: DerefMut<Target = [T]>, T> DerefMut for Buffer<V, T> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        match self {
+            Buffer::Heap(heap) => heap,
+            Buffer::Raw(raw) => raw,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::alloc::System;
+    use std::sync::atomic::{AtomicIsize, AtomicUsize, Ordering};
+
+    /// Counts the live bytes and the calls of the system allocator.
+    #[derive(Default)]
+    struct Counting {
+        live: AtomicIsize,
+        calls: AtomicUsize,
+    }
+
+    unsafe impl GlobalAlloc for Counting {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            self.live
+                .fetch_add(layout.size() as isize, Ordering::Relaxed);
+            self.calls.fetch_add(1, Ordering::Relaxed);
+            System.alloc(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            self.live
+                .fetch_sub(layout.size() as isize, Ordering::Relaxed);
+            System.dealloc(ptr, layout)
+        }
+
+        unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+            let delta = new_size as isize - layout.size() as isize;
+            self.live.fetch_add(delta, Ordering::Relaxed);
+            self.calls.fetch_add(1, Ordering::Relaxed);
+            System.realloc(ptr, layout, new_size)
+        }
+    }
+
+    #[test]
+    fn raw_buffer() {
+        let counting = Arc::new(Counting::default());
+        let alloc: SharedAlloc = counting.clone();
+        let mut buffer = RawBuffer::new_in(alloc.clone());
+        assert!(buffer.is_empty());
+        assert_eq!(counting.calls.load(Ordering::Relaxed), 0);
+        for i in 0..1000u64 {
+            buffer.push(i * 3);
+        }
+        assert!(buffer.iter().copied().eq((0..1000).map(|i| i * 3)));
+        buffer[7] = 1;
+        let copy = buffer.clone();
+        assert_eq!(copy[..], buffer[..]);
+        let filled = RawBuffer::try_filled_in(alloc.clone(), 100, 9u32).unwrap();
+        assert_eq!(filled[..], [9; 100]);
+        assert!(counting.live.load(Ordering::Relaxed) >= (2000 * 8 + 400) as isize);
+        drop((buffer, copy, filled));
+        assert_eq!(counting.live.load(Ordering::Relaxed), 0);
+
+        assert!(RawBuffer::<u64>::try_filled_in(alloc, usize::MAX / 4, 0).is_err());
+    }
+
+    #[test]
+    fn heap_or_raw() {
+        let heap: Buffer<Vec<u8>, u8> = vec![1, 2, 3].into();
+        let alloc: SharedAlloc = Arc::new(Counting::default());
+        let raw: Buffer<Vec<u8>, u8> = Buffer::Raw(RawBuffer::try_filled_in(alloc, 3, 2).unwrap());
+        assert_eq!(heap[..], [1, 2, 3]);
+        assert_eq!(raw.clone()[..], [2, 2, 2]);
+    }
+}
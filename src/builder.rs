@@ -0,0 +1,193 @@
+//! # A resumable builder
+//! `StreamingBuilder` builds the spline points over the keys in chunks, keeping the corridor between calls,
+//! so a long build can be interleaved with other work, e.g., yielding to an async executor between chunks.
+//! Each `process_chunk` costs time linear in the chunk, and `finish` only builds the radix table.
+//! The result is the same index as building over all keys at once.
+
+use std::borrow::Cow;
+
+use crate::common::Points;
+use crate::error::{BuildError, DataError};
+use crate::owned::OwnedRadixSpline;
+use crate::params::{MaxError, RadixBits};
+use crate::radix::{effective_max_error, Corridor, RadixSpline};
+
+/// Builds an `OwnedRadixSpline` over sorted keys passed in chunks.
+pub struct StreamingBuilder {
+    num_radix_bits: RadixBits,
+    max_error: MaxError,
+    keys: Vec<u64>,
+    points: Points,
+    corridor: Option<Corridor>, // `None` until the first key
+}
+
+impl StreamingBuilder {
+    pub fn new(num_radix_bits: RadixBits, max_error: MaxError) -> Self {
+        StreamingBuilder {
+            num_radix_bits,
+            max_error,
+            keys: vec![],
+            points: Points::new(),
+            corridor: None,
+        }
+    }
+
+    /// The number of keys processed so far, e.g., to report progress.
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Process the next `chunk` of keys, which continues the sorted keys so far.
+    /// An unsorted chunk is rejected as a whole, with the position of the first unsorted key among all keys,
+    /// and the builder is left as it was.
+    pub fn process_chunk(&mut self, chunk: &[u64]) -> Result<(), DataError> {
+        let offset = self.keys.len();
+        let prev = self.keys.last().copied();
+        if let Some(i) = (0..chunk.len()).find(|&i| {
+            let before = if i == 0 { prev } else { Some(chunk[i - 1]) };
+            before.is_some_and(|before| before > chunk[i])
+        }) {
+            return Err(DataError::NotSorted {
+                position: offset + i,
+            });
+        }
+        let Some((&first, _)) = chunk.split_first() else {
+            return Ok(());
+        };
+        let max_error = self.max_error.get();
+        let corridor = self
+            .corridor
+            .get_or_insert_with(|| Corridor::new(&mut self.points, first, max_error));
+        for (i, &key) in chunk.iter().enumerate() {
+            if offset + i > 0 {
+                corridor.push(&mut self.points, key, offset + i);
+            }
+        }
+        self.keys.extend_from_slice(chunk);
+        Ok(())
+    }
+
+    /// Build the radix table, and return the index over all processed keys.
+    /// With fewer keys than twice `max_error`, `max_error` is clamped as in `RadixSpline::new`,
+    /// so the points are rebuilt over all keys.
+    pub fn finish(self) -> Result<OwnedRadixSpline, BuildError> {
+        let StreamingBuilder {
+            num_radix_bits,
+            max_error,
+            keys,
+            mut points,
+            corridor,
+        } = self;
+        let Some(corridor) = corridor else {
+            return Err(DataError::TooFewKeys { len: 0 }.into());
+        };
+        let (num_radix_bits, max_error) = (num_radix_bits.get(), max_error.get());
+        let index = if effective_max_error(max_error, keys.len()) < max_error {
+            RadixSpline::try_from_cow(Cow::Owned(keys), num_radix_bits, max_error)?
+        } else {
+            corridor.finish(&mut points);
+            RadixSpline::from_points(Cow::Owned(keys), points, num_radix_bits, max_error)?
+        };
+        Ok(OwnedRadixSpline::from_index(index))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::Rng;
+
+    const BITS: RadixBits = RadixBits::new(12);
+    const ERROR: MaxError = MaxError::new(8);
+
+    fn random_keys(len: usize) -> Vec<u64> {
+        let mut rng = rand::thread_rng();
+        let mut data: Vec<u64> = (0..len).map(|_| rng.gen_range(0..1 << 24)).collect();
+        data.sort_unstable();
+        data
+    }
+
+    fn chunked(data: &[u64], chunk_size: usize) -> OwnedRadixSpline {
+        let mut builder = StreamingBuilder::new(BITS, ERROR);
+        for chunk in data.chunks(chunk_size) {
+            builder.process_chunk(chunk).unwrap();
+        }
+        assert_eq!(builder.len(), data.len());
+        builder.finish().unwrap()
+    }
+
+    /// Any chunking gives the same index as building over all keys at once.
+    #[test]
+    fn chunkings() {
+        for len in [1, 2, 10, 20_000] {
+            let data = random_keys(len);
+            let expected = RadixSpline::new(&data, BITS, ERROR).to_bytes();
+            for chunk_size in [1, 1000, len] {
+                let index = chunked(&data, chunk_size);
+                assert_eq!(
+                    index.to_bytes(),
+                    expected,
+                    "len {} chunk {}",
+                    len,
+                    chunk_size
+                );
+                assert!(index.verify().is_ok());
+            }
+        }
+    }
+
+    /// Two builders fed alternately keep their own state.
+    #[test]
+    fn interleaved() {
+        let (a, b) = (random_keys(10_000), random_keys(7_000));
+        let mut builder_a = StreamingBuilder::new(BITS, ERROR);
+        let mut builder_b = StreamingBuilder::new(BITS, ERROR);
+        let mut chunks_b = b.chunks(300);
+        for chunk in a.chunks(1000) {
+            builder_a.process_chunk(chunk).unwrap();
+            if let Some(chunk) = chunks_b.next() {
+                builder_b.process_chunk(chunk).unwrap();
+            }
+        }
+        for chunk in chunks_b {
+            builder_b.process_chunk(chunk).unwrap();
+        }
+        let expected_a = RadixSpline::new(&a, BITS, ERROR).to_bytes();
+        let expected_b = RadixSpline::new(&b, BITS, ERROR).to_bytes();
+        assert_eq!(builder_a.finish().unwrap().to_bytes(), expected_a);
+        assert_eq!(builder_b.finish().unwrap().to_bytes(), expected_b);
+    }
+
+    #[test]
+    fn errors() {
+        let mut builder = StreamingBuilder::new(BITS, ERROR);
+        builder.process_chunk(&[1, 2, 5]).unwrap();
+        assert_eq!(
+            builder.process_chunk(&[4, 6]),
+            Err(DataError::NotSorted { position: 3 })
+        );
+        assert_eq!(
+            builder.process_chunk(&[5, 7, 6]),
+            Err(DataError::NotSorted { position: 5 })
+        );
+        // the rejected chunks left no trace
+        builder.process_chunk(&[]).unwrap();
+        builder.process_chunk(&[5, 9]).unwrap();
+        let data = vec![1, 2, 5, 5, 9];
+        assert_eq!(
+            builder.finish().unwrap().to_bytes(),
+            RadixSpline::new(&data, BITS, ERROR).to_bytes()
+        );
+
+        let empty = StreamingBuilder::new(BITS, ERROR);
+        assert!(empty.is_empty());
+        assert_eq!(
+            empty.finish().err(),
+            Some(BuildError::Data(DataError::TooFewKeys { len: 0 }))
+        );
+    }
+}
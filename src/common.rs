@@ -1,47 +1,159 @@
-//! # Common data type: `Point` and `Line`
+//! # Common data type: `Key`, `Point` and `Line`
 
-/// `x` is the *key* (assuming it is always `u64); `y` is the *position*.
+use std::fmt;
+
+use crate::spline_corridor::SplineKey;
+
+/// The stored type of positions in spline points and the radix table.
+/// With the `compact-table` feature, it is `u32`, which halves the memory but limits the data to `u32::MAX` elements.
+#[cfg(feature = "compact-table")]
+pub type Position = u32;
+#[cfg(not(feature = "compact-table"))]
+pub type Position = usize;
+
+/// Narrow a `usize` position to a stored `Position`, saturating at `Position::MAX`.
+/// Only the corridor bounds can exceed it, and a lower upper bound only makes the corridor stricter.
+#[cfg(feature = "compact-table")]
+#[inline]
+pub fn narrow(position: usize) -> Position {
+    position.min(u32::MAX as usize) as u32
+}
+#[cfg(not(feature = "compact-table"))]
+#[inline]
+pub fn narrow(position: usize) -> Position {
+    position
+}
+
+/// Widen a stored `Position` to `usize`.
+#[allow(clippy::unnecessary_cast)] // `Position` is `usize` without `compact-table`
+#[inline]
+pub fn widen(position: Position) -> usize {
+    position as usize
+}
+
+/// The storage of spline points.
+/// With the `inline-storage` feature, up to `INLINE_POINTS` points are kept inline, so a tiny index needs no heap allocation.
+#[cfg(feature = "inline-storage")]
+pub type Points<K = u64> = smallvec::SmallVec<[Point<K>; INLINE_POINTS]>;
+#[cfg(not(feature = "inline-storage"))]
+pub type Points<K = u64> = Vec<Point<K>>;
+
+/// The storage of the radix table, see `Points`.
+#[cfg(feature = "inline-storage")]
+pub type Table = smallvec::SmallVec<[Position; INLINE_TABLE]>;
+#[cfg(not(feature = "inline-storage"))]
+pub type Table = Vec<Position>;
+
+/// The storage of the errors of spline segments, one per point, see `Points`.
+#[cfg(feature = "inline-storage")]
+pub type SegmentErrors = smallvec::SmallVec<[u8; INLINE_POINTS]>;
+#[cfg(not(feature = "inline-storage"))]
+pub type SegmentErrors = Vec<u8>;
+
+/// The storage of the mean signed errors of spline segments, one per point, see `Points`.
+#[cfg(feature = "inline-storage")]
+pub type SegmentBiases = smallvec::SmallVec<[i8; INLINE_POINTS]>;
+#[cfg(not(feature = "inline-storage"))]
+pub type SegmentBiases = Vec<i8>;
+
+/// The inline capacity of `Points`.
+#[cfg(feature = "inline-storage")]
+pub const INLINE_POINTS: usize = 8;
+/// The inline capacity of `Table`, i.e., tables of at most 3 radix bits.
+#[cfg(feature = "inline-storage")]
+pub const INLINE_TABLE: usize = 16;
+
+/// A key type of `RadixSpline`, i.e., an unsigned integer, whose radix prefixes are its high bits.
+pub trait Key: SplineKey + Default + fmt::Debug {
+    /// The width of the key in bits, which bounds the radix shift.
+    const BITS: u32;
+
+    fn leading_zeros(self) -> u32;
+
+    fn checked_sub(self, other: Self) -> Option<Self>;
+
+    /// The next key, or `None` for the largest one.
+    fn successor(self) -> Option<Self>;
+
+    /// `(self - min) >> shift`, the radix prefix of a key not less than `min`, widened to `u128`.
+    fn prefix(self, min: Self, shift: u32) -> u128;
+}
+
+macro_rules! key {
+    ($($t:ty),*) => {$(
+        impl Key for $t {
+            const BITS: u32 = <$t>::BITS;
+
+            #[inline]
+            fn leading_zeros(self) -> u32 {
+                <$t>::leading_zeros(self)
+            }
+
+            #[inline]
+            fn checked_sub(self, other: Self) -> Option<Self> {
+                <$t>::checked_sub(self, other)
+            }
+
+            #[inline]
+            fn successor(self) -> Option<Self> {
+                self.checked_add(1)
+            }
+
+            #[inline]
+            fn prefix(self, min: Self, shift: u32) -> u128 {
+                ((self - min) >> shift) as u128
+            }
+        }
+    )*};
+}
+
+key!(u16, u32, u64, u128);
+
+/// `x` is the *key* (`u64` unless another `Key` is given); `y` is the *position*.
 /// Note data\[y\] == x holds.
 /// When it is compared, only *key* is involved.
 #[derive(Clone, Copy, Debug, Default)]
-pub struct Point {
-    key: u64,        // x
-    position: usize, // y
+pub struct Point<K = u64> {
+    key: K,             // x
+    position: Position, // y
 }
 
-impl Point {
-    pub fn new(key: u64, position: usize) -> Self {
-        Point { key, position }
+impl<K: Copy> Point<K> {
+    pub fn new(key: K, position: usize) -> Self {
+        Point {
+            key,
+            position: narrow(position),
+        }
     }
 
-    pub fn key(&self) -> u64 {
+    pub fn key(&self) -> K {
         self.key
     }
 
     pub fn position(&self) -> usize {
-        self.position
+        widen(self.position)
     }
 }
 
-impl PartialEq for Point {
+impl<K: Ord> PartialEq for Point<K> {
     fn eq(&self, other: &Self) -> bool {
         self.key == other.key
     }
 }
 
-impl PartialOrd for Point {
+impl<K: Ord> PartialOrd for Point<K> {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        self.key.partial_cmp(&other.key)
+        Some(self.cmp(other))
     }
 }
 
-impl Ord for Point {
+impl<K: Ord> Ord for Point<K> {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
         self.key.cmp(&other.key)
     }
 }
 
-impl Eq for Point {}
+impl<K: Ord> Eq for Point<K> {}
 
 /// How are two lines (with the same starting point) related?
 pub enum Direction {
@@ -51,37 +163,33 @@ pub enum Direction {
 }
 
 /// A line connecting `start` and `end` point
-pub struct Line {
-    start: Point,
-    end: Point,
+pub struct Line<K = u64> {
+    start: Point<K>,
+    end: Point<K>,
 }
 
-impl Line {
-    pub fn new(start: Point, end: Point) -> Self {
+impl<K: Key> Line<K> {
+    pub fn new(start: Point<K>, end: Point<K>) -> Self {
         Line { start, end }
     }
 
-    pub fn is_vertical(&self) -> bool {
-        self.start.key == self.end.key
-    }
-
     /// Note that it is applied when two lines have the same starting point.
-    fn get_direction(&self, other: &Line) -> Direction {
+    fn get_direction(&self, other: &Line<K>) -> Direction {
         // dy can be less than 0
         let (dy, dx) = (
-            self.end.position as i64 - self.start.position as i64,
-            self.end.key - self.start.key,
+            self.end.position() as f64 - self.start.position() as f64,
+            self.end.key.offset_from(self.start.key),
         );
         assert!(dx > 0);
 
         let (other_dy, other_dx) = (
-            other.end.position as i64 - other.start.position as i64,
-            other.end.key - other.start.key,
+            other.end.position() as f64 - other.start.position() as f64,
+            other.end.key.offset_from(other.start.key),
         );
         assert!(other_dx > 0);
 
-        let sin = dy as f64 / dx as f64;
-        let other_sin = other_dy as f64 / other_dx as f64;
+        let sin = dy / dx as f64;
+        let other_sin = other_dy / other_dx as f64;
 
         match sin.partial_cmp(&other_sin) {
             Some(std::cmp::Ordering::Equal) => Direction::Coincide,
@@ -91,11 +199,11 @@ impl Line {
         }
     }
 
-    pub fn is_left(&self, other: &Line) -> bool {
+    pub fn is_left(&self, other: &Line<K>) -> bool {
         matches!(self.get_direction(other), Direction::Left)
     }
 
-    pub fn is_right(&self, other: &Line) -> bool {
+    pub fn is_right(&self, other: &Line<K>) -> bool {
         matches!(self.get_direction(other), Direction::Right)
     }
 }
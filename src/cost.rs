@@ -0,0 +1,173 @@
+//! # Scan cost
+//! The numbers a query planner needs to choose between an index-assisted scan and a sequential scan of a key range.
+//! Choosing is left to the caller, e.g., with constants measured by `calibrate_probe_cost`.
+
+use std::hint::black_box;
+use std::time::Instant;
+
+use crate::radix::RadixSpline;
+
+/// The estimated cost of scanning the keys within an inclusive key range.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ScanCost {
+    /// the estimated number of keys in the range, off by at most `2 * max_error + 2` on data without duplicates
+    pub estimated_rows: usize,
+    /// the random accesses of an index-assisted scan: the comparisons of one lookup plus one per row
+    pub index_probes: usize,
+    /// the bytes of a sequential scan over all keys
+    pub sequential_bytes: usize,
+}
+
+/// The measured cost of one index probe and of one sequentially scanned byte.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ProbeCost {
+    pub probe_nanos: f64,
+    pub byte_nanos: f64,
+}
+
+impl ScanCost {
+    /// Whether the index-assisted scan is estimated to be cheaper than the sequential scan.
+    pub fn prefers_index(&self, cost: &ProbeCost) -> bool {
+        self.index_probes as f64 * cost.probe_nanos < self.sequential_bytes as f64 * cost.byte_nanos
+    }
+}
+
+impl<'a> RadixSpline<'a> {
+    /// Estimate the cost of scanning the keys within `[range.0, range.1]`, from the spline alone without touching the keys.
+    /// The estimates are monotone: widening the range never decreases them.
+    pub fn scan_cost_estimate(&self, range: (u64, u64)) -> ScanCost {
+        let (lo, hi) = range;
+        let estimated_rows = if lo > hi {
+            0
+        } else {
+            let end = hi
+                .checked_add(1)
+                .map_or(self.data().len(), |end| self.estimate_lower_bound(end));
+            end.saturating_sub(self.estimate_lower_bound(lo))
+        };
+        ScanCost {
+            estimated_rows,
+            index_probes: self.estimated_search_comparisons().ceil() as usize + estimated_rows,
+            sequential_bytes: size_of_val(self.data()),
+        }
+    }
+
+    /// Measure the cost constants of `ScanCost` on this index and machine, with searches of evenly spaced keys
+    /// and a sequential pass over the keys. It runs for roughly as long as one pass plus 10,000 searches.
+    pub fn calibrate_probe_cost(&self) -> ProbeCost {
+        let data = self.data();
+        let samples = 10_000;
+        let step = (data.len() / samples).max(1);
+        let keys: Vec<u64> = data.iter().step_by(step).copied().collect();
+
+        let start = Instant::now();
+        for &key in &keys {
+            black_box(self.search(black_box(key)));
+        }
+        let search_nanos = start.elapsed().as_nanos() as f64 / keys.len() as f64;
+
+        let start = Instant::now();
+        black_box(data.iter().fold(0u64, |sum, &key| sum.wrapping_add(key)));
+        let scan_nanos = start.elapsed().as_nanos() as f64;
+
+        ProbeCost {
+            probe_nanos: search_nanos / self.estimated_search_comparisons().max(1.0),
+            byte_nanos: scan_nanos / size_of_val(data) as f64,
+        }
+    }
+
+    /// The predicted position of the first key not less than `key`.
+    fn estimate_lower_bound(&self, key: u64) -> usize {
+        if key <= self.min_key() {
+            0
+        } else if key > self.max_key() {
+            self.data().len()
+        } else {
+            self.predict(key)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{distributions::Uniform, Rng};
+
+    use super::*;
+    use crate::{MaxError, RadixBits};
+
+    fn random_data() -> Vec<u64> {
+        let mut data: Vec<u64> = rand::thread_rng()
+            .sample_iter(&Uniform::from(0..u64::MAX / 2))
+            .take(100000)
+            .collect();
+        data.sort_unstable();
+        data.dedup();
+        data
+    }
+
+    #[test]
+    fn estimated_rows() {
+        let data = random_data();
+        let mut rng = rand::thread_rng();
+        for max_error in [0, 4, 32] {
+            let radix_spline =
+                RadixSpline::new(&data, RadixBits::new(18), MaxError::new(max_error));
+            let bound = 2 * max_error + 2;
+            for _ in 0..10000 {
+                let (a, b) = (rng.gen::<u64>(), rng.gen::<u64>());
+                let (lo, hi) = (a.min(b), a.max(b));
+                let exact = data.partition_point(|&k| k <= hi) - data.partition_point(|&k| k < lo);
+                let cost = radix_spline.scan_cost_estimate((lo, hi));
+                assert!(
+                    cost.estimated_rows.abs_diff(exact) <= bound,
+                    "{:?}: {} vs {}",
+                    (lo, hi),
+                    cost.estimated_rows,
+                    exact
+                );
+            }
+            let full = radix_spline.scan_cost_estimate((0, u64::MAX));
+            assert_eq!(full.estimated_rows, data.len());
+            assert_eq!(full.sequential_bytes, data.len() * 8);
+            assert_eq!(radix_spline.scan_cost_estimate((5, 4)).estimated_rows, 0);
+        }
+    }
+
+    #[test]
+    fn monotone() {
+        let data = random_data();
+        let radix_spline = RadixSpline::new(&data, RadixBits::new(18), MaxError::new(32));
+        let mut rng = rand::thread_rng();
+        for _ in 0..1000 {
+            let center = rng.gen_range(0..u64::MAX / 2);
+            let mut previous = ScanCost::default();
+            for width in (0..64).map(|bits| (1u64 << bits) - 1) {
+                let range = (center.saturating_sub(width), center.saturating_add(width));
+                let cost = radix_spline.scan_cost_estimate(range);
+                assert!(cost.estimated_rows >= previous.estimated_rows);
+                assert!(cost.index_probes >= previous.index_probes);
+                previous = cost;
+            }
+        }
+    }
+
+    #[test]
+    fn calibrate_probe_cost() {
+        let data = random_data();
+        let radix_spline = RadixSpline::new(&data, RadixBits::new(18), MaxError::new(32));
+        let cost = radix_spline.calibrate_probe_cost();
+        assert!(cost.probe_nanos.is_finite() && cost.probe_nanos >= 0.0);
+        assert!(cost.byte_nanos.is_finite() && cost.byte_nanos >= 0.0);
+
+        // a random access costing ten sequential bytes: a point lookup beats a full scan, and the full range does not
+        let point = radix_spline.scan_cost_estimate((data[10], data[10]));
+        let probe_cost = ProbeCost {
+            probe_nanos: 10.0,
+            byte_nanos: 1.0,
+        };
+        assert!(point.prefers_index(&probe_cost));
+        assert!(!radix_spline
+            .scan_cost_estimate((0, u64::MAX))
+            .prefers_index(&probe_cost));
+    }
+}
@@ -0,0 +1,329 @@
+//! # The layout of the reference C++ implementation
+//! `write_to` and `read_from` use the byte layout of `rs::Serializer<uint64_t>` of the original C++ RadixSpline
+//! (learnedsystems/RadixSpline), so an index built on one side is loaded on the other. Like the C++ model,
+//! it holds no keys, and `read_from` attaches it to the data. All integers are little-endian, as the C++ code writes
+//! them on x86-64 and AArch64, and `size_t` is 8 bytes:
+//!
+//! - `min_key: u64`, `max_key: u64`, `num_keys: size_t`, `num_radix_bits: size_t`, `num_shift_bits: size_t`,
+//!   `max_error: size_t`
+//! - the radix table: `size: size_t`, then `size` entries of `u32`
+//! - the spline points: `size: size_t`, then `size` pairs of `x: u64` (the key) and `y: f64` (the position)
+//!
+//! The radix table is the same as ours, with an entry per prefix up to that of `max_key` and an end entry,
+//! so it is checked against the spline points. A longer table is read as well, if every entry past the end entry
+//! is an end entry, i.e., the number of spline points, as a table sized for more prefixes than the keys use is filled.
+//! `write_to` writes the shortest table. What the layout cannot represent fails with an error rather than
+//! being misread:
+//!
+//! - keys other than `u64`, e.g., a `uint32_t` index, whose keys are 4 bytes: its header does not match the data
+//! - a point position which is not an integral key position, e.g., a fractional `y`, or a point which is not at the
+//!   first occurrence of its key, as our search relies on
+//! - when writing, a point position beyond 2^53, which `f64` does not hold exactly, or more spline points than a
+//!   `u32` table entry counts
+//!
+//! `num_radix_bits` is the effective number of `num_radix_bits()`, so it may be fewer than requested at build time.
+//! The segment errors are not in the layout, so they are measured again over the data when reading.
+
+use std::borrow::Cow;
+use std::io::{self, Read, Write};
+
+use crate::common::{narrow, widen, Point, Points, Table};
+use crate::radix::{get_num_shift_bits, RadixSpline};
+
+/// The largest integer that `f64` holds exactly, and with it every smaller one.
+const MAX_EXACT_F64: u64 = 1 << 53;
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn read_u64(reader: &mut impl Read) -> io::Result<u64> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+fn read_u32(reader: &mut impl Read) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+impl<'a> RadixSpline<'a> {
+    /// Write the model in the layout of the reference C++ `Serializer`, without the keys, see the module documentation.
+    pub fn write_to(&self, mut writer: impl Write) -> io::Result<()> {
+        let too_large = |message| io::Error::new(io::ErrorKind::InvalidInput, message);
+        let data = self.data();
+        let header = [
+            self.min_key(),
+            self.max_key(),
+            data.len() as u64,
+            u64::from(self.num_radix_bits()),
+            u64::from(self.shift_radix_bits()),
+            self.max_error() as u64,
+        ];
+        let table = self.table();
+        let mut bytes = Vec::with_capacity(8 * header.len() + 8 + 4 * table.len());
+        for word in header.into_iter().chain([table.len() as u64]) {
+            bytes.extend_from_slice(&word.to_le_bytes());
+        }
+        for &entry in table.iter() {
+            let entry = u32::try_from(widen(entry))
+                .map_err(|_| too_large("more spline points than a u32 table entry counts"))?;
+            bytes.extend_from_slice(&entry.to_le_bytes());
+        }
+        writer.write_all(&bytes)?;
+
+        let points = self.points();
+        writer.write_all(&(points.len() as u64).to_le_bytes())?;
+        for point in points {
+            let position = point.position() as u64;
+            if position > MAX_EXACT_F64 {
+                return Err(too_large(
+                    "a spline point position beyond 2^53 is not exact in f64",
+                ));
+            }
+            writer.write_all(&point.key().to_le_bytes())?;
+            writer.write_all(&(position as f64).to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Read a model of `write_to` or of the reference C++ `Serializer` from `reader`, and attach it to its `data`,
+    /// which is borrowed as by `new`. The reader is left right after the model.
+    /// The header must describe `data`, and the points and the radix table must be those of `data`.
+    pub fn read_from(mut reader: impl Read, data: &'a [u64]) -> io::Result<Self> {
+        let reader = &mut reader;
+        let mut header = [0; 6];
+        for word in &mut header {
+            *word = read_u64(reader)?;
+        }
+        let [min_key, max_key, num_keys, num_radix_bits, shift_radix_bits, max_error] = header;
+        if num_keys != data.len() as u64
+            || data.first() != Some(&min_key)
+            || data.last() != Some(&max_key)
+        {
+            return Err(invalid("the model is of other data"));
+        }
+        let num_radix_bits = u32::try_from(num_radix_bits)
+            .ok()
+            .filter(|&bits| get_num_shift_bits(max_key - min_key, bits) as u64 == shift_radix_bits)
+            .ok_or_else(|| invalid("num_shift_bits does not follow from num_radix_bits"))?;
+        let shift_radix_bits = get_num_shift_bits(max_key - min_key, num_radix_bits);
+        let max_error =
+            usize::try_from(max_error).map_err(|_| invalid("max_error out of range"))?;
+
+        // the table covers at least the prefixes up to that of `max_key` and an end entry, and the entries
+        // are only allocated as they are read, so a huge table of a crafted header fails at the end of the input
+        let table_len = read_u64(reader)?;
+        let max_prefix = (max_key - min_key) >> shift_radix_bits;
+        let used_len = max_prefix
+            .checked_add(2)
+            .filter(|&used_len| used_len <= table_len)
+            .and_then(|used_len| usize::try_from(used_len).ok())
+            .ok_or_else(|| invalid("bad radix table length"))?;
+        let table_len =
+            usize::try_from(table_len).map_err(|_| invalid("bad radix table length"))?;
+        let mut entries = Vec::with_capacity(table_len.min(1 << 20));
+        for _ in 0..table_len {
+            entries.push(read_u32(reader)?);
+        }
+
+        // there is at most a point per key
+        let num_points = read_u64(reader)?;
+        if num_points > num_keys {
+            return Err(invalid("more spline points than keys"));
+        }
+        let mut points = Points::with_capacity(num_points as usize);
+        for _ in 0..num_points {
+            let key = read_u64(reader)?;
+            let position = f64::from_bits(read_u64(reader)?);
+            let exact = position >= 0.0 && position < num_keys as f64 && position.fract() == 0.0;
+            if !exact {
+                return Err(invalid("a spline point position is not a key position"));
+            }
+            points.push(Point::new(key, position as usize));
+        }
+        if entries.iter().any(|&entry| u64::from(entry) > num_points) {
+            return Err(invalid("table entry out of range"));
+        }
+        // entries past the end entry are end entries too, e.g., of a table sized for more prefixes
+        if entries[used_len..].iter().any(|&entry| u64::from(entry) != num_points) {
+            return Err(invalid("radix table does not match the spline points"));
+        }
+        let table: Table = entries[..used_len]
+            .iter()
+            .map(|&entry| narrow(entry as usize))
+            .collect();
+
+        RadixSpline::from_parts(
+            Cow::Borrowed(data),
+            shift_radix_bits,
+            max_error,
+            points,
+            table,
+            None,
+        )
+        .map_err(invalid)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{MaxError, RadixBits};
+    use rand::Rng;
+
+    fn write(radix_spline: &RadixSpline) -> Vec<u8> {
+        let mut bytes = vec![];
+        radix_spline.write_to(&mut bytes).unwrap();
+        bytes
+    }
+
+    /// The bytes of a model, field by field as in the module documentation.
+    fn layout(header: [u64; 6], table: &[u32], points: &[(u64, f64)]) -> Vec<u8> {
+        let mut bytes = vec![];
+        for word in header.into_iter().chain([table.len() as u64]) {
+            bytes.extend_from_slice(&word.to_le_bytes());
+        }
+        for entry in table {
+            bytes.extend_from_slice(&entry.to_le_bytes());
+        }
+        bytes.extend_from_slice(&(points.len() as u64).to_le_bytes());
+        for &(x, y) in points {
+            bytes.extend_from_slice(&x.to_le_bytes());
+            bytes.extend_from_slice(&y.to_le_bytes());
+        }
+        bytes
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut rng = rand::thread_rng();
+        let mut data: Vec<u64> = (0..20_000).map(|_| rng.gen_range(0..1 << 40)).collect();
+        data.extend([data[0]; 50]);
+        data.sort_unstable();
+        for (num_radix_bits, max_error) in [(0, 0), (8, 4), (18, 32)] {
+            let radix_spline = RadixSpline::new(
+                &data,
+                RadixBits::new(num_radix_bits),
+                MaxError::new(max_error),
+            );
+            let mut bytes = write(&radix_spline);
+            let len = bytes.len();
+            // the reader stops right after the model
+            bytes.extend_from_slice(b"next");
+            let mut reader = &bytes[..];
+            let model = RadixSpline::read_from(&mut reader, &data).unwrap();
+            assert_eq!(reader, b"next");
+            assert_eq!(write(&model), bytes[..len]);
+            assert_eq!(model.serialize(), radix_spline.serialize());
+            for key in (0..10_000)
+                .map(|_| rng.gen_range(0..1 << 41))
+                .chain(data.iter().copied())
+            {
+                assert_eq!(model.search(key), radix_spline.search(key));
+            }
+        }
+    }
+
+    /// A model written field by field from the layout as documented: keys `10, 20, 30, 40, 1000` with 2 radix bits,
+    /// i.e., 10 bits of key range shifted by 8, and the points `(10, 0)`, `(40, 3)` and `(1000, 4)`.
+    /// It checks the reading of the documented fields only: `tests/cpp_fixture.rs` reads a model of the C++ code.
+    #[test]
+    fn hand_written_layout() {
+        let data = [10, 20, 30, 40, 1000];
+        let header = [10, 1000, 5, 2, 8, 1];
+        let table = [0, 2, 2, 2, 3];
+        let points = [(10, 0.0), (40, 3.0), (1000, 4.0)];
+        let bytes = layout(header, &table, &points);
+        let model = RadixSpline::read_from(&bytes[..], &data).unwrap();
+        assert_eq!(model.num_radix_bits(), 2);
+        for key in 0..1100 {
+            assert_eq!(
+                model.search(key),
+                data.iter().position(|&x| x == key),
+                "{}",
+                key
+            );
+        }
+        assert_eq!(write(&model), bytes);
+
+        // a table sized for more prefixes, with end entries past the end entry
+        let padded = layout(header, &[0, 2, 2, 2, 3, 3, 3, 3], &points);
+        let model = RadixSpline::read_from(&padded[..], &data).unwrap();
+        assert_eq!(model.table_len(), 5);
+        assert_eq!(write(&model), bytes);
+    }
+
+    #[test]
+    fn rejected() {
+        let data = [10, 20, 30, 40, 1000];
+        let header = [10, 1000, 5, 2, 8, 1];
+        let table = [0, 2, 2, 2, 3];
+        let points = [(10, 0.0), (40, 3.0), (1000, 4.0)];
+        let read = |header, table: &[u32], points: &[(u64, f64)]| {
+            let bytes = layout(header, table, points);
+            let error = RadixSpline::read_from(&bytes[..], &data).err().unwrap();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+            error.to_string()
+        };
+
+        let other = "the model is of other data";
+        assert_eq!(read([11, 1000, 5, 2, 8, 1], &table, &points), other);
+        assert_eq!(read([10, 999, 5, 2, 8, 1], &table, &points), other);
+        assert_eq!(read([10, 1000, 6, 2, 8, 1], &table, &points), other);
+        let shift = "num_shift_bits does not follow from num_radix_bits";
+        assert_eq!(read([10, 1000, 5, 2, 7, 1], &table, &points), shift);
+        assert_eq!(read([10, 1000, 5, 1 << 40, 8, 1], &table, &points), shift);
+        assert_eq!(read(header, &table[..4], &points), "bad radix table length");
+        assert_eq!(
+            read(header, &[0, 2, 2, 2, 3, 3, 2], &points),
+            "radix table does not match the spline points"
+        );
+        assert_eq!(
+            read(header, &[0, 1, 2, 2, 3], &points),
+            "radix table does not match the spline points"
+        );
+        assert_eq!(
+            read(header, &[0, 2, 2, 2, 4], &points),
+            "table entry out of range"
+        );
+        let position = "a spline point position is not a key position";
+        for y in [2.5, -1.0, 5.0, f64::NAN, f64::INFINITY] {
+            assert_eq!(
+                read(header, &table, &[(10, 0.0), (40, y), (1000, 4.0)]),
+                position
+            );
+        }
+        assert_eq!(
+            read(header, &table, &[(10, 0.0), (40, 2.0), (1000, 4.0)]),
+            "spline points are not on the data"
+        );
+        // a point at the second occurrence of its key
+        let duplicates = [10, 20, 20, 40, 1000];
+        let at_second = layout(header, &table, &[(10, 0.0), (20, 2.0), (1000, 4.0)]);
+        let error = RadixSpline::read_from(&at_second[..], &duplicates)
+            .err()
+            .unwrap();
+        assert_eq!(error.to_string(), "spline points are not on the data");
+
+        // a table of 2^60 + 1 entries, which the input does not hold
+        let wide = [0, u64::MAX];
+        let huge = (1u64 << 60) + 1;
+        let mut bytes = layout([0, u64::MAX, 2, 60, 4, 0], &[], &[]);
+        bytes[48..56].copy_from_slice(&huge.to_le_bytes());
+        let error = RadixSpline::read_from(&bytes[..], &wide).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+
+        // a truncated model fails to read whatever it stops at
+        let bytes = layout(header, &table, &points);
+        for len in 0..bytes.len() {
+            assert!(
+                RadixSpline::read_from(&bytes[..len], &data).is_err(),
+                "{}",
+                len
+            );
+        }
+    }
+}
@@ -0,0 +1,227 @@
+//! # Benchmark data
+//! Seeded, sorted key sets and lookup probes shared by the benchmarks and the binary, without a dependency
+//! on a random number crate. The same seed gives the same keys and probes on every platform.
+//!
+//! The probes are sampled from the sorted keys in one of the `ProbeSampling` modes, so their multiplicity
+//! is explicit: sampling positions repeats hot duplicated keys as often as the data does, while sampling
+//! distinct keys weighs every key the same.
+
+/// A small seeded generator, SplitMix64, good enough for benchmark data.
+#[derive(Clone, Debug)]
+pub struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    pub fn new(seed: u64) -> Self {
+        SplitMix64 { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// A value in `0..bound`, which must be positive. The bias is at most `bound / 2^64`.
+    pub fn below(&mut self, bound: u64) -> u64 {
+        assert!(bound > 0, "the bound must be positive");
+        ((self.next_u64() as u128 * bound as u128) >> 64) as u64
+    }
+}
+
+/// `len` keys drawn uniformly from `0..max_key`, sorted, with the duplicates the draws give.
+pub fn uniform_keys(len: usize, max_key: u64, seed: u64) -> Vec<u64> {
+    let mut rng = SplitMix64::new(seed);
+    let mut keys: Vec<u64> = (0..len).map(|_| rng.below(max_key)).collect();
+    keys.sort_unstable();
+    keys
+}
+
+/// How `sample_probes` picks the probe keys.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProbeSampling {
+    /// the keys at uniform positions, so a key is picked as often as it occurs
+    Positions,
+    /// uniform over the distinct keys, whatever their number of occurrences
+    DistinctKeys,
+    /// uniform over the keys within `[min_key, max_key]` which are absent from the data,
+    /// or just outside it if there is none
+    AbsentKeys,
+}
+
+/// Sample `count` probes from the sorted, non-empty `data`, in the order drawn.
+pub fn sample_probes(data: &[u64], count: usize, sampling: ProbeSampling, seed: u64) -> Vec<u64> {
+    assert!(!data.is_empty(), "probes need keys to sample from");
+    debug_assert!(data.windows(2).all(|w| w[0] <= w[1]), "data must be sorted");
+    let mut rng = SplitMix64::new(seed);
+    match sampling {
+        ProbeSampling::Positions => (0..count)
+            .map(|_| data[rng.below(data.len() as u64) as usize])
+            .collect(),
+        ProbeSampling::DistinctKeys => {
+            let mut distinct = data.to_vec();
+            distinct.dedup();
+            (0..count)
+                .map(|_| distinct[rng.below(distinct.len() as u64) as usize])
+                .collect()
+        }
+        ProbeSampling::AbsentKeys => {
+            let mut distinct = data.to_vec();
+            distinct.dedup();
+            absent_keys(&distinct, count, &mut rng)
+        }
+    }
+}
+
+/// Uniform over the gaps of the strictly increasing `distinct`, by the rank of an absent key.
+fn absent_keys(distinct: &[u64], count: usize, rng: &mut SplitMix64) -> Vec<u64> {
+    let (min_key, max_key) = (distinct[0], distinct[distinct.len() - 1]);
+    // the absent keys below `distinct[i]`
+    let absent_before = |i: usize| distinct[i] - min_key - i as u64;
+    let total = absent_before(distinct.len() - 1);
+    if total == 0 {
+        // a dense key range, so the probes are the neighbours outside it
+        let outside = match (min_key.checked_sub(1), max_key.checked_add(1)) {
+            (Some(below), _) => below,
+            (None, Some(beyond)) => beyond,
+            (None, None) => unreachable!("the keys cannot cover all of u64"),
+        };
+        return vec![outside; count];
+    }
+    (0..count)
+        .map(|_| {
+            let rank = rng.below(total);
+            // the first key with more than `rank` absent keys below it, which ends the gap of `rank`
+            let (mut low, mut high) = (1, distinct.len() - 1);
+            while low < high {
+                let mid = low + (high - low) / 2;
+                if absent_before(mid) <= rank {
+                    low = mid + 1;
+                } else {
+                    high = mid;
+                }
+            }
+            distinct[low - 1] + 1 + (rank - absent_before(low - 1))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Skewed keys: one hot key holding 90% of the positions, and 9 cold ones.
+    fn skewed() -> Vec<u64> {
+        let mut data = vec![500; 900];
+        for key in 0..9u64 {
+            data.extend(std::iter::repeat_n(key * 100 + 1, 100 / 9));
+        }
+        data.sort_unstable();
+        data
+    }
+
+    fn count_of(probes: &[u64], key: u64) -> usize {
+        probes.iter().filter(|&&probe| probe == key).count()
+    }
+
+    #[test]
+    fn deterministic() {
+        assert_eq!(
+            uniform_keys(1000, 1 << 40, 7),
+            uniform_keys(1000, 1 << 40, 7)
+        );
+        assert_ne!(
+            uniform_keys(1000, 1 << 40, 7),
+            uniform_keys(1000, 1 << 40, 8)
+        );
+        let data = uniform_keys(1000, 1 << 20, 1);
+        assert!(data.windows(2).all(|w| w[0] <= w[1]));
+        assert!(data.iter().all(|&key| key < 1 << 20));
+        for sampling in [
+            ProbeSampling::Positions,
+            ProbeSampling::DistinctKeys,
+            ProbeSampling::AbsentKeys,
+        ] {
+            let probes = sample_probes(&data, 100, sampling, 3);
+            assert_eq!(probes.len(), 100);
+            assert_eq!(probes, sample_probes(&data, 100, sampling, 3));
+        }
+    }
+
+    #[test]
+    fn below() {
+        let mut rng = SplitMix64::new(0);
+        let mut counts = [0usize; 10];
+        for _ in 0..100_000 {
+            counts[rng.below(10) as usize] += 1;
+        }
+        assert!(
+            counts.iter().all(|&n| n.abs_diff(10_000) < 500),
+            "{:?}",
+            counts
+        );
+        assert_eq!(SplitMix64::new(0).below(1), 0);
+    }
+
+    /// Sampling positions follows the multiplicity of the keys.
+    #[test]
+    fn positions() {
+        let data = skewed();
+        let probes = sample_probes(&data, 10_000, ProbeSampling::Positions, 1);
+        assert!(probes.iter().all(|probe| data.binary_search(probe).is_ok()));
+        let hot = count_of(&probes, 500) as f64 / probes.len() as f64;
+        let expected = 900.0 / data.len() as f64;
+        assert!((hot - expected).abs() < 0.02, "{} vs {}", hot, expected);
+    }
+
+    /// Sampling distinct keys weighs the hot key as any other.
+    #[test]
+    fn distinct_keys() {
+        let data = skewed();
+        let probes = sample_probes(&data, 10_000, ProbeSampling::DistinctKeys, 2);
+        for key in (0..9).map(|key| key * 100 + 1).chain([500]) {
+            let share = count_of(&probes, key);
+            assert!(share.abs_diff(1000) < 150, "key {}: {}", key, share);
+        }
+        assert!(probes.iter().all(|probe| data.binary_search(probe).is_ok()));
+    }
+
+    /// Absent keys are within the key range, miss the data, and spread over the gaps by their sizes.
+    #[test]
+    fn absent_keys() {
+        // a gap of 10 absent keys and a gap of 90
+        let data = vec![0, 0, 11, 11, 11, 102];
+        let probes = sample_probes(&data, 10_000, ProbeSampling::AbsentKeys, 3);
+        assert!(probes
+            .iter()
+            .all(|probe| data.binary_search(probe).is_err()));
+        assert!(probes.iter().all(|&probe| probe > 0 && probe < 102));
+        let small_gap = probes.iter().filter(|&&probe| probe < 11).count();
+        assert!(small_gap.abs_diff(1000) < 150, "{}", small_gap);
+        for key in (1..11).chain(12..102) {
+            assert!(probes.contains(&key), "{} is never drawn", key);
+        }
+
+        // without a gap, the probes are just outside the keys
+        assert_eq!(
+            sample_probes(&[5, 6, 6, 7], 3, ProbeSampling::AbsentKeys, 0),
+            vec![4; 3]
+        );
+        assert_eq!(
+            sample_probes(&[0, 1], 2, ProbeSampling::AbsentKeys, 0),
+            vec![2; 2]
+        );
+        assert_eq!(
+            sample_probes(
+                &[u64::MAX - 1, u64::MAX - 1, u64::MAX],
+                1,
+                ProbeSampling::AbsentKeys,
+                0
+            ),
+            vec![u64::MAX - 2]
+        );
+    }
+}
@@ -0,0 +1,169 @@
+//! # Data drift
+//! Measure how well an index built over older data still describes newer data.
+//! Deciding whether a rebuild is needed is left to the caller.
+
+use crate::model::RadixSplineModel;
+use crate::radix::RadixSpline;
+
+/// A summary of the divergence between an index and a (possibly changed) data set.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DriftReport {
+    pub samples: usize,
+    /// the max `|estimated key - actual key|` over the sampled positions
+    pub max_key_delta: u64,
+    /// the mean `|estimated key - actual key|` over the sampled positions
+    pub mean_key_delta: f64,
+    /// the fraction of sampled keys whose predicted position is not within `max_error`
+    pub outside_fraction: f64,
+    /// the first sampled position whose key is not within `max_error`
+    pub first_outside: Option<usize>,
+}
+
+impl RadixSplineModel {
+    /// Sample `sample` evenly spaced positions of `data`, and compare them with this model,
+    /// e.g., a model saved with an older version of the data, which is no longer at hand.
+    pub fn drift_against(&self, data: &[u64], sample: usize) -> DriftReport {
+        drift(
+            data,
+            sample,
+            |position| self.estimate_key_at(position),
+            |key, position| {
+                let bound = self.estimate(key);
+                (bound.start..bound.stop).contains(&position)
+            },
+        )
+    }
+}
+
+impl<'a> RadixSpline<'a> {
+    /// The same as `RadixSplineModel::drift_against`, with the index at hand.
+    pub fn drift_against(&self, data: &[u64], sample: usize) -> DriftReport {
+        drift(
+            data,
+            sample,
+            |position| self.estimate_key_at(position),
+            |key, position| {
+                key >= self.min_key()
+                    && key <= self.max_key()
+                    && self.predict(key).abs_diff(position) <= self.max_error()
+            },
+        )
+    }
+}
+
+/// Compare `sample` evenly spaced positions of `data` with a model, which estimates the key at a position,
+/// and tells whether a key is predicted within its error of a position.
+fn drift(
+    data: &[u64],
+    sample: usize,
+    estimate_key_at: impl Fn(usize) -> u64,
+    within: impl Fn(u64, usize) -> bool,
+) -> DriftReport {
+    if data.is_empty() || sample == 0 {
+        return DriftReport::default();
+    }
+    let step = (data.len() / sample).max(1);
+
+    let mut report = DriftReport::default();
+    let mut total_delta = 0u128;
+    let mut outside = 0usize;
+    for position in (0..data.len()).step_by(step).take(sample) {
+        let key = data[position];
+
+        let delta = estimate_key_at(position).abs_diff(key);
+        report.max_key_delta = report.max_key_delta.max(delta);
+        total_delta += delta as u128;
+
+        if !within(key, position) {
+            outside += 1;
+            report.first_outside.get_or_insert(position);
+        }
+        report.samples += 1;
+    }
+    report.mean_key_delta = total_delta as f64 / report.samples as f64;
+    report.outside_fraction = outside as f64 / report.samples as f64;
+    report
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{MaxError, RadixBits};
+    use rand::{distributions::Uniform, Rng};
+
+    fn random_data(n: usize) -> Vec<u64> {
+        let range = Uniform::from(0..100000000);
+        let mut data: Vec<u64> = rand::thread_rng().sample_iter(&range).take(n).collect();
+        data.sort_unstable();
+        data.dedup();
+        data
+    }
+
+    #[test]
+    fn identical_data() {
+        let data = random_data(100000);
+        let radix_spline = RadixSpline::new(&data, RadixBits::DEFAULT, MaxError::DEFAULT);
+
+        let report = radix_spline.drift_against(&data, 1000);
+
+        assert_eq!(report.samples, 1000);
+        assert_eq!(report.outside_fraction, 0.0);
+        assert_eq!(report.first_outside, None);
+        assert_eq!(radix_spline.to_model().drift_against(&data, 1000), report);
+    }
+
+    #[test]
+    fn appended_tail() {
+        let data = random_data(100000);
+        let radix_spline = RadixSpline::new(&data, RadixBits::DEFAULT, MaxError::DEFAULT);
+
+        let mut grown = data.clone();
+        let max_key = data[data.len() - 1];
+        grown.extend((1..=10000).map(|i| max_key + i * 1000));
+
+        let report = radix_spline.drift_against(&grown, 1000);
+        assert_eq!(radix_spline.to_model().drift_against(&grown, 1000), report);
+
+        // only the appended tail drifts
+        assert!(report.first_outside.unwrap() >= data.len());
+        assert!(report.outside_fraction > 0.0 && report.outside_fraction < 0.1);
+        assert!(report.max_key_delta > 1000);
+    }
+
+    #[test]
+    fn shuffled_middle() {
+        use rand::seq::SliceRandom;
+
+        let data = random_data(100000);
+        let radix_spline = RadixSpline::new(&data, RadixBits::DEFAULT, MaxError::DEFAULT);
+
+        let mut changed = data.clone();
+        let (from, to) = (data.len() / 4, data.len() * 3 / 4);
+        changed[from..to].shuffle(&mut rand::thread_rng());
+
+        let report = radix_spline.drift_against(&changed, 1000);
+
+        assert!(report.first_outside.unwrap() >= from);
+        assert!(report.outside_fraction > 0.4);
+        assert!(report.max_key_delta > (data[to] - data[from]) / 2);
+    }
+
+    /// A saved model is compared with today's data, once the data it was built over is gone.
+    #[test]
+    fn saved_model() {
+        let data = random_data(100000);
+        let model = {
+            let old = data.clone();
+            RadixSpline::new(&old, RadixBits::DEFAULT, MaxError::DEFAULT).to_model()
+        };
+        let report = model.drift_against(&data, 1000);
+        assert_eq!(report.outside_fraction, 0.0);
+
+        let mut grown = data.clone();
+        let max_key = data[data.len() - 1];
+        grown.extend((1..=10000).map(|i| max_key + i * 1000));
+        let report = model.drift_against(&grown, 1000);
+        assert!(report.first_outside.unwrap() >= data.len());
+        assert!(report.max_key_delta > 1000);
+    }
+}
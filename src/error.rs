@@ -0,0 +1,185 @@
+//! # Build errors
+//! A failed build is either a configuration problem, which a retry with other parameters may fix,
+//! or a data problem, which no configuration fixes.
+//! A model which cannot be paired with its data again fails with a `DeserializeError` instead.
+
+use std::fmt;
+
+/// Why an index could not be built, see `RadixSpline::try_new`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BuildError {
+    Config(ConfigError),
+    Data(DataError),
+}
+
+/// A build failure caused by the parameters.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConfigError {
+    /// the radix table over the key range has more than `u32::MAX` entries or cannot be allocated with `num_radix_bits`
+    TableTooLarge { num_radix_bits: u32 },
+    /// more than `RadixBits::MAX` radix bits
+    RadixBitsOutOfRange { num_radix_bits: u32 },
+    /// a max error larger than `MaxError::MAX`
+    MaxErrorOutOfRange { max_error: usize },
+}
+
+/// A build failure caused by the data.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DataError {
+    /// an index needs at least one key
+    TooFewKeys { len: usize },
+    /// `data[position - 1] > data[position]`
+    NotSorted { position: usize },
+    /// more keys than the positions of the `compact-table` feature can address
+    TooManyKeys { len: usize },
+    /// a NaN float key, which has no order, see `F64RadixSpline`
+    NaN { position: usize },
+    /// the data no longer matches its checksum from the build, see `OwnedRadixSpline::revalidate`
+    MutatedAfterBuild,
+}
+
+/// Why a model could not be deserialized, see `RadixSpline::deserialize`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeserializeError {
+    /// the bytes are not a serialized model
+    BadMagic,
+    /// a model of a format version this build does not read
+    UnsupportedVersion { version: u8 },
+    /// the bytes end before the model, or continue after it
+    BadLength,
+    /// the model is malformed, or it does not describe the data
+    Mismatch(&'static str),
+}
+
+impl BuildError {
+    /// Whether building again with other parameters may succeed, as opposed to a problem of the data.
+    pub fn is_retryable_with_new_config(&self) -> bool {
+        matches!(self, BuildError::Config(_))
+    }
+}
+
+impl From<ConfigError> for BuildError {
+    fn from(error: ConfigError) -> Self {
+        BuildError::Config(error)
+    }
+}
+
+impl From<DataError> for BuildError {
+    fn from(error: DataError) -> Self {
+        BuildError::Data(error)
+    }
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BuildError::Config(ConfigError::TableTooLarge { num_radix_bits }) => write!(
+                f,
+                "the radix table is too large, reduce `num_radix_bits` ({})",
+                num_radix_bits
+            ),
+            BuildError::Config(ConfigError::RadixBitsOutOfRange { num_radix_bits }) => write!(
+                f,
+                "the number of radix bits is at most 32, got {}",
+                num_radix_bits
+            ),
+            BuildError::Config(ConfigError::MaxErrorOutOfRange { max_error }) => {
+                write!(f, "the max error is at most u32::MAX, got {}", max_error)
+            }
+            BuildError::Data(DataError::TooFewKeys { len }) => {
+                write!(f, "an index needs at least one key, got {}", len)
+            }
+            BuildError::Data(DataError::NotSorted { position }) => {
+                write!(f, "the data is not sorted at position {}", position)
+            }
+            BuildError::Data(DataError::TooManyKeys { len }) => write!(
+                f,
+                "the compact-table feature supports at most u32::MAX keys, got {}",
+                len
+            ),
+            BuildError::Data(DataError::NaN { position }) => {
+                write!(f, "the key at position {} is NaN", position)
+            }
+            BuildError::Data(DataError::MutatedAfterBuild) => {
+                write!(f, "data mutated after index build")
+            }
+        }
+    }
+}
+
+impl std::error::Error for BuildError {}
+
+impl fmt::Display for DeserializeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeserializeError::BadMagic => write!(f, "not a serialized radix spline model"),
+            DeserializeError::UnsupportedVersion { version } => {
+                write!(f, "unsupported model version {}", version)
+            }
+            DeserializeError::BadLength => write!(f, "bad model length"),
+            DeserializeError::Mismatch(reason) => write!(f, "bad model: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for DeserializeError {}
+
+#[cfg(test)]
+mod test {
+    use crate::RadixSpline;
+
+    use super::*;
+    use crate::{MaxError, RadixBits};
+
+    #[test]
+    fn classify() {
+        let wide = [0, u64::MAX];
+        let config = RadixSpline::try_new(&wide, RadixBits::new(32), MaxError::new(32))
+            .err()
+            .unwrap();
+        assert_eq!(
+            config,
+            BuildError::Config(ConfigError::TableTooLarge { num_radix_bits: 32 })
+        );
+        assert!(config.is_retryable_with_new_config());
+
+        let empty = RadixSpline::try_new(&[], RadixBits::new(18), MaxError::new(32))
+            .err()
+            .unwrap();
+        assert_eq!(empty, BuildError::Data(DataError::TooFewKeys { len: 0 }));
+        assert!(!empty.is_retryable_with_new_config());
+
+        let unsorted = RadixSpline::try_new(&[1, 2, 5, 4], RadixBits::new(18), MaxError::new(32))
+            .err()
+            .unwrap();
+        assert_eq!(
+            unsorted,
+            BuildError::Data(DataError::NotSorted { position: 3 })
+        );
+        assert!(!unsorted.is_retryable_with_new_config());
+        assert_eq!(unsorted.to_string(), "the data is not sorted at position 3");
+    }
+
+    #[test]
+    #[should_panic(expected = "the radix table is too large")]
+    fn new_panics() {
+        let wide = vec![0, u64::MAX];
+        RadixSpline::new(&wide, RadixBits::new(32), MaxError::new(32));
+    }
+
+    #[test]
+    fn tuned_recovers() {
+        let data: Vec<u64> = (0..1000u64).map(|i| i << 50).chain([u64::MAX]).collect();
+        let radix_spline =
+            RadixSpline::try_new_tuned(&data, RadixBits::new(32), MaxError::new(8)).unwrap();
+        for (i, &key) in data.iter().enumerate() {
+            assert_eq!(radix_spline.search(key), Some(i));
+        }
+        // an untunable failure is returned as is
+        assert_eq!(
+            RadixSpline::try_new_tuned(&[2, 1], RadixBits::new(32), MaxError::new(8)).err(),
+            Some(BuildError::Data(DataError::NotSorted { position: 1 }))
+        );
+        assert!(RadixSpline::try_new_tuned(&[], RadixBits::new(18), MaxError::new(8)).is_err());
+    }
+}
@@ -0,0 +1,121 @@
+//! # Exhaustive small-domain tests
+//! Enumerate every sorted multiset of keys from a tiny domain, build both index types, and check every probe key against a binary-search oracle.
+
+use crate::{GreedySplineCorridor, MaxError, RadixBits, RadixSpline, SearchStrategy};
+
+/// Call `f` with every sorted sequence of length `len` over keys `0..=max_key`.
+fn for_each_sorted(len: usize, max_key: u64, f: &mut impl FnMut(&Vec<u64>)) {
+    fn go(data: &mut Vec<u64>, len: usize, max_key: u64, f: &mut impl FnMut(&Vec<u64>)) {
+        if data.len() == len {
+            f(data);
+            return;
+        }
+        let from = data.last().copied().unwrap_or(0);
+        for key in from..=max_key {
+            data.push(key);
+            go(data, len, max_key, f);
+            data.pop();
+        }
+    }
+    go(&mut Vec::with_capacity(len), len, max_key, f)
+}
+
+fn check(data: &Vec<u64>, max_key: u64) {
+    check_with(data, max_key, 0..=3, &[2, 18]);
+}
+
+fn check_with(
+    data: &Vec<u64>,
+    max_key: u64,
+    max_errors: std::ops::RangeInclusive<usize>,
+    radix_bits: &[u32],
+) {
+    for max_error in max_errors {
+        let spline = GreedySplineCorridor::new(data, MaxError::new(max_error));
+        // each strategy finds the same positions
+        let radix_splines: Vec<RadixSpline> = radix_bits
+            .iter()
+            .flat_map(|&bits| {
+                [SearchStrategy::Window, SearchStrategy::BiasedProbe].map(|strategy| {
+                    RadixSpline::new(data, RadixBits::new(bits), MaxError::new(max_error))
+                        .with_search_strategy(strategy)
+                })
+            })
+            .collect();
+        for key in 0..=max_key + 1 {
+            // the expected position is the first occurrence of `key`
+            let first = data.partition_point(|&x| x < key);
+            let expected = if data.get(first) == Some(&key) {
+                Some(first)
+            } else {
+                None
+            };
+            let message = || format!("{:?} max_error={} key={}", data, max_error, key);
+
+            assert_eq!(spline.search(key), expected, "{}", message());
+
+            for radix_spline in &radix_splines {
+                assert_eq!(radix_spline.search(key), expected, "{}", message());
+            }
+        }
+    }
+}
+
+#[test]
+fn tiny_sequences() {
+    // the smallest inputs, with every radix bits (a shift of 0..=4 here) and more error bounds
+    for len in 1..=4 {
+        for_each_sorted(len, 15, &mut |data| {
+            check_with(data, 15, 0..=8, &[0, 1, 2, 3, 4, 5, 18])
+        });
+    }
+}
+
+#[test]
+fn short_sequences() {
+    for len in 1..=6 {
+        for_each_sorted(len, 15, &mut |data| check(data, 15));
+    }
+}
+
+#[test]
+fn long_sequences() {
+    // a subset of the domain, to keep `cargo test` fast: `long_sequences_full_domain` covers all of it
+    for len in 7..=8 {
+        for_each_sorted(len, 7, &mut |data| check(data, 7));
+    }
+}
+
+/// Lengths 7 and 8 over the whole domain, where the runs of duplicates and the boundaries are the most varied:
+/// about 660k sequences, which takes minutes without optimizations. CI runs it with `--release -- --include-ignored`.
+#[test]
+#[ignore = "slow without optimizations, run with `cargo test --release -- --include-ignored`"]
+fn long_sequences_full_domain() {
+    for len in 7..=8 {
+        for_each_sorted(len, 15, &mut |data| check(data, 15));
+    }
+}
+
+#[test]
+fn default_parameters_on_tiny_data() {
+    use crate::LearnedIndex;
+    use rand::Rng;
+
+    // `max_error` 32 is degenerate for all these lengths, and clamped below `len / 2`
+    let mut rng = rand::thread_rng();
+    for len in 3..=64 {
+        for max_key in [len as u64 / 2, len as u64, 4 * len as u64, 1 << 20] {
+            for _ in 0..5 {
+                let mut data: Vec<u64> = (0..len).map(|_| rng.gen_range(0..=max_key)).collect();
+                data.sort_unstable();
+                let radix_spline = RadixSpline::new(&data, RadixBits::DEFAULT, MaxError::DEFAULT);
+                assert!(LearnedIndex::max_error(&radix_spline) < (len / 2).max(1));
+                for (i, &key) in data.iter().enumerate() {
+                    let first = data.partition_point(|&x| x < key);
+                    assert_eq!(radix_spline.search(key), Some(first), "{:?} {}", data, i);
+                }
+                assert_eq!(radix_spline.search(max_key + 1), None);
+            }
+        }
+    }
+}
@@ -0,0 +1,161 @@
+//! # Prefix histogram
+//! Key and spline point counts per radix prefix, for hot-spot analysis.
+
+use crate::common::widen;
+use crate::radix::RadixSpline;
+
+/// The keys and spline points within one radix prefix.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PrefixBucket {
+    pub prefix: usize,
+    pub key_count: usize,
+    pub point_count: usize,
+}
+
+/// A prefix bucket with its probe count, i.e., how hot it is relative to the memory spent on it.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct HotBucket {
+    pub bucket: PrefixBucket,
+    pub probes: u64,
+    /// the share of probes divided by the share of spline points; > 1 means hotter than its memory
+    pub heat: f64,
+}
+
+/// Hot-spot report built from a probe-count histogram.
+pub struct HotColdReport {
+    pub buckets: Vec<HotBucket>,
+}
+
+impl HotColdReport {
+    /// the `n` buckets with the most probes
+    pub fn hottest(&self, n: usize) -> Vec<HotBucket> {
+        let mut buckets = self.buckets.clone();
+        buckets.sort_by(|a, b| {
+            b.probes
+                .cmp(&a.probes)
+                .then(a.bucket.prefix.cmp(&b.bucket.prefix))
+        });
+        buckets.truncate(n);
+        buckets
+    }
+
+    /// the `n` buckets with the most spline points
+    pub fn most_point_dense(&self, n: usize) -> Vec<HotBucket> {
+        let mut buckets = self.buckets.clone();
+        buckets.sort_by(|a, b| {
+            (b.bucket.point_count.cmp(&a.bucket.point_count))
+                .then(a.bucket.prefix.cmp(&b.bucket.prefix))
+        });
+        buckets.truncate(n);
+        buckets
+    }
+}
+
+impl<'a> RadixSpline<'a> {
+    /// Count keys (one pass over the data) and spline points (table deltas) for every radix prefix.
+    pub fn prefix_histogram(&self) -> Vec<PrefixBucket> {
+        let table = self.table();
+        let mut buckets: Vec<PrefixBucket> = table
+            .windows(2)
+            .enumerate()
+            .map(|(prefix, w)| PrefixBucket {
+                prefix,
+                key_count: 0,
+                point_count: widen(w[1] - w[0]),
+            })
+            .collect();
+
+        for &key in self.data() {
+            // all keys are within the domain
+            let prefix = self.prefix_of(key).unwrap();
+            buckets[prefix].key_count += 1;
+        }
+        buckets
+    }
+
+    /// Merge a probe-count histogram over the same prefix space (see `prefix_of`) into a hot/cold report.
+    pub fn hot_cold_report(&self, probes: &[u64]) -> HotColdReport {
+        let histogram = self.prefix_histogram();
+        assert_eq!(probes.len(), histogram.len());
+
+        let total_probes = probes.iter().sum::<u64>().max(1) as f64;
+        let total_points = histogram.iter().map(|b| b.point_count).sum::<usize>() as f64;
+        let buckets = histogram
+            .into_iter()
+            .zip(probes)
+            .map(|(bucket, &probes)| {
+                let probe_share = probes as f64 / total_probes;
+                // a bucket without points still costs a table entry
+                let point_share = bucket.point_count.max(1) as f64 / total_points;
+                HotBucket {
+                    bucket,
+                    probes,
+                    heat: probe_share / point_share,
+                }
+            })
+            .collect();
+        HotColdReport { buckets }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{MaxError, RadixBits};
+
+    fn key_counts(radix_spline: &RadixSpline) -> Vec<usize> {
+        radix_spline
+            .prefix_histogram()
+            .iter()
+            .map(|b| b.key_count)
+            .collect()
+    }
+
+    #[test]
+    fn prefix_histogram() {
+        let data: Vec<u64> = vec![0, 1, 2, 3, 8, 9, 15];
+
+        let radix_spline = RadixSpline::new(&data, RadixBits::new(1), MaxError::new(0));
+        assert_eq!(key_counts(&radix_spline), vec![4, 3]);
+
+        let radix_spline = RadixSpline::new(&data, RadixBits::new(2), MaxError::new(0));
+        assert_eq!(key_counts(&radix_spline), vec![4, 0, 2, 1]);
+
+        let radix_spline = RadixSpline::new(&data, RadixBits::new(4), MaxError::new(0));
+        let mut expected = vec![0; 16];
+        for &key in &data {
+            expected[key as usize] = 1;
+        }
+        assert_eq!(key_counts(&radix_spline), expected);
+
+        // every spline point is counted in its prefix
+        for radix_bits in [1, 2, 4, 18] {
+            let radix_spline =
+                RadixSpline::new(&data, RadixBits::new(radix_bits), MaxError::new(0));
+            let histogram = radix_spline.prefix_histogram();
+            let points: usize = histogram.iter().map(|b| b.point_count).sum();
+            assert_eq!(points, radix_spline.points().len());
+        }
+    }
+
+    #[test]
+    fn hot_cold_report() {
+        let data: Vec<u64> = (0..1000).map(|i| i * i).collect();
+        let radix_spline = RadixSpline::new(&data, RadixBits::new(4), MaxError::new(2));
+
+        let mut probes = vec![0; radix_spline.prefix_histogram().len()];
+        for key in data.iter().take(100) {
+            probes[radix_spline.prefix_of(*key).unwrap()] += 1;
+        }
+
+        let report = radix_spline.hot_cold_report(&probes);
+        let hottest = report.hottest(1);
+        assert_eq!(hottest[0].bucket.prefix, 0);
+        assert_eq!(hottest[0].probes, 100);
+        assert!(hottest[0].heat > 1.0);
+
+        let dense = report.most_point_dense(3);
+        assert!(dense[0].bucket.point_count >= dense[1].bucket.point_count);
+        assert!(dense[1].bucket.point_count >= dense[2].bucket.point_count);
+    }
+}
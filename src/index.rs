@@ -0,0 +1,115 @@
+//! # The common interface of the learned indexes
+//! The lookup contract shared by every index over sorted `u64` keys, which `testing::assert_conformance` checks.
+
+use std::ops::Range;
+
+use crate::owned::OwnedRadixSpline;
+use crate::radix::RadixSpline;
+use crate::spline_corridor::GreedySplineCorridor;
+
+/// A learned index over sorted (possibly repeated) `u64` keys, where positions refer to the sorted data.
+pub trait LearnedIndex {
+    /// The number of indexed keys.
+    fn len(&self) -> usize;
+
+    /// The position of the first occurrence of `key`, or `None` if it is absent (including keys outside `[min_key, max_key]`).
+    fn search(&self, key: u64) -> Option<usize>;
+
+    /// The position of the first key not less than `key`, i.e., `0` below the domain and `len()` above it.
+    fn lower_bound(&self, key: u64) -> usize;
+
+    /// The predicted position of `key`, or `None` outside `[min_key, max_key]`.
+    /// Some occurrence of a present key is within `max_error` positions of it.
+    fn predict(&self, key: u64) -> Option<usize>;
+
+    /// The error bound of `predict`.
+    fn max_error(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The half-open range of positions holding `key`, which is empty at `lower_bound(key)` if it is absent.
+    fn equal_range(&self, key: u64) -> Range<usize> {
+        let start = self.lower_bound(key);
+        let end = match key.checked_add(1) {
+            Some(next) => self.lower_bound(next),
+            None => self.len(),
+        };
+        start..end
+    }
+}
+
+impl<'a> LearnedIndex for RadixSpline<'a> {
+    fn len(&self) -> usize {
+        self.data().len()
+    }
+
+    fn search(&self, key: u64) -> Option<usize> {
+        RadixSpline::search(self, key)
+    }
+
+    fn lower_bound(&self, key: u64) -> usize {
+        self.lower_bound_position(key)
+    }
+
+    fn predict(&self, key: u64) -> Option<usize> {
+        self.prefix_of(key).map(|_| RadixSpline::predict(self, key))
+    }
+
+    fn max_error(&self) -> usize {
+        RadixSpline::max_error(self)
+    }
+
+    fn equal_range(&self, key: u64) -> Range<usize> {
+        RadixSpline::equal_range(self, key)
+    }
+}
+
+impl LearnedIndex for OwnedRadixSpline {
+    fn len(&self) -> usize {
+        LearnedIndex::len(&**self)
+    }
+
+    fn search(&self, key: u64) -> Option<usize> {
+        LearnedIndex::search(&**self, key)
+    }
+
+    fn lower_bound(&self, key: u64) -> usize {
+        LearnedIndex::lower_bound(&**self, key)
+    }
+
+    fn predict(&self, key: u64) -> Option<usize> {
+        LearnedIndex::predict(&**self, key)
+    }
+
+    fn max_error(&self) -> usize {
+        LearnedIndex::max_error(&**self)
+    }
+
+    fn equal_range(&self, key: u64) -> Range<usize> {
+        LearnedIndex::equal_range(&**self, key)
+    }
+}
+
+impl<'a> LearnedIndex for GreedySplineCorridor<'a> {
+    fn len(&self) -> usize {
+        self.data().len()
+    }
+
+    fn search(&self, key: u64) -> Option<usize> {
+        GreedySplineCorridor::search(self, key)
+    }
+
+    fn lower_bound(&self, key: u64) -> usize {
+        GreedySplineCorridor::lower_bound(self, key)
+    }
+
+    fn predict(&self, key: u64) -> Option<usize> {
+        GreedySplineCorridor::predict(self, key)
+    }
+
+    fn max_error(&self) -> usize {
+        GreedySplineCorridor::max_error(self)
+    }
+}
@@ -0,0 +1,386 @@
+//! # Floating point keys
+//! `f64_to_ordered_u64` maps an `f64` to a `u64` of the same order, so sorted floats, e.g., timestamps,
+//! can be indexed as `u64` keys: a positive float gets its sign bit set, and a negative one has all its bits flipped.
+//! The order is that of `f64::total_cmp`, i.e., `-0.0` just below `0.0`, and the infinities beyond all finite values.
+//!
+//! `F64RadixSpline` encodes the data and the queries transparently. It treats `-0.0` as `0.0`, as `<` does,
+//! and rejects NaN, which has no place in the order of `<`.
+//!
+//! ## Out-of-order keys
+//! Sensor data often has small glitches out of order. `MonotoneRepair` chooses to reject them, to clamp them
+//! to the previous key, or to drop them, and the `RepairReport` of the build tells what was repaired.
+
+use std::borrow::Cow;
+use std::ops::Range;
+
+use crate::error::{BuildError, DataError};
+use crate::params::{MaxError, RadixBits};
+use crate::radix::RadixSpline;
+
+const SIGN: u64 = 1 << 63;
+
+/// The `u64` of the same order as `value`, see the module documentation. `ordered_u64_to_f64` is the inverse.
+pub fn f64_to_ordered_u64(value: f64) -> u64 {
+    let bits = value.to_bits();
+    if bits & SIGN == 0 {
+        bits | SIGN
+    } else {
+        !bits
+    }
+}
+
+/// The `f64` of `f64_to_ordered_u64`, bit for bit.
+pub fn ordered_u64_to_f64(key: u64) -> f64 {
+    if key & SIGN == 0 {
+        f64::from_bits(!key)
+    } else {
+        f64::from_bits(key & !SIGN)
+    }
+}
+
+/// The key of `value` in `F64RadixSpline`, where `-0.0` is `0.0`, or `None` for NaN.
+fn encode(value: f64) -> Option<u64> {
+    // `-0.0 == 0.0`, so this also maps `-0.0` to `0.0`
+    let value = if value == 0.0 { 0.0 } else { value };
+    (!value.is_nan()).then(|| f64_to_ordered_u64(value))
+}
+
+/// How `F64RadixSpline::new` treats a key less than the one before it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MonotoneRepair {
+    /// fail the build with `DataError::NotSorted` at the first such key
+    #[default]
+    Strict,
+    /// replace the key with the previous one, so positions are those of `data`
+    ClampToPrevious,
+    /// build over the keys not less than every key kept before them, see `RepairReport::original_position`
+    DropViolations,
+}
+
+/// What `F64RadixSpline::new` repaired with its `MonotoneRepair`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RepairReport {
+    /// the keys clamped or dropped
+    pub repaired: usize,
+    /// the position in `data` of the first key out of order, if any
+    pub first_violation: Option<usize>,
+    // the position in `data` of each kept key, only if some were dropped
+    original_positions: Option<Vec<usize>>,
+}
+
+impl RepairReport {
+    /// The position in `data` of the key at `position` of the index, which differ only if keys were dropped.
+    /// `None` if `position` is out of bounds of the dropped index.
+    pub fn original_position(&self, position: usize) -> Option<usize> {
+        match &self.original_positions {
+            Some(positions) => positions.get(position).copied(),
+            None => Some(position),
+        }
+    }
+}
+
+/// A `RadixSpline` over sorted `f64` keys, which owns their `u64` encoding.
+pub struct F64RadixSpline {
+    index: RadixSpline<'static>,
+}
+
+impl F64RadixSpline {
+    /// `data` is sorted by `<`, but for the keys out of order that `repair` allows, and non-empty.
+    /// A NaN fails the build with `DataError::NaN` whatever `repair` is.
+    pub fn new(
+        data: &[f64],
+        num_radix_bits: RadixBits,
+        max_error: MaxError,
+        repair: MonotoneRepair,
+    ) -> Result<(Self, RepairReport), BuildError> {
+        let mut keys = Vec::with_capacity(data.len());
+        let mut report = RepairReport::default();
+        let mut original_positions = vec![];
+        for (position, &value) in data.iter().enumerate() {
+            let key = encode(value).ok_or(DataError::NaN { position })?;
+            match keys.last() {
+                Some(&last) if last > key => {
+                    report.first_violation.get_or_insert(position);
+                    match repair {
+                        MonotoneRepair::Strict => {
+                            return Err(DataError::NotSorted { position }.into())
+                        }
+                        MonotoneRepair::ClampToPrevious => {
+                            report.repaired += 1;
+                            keys.push(last);
+                        }
+                        MonotoneRepair::DropViolations => {
+                            if report.repaired == 0 {
+                                // the positions so far are kept as they are
+                                original_positions.extend(0..position);
+                            }
+                            report.repaired += 1;
+                        }
+                    }
+                }
+                _ => {
+                    if report.repaired > 0 && repair == MonotoneRepair::DropViolations {
+                        original_positions.push(position);
+                    }
+                    keys.push(key);
+                }
+            }
+        }
+        if report.repaired > 0 && repair == MonotoneRepair::DropViolations {
+            report.original_positions = Some(original_positions);
+        }
+        let index =
+            RadixSpline::try_from_cow(Cow::Owned(keys), num_radix_bits.get(), max_error.get())?;
+        Ok((F64RadixSpline { index }, report))
+    }
+
+    pub fn len(&self) -> usize {
+        self.index.data().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The position of the first occurrence of `key`, where `-0.0` finds `0.0`. NaN is never found.
+    pub fn search(&self, key: f64) -> Option<usize> {
+        self.index.search(encode(key)?)
+    }
+
+    /// The positions of the keys within `lo..=hi`, empty if `lo > hi`, or if a bound is NaN.
+    pub fn range(&self, lo: f64, hi: f64) -> Range<usize> {
+        match (encode(lo), encode(hi)) {
+            (Some(lo), Some(hi)) => self.index.range(lo..=hi),
+            _ => 0..0,
+        }
+    }
+
+    /// The key at `pos`, or `None` if `pos` is out of bounds. A `-0.0` of the data is `0.0` here.
+    pub fn key_at_pos(&self, pos: usize) -> Option<f64> {
+        self.index.key_at_pos(pos).map(ordered_u64_to_f64)
+    }
+
+    /// The index over the encoded keys, e.g., to query it with `f64_to_ordered_u64` keys.
+    pub fn index(&self) -> &RadixSpline<'static> {
+        &self.index
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::Rng;
+
+    const SPECIAL: [f64; 12] = [
+        f64::NEG_INFINITY,
+        f64::MIN,
+        -1.5,
+        -f64::MIN_POSITIVE,
+        -5e-324,
+        -0.0,
+        0.0,
+        5e-324,
+        f64::MIN_POSITIVE,
+        1.5,
+        f64::MAX,
+        f64::INFINITY,
+    ];
+
+    #[test]
+    fn round_trip() {
+        let mut rng = rand::thread_rng();
+        let values = SPECIAL
+            .into_iter()
+            .chain([f64::NAN, -f64::NAN])
+            .chain((0..100_000).map(|_| f64::from_bits(rng.gen())));
+        for value in values {
+            let decoded = ordered_u64_to_f64(f64_to_ordered_u64(value));
+            assert_eq!(decoded.to_bits(), value.to_bits(), "{}", value);
+        }
+        for key in (0..100_000)
+            .map(|_| rng.gen())
+            .chain([0, SIGN - 1, SIGN, u64::MAX])
+        {
+            assert_eq!(f64_to_ordered_u64(ordered_u64_to_f64(key)), key);
+        }
+    }
+
+    /// The order of the keys is `f64::total_cmp`, so that of `<` but for NaN and the two zeros.
+    #[test]
+    fn ordering() {
+        assert!(SPECIAL
+            .windows(2)
+            .all(|w| f64_to_ordered_u64(w[0]) < f64_to_ordered_u64(w[1])));
+        let mut rng = rand::thread_rng();
+        for _ in 0..100_000 {
+            let (a, b) = (f64::from_bits(rng.gen()), f64::from_bits(rng.gen()));
+            let (x, y) = (f64_to_ordered_u64(a), f64_to_ordered_u64(b));
+            assert_eq!(x.cmp(&y), a.total_cmp(&b), "{} vs {}", a, b);
+            if !a.is_nan() && !b.is_nan() && a != b {
+                assert_eq!(x < y, a < b, "{} vs {}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn f64_radix_spline() {
+        let mut rng = rand::thread_rng();
+        let mut data: Vec<f64> = (0..10_000).map(|_| rng.gen_range(-1e6..1e6)).collect();
+        data.extend(SPECIAL);
+        data.extend([0.0, -0.0, 1.5, 1.5]);
+        data.sort_by(f64::total_cmp);
+        // sorted by `<`, with a `0.0` before a `-0.0`
+        let zero = data.iter().position(|&x| x == 0.0).unwrap();
+        data[zero..zero + 4].copy_from_slice(&[0.0, -0.0, 0.0, -0.0]);
+        let (index, report) = F64RadixSpline::new(
+            &data,
+            RadixBits::new(12),
+            MaxError::new(16),
+            MonotoneRepair::Strict,
+        )
+        .unwrap();
+        assert_eq!(report, RepairReport::default());
+        assert_eq!(index.len(), data.len());
+
+        let probes = data.iter().copied().chain([-2e6, 2e6, 0.25, -0.25]);
+        for key in probes {
+            let first = data.partition_point(|&x| x < key);
+            let end = data.partition_point(|&x| x <= key);
+            let expected = (first < end).then_some(first);
+            assert_eq!(index.search(key), expected, "search({})", key);
+            assert_eq!(index.range(key, key), first..end, "range({})", key);
+        }
+        assert_eq!(index.search(-0.0), Some(zero));
+        assert_eq!(index.range(-0.0, 0.0), zero..zero + 4);
+        assert_eq!(index.range(f64::NEG_INFINITY, f64::INFINITY), 0..data.len());
+        assert_eq!(
+            index.range(-1.5, 1.5),
+            data.partition_point(|&x| x < -1.5)..data.partition_point(|&x| x <= 1.5)
+        );
+        assert_eq!(index.key_at_pos(0), Some(f64::NEG_INFINITY));
+        assert_eq!(
+            index.key_at_pos(zero + 1).map(f64::to_bits),
+            Some(0f64.to_bits())
+        );
+        assert_eq!(index.key_at_pos(data.len()), None);
+
+        assert_eq!(index.search(f64::NAN), None);
+        assert!(index.range(f64::NAN, 0.0).is_empty());
+        assert!(index.range(1.0, -1.0).is_empty());
+    }
+
+    #[test]
+    fn rejected() {
+        let new = |data: &[f64]| {
+            F64RadixSpline::new(
+                data,
+                RadixBits::new(8),
+                MaxError::new(4),
+                MonotoneRepair::Strict,
+            )
+            .err()
+        };
+        assert_eq!(
+            new(&[1.0, f64::NAN, 2.0]),
+            Some(BuildError::Data(DataError::NaN { position: 1 }))
+        );
+        assert_eq!(
+            new(&[-f64::NAN]),
+            Some(BuildError::Data(DataError::NaN { position: 0 }))
+        );
+        assert_eq!(
+            new(&[1.0, -1.0]),
+            Some(BuildError::Data(DataError::NotSorted { position: 1 }))
+        );
+        assert_eq!(
+            new(&[]),
+            Some(BuildError::Data(DataError::TooFewKeys { len: 0 }))
+        );
+        assert!(new(&[-0.0, 0.0, -0.0]).is_none());
+    }
+
+    fn build(data: &[f64], repair: MonotoneRepair) -> (F64RadixSpline, RepairReport) {
+        F64RadixSpline::new(data, RadixBits::new(10), MaxError::new(8), repair).unwrap()
+    }
+
+    /// Rising timestamps with a few isolated keys jittered below the one before them.
+    #[test]
+    fn isolated_glitches() {
+        let mut data: Vec<f64> = (0..5_000).map(|i| i as f64 * 0.5).collect();
+        let glitches = [17, 400, 401, 2_500, 4_999];
+        for &i in &glitches {
+            data[i] -= 1.25;
+        }
+        let strict = F64RadixSpline::new(
+            &data,
+            RadixBits::new(10),
+            MaxError::new(8),
+            MonotoneRepair::Strict,
+        );
+        assert_eq!(
+            strict.err(),
+            Some(BuildError::Data(DataError::NotSorted { position: 17 }))
+        );
+
+        let (clamped, report) = build(&data, MonotoneRepair::ClampToPrevious);
+        assert_eq!(report.repaired, glitches.len());
+        assert_eq!(report.first_violation, Some(17));
+        assert_eq!(clamped.len(), data.len());
+        for (i, &value) in data.iter().enumerate() {
+            // a glitch takes the key of the previous one, and positions are those of `data`
+            let expected = if glitches.contains(&i) {
+                clamped.key_at_pos(i - 1).unwrap()
+            } else {
+                value
+            };
+            assert_eq!(clamped.key_at_pos(i), Some(expected), "{}", i);
+            assert_eq!(report.original_position(i), Some(i));
+        }
+        assert_eq!(clamped.search(data[17]), None);
+        assert_eq!(clamped.range(data[16], data[16]), 16..18);
+
+        let (dropped, report) = build(&data, MonotoneRepair::DropViolations);
+        assert_eq!(report.repaired, glitches.len());
+        assert_eq!(dropped.len(), data.len() - glitches.len());
+        for i in (0..data.len()).filter(|i| !glitches.contains(i)) {
+            let position = dropped.search(data[i]).unwrap();
+            assert_eq!(report.original_position(position), Some(i));
+        }
+        for &i in &glitches {
+            assert_eq!(dropped.search(data[i]), None);
+        }
+        assert_eq!(report.original_position(dropped.len()), None);
+    }
+
+    /// A long section decreasing from a peak: dropping keeps only the keys above the peak after it.
+    #[test]
+    fn long_decreasing_section() {
+        let mut data: Vec<f64> = (0..1_000).map(|i| i as f64).collect();
+        // 2000 keys decreasing from 999 down to 0, then rising again from 1000
+        data.extend((0..2_000).map(|i| 999.0 - i as f64 / 2.0));
+        data.extend((1_000..3_000).map(|i| i as f64));
+        let (dropped, report) = build(&data, MonotoneRepair::DropViolations);
+        // the decreasing section keeps only its first key, equal to the peak
+        assert_eq!(report.repaired, 1_999);
+        assert_eq!(report.first_violation, Some(1_001));
+        assert_eq!(dropped.len(), data.len() - 1_999);
+        for position in 0..dropped.len() {
+            let original = report.original_position(position).unwrap();
+            assert_eq!(dropped.key_at_pos(position), Some(data[original]));
+        }
+        assert_eq!(report.original_position(999), Some(999));
+        assert_eq!(report.original_position(1_000), Some(1_000));
+        assert_eq!(report.original_position(1_001), Some(3_000));
+        assert_eq!(dropped.search(999.0), Some(999));
+        assert_eq!(dropped.range(999.0, 999.0), 999..1_001);
+        assert_eq!(
+            report.original_position(dropped.search(2_500.0).unwrap()),
+            Some(data.len() - 500)
+        );
+        assert_eq!(dropped.search(500.5), None);
+
+        let (clamped, report) = build(&data, MonotoneRepair::ClampToPrevious);
+        assert_eq!(report.repaired, 1_999);
+        assert_eq!(clamped.range(999.0, 999.0), 999..3_000);
+    }
+}
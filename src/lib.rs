@@ -1,6 +1,69 @@
+#[cfg(feature = "accuracy-study")]
+mod accuracy;
+mod blob;
+mod builder;
+mod block;
+mod buffer;
 mod common;
+mod cpp;
+mod cost;
+pub mod datagen;
+mod drift;
+mod error;
+#[cfg(test)]
+mod exhaustive;
+mod histogram;
+mod index;
+pub mod key;
+mod lsm;
+mod model;
+mod mini;
+mod owned;
+mod paged;
+mod params;
+mod partition;
 mod radix;
+mod rle;
+mod scan;
+mod scratch;
+mod sentinel;
+mod set;
+mod sketch;
 mod spline_corridor;
+mod table;
+mod tail;
+mod validity;
+mod verify;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
 
-pub use radix::RadixSpline;
-pub use spline_corridor::GreedySplineCorridor;
+#[cfg(feature = "accuracy-study")]
+pub use accuracy::AccuracyReport;
+#[cfg(feature = "mmap")]
+pub use blob::{MmapKeys, MmappedIndex};
+pub use block::BlockIndex;
+pub use builder::StreamingBuilder;
+pub use common::Key;
+pub use cost::{ProbeCost, ScanCost};
+pub use drift::DriftReport;
+pub use error::{BuildError, ConfigError, DataError, DeserializeError};
+pub use histogram::{HotBucket, HotColdReport, PrefixBucket};
+pub use index::LearnedIndex;
+pub use key::{F64RadixSpline, MonotoneRepair, RepairReport};
+pub use lsm::{merge_lookups, merge_range, MergeIter, MultiRunReader, RangeIter, RunId, SortedRun};
+pub use mini::MiniModel;
+pub use model::RadixSplineModel;
+pub use owned::OwnedRadixSpline;
+pub use paged::{PagedLookup, PositionEstimator};
+pub use params::{MaxError, RadixBits};
+pub use radix::{LongRun, RadixSpline, SearchBound, SearchStrategy};
+pub use rle::RleRadixSpline;
+pub use scan::ScanState;
+pub use scratch::BuildScratch;
+pub use sentinel::TrimmedRadixSpline;
+pub use set::{Difference, FrozenSet, Intersection, Union};
+pub use spline_corridor::{GreedySplineCorridor, GreedySplineCorridorU64, SplineKey};
+pub use table::TableLayout;
+pub use tail::{AppendedRadixSpline, TailPolicy};
+pub use validity::{all_valid_bitmap, ValidityView};
+pub use verify::{ErrorReport, VerifyReport};
@@ -0,0 +1,379 @@
+//! # Multi-run reads
+//! An LSM-style read path over several sorted runs: newer runs shadow older ones, and a tombstone value deletes a key.
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use crate::owned::OwnedRadixSpline;
+use crate::params::{MaxError, RadixBits};
+use crate::radix::RadixSpline;
+
+/// The index of a run in a list of runs ordered from oldest to newest, i.e., a larger `RunId` is newer.
+pub type RunId = usize;
+
+/// Resolve the probe results of one key over several runs: the newest hit wins, unless it is a tombstone.
+/// `results` holds the position found in each probed run (in any order), and `tombstones(run, pos)` tells whether the entry is a deletion.
+pub fn merge_lookups(
+    results: &[(RunId, Option<usize>)],
+    tombstones: &impl Fn(RunId, usize) -> bool,
+) -> Option<(RunId, usize)> {
+    let (run, pos) = results
+        .iter()
+        .filter_map(|&(run, pos)| pos.map(|pos| (run, pos)))
+        .max_by_key(|&(run, _)| run)?;
+    if tombstones(run, pos) {
+        None
+    } else {
+        Some((run, pos))
+    }
+}
+
+/// Merge the keys within `[lo, hi]` of several indexed runs (oldest first) in global key order.
+/// A key present in several runs is yielded once from the newest run, and entries for which `tombstones(run, pos)` holds are skipped.
+/// Each run seeks to `lo` with its own index.
+pub fn merge_range<'a, F>(
+    runs: &[&'a RadixSpline<'_>],
+    lo: u64,
+    hi: u64,
+    tombstones: F,
+) -> MergeIter<'a, F>
+where
+    F: Fn(RunId, usize) -> bool,
+{
+    let mut heap = BinaryHeap::with_capacity(runs.len());
+    for (run, index) in runs.iter().enumerate() {
+        let pos = index.lower_bound_position(lo);
+        if let Some(&key) = index.data().get(pos) {
+            if key <= hi {
+                heap.push(Reverse((key, Reverse(run), pos)));
+            }
+        }
+    }
+    MergeIter {
+        runs: runs.iter().map(|index| index.data()).collect(),
+        heap,
+        hi,
+        tombstones,
+    }
+}
+
+/// A sorted run: distinct sorted keys indexed by a radix spline, and one value per key.
+pub struct SortedRun<V> {
+    index: OwnedRadixSpline,
+    values: Vec<V>,
+}
+
+impl<V> SortedRun<V> {
+    /// `keys` is sorted, distinct and non-empty, and `values[i]` belongs to `keys[i]`.
+    pub fn new(keys: Vec<u64>, values: Vec<V>) -> Self {
+        assert_eq!(keys.len(), values.len());
+        assert!(
+            keys.windows(2).all(|w| w[0] < w[1]),
+            "keys must be sorted and distinct"
+        );
+        SortedRun {
+            index: OwnedRadixSpline::new(keys, RadixBits::DEFAULT, MaxError::DEFAULT),
+            values,
+        }
+    }
+
+    fn keys(&self) -> &[u64] {
+        self.index.data()
+    }
+}
+
+/// `MultiRunReader` answers point lookups and range scans over runs ordered from oldest to newest.
+pub struct MultiRunReader<V> {
+    runs: Vec<SortedRun<V>>, // oldest first
+    tombstone: V,
+}
+
+impl<V: PartialEq> MultiRunReader<V> {
+    /// A value equal to `tombstone` marks its key as deleted.
+    pub fn new(tombstone: V) -> Self {
+        MultiRunReader {
+            runs: vec![],
+            tombstone,
+        }
+    }
+
+    /// Add a run, which is newer than all existing runs.
+    pub fn push_run(&mut self, run: SortedRun<V>) {
+        self.runs.push(run);
+    }
+
+    pub fn num_runs(&self) -> usize {
+        self.runs.len()
+    }
+
+    /// Look up `key` by probing runs from the newest to the oldest, stopping at the first hit.
+    pub fn get(&self, key: u64) -> Option<&V> {
+        for run in self.runs.iter().rev() {
+            if let Some(idx) = run.index.search(key) {
+                let value = &run.values[idx];
+                return if *value == self.tombstone {
+                    None
+                } else {
+                    Some(value)
+                };
+            }
+        }
+        None
+    }
+
+    /// Scan keys within `[lo, hi]` across all runs in key order.
+    /// A key present in several runs is yielded once with its newest value, and deleted keys are skipped.
+    /// Each run seeks to `lo` with its own index.
+    pub fn range(&self, lo: u64, hi: u64) -> RangeIter<'_, V> {
+        let cursors = self
+            .runs
+            .iter()
+            .map(|run| run.index.lower_bound(lo))
+            .collect();
+        RangeIter {
+            reader: self,
+            cursors,
+            hi,
+        }
+    }
+}
+
+/// A merged iterator over several runs, with one cursor per run.
+pub struct RangeIter<'a, V> {
+    reader: &'a MultiRunReader<V>,
+    cursors: Vec<usize>,
+    hi: u64, // inclusive
+}
+
+impl<'a, V: PartialEq> Iterator for RangeIter<'a, V> {
+    type Item = (u64, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let runs = &self.reader.runs;
+        loop {
+            // the smallest key under all cursors
+            let key = runs
+                .iter()
+                .zip(&self.cursors)
+                .filter_map(|(run, &cursor)| run.keys().get(cursor).copied())
+                .filter(|&key| key <= self.hi)
+                .min()?;
+
+            // every run holding `key` moves on, and the newest one wins
+            let mut value = None;
+            for (run, cursor) in runs.iter().zip(self.cursors.iter_mut()) {
+                if run.keys().get(*cursor) == Some(&key) {
+                    value = Some(&run.values[*cursor]);
+                    *cursor += 1;
+                }
+            }
+
+            let value = value.unwrap();
+            if *value != self.reader.tombstone {
+                return Some((key, value));
+            }
+        }
+    }
+}
+
+/// A k-way merge over several runs, see `merge_range`.
+pub struct MergeIter<'a, F> {
+    runs: Vec<&'a [u64]>,
+    // the head of each unfinished run, the smallest key first, and the newest run first among equal keys
+    heap: BinaryHeap<Reverse<(u64, Reverse<RunId>, usize)>>,
+    hi: u64, // inclusive
+    tombstones: F,
+}
+
+impl<'a, F> MergeIter<'a, F> {
+    /// move the cursor of `run` past `pos`
+    fn advance(&mut self, run: RunId, pos: usize) {
+        if let Some(&key) = self.runs[run].get(pos + 1) {
+            if key <= self.hi {
+                self.heap.push(Reverse((key, Reverse(run), pos + 1)));
+            }
+        }
+    }
+}
+
+impl<'a, F: Fn(RunId, usize) -> bool> Iterator for MergeIter<'a, F> {
+    /// `(run, pos, key)` of the newest entry of each key
+    type Item = (RunId, usize, u64);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let Reverse((key, Reverse(run), pos)) = self.heap.pop()?;
+            self.advance(run, pos);
+            // the older entries of `key` (and repeats within a run) are shadowed
+            while let Some(&Reverse((next, Reverse(older), older_pos))) = self.heap.peek() {
+                if next != key {
+                    break;
+                }
+                self.heap.pop();
+                self.advance(older, older_pos);
+            }
+            if !(self.tombstones)(run, pos) {
+                return Some((run, pos, key));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{MaxError, RadixBits};
+    use std::collections::BTreeMap;
+
+    const TOMBSTONE: u64 = u64::MAX;
+
+    fn reader() -> MultiRunReader<u64> {
+        let mut reader = MultiRunReader::new(TOMBSTONE);
+        // the oldest run: every even key
+        let keys: Vec<u64> = (0..1000).map(|i| i * 2).collect();
+        let values = keys.iter().map(|&k| k * 10).collect();
+        reader.push_run(SortedRun::new(keys, values));
+        // overwrites multiples of 3, and deletes multiples of 7
+        let keys: Vec<u64> = (0..2000).filter(|k| k % 3 == 0 || k % 7 == 0).collect();
+        let values = keys
+            .iter()
+            .map(|&k| if k % 7 == 0 { TOMBSTONE } else { k * 100 })
+            .collect();
+        reader.push_run(SortedRun::new(keys, values));
+        // the newest run: revives multiples of 49
+        let keys: Vec<u64> = (0..2000).filter(|k| k % 49 == 0).collect();
+        let values = keys.iter().map(|&k| k + 1).collect();
+        reader.push_run(SortedRun::new(keys, values));
+        reader
+    }
+
+    fn expected() -> BTreeMap<u64, u64> {
+        let mut expected = BTreeMap::new();
+        for k in (0..2000).filter(|k| k % 2 == 0) {
+            expected.insert(k, k * 10);
+        }
+        for k in (0..2000).filter(|k| k % 3 == 0 || k % 7 == 0) {
+            if k % 7 == 0 {
+                expected.remove(&k);
+            } else {
+                expected.insert(k, k * 100);
+            }
+        }
+        for k in (0..2000).filter(|k| k % 49 == 0) {
+            expected.insert(k, k + 1);
+        }
+        expected
+    }
+
+    #[test]
+    fn get() {
+        let reader = reader();
+        let expected = expected();
+
+        for key in 0..2100 {
+            assert_eq!(reader.get(key), expected.get(&key), "{}", key);
+        }
+    }
+
+    #[test]
+    fn range() {
+        let reader = reader();
+        let expected = expected();
+
+        for (lo, hi) in [
+            (0, 2100),
+            (0, 0),
+            (14, 14),
+            (15, 15),
+            (100, 500),
+            (1999, 3000),
+            (1998, 1998),
+            (1, 97),
+            (2000, 2100),
+        ] {
+            let found: Vec<(u64, u64)> = reader.range(lo, hi).map(|(k, &v)| (k, v)).collect();
+            let wanted: Vec<(u64, u64)> = expected.range(lo..=hi).map(|(&k, &v)| (k, v)).collect();
+            assert_eq!(found, wanted, "{}..={}", lo, hi);
+        }
+        assert_eq!(reader.range(10, 5).count(), 0);
+    }
+
+    #[test]
+    fn merge_lookups() {
+        let tombstones = |run: RunId, pos: usize| run == 1 && pos == 7;
+
+        assert_eq!(super::merge_lookups(&[], &tombstones), None);
+        assert_eq!(
+            super::merge_lookups(&[(0, None), (1, None)], &tombstones),
+            None
+        );
+        assert_eq!(
+            super::merge_lookups(&[(2, Some(3)), (0, Some(5)), (1, None)], &tombstones),
+            Some((2, 3))
+        );
+        assert_eq!(
+            super::merge_lookups(&[(0, Some(5)), (1, Some(8))], &tombstones),
+            Some((1, 8))
+        );
+        // the newest hit is deleted, which shadows the older one
+        assert_eq!(
+            super::merge_lookups(&[(0, Some(5)), (1, Some(7))], &tombstones),
+            None
+        );
+    }
+
+    #[test]
+    fn merge_range() {
+        use rand::{distributions::Uniform, Rng};
+
+        let mut rng = rand::thread_rng();
+        // overlapping runs with repeats, where the last entry of a run in `deleted` is a tombstone
+        let runs: Vec<Vec<u64>> = (0..5)
+            .map(|_| {
+                let mut keys: Vec<u64> = (&mut rng)
+                    .sample_iter(&Uniform::from(0..3000))
+                    .take(1000)
+                    .collect();
+                keys.sort_unstable();
+                keys
+            })
+            .collect();
+        let deleted: Vec<Vec<bool>> = runs
+            .iter()
+            .map(|keys| keys.iter().map(|_| rng.gen_ratio(1, 10)).collect())
+            .collect();
+        let indexes: Vec<RadixSpline> = runs
+            .iter()
+            .map(|keys| RadixSpline::new(keys, RadixBits::new(8), MaxError::new(4)))
+            .collect();
+        let indexes: Vec<&RadixSpline> = indexes.iter().collect();
+
+        // newest first, and the first occurrence within a run
+        let mut expected: BTreeMap<u64, (RunId, usize)> = BTreeMap::new();
+        for (run, keys) in runs.iter().enumerate() {
+            for (pos, &key) in keys.iter().enumerate() {
+                if pos == 0 || keys[pos - 1] != key {
+                    expected.insert(key, (run, pos));
+                }
+            }
+        }
+
+        for (lo, hi) in [
+            (0, 3000),
+            (0, 0),
+            (100, 100),
+            (1000, 1500),
+            (2990, 5000),
+            (7, 3),
+        ] {
+            let found: Vec<(RunId, usize, u64)> =
+                super::merge_range(&indexes, lo, hi, |run, pos| deleted[run][pos]).collect();
+            let wanted: Vec<(RunId, usize, u64)> = expected
+                .iter()
+                .filter(|(&key, &(run, pos))| lo <= key && key <= hi && !deleted[run][pos])
+                .map(|(&key, &(run, pos))| (run, pos, key))
+                .collect();
+            assert_eq!(found, wanted, "{}..={}", lo, hi);
+        }
+    }
+}
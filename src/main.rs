@@ -1,9 +1,16 @@
+use radix_spline::datagen::{sample_probes, uniform_keys, ProbeSampling};
+use radix_spline::BuildError;
 use radix_spline::GreedySplineCorridor;
+use radix_spline::MaxError;
+use radix_spline::RadixBits;
 use radix_spline::RadixSpline;
+use std::error::Error;
+use std::hint::black_box;
+use std::process::exit;
 use std::time::Instant;
 
 use std::fs::File;
-use std::io::{BufReader, Read};
+use std::io::{self, BufReader, BufWriter, Read, Write};
 
 fn load_data(path: &str) -> Vec<u64> {
     let mut buffer = [0u8; std::mem::size_of::<u64>()];
@@ -20,17 +27,186 @@ fn load_data(path: &str) -> Vec<u64> {
     data
 }
 
+/// The options of `sosd-bench`.
+#[derive(Debug, PartialEq)]
+struct SosdOptions {
+    data: String,
+    probes: String,
+    radix_bits: u32,
+    max_error: usize,
+    bounds: Option<String>,
+}
+
+impl SosdOptions {
+    fn parse(args: &[String]) -> Result<Self, String> {
+        let mut paths = vec![];
+        let mut options = SosdOptions {
+            data: String::new(),
+            probes: String::new(),
+            radix_bits: 18,
+            max_error: 32,
+            bounds: None,
+        };
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or(format!("{} needs a value", arg));
+            match arg.as_str() {
+                "--radix-bits" => {
+                    options.radix_bits = value()?.parse().map_err(|_| "bad --radix-bits")?
+                }
+                "--max-error" => {
+                    options.max_error = value()?.parse().map_err(|_| "bad --max-error")?
+                }
+                "--bounds" => options.bounds = Some(value()?.clone()),
+                _ if arg.starts_with("--") => return Err(format!("unknown option {}", arg)),
+                _ => paths.push(arg.clone()),
+            }
+        }
+        let [data, probes] = <[String; 2]>::try_from(paths)
+            .map_err(|_| "expected the <data> and <probes> paths".to_string())?;
+        options.data = data;
+        options.probes = probes;
+        Ok(options)
+    }
+}
+
+/// Run the protocol of the SOSD benchmark: build the index, then for each probe get its search bound from the index
+/// and resolve the position with a binary search within the bound.
+/// It prints SOSD's result line, `RESULT: <index>,<variant>,<ns per lookup>,<size in bytes>,<build ns>,<searcher>`,
+/// and writes the `key,lo,hi,position` of each probe to `bounds`, where `lo..hi` is the search bound.
+fn sosd_bench(
+    data: &[u64],
+    probes: &[u64],
+    radix_bits: u32,
+    max_error: usize,
+    bounds: Option<&mut dyn Write>,
+    mut out: impl Write,
+) -> Result<(), Box<dyn Error>> {
+    let params = (
+        RadixBits::try_from(radix_bits).map_err(BuildError::from)?,
+        MaxError::try_from(max_error).map_err(BuildError::from)?,
+    );
+    let start = Instant::now();
+    let radix_spline = RadixSpline::try_new(data, params.0, params.1)?;
+    let build_nanos = start.elapsed().as_nanos();
+
+    let resolve = |key: u64| {
+        let bound = radix_spline.search_bound(key);
+        let position = bound.start + data[bound.start..bound.stop].partition_point(|&x| x < key);
+        (bound, position)
+    };
+    let start = Instant::now();
+    let mut sum = 0usize;
+    for &key in probes {
+        sum = sum.wrapping_add(resolve(black_box(key)).1);
+    }
+    black_box(sum);
+    let lookup_nanos = start.elapsed().as_nanos() / probes.len().max(1) as u128;
+
+    // validate the positions and write the bounds outside of the timed loop
+    let mut bounds = bounds;
+    if let Some(bounds) = bounds.as_mut() {
+        writeln!(bounds, "key,lo,hi,position")?;
+    }
+    for &key in probes {
+        let (bound, position) = resolve(key);
+        if data.get(position) != Some(&key) {
+            return Err(format!("the probe {} is not in the data", key).into());
+        }
+        if let Some(bounds) = bounds.as_mut() {
+            writeln!(
+                bounds,
+                "{},{},{},{}",
+                key, bound.start, bound.stop, position
+            )?;
+        }
+    }
+    if let Some(bounds) = bounds {
+        bounds.flush()?;
+    }
+
+    writeln!(
+        out,
+        "RESULT: RadixSpline,{} {},{},{},{},BinarySearch",
+        radix_bits,
+        max_error,
+        lookup_nanos,
+        radix_spline.size_in_bytes(),
+        build_nanos
+    )?;
+    Ok(())
+}
+
+/// The exit code of a failed `sosd-bench`: 3 if other parameters may fix it, 4 for bad data, and 1 otherwise.
+fn exit_code(error: &(dyn Error + 'static)) -> i32 {
+    match error.downcast_ref::<BuildError>() {
+        Some(error) if error.is_retryable_with_new_config() => 3,
+        Some(_) => 4,
+        None => 1,
+    }
+}
+
 fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.get(1).map(String::as_str) == Some("sosd-bench") {
+        let options = SosdOptions::parse(&args[2..]).unwrap_or_else(|message| {
+            eprintln!("{}", message);
+            eprintln!(
+                "Usage: {} sosd-bench <data> <probes> [--radix-bits N] [--max-error M] [--bounds <csv>]",
+                args[0]
+            );
+            exit(2);
+        });
+        let data = load_data(&options.data);
+        let probes = load_data(&options.probes);
+        let mut bounds = options
+            .bounds
+            .map(|path| BufWriter::new(File::create(path).expect("Unable to create file")));
+        let bounds = bounds.as_mut().map(|bounds| bounds as &mut dyn Write);
+        let result = sosd_bench(
+            &data,
+            &probes,
+            options.radix_bits,
+            options.max_error,
+            bounds,
+            io::stdout(),
+        );
+        if let Err(error) = result {
+            eprintln!("{}", error);
+            exit(exit_code(&*error));
+        }
+        return;
+    }
+
+    if args.get(1).map(String::as_str) == Some("random") {
+        println!("generate data...");
+        let (data, keys) = random_data_keys(10000000, 20000);
+        bench(&data, &keys);
+        return;
+    }
+
+    // mapped in place, rather than copied into a second 1.6 GB of memory
+    #[cfg(feature = "mmap")]
+    let data = unsafe { radix_spline::MmapKeys::open("data/fb_200M_uint64") }
+        .expect("Unable to open file");
+    #[cfg(not(feature = "mmap"))]
     let data = load_data("data/fb_200M_uint64");
     println!("load data...");
     let keys = load_data("data/fb_20K_unit64");
     bench(&data, &keys);
-    // let radix_spline = RadixSpline::default(&data);
+    // let radix_spline = RadixSpline::new(&data, RadixBits::DEFAULT, MaxError::DEFAULT);
+}
+
+/// Uniform keys, and probes of distinct keys, so duplicated keys are not probed more often than others.
+fn random_data_keys(len: usize, num_probes: usize) -> (Vec<u64>, Vec<u64>) {
+    let data = uniform_keys(len, 100000000, 42);
+    let keys = sample_probes(&data, num_probes, ProbeSampling::DistinctKeys, 42);
+    (data, keys)
 }
 
-fn bench(data: &Vec<u64>, keys: &Vec<u64>) {
-    let spline = GreedySplineCorridor::default(data);
-    let radix_spline = RadixSpline::default(data);
+fn bench(data: &[u64], keys: &[u64]) {
+    let spline = GreedySplineCorridor::new(data, MaxError::DEFAULT);
+    let radix_spline = RadixSpline::new(data, RadixBits::DEFAULT, MaxError::DEFAULT);
     let mut binary_total = 0;
     let mut spline_total = 0;
     let mut radix_spline_total = 0;
@@ -70,3 +246,132 @@ fn bench(data: &Vec<u64>, keys: &Vec<u64>) {
         radix_spline_total / keys.len() as u128
     );
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn args(args: &str) -> Vec<String> {
+        args.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn exit_codes() {
+        let run = |data: &[u64], radix_bits| {
+            let error = sosd_bench(data, &[], radix_bits, 32, None, &mut vec![]).unwrap_err();
+            exit_code(&*error)
+        };
+        assert_eq!(run(&[0, u64::MAX], 32), 3);
+        assert_eq!(run(&[0, u64::MAX], 64), 3);
+        assert_eq!(run(&[2, 1], 18), 4);
+        assert_eq!(run(&[], 18), 4);
+    }
+
+    #[test]
+    fn random_probes_are_present() {
+        let (data, keys) = random_data_keys(10000, 1000);
+        assert_eq!((data.len(), keys.len()), (10000, 1000));
+        assert!(data.windows(2).all(|w| w[0] <= w[1]));
+        assert!(keys.iter().all(|key| data.binary_search(key).is_ok()));
+        bench(&data, &keys);
+    }
+
+    #[test]
+    fn parse_options() {
+        let options = SosdOptions::parse(&args("keys probes --max-error 8")).unwrap();
+        assert_eq!(
+            options,
+            SosdOptions {
+                data: "keys".to_string(),
+                probes: "probes".to_string(),
+                radix_bits: 18,
+                max_error: 8,
+                bounds: None,
+            }
+        );
+        let options =
+            SosdOptions::parse(&args("--radix-bits 20 keys --bounds out.csv probes")).unwrap();
+        assert_eq!(options.radix_bits, 20);
+        assert_eq!(options.bounds.as_deref(), Some("out.csv"));
+
+        assert!(SosdOptions::parse(&args("keys")).is_err());
+        assert!(SosdOptions::parse(&args("keys probes --max-error")).is_err());
+        assert!(SosdOptions::parse(&args("keys probes --radix-bits x")).is_err());
+        assert!(SosdOptions::parse(&args("keys probes --unknown 1")).is_err());
+    }
+
+    /// Write `keys` in the SOSD layout, i.e., the count followed by the keys as little-endian `u64`.
+    fn write_fixture(name: &str, keys: &[u64]) -> String {
+        let path =
+            std::env::temp_dir().join(format!("radix-spline-{}-{}", std::process::id(), name));
+        let mut file = BufWriter::new(File::create(&path).unwrap());
+        for word in std::iter::once(keys.len() as u64).chain(keys.iter().copied()) {
+            file.write_all(&word.to_le_bytes()).unwrap();
+        }
+        path.to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn sosd_output() {
+        let keys: Vec<u64> = (0..100000u64).map(|i| i * i / 7).collect();
+        let probes: Vec<u64> = keys.iter().step_by(97).copied().collect();
+        let (data_path, probes_path) = (
+            write_fixture("data", &keys),
+            write_fixture("probes", &probes),
+        );
+        let data = load_data(&data_path);
+        assert_eq!(data, keys);
+
+        let mut out = vec![];
+        let mut bounds = vec![];
+        sosd_bench(
+            &data,
+            &load_data(&probes_path),
+            18,
+            32,
+            Some(&mut bounds),
+            &mut out,
+        )
+        .unwrap();
+
+        let out = String::from_utf8(out).unwrap();
+        let fields: Vec<&str> = out
+            .trim_end()
+            .strip_prefix("RESULT: ")
+            .unwrap()
+            .split(',')
+            .collect();
+        assert_eq!(fields.len(), 6);
+        assert_eq!(fields[..2], ["RadixSpline", "18 32"]);
+        assert!(fields[2..5]
+            .iter()
+            .all(|field| field.parse::<u128>().is_ok()));
+        assert_eq!(fields[5], "BinarySearch");
+
+        let bounds = String::from_utf8(bounds).unwrap();
+        let mut lines = bounds.lines();
+        assert_eq!(lines.next(), Some("key,lo,hi,position"));
+        let rows: Vec<Vec<usize>> = lines
+            .map(|line| {
+                line.split(',')
+                    .map(|field| field.parse().unwrap())
+                    .collect()
+            })
+            .collect();
+        assert_eq!(rows.len(), probes.len());
+        for (row, &probe) in rows.iter().zip(&probes) {
+            let &[key, lo, hi, position] = &row[..] else {
+                panic!("a row has four fields");
+            };
+            assert_eq!(key as u64, probe);
+            assert!(lo <= position && position < hi && hi - lo <= 65);
+            assert_eq!(position, keys.partition_point(|&x| x < probe));
+        }
+
+        // a probe missing from the data is an error
+        let error = sosd_bench(&data, &[1 << 62], 18, 32, None, &mut vec![]).unwrap_err();
+        assert_eq!(exit_code(&*error), 1);
+        std::fs::remove_file(data_path).unwrap();
+        std::fs::remove_file(probes_path).unwrap();
+    }
+}
@@ -0,0 +1,304 @@
+//! # A downsampled mini model
+//! `MiniModel` keeps a few of the spline points of an index, without the keys or the radix table,
+//! e.g., a sketch of the key distribution of a shard for routing queries on another node.
+//! Its error bound is derived from the full model, so it holds for the original data without scanning it.
+//!
+//! The serialized form is LEB128 varints: the number of keys, the error bound, the number of points,
+//! the first point, then the deltas of keys and positions of the rest. It is at most 30 bytes plus 20 bytes per point,
+//! and much less for the typical key gaps.
+
+use std::io;
+
+use crate::common::Point;
+use crate::radix::{interpolate, RadixSpline, SearchBound};
+
+/// A subset of the spline points of an index, with an error bound for all keys of its data.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MiniModel {
+    num_keys: usize,
+    max_error: usize,
+    points: Vec<(u64, usize)>, // `(key, position)`, as `Point`
+}
+
+impl<'a> RadixSpline<'a> {
+    /// Keep at most `max_points` (at least 2) spline points, evenly spaced in position, including the first and the last.
+    /// The error bound of the mini model is measured against the full model at its points, plus the segment errors.
+    pub fn downsample(&self, max_points: usize) -> MiniModel {
+        assert!(max_points >= 2, "a mini model needs at least 2 points");
+        let points = self.points();
+        let last = self.data().len() - 1;
+        let mut kept: Vec<usize> = if max_points >= points.len() {
+            (0..points.len()).collect()
+        } else {
+            (0..max_points)
+                .map(|i| {
+                    let target = (i as u128 * last as u128 / (max_points - 1) as u128) as usize;
+                    points
+                        .partition_point(|p| p.position() < target)
+                        .min(points.len() - 1)
+                })
+                .collect()
+        };
+        kept.dedup();
+        let mut mini = MiniModel {
+            num_keys: self.data().len(),
+            max_error: 0,
+            points: kept
+                .iter()
+                .map(|&i| (points[i].key(), points[i].position()))
+                .collect(),
+        };
+
+        // Both models are linear between two consecutive full points, so their difference is at most the larger one
+        // at the ends. The floors add one position, and the full model is within its segment error of the data.
+        let deviation = |point: &Point| mini.predict(point.key()).abs_diff(point.position());
+        let mut max_error = 0;
+        for point_location in 1..points.len() {
+            let ends =
+                deviation(&points[point_location - 1]).max(deviation(&points[point_location]));
+            max_error = max_error.max(ends + 1 + self.segment_error(point_location));
+        }
+        mini.max_error = max_error;
+        mini
+    }
+}
+
+impl MiniModel {
+    /// The number of keys of the original data.
+    pub fn len(&self) -> usize {
+        self.num_keys
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.num_keys == 0
+    }
+
+    pub fn num_points(&self) -> usize {
+        self.points.len()
+    }
+
+    /// The error bound of `predict` for all keys of the original data.
+    pub fn max_error(&self) -> usize {
+        self.max_error
+    }
+
+    pub fn min_key(&self) -> u64 {
+        self.points[0].0
+    }
+
+    pub fn max_key(&self) -> u64 {
+        self.points[self.points.len() - 1].0
+    }
+
+    fn point(&self, i: usize) -> Point {
+        let (key, position) = self.points[i];
+        Point::new(key, position)
+    }
+
+    /// predict the position of `key`, which must be within `[min_key, max_key]`.
+    fn predict(&self, key: u64) -> usize {
+        let end = self.points.partition_point(|&(k, _)| k < key);
+        if end == 0 || self.points[end].0 == key {
+            return self.points[end].1;
+        }
+        interpolate(self.point(end - 1), self.point(end), key)
+    }
+
+    /// The positions that hold the first occurrence of `key` in the original data, if it is present there.
+    /// A key outside `[min_key, max_key]` gets an empty bound at either end, as `RadixSpline::search_bound`.
+    pub fn estimate(&self, key: u64) -> SearchBound {
+        if key < self.min_key() {
+            return SearchBound { start: 0, stop: 0 };
+        }
+        if key > self.max_key() {
+            return SearchBound {
+                start: self.num_keys,
+                stop: self.num_keys,
+            };
+        }
+        let predicted = self.predict(key);
+        SearchBound {
+            start: predicted.saturating_sub(self.max_error),
+            stop: predicted
+                .saturating_add(self.max_error)
+                .min(self.num_keys - 1)
+                + 1,
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![];
+        for value in [self.num_keys, self.max_error, self.points.len()] {
+            write_varint(&mut bytes, value as u64);
+        }
+        let mut prev = (0, 0);
+        for &(key, position) in &self.points {
+            write_varint(&mut bytes, key - prev.0);
+            write_varint(&mut bytes, (position - prev.1) as u64);
+            prev = (key, position);
+        }
+        bytes
+    }
+
+    /// Read a mini model written by `to_bytes`, checking that its points are sorted and within the keys.
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<Self> {
+        let mut bytes = bytes;
+        let mut next = || read_varint(&mut bytes);
+        let len = |value: u64| usize::try_from(value).map_err(|_| invalid("bad header"));
+        let num_keys = len(next()?)?;
+        let max_error = len(next()?)?;
+        let num_points = len(next()?)?;
+        if num_points == 0 || num_points > num_keys {
+            return Err(invalid("bad header"));
+        }
+        let mut points = Vec::with_capacity(num_points);
+        let mut prev = (0u64, 0usize);
+        for i in 0..num_points {
+            let (key_delta, position_delta) = (next()?, len(next()?)?);
+            if i > 0 && key_delta == 0 {
+                return Err(invalid("points are not sorted"));
+            }
+            let point = (
+                prev.0.checked_add(key_delta),
+                prev.1.checked_add(position_delta),
+            );
+            let (Some(key), Some(position)) = point else {
+                return Err(invalid("point out of range"));
+            };
+            if position >= num_keys {
+                return Err(invalid("point out of range"));
+            }
+            points.push((key, position));
+            prev = (key, position);
+        }
+        if !bytes.is_empty() {
+            return Err(invalid("trailing bytes"));
+        }
+        Ok(MiniModel {
+            num_keys,
+            max_error,
+            points,
+        })
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn write_varint(bytes: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        bytes.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+fn read_varint(bytes: &mut &[u8]) -> io::Result<u64> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let (&byte, rest) = bytes
+            .split_first()
+            .ok_or_else(|| invalid("unexpected end"))?;
+        *bytes = rest;
+        let bits = u64::from(byte & 0x7f);
+        if bits << shift >> shift != bits {
+            return Err(invalid("varint overflows"));
+        }
+        value |= bits << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(invalid("varint overflows"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testing::{adversarial, Adversarial};
+    use crate::{MaxError, RadixBits};
+    use rand::Rng;
+
+    /// The first occurrence of every key is within its estimate.
+    fn assert_bound(mini: &MiniModel, data: &[u64]) {
+        for (first, &key) in data.iter().enumerate() {
+            if first > 0 && data[first - 1] == key {
+                continue;
+            }
+            let bound = mini.estimate(key);
+            assert!(
+                (bound.start..bound.stop).contains(&first),
+                "key {} at {} not in {:?}",
+                key,
+                first,
+                bound
+            );
+        }
+    }
+
+    #[test]
+    fn bound() {
+        let mut rng = rand::thread_rng();
+        let mut random: Vec<u64> = (0..200_000).map(|_| rng.gen_range(0..1 << 48)).collect();
+        random.sort_unstable();
+        let mut datasets: Vec<Vec<u64>> = Adversarial::ALL.into_iter().map(adversarial).collect();
+        datasets.push(random);
+        datasets.push(vec![7, 7, 7]);
+        for data in &datasets {
+            let index = RadixSpline::new(data, RadixBits::new(12), MaxError::new(16));
+            for max_points in [2, 3, 16, 64, usize::MAX] {
+                let mini = index.downsample(max_points);
+                assert!(mini.num_points() <= max_points);
+                assert_eq!(mini.len(), data.len());
+                assert_eq!(mini.min_key(), data[0]);
+                assert_eq!(mini.max_key(), data[data.len() - 1]);
+                assert_bound(&mini, data);
+                if let Some(before) = mini.min_key().checked_sub(1) {
+                    assert_eq!(mini.estimate(before).stop, 0);
+                }
+                if let Some(beyond) = mini.max_key().checked_add(1) {
+                    assert_eq!(mini.estimate(beyond).start, data.len());
+                }
+            }
+            // with all points, only the floors are added to the error
+            assert!(index.downsample(usize::MAX).max_error() <= index.max_error() + 1);
+        }
+    }
+
+    #[test]
+    fn serialization() {
+        let mut rng = rand::thread_rng();
+        let mut data: Vec<u64> = (0..200_000).map(|_| rng.gen_range(0..1 << 48)).collect();
+        data.sort_unstable();
+        let index = RadixSpline::new(&data, RadixBits::new(18), MaxError::new(32));
+        let mini = index.downsample(64);
+        // two targets may fall in the same long segment
+        assert!((32..=64).contains(&mini.num_points()));
+        let bytes = mini.to_bytes();
+        assert!(bytes.len() <= 600, "{} bytes", bytes.len());
+        assert!(bytes.len() <= 30 + 20 * 64);
+        assert_eq!(MiniModel::from_bytes(&bytes).unwrap(), mini);
+
+        assert!(MiniModel::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert!(MiniModel::from_bytes(&trailing).is_err());
+        assert!(MiniModel::from_bytes(&[0x80; 11]).is_err());
+        // a repeated key
+        let mut repeated = vec![];
+        for value in [10, 0, 2, 5, 0, 0, 3] {
+            write_varint(&mut repeated, value);
+        }
+        assert!(MiniModel::from_bytes(&repeated).is_err());
+
+        let extreme = MiniModel {
+            num_keys: usize::MAX,
+            max_error: usize::MAX,
+            points: vec![(0, 0), (u64::MAX, usize::MAX - 1)],
+        };
+        let bytes = extreme.to_bytes();
+        assert!(bytes.len() <= 30 + 20 * 2);
+        assert_eq!(MiniModel::from_bytes(&bytes).unwrap(), extreme);
+    }
+}
@@ -0,0 +1,598 @@
+//! # Model-only serialization
+//! `serialize` persists the learned structures of an index without its keys, for data stored elsewhere,
+//! e.g., a key file which is loaded or mapped separately. `deserialize` pairs them with the data again.
+//!
+//! The layout is `MAGIC`, a `VERSION` byte, then little-endian `u64` words: the number of keys, `min_key`,
+//! `shift_radix_bits`, `max_error`, the number of points, the points as `(key, position)` pairs,
+//! the number of table entries, the radix table, the number of segment words and the segment words, see `segment_words`.
+//! The points and the table are checked against the data, so a model of other data is rejected.
+//! The segment errors are taken from the model, and measured again over the data only for a model of `VERSION` 1,
+//! which has none; `verify` checks them against the data.
+//!
+//! `to_model` gives the same parts as a `RadixSplineModel`, which the `serde` feature makes serializable
+//! in any serde format, and `from_model` checks them against the data as `deserialize` does.
+
+use std::borrow::Cow;
+
+use crate::common::{narrow, widen, Point, Points, Table};
+use crate::error::DeserializeError;
+use crate::radix::{interpolate, RadixSpline, SearchBound};
+
+const MAGIC: [u8; 8] = *b"RSMODEL\0";
+const VERSION: u8 = 2;
+/// The version before the segment words.
+const VERSION_WITHOUT_SEGMENTS: u8 = 1;
+
+/// The model of an index without its keys: what `serialize` writes, as plain values.
+/// Positions and table entries are `u64`, so a model moves between platforms.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RadixSplineModel {
+    num_keys: u64,
+    min_key: u64,
+    shift_radix_bits: u32,
+    max_error: u64,
+    points: Vec<(u64, u64)>,
+    table: Vec<u64>,
+    /// empty in a model of an older version, whose segment errors are measured over the data
+    #[cfg_attr(feature = "serde", serde(default))]
+    segments: Vec<u64>,
+}
+
+impl RadixSplineModel {
+    /// The number of keys of the data of the model.
+    pub fn num_keys(&self) -> usize {
+        usize::try_from(self.num_keys).unwrap_or(usize::MAX)
+    }
+
+    /// The positions `start..stop` that hold the first occurrence of `key` if it is in the data of the model,
+    /// as `RadixSpline::search_bound`, from the spline points alone, e.g., for keys stored in pages on disk.
+    /// A key outside `[min_key, max_key]` gets an empty bound at either end. A malformed model, e.g., deserialized
+    /// but not checked by `from_model`, gets the whole range of positions where a segment is malformed.
+    pub fn estimate(&self, key: u64) -> SearchBound {
+        let num_keys = self.num_keys();
+        let whole = SearchBound {
+            start: 0,
+            stop: num_keys,
+        };
+        let position = |p: u64| usize::try_from(p).ok().filter(|&p| p < num_keys);
+        let (Some(&(min_key, _)), Some(&(max_key, _))) = (self.points.first(), self.points.last())
+        else {
+            return whole;
+        };
+        if key < min_key {
+            return SearchBound { start: 0, stop: 0 };
+        }
+        if key > max_key {
+            return SearchBound {
+                start: num_keys,
+                stop: num_keys,
+            };
+        }
+        let end = self.points.partition_point(|&(k, _)| k < key);
+        let (end_key, end_position) = self.points[end];
+        if end_key == key {
+            return position(end_position).map_or(whole, |p| SearchBound {
+                start: p,
+                stop: p + 1,
+            });
+        }
+        let (start_key, start_position) = self.points[end - 1];
+        let segment = (position(start_position), position(end_position));
+        let (Some(start_position), Some(end_position)) = segment else {
+            return whole;
+        };
+        if start_key >= end_key || start_position > end_position {
+            return whole;
+        }
+        let predicted = interpolate(
+            Point::new(start_key, start_position),
+            Point::new(end_key, end_position),
+            key,
+        );
+        let max_error = usize::try_from(self.max_error).unwrap_or(usize::MAX);
+        SearchBound {
+            start: predicted.saturating_sub(max_error),
+            stop: predicted.saturating_add(max_error).min(num_keys - 1) + 1,
+        }
+    }
+
+    /// The key at `position` by interpolating the spline points inversely, as `RadixSpline::estimate_key_at`.
+    /// Positions beyond the last point are clamped to the maximum key. A malformed segment gets its start key.
+    pub fn estimate_key_at(&self, position: usize) -> u64 {
+        let position = position as u64;
+        let idx = self.points.partition_point(|&(_, p)| p < position);
+        let Some(&(end_key, end_position)) = self.points.get(idx) else {
+            return self.points.last().map_or(self.min_key, |&(key, _)| key);
+        };
+        if idx == 0 || end_position == position {
+            return end_key;
+        }
+        let (start_key, start_position) = self.points[idx - 1];
+        if start_key > end_key || !(start_position..end_position).contains(&position) {
+            return start_key;
+        }
+        start_key
+            + ((position - start_position) as u128 * (end_key - start_key) as u128
+                / (end_position - start_position) as u128) as u64
+    }
+}
+
+/// Reads the little-endian words after the header.
+struct Words<'b> {
+    bytes: &'b [u8],
+}
+
+impl<'b> Words<'b> {
+    fn next(&mut self) -> Result<u64, DeserializeError> {
+        let (word, rest) = self
+            .bytes
+            .split_first_chunk::<8>()
+            .ok_or(DeserializeError::BadLength)?;
+        self.bytes = rest;
+        Ok(u64::from_le_bytes(*word))
+    }
+
+    fn next_usize(&mut self) -> Result<usize, DeserializeError> {
+        usize::try_from(self.next()?).map_err(|_| DeserializeError::Mismatch("value out of range"))
+    }
+
+    /// Check that the rest holds `len` items of `width` words, before allocating for them.
+    fn check_len(&self, len: usize, width: usize) -> Result<(), DeserializeError> {
+        match len.checked_mul(width * 8) {
+            Some(bytes) if bytes <= self.bytes.len() => Ok(()),
+            _ => Err(DeserializeError::BadLength),
+        }
+    }
+}
+
+impl<'a> RadixSpline<'a> {
+    /// The model without the keys, see `deserialize`.
+    pub fn serialize(&self) -> Vec<u8> {
+        let table = self.table();
+        let points = self.points();
+        let mut bytes = Vec::with_capacity(9 + 8 * (7 + 3 * points.len() + table.len()));
+        bytes.extend_from_slice(&MAGIC);
+        bytes.push(VERSION);
+        let header = [
+            self.data().len() as u64,
+            self.min_key(),
+            u64::from(self.shift_radix_bits()),
+            self.max_error() as u64,
+            points.len() as u64,
+        ];
+        let points = points.iter().flat_map(|p| [p.key(), p.position() as u64]);
+        let table_len = table.len() as u64;
+        let table = table.iter().map(|&t| widen(t) as u64);
+        let segments = self.segment_words();
+        let words = header
+            .into_iter()
+            .chain(points)
+            .chain([table_len])
+            .chain(table)
+            .chain([segments.len() as u64])
+            .chain(segments);
+        for word in words {
+            bytes.extend_from_slice(&word.to_le_bytes());
+        }
+        bytes
+    }
+
+    /// Pair a model of `serialize` with its `data`, which is borrowed as by `new`.
+    /// The format, the length and the structures are checked, and the model must describe `data`.
+    pub fn deserialize(bytes: &[u8], data: &'a [u64]) -> Result<Self, DeserializeError> {
+        let (magic, rest) = bytes
+            .split_first_chunk::<8>()
+            .ok_or(DeserializeError::BadMagic)?;
+        if *magic != MAGIC {
+            return Err(DeserializeError::BadMagic);
+        }
+        let (&version, rest) = rest.split_first().ok_or(DeserializeError::BadLength)?;
+        if version != VERSION && version != VERSION_WITHOUT_SEGMENTS {
+            return Err(DeserializeError::UnsupportedVersion { version });
+        }
+        let mut words = Words { bytes: rest };
+        let num_keys = words.next()?;
+        let min_key = words.next()?;
+        let shift_radix_bits =
+            u32::try_from(words.next()?).map_err(|_| DeserializeError::Mismatch("bad shift"))?;
+        let max_error = words.next()?;
+        // a model of other data is rejected before reading its structures
+        if usize::try_from(num_keys) != Ok(data.len()) || data.first() != Some(&min_key) {
+            return Err(DeserializeError::Mismatch("the model is of other data"));
+        }
+
+        let num_points = words.next_usize()?;
+        words.check_len(num_points, 2)?;
+        let mut points = Vec::with_capacity(num_points);
+        for _ in 0..num_points {
+            points.push((words.next()?, words.next()?));
+        }
+        let table_len = words.next_usize()?;
+        words.check_len(table_len, 1)?;
+        let mut table = Vec::with_capacity(table_len);
+        for _ in 0..table_len {
+            table.push(words.next()?);
+        }
+        let mut segments = vec![];
+        if version != VERSION_WITHOUT_SEGMENTS {
+            let num_segments = words.next_usize()?;
+            words.check_len(num_segments, 1)?;
+            segments.reserve_exact(num_segments);
+            for _ in 0..num_segments {
+                segments.push(words.next()?);
+            }
+        }
+        if !words.bytes.is_empty() {
+            return Err(DeserializeError::BadLength);
+        }
+        let model = RadixSplineModel {
+            num_keys,
+            min_key,
+            shift_radix_bits,
+            max_error,
+            points,
+            table,
+            segments,
+        };
+        RadixSpline::from_model(model, data)
+    }
+
+    /// The model without the keys, the same as `serialize` as plain values, see `from_model`.
+    pub fn to_model(&self) -> RadixSplineModel {
+        RadixSplineModel {
+            num_keys: self.data().len() as u64,
+            min_key: self.min_key(),
+            shift_radix_bits: self.shift_radix_bits(),
+            max_error: self.max_error() as u64,
+            points: self
+                .points()
+                .iter()
+                .map(|p| (p.key(), p.position() as u64))
+                .collect(),
+            table: self.table().iter().map(|&t| widen(t) as u64).collect(),
+            segments: self.segment_words().collect(),
+        }
+    }
+
+    /// Pair `model` with its `data`, which is borrowed as by `new`, e.g., after deserializing it with serde.
+    /// `data` must have the length and the first key of the model, and the points and the table must describe it.
+    /// Without segment words, e.g., a model serialized by an older version, the segment errors are measured over `data`.
+    pub fn from_model(model: RadixSplineModel, data: &'a [u64]) -> Result<Self, DeserializeError> {
+        let num_keys = data.len();
+        if usize::try_from(model.num_keys) != Ok(num_keys) || data.first() != Some(&model.min_key) {
+            return Err(DeserializeError::Mismatch("the model is of other data"));
+        }
+        let position = |value: u64| usize::try_from(value).ok().filter(|&p| p < num_keys);
+        let mut points = Points::with_capacity(model.points.len());
+        for (key, position) in model.points.into_iter().map(|(k, p)| (k, position(p))) {
+            let position = position.ok_or(DeserializeError::Mismatch("point out of range"))?;
+            points.push(Point::new(key, position));
+        }
+        let num_points = points.len() as u64;
+        let mut table = Table::with_capacity(model.table.len());
+        for entry in model.table {
+            if entry > num_points {
+                return Err(DeserializeError::Mismatch("table entry out of range"));
+            }
+            table.push(narrow(entry as usize));
+        }
+        let max_error = usize::try_from(model.max_error)
+            .map_err(|_| DeserializeError::Mismatch("value out of range"))?;
+
+        RadixSpline::from_parts(
+            Cow::Borrowed(data),
+            model.shift_radix_bits,
+            max_error,
+            points,
+            table,
+            (!model.segments.is_empty()).then_some(&model.segments[..]),
+        )
+        .map_err(DeserializeError::Mismatch)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{MaxError, RadixBits};
+    use rand::Rng;
+
+    #[test]
+    fn round_trip() {
+        let mut rng = rand::thread_rng();
+        let mut data: Vec<u64> = (0..10_000).map(|_| rng.gen_range(0..1 << 40)).collect();
+        data.sort_unstable();
+        let radix_spline = RadixSpline::new(&data, RadixBits::new(12), MaxError::new(16));
+        let bytes = radix_spline.serialize();
+        assert!(bytes.len() < data.len() * 8);
+
+        let model = RadixSpline::deserialize(&bytes, &data).unwrap();
+        assert_eq!(model.to_bytes(), radix_spline.to_bytes());
+        assert_eq!(model.serialize(), bytes);
+        for &key in &data {
+            assert_eq!(model.search(key), radix_spline.search(key));
+        }
+        for _ in 0..10_000 {
+            let key = rng.gen_range(0..1 << 41);
+            assert_eq!(model.search(key), radix_spline.search(key));
+        }
+    }
+
+    #[test]
+    fn rejected() {
+        let data: Vec<u64> = (0..10_000u64).map(|x| x * x).collect();
+        let bytes = RadixSpline::new(&data, RadixBits::new(12), MaxError::new(16)).serialize();
+        let deserialize = |bytes: &[u8], data: &[u64]| RadixSpline::deserialize(bytes, data).err();
+
+        assert_eq!(
+            deserialize(&bytes[..4], &data),
+            Some(DeserializeError::BadMagic)
+        );
+        let mut bad = bytes.clone();
+        bad[0] = b'X';
+        assert_eq!(deserialize(&bad, &data), Some(DeserializeError::BadMagic));
+        let mut bad = bytes.clone();
+        bad[8] = VERSION + 1;
+        assert_eq!(
+            deserialize(&bad, &data),
+            Some(DeserializeError::UnsupportedVersion {
+                version: VERSION + 1
+            })
+        );
+        for len in [9, 20, bytes.len() / 2, bytes.len() - 1] {
+            assert_eq!(
+                deserialize(&bytes[..len], &data),
+                Some(DeserializeError::BadLength)
+            );
+        }
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert_eq!(
+            deserialize(&trailing, &data),
+            Some(DeserializeError::BadLength)
+        );
+
+        // the model of other data
+        let other: Vec<u64> = (0..10_000u64).map(|x| x * x + 1).collect();
+        assert!(matches!(
+            deserialize(&bytes, &other),
+            Some(DeserializeError::Mismatch(_))
+        ));
+        let mut shifted = data.clone();
+        let radix_spline = RadixSpline::new(&data, RadixBits::new(12), MaxError::new(16));
+        shifted[radix_spline.points()[1].position()] += 1;
+        assert!(matches!(
+            deserialize(&bytes, &shifted),
+            Some(DeserializeError::Mismatch(_))
+        ));
+        assert!(matches!(
+            deserialize(&bytes, &data[..9999]),
+            Some(DeserializeError::Mismatch(_))
+        ));
+    }
+
+    /// A model is attached only to data of its length, so no lookup ever slices beyond the data:
+    /// an empty, a shorter or a longer slice is an error rather than a panic, even if it agrees
+    /// with the data where both have keys, and the model still works on its own data.
+    #[test]
+    fn attach_other_lengths() {
+        let data: Vec<u64> = (0..10_000u64).map(|x| x * x).collect();
+        let bytes = RadixSpline::new(&data, RadixBits::new(12), MaxError::new(16)).serialize();
+        let mut longer = data.clone();
+        longer.extend([data[9999], u64::MAX]);
+        let slices: [&[u64]; 5] = [&[], &data[..1], &data[..5000], &data[..9999], &longer];
+        for slice in slices {
+            let attached = RadixSpline::deserialize(&bytes, slice);
+            assert!(
+                matches!(attached, Err(DeserializeError::Mismatch(_))),
+                "{} keys",
+                slice.len()
+            );
+        }
+
+        let model = RadixSpline::deserialize(&bytes, &data).unwrap();
+        let last = data[9999];
+        let keys = [0, 1, 2, 4, data[5000], last, last + 1, u64::MAX];
+        for key in keys {
+            let first = data.partition_point(|&x| x < key);
+            let end = data.partition_point(|&x| x <= key);
+            assert_eq!(model.search(key), (first < end).then_some(first));
+            assert_eq!(model.lower_bound(key), first);
+            assert_eq!(model.upper_bound(key), end);
+            assert_eq!(model.range(key..=key), first..end);
+            let bound = model.search_bound(key);
+            assert!(bound.start <= bound.stop && bound.stop <= data.len());
+        }
+    }
+
+    #[test]
+    fn model() {
+        let data: Vec<u64> = (0..10_000u64).map(|x| x * x).collect();
+        let radix_spline = RadixSpline::new(&data, RadixBits::new(12), MaxError::new(16));
+        let model = radix_spline.to_model();
+        let attached = RadixSpline::from_model(model.clone(), &data).unwrap();
+        assert_eq!(attached.serialize(), radix_spline.serialize());
+        assert_eq!(attached.to_model(), model);
+        assert!(matches!(
+            RadixSpline::from_model(model, &data[1..]),
+            Err(DeserializeError::Mismatch(_))
+        ));
+    }
+
+    /// A model of the version before the segment words has its segment errors measured over the data.
+    #[test]
+    fn without_segments() {
+        let data: Vec<u64> = (0..10_000u64).flat_map(|x| [x * x; 3]).collect();
+        let radix_spline = RadixSpline::new(&data, RadixBits::new(12), MaxError::new(16));
+        let bytes = radix_spline.serialize();
+        let num_segments = radix_spline.num_spline_points();
+        let mut older = bytes[..bytes.len() - 8 * (num_segments + 1)].to_vec();
+        older[8] = VERSION_WITHOUT_SEGMENTS;
+        let attached = RadixSpline::deserialize(&older, &data).unwrap();
+        assert!(attached.segment_words().eq(radix_spline.segment_words()));
+        assert_eq!(attached.serialize(), bytes);
+
+        let mut model = radix_spline.to_model();
+        model.segments.clear();
+        let attached = RadixSpline::from_model(model, &data).unwrap();
+        assert_eq!(attached.to_model(), radix_spline.to_model());
+
+        let mut model = radix_spline.to_model();
+        model.segments.pop();
+        assert_eq!(
+            RadixSpline::from_model(model, &data).err(),
+            Some(DeserializeError::Mismatch("bad segment errors"))
+        );
+    }
+
+    /// A model goes through JSON and back, and a tampered one is rejected when it is attached.
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_json() {
+        use serde_json::Value;
+
+        let data: Vec<u64> = (0..10_000u64).map(|x| x * x * 3).collect();
+        let radix_spline = RadixSpline::new(&data, RadixBits::new(12), MaxError::new(16));
+        let json = serde_json::to_string(&radix_spline.to_model()).unwrap();
+        let model: RadixSplineModel = serde_json::from_str(&json).unwrap();
+        assert_eq!(model, radix_spline.to_model());
+        let attached = RadixSpline::from_model(model, &data).unwrap();
+        for key in (0..30_000).chain(data.iter().copied()) {
+            assert_eq!(attached.search(key), radix_spline.search(key));
+        }
+
+        let attach = |value: &Value| {
+            let model: RadixSplineModel = serde_json::from_value(value.clone()).unwrap();
+            RadixSpline::from_model(model, &data).err()
+        };
+        let value: Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(attach(&value), None);
+        let tampered = |edit: &dyn Fn(&mut Value)| {
+            let mut value = value.clone();
+            edit(&mut value);
+            attach(&value)
+        };
+        let mismatch = |message| Some(DeserializeError::Mismatch(message));
+        // a table entry moved to the next spline point, and one beyond the points
+        let entry = value["table"][100].as_u64().unwrap();
+        assert_eq!(
+            tampered(&|v| v["table"][100] = (entry + 1).into()),
+            mismatch("radix table does not match the spline points")
+        );
+        assert_eq!(
+            tampered(&|v| v["table"][100] = u64::MAX.into()),
+            mismatch("table entry out of range")
+        );
+        assert_eq!(
+            tampered(&|v| {
+                v["table"].as_array_mut().unwrap().pop();
+            }),
+            mismatch("bad radix table length")
+        );
+        assert_eq!(
+            tampered(&|v| v["points"][1][1] = 5.into()),
+            mismatch("spline points are not on the data")
+        );
+        assert_eq!(
+            tampered(&|v| v["min_key"] = 1.into()),
+            mismatch("the model is of other data")
+        );
+        assert_eq!(
+            tampered(&|v| v["num_keys"] = 9999.into()),
+            mismatch("the model is of other data")
+        );
+        assert_eq!(
+            tampered(&|v| v["segments"][0] = u64::MAX.into()),
+            mismatch("bad segment errors")
+        );
+        // a model of an older version has no segment words
+        let mut older = value.clone();
+        older.as_object_mut().unwrap().remove("segments");
+        assert_eq!(attach(&older), None);
+        // a missing field is an error of serde itself
+        let mut missing = value.clone();
+        missing.as_object_mut().unwrap().remove("table");
+        assert!(serde_json::from_value::<RadixSplineModel>(missing).is_err());
+    }
+
+    /// A model goes through bincode, a compact binary format, and back, and a tampered one is rejected when it is attached.
+    #[cfg(feature = "serde")]
+    #[test]
+    fn bincode() {
+        let data: Vec<u64> = (0..10_000u64).map(|x| x * x * 3).collect();
+        let radix_spline = RadixSpline::new(&data, RadixBits::new(12), MaxError::new(16));
+        let mut bytes = bincode::serialize(&radix_spline.to_model()).unwrap();
+        let model: RadixSplineModel = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(model, radix_spline.to_model());
+        let attached = RadixSpline::from_model(model.clone(), &data).unwrap();
+        assert_eq!(attached.serialize(), radix_spline.serialize());
+        for key in (0..30_000).chain(data.iter().copied()) {
+            assert_eq!(attached.search(key), radix_spline.search(key));
+        }
+
+        // a table entry moved to the next spline point in the encoded bytes, where bincode writes
+        // the fields in order, integers as fixed little-endian and a vector after its `u64` length
+        let table = 8 + 8 + 4 + 8 + 8 + 16 * model.points.len() + 8;
+        let entry = table + 8 * 100;
+        assert_eq!(bytes[entry..entry + 8], model.table[100].to_le_bytes());
+        bytes[entry..entry + 8].copy_from_slice(&(model.table[100] + 1).to_le_bytes());
+        let tampered: RadixSplineModel = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(
+            RadixSpline::from_model(tampered, &data).err(),
+            Some(DeserializeError::Mismatch(
+                "radix table does not match the spline points"
+            ))
+        );
+        // truncated bytes are an error of bincode itself
+        assert!(bincode::deserialize::<RadixSplineModel>(&bytes[..bytes.len() - 1]).is_err());
+    }
+
+    /// A model with malformed segments bounds their keys by all positions rather than panicking.
+    #[test]
+    fn estimate_malformed() {
+        let data: Vec<u64> = (0..1000).map(|x| x * 3).collect();
+        let model = RadixSpline::new(&data, RadixBits::new(8), MaxError::new(4)).to_model();
+        let whole = SearchBound {
+            start: 0,
+            stop: data.len(),
+        };
+        let mut reversed = model.clone();
+        let last = reversed.points.len() - 1;
+        reversed.points.swap(1, last - 1);
+        let mut beyond = model.clone();
+        beyond.points[1].1 = u64::MAX;
+        for key in [1, 4, 1500, 2996] {
+            for model in [&reversed, &beyond] {
+                let bound = model.estimate(key);
+                assert!(bound.start <= bound.stop && bound.stop <= data.len());
+            }
+        }
+        let segment = |model: &RadixSplineModel| (model.points[0].0 + model.points[1].0) / 2;
+        assert_eq!(beyond.estimate(segment(&beyond)), whole);
+        let mut empty = model.clone();
+        empty.points.clear();
+        assert_eq!(empty.estimate(3), whole);
+        for position in [0, 1, 500, 999, 5000] {
+            for model in [&reversed, &beyond, &empty] {
+                model.estimate_key_at(position);
+            }
+        }
+    }
+
+    /// The model estimates the same key at each position as the index.
+    #[test]
+    fn estimate_key_at() {
+        let data: Vec<u64> = (0..10_000u64).map(|x| x * x / 5).collect();
+        let radix_spline = RadixSpline::new(&data, RadixBits::new(10), MaxError::new(8));
+        let model = radix_spline.to_model();
+        for position in (0..data.len() + 10).step_by(7) {
+            assert_eq!(
+                model.estimate_key_at(position),
+                radix_spline.estimate_key_at(position),
+                "{}",
+                position
+            );
+        }
+    }
+}
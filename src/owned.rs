@@ -0,0 +1,278 @@
+//! # An owned radix spline
+//! `OwnedRadixSpline` owns its sorted data, so it can be returned from functions and stored alongside other state without a borrow.
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::ops::Deref;
+
+use crate::error::DataError;
+use crate::params::{MaxError, RadixBits};
+use crate::radix::RadixSpline;
+
+/// The positions sampled by `Checksum`.
+const CHECKSUM_SAMPLES: usize = 64;
+
+/// A cheap fingerprint of the data: its length, first and last keys, and the xor of evenly spaced keys.
+/// It catches most mutations, not all of them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Checksum {
+    len: usize,
+    first: u64,
+    last: u64,
+    sample: u64,
+}
+
+impl Checksum {
+    fn of(data: &[u64]) -> Self {
+        let len = data.len();
+        let step = len.div_ceil(CHECKSUM_SAMPLES).max(1);
+        Checksum {
+            len,
+            first: data.first().copied().unwrap_or(0),
+            last: data.last().copied().unwrap_or(0),
+            sample: data.iter().step_by(step).fold(0, |xor, &key| xor ^ key),
+        }
+    }
+}
+
+/// A `RadixSpline` which owns its data. It dereferences to `RadixSpline`, so the whole query API is available.
+/// In debug builds, every dereference checks that the data still matches its checksum from the build,
+/// and panics with "data mutated after index build" otherwise; `revalidate` is the same check in release builds.
+pub struct OwnedRadixSpline {
+    index: RadixSpline<'static>,
+    checksum: Checksum,
+}
+
+impl OwnedRadixSpline {
+    /// `data` is sorted and non-empty.
+    pub fn new(data: Vec<u64>, num_radix_bits: RadixBits, max_error: MaxError) -> Self {
+        OwnedRadixSpline::from_index(RadixSpline::from_vec(
+            data,
+            num_radix_bits.get(),
+            max_error.get(),
+        ))
+    }
+
+    /// Take an index which owns its data, taking the checksum of the data now.
+    pub(crate) fn from_index(index: RadixSpline<'static>) -> Self {
+        let checksum = Checksum::of(index.data());
+        OwnedRadixSpline { index, checksum }
+    }
+
+    /// Check that the data still matches its checksum from the build.
+    pub fn revalidate(&self) -> Result<(), DataError> {
+        if Checksum::of(self.index.data()) == self.checksum {
+            Ok(())
+        } else {
+            Err(DataError::MutatedAfterBuild)
+        }
+    }
+
+    /// Sort `data` and build an index over it, i.e., one call from unsorted input to a ready-to-query index.
+    pub fn from_unsorted(
+        mut data: Vec<u64>,
+        num_radix_bits: RadixBits,
+        max_error: MaxError,
+    ) -> Self {
+        data.sort_unstable();
+        OwnedRadixSpline::new(data, num_radix_bits, max_error)
+    }
+
+    /// Merge independently sorted `runs` into one sorted sequence (k-way merge), and build an index which owns it.
+    /// Positions returned by `search` refer to the merged array.
+    pub fn from_runs(runs: &[&[u64]], num_radix_bits: RadixBits, max_error: MaxError) -> Self {
+        OwnedRadixSpline::new(merge_runs(runs), num_radix_bits, max_error)
+    }
+
+    /// default `max_radix_bits` is 18, and default `max_error` is 32
+    #[deprecated(
+        since = "0.1.0",
+        note = "use `OwnedRadixSpline::from(data)` or `OwnedRadixSpline::new(data, RadixBits::DEFAULT, MaxError::DEFAULT)`"
+    )]
+    pub fn default(data: Vec<u64>) -> Self {
+        OwnedRadixSpline::new(data, RadixBits::DEFAULT, MaxError::DEFAULT)
+    }
+
+    /// The sorted data, dropping the index.
+    pub fn into_inner(self) -> Vec<u64> {
+        self.index.into_data().into_owned()
+    }
+}
+
+impl From<Vec<u64>> for OwnedRadixSpline {
+    /// Sort `data` unless it is sorted already, and build with the default parameters.
+    /// Panics if `data` is empty, as `new`.
+    fn from(mut data: Vec<u64>) -> Self {
+        if !data.windows(2).all(|w| w[0] <= w[1]) {
+            data.sort_unstable();
+        }
+        OwnedRadixSpline::new(data, RadixBits::DEFAULT, MaxError::DEFAULT)
+    }
+}
+
+impl FromIterator<u64> for OwnedRadixSpline {
+    /// Collect the keys in any order, as `From<Vec<u64>>`.
+    fn from_iter<I: IntoIterator<Item = u64>>(keys: I) -> Self {
+        OwnedRadixSpline::from(keys.into_iter().collect::<Vec<u64>>())
+    }
+}
+
+impl Deref for OwnedRadixSpline {
+    type Target = RadixSpline<'static>;
+
+    fn deref(&self) -> &Self::Target {
+        #[cfg(debug_assertions)]
+        if let Err(error) = self.revalidate() {
+            panic!("{}", crate::error::BuildError::from(error));
+        }
+        &self.index
+    }
+}
+
+/// k-way merge of sorted `runs`, using a min-heap of the current head of each run.
+fn merge_runs(runs: &[&[u64]]) -> Vec<u64> {
+    debug_assert!(runs.iter().all(|run| run.windows(2).all(|w| w[0] <= w[1])));
+    let mut merged = Vec::with_capacity(runs.iter().map(|run| run.len()).sum());
+    // (key, run, offset in run)
+    let mut heap: BinaryHeap<Reverse<(u64, usize, usize)>> = runs
+        .iter()
+        .enumerate()
+        .filter(|(_, run)| !run.is_empty())
+        .map(|(r, run)| Reverse((run[0], r, 0)))
+        .collect();
+    while let Some(Reverse((key, r, offset))) = heap.pop() {
+        merged.push(key);
+        if let Some(&next) = runs[r].get(offset + 1) {
+            heap.push(Reverse((next, r, offset + 1)));
+        }
+    }
+    merged
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn build() -> OwnedRadixSpline {
+        let data: Vec<u64> = (0..100000).map(|i| i * 3).collect();
+        OwnedRadixSpline::new(data, RadixBits::DEFAULT, MaxError::DEFAULT)
+    }
+
+    #[test]
+    fn search() {
+        let owned = build();
+
+        assert_eq!(owned.search(300), Some(100));
+        assert_eq!(owned.search(301), None);
+        assert_eq!(owned.min_key(), 0);
+        assert_eq!(owned.max_key(), 299997);
+    }
+
+    /// The index lives next to other state, with no lifetime on the struct.
+    #[test]
+    fn stored_in_struct() {
+        struct Shard {
+            name: String,
+            index: OwnedRadixSpline,
+        }
+
+        fn open(name: &str) -> Shard {
+            Shard {
+                name: name.to_string(),
+                index: OwnedRadixSpline::new(
+                    (0..1000).map(|i| i * i).collect(),
+                    RadixBits::new(8),
+                    MaxError::new(4),
+                ),
+            }
+        }
+
+        let shards: Vec<Shard> = ["a", "b"].into_iter().map(open).collect();
+        assert_eq!(shards[1].name, "b");
+        assert_eq!(shards[1].index.search(81), Some(9));
+        assert_eq!(shards[0].index.search(82), None);
+    }
+
+    /// `From` and `FromIterator` sort or keep the keys, build the same index as the borrowed one,
+    /// and `into_inner` gives the data back without a copy.
+    #[test]
+    fn from_vec() {
+        let sorted: Vec<u64> = (0..10_000).map(|i| i * i).collect();
+        let borrowed = RadixSpline::new(&sorted, RadixBits::DEFAULT, MaxError::DEFAULT);
+        let owned = OwnedRadixSpline::from(sorted.clone());
+        let collected: OwnedRadixSpline = sorted.iter().rev().copied().collect();
+        for index in [&owned, &collected] {
+            assert_eq!(index.to_bytes(), borrowed.to_bytes());
+            assert_eq!(index.search(81), Some(9));
+            assert_eq!(index.range(80..=10_000), borrowed.range(80..=10_000));
+        }
+
+        let data = collected.into_inner();
+        assert_eq!(data, sorted);
+        let ptr = owned.data().as_ptr();
+        let data = owned.into_inner();
+        assert_eq!(data.as_ptr(), ptr);
+    }
+
+    #[test]
+    fn revalidate() {
+        let mut owned = build();
+        assert_eq!(owned.revalidate(), Ok(()));
+        // the keys at the sampled positions, the first and the last one
+        for position in [0, 1563 * 7, 99999] {
+            let mut corrupted = build();
+            corrupted.index.data_mut()[position] += 1;
+            assert_eq!(
+                corrupted.revalidate(),
+                Err(DataError::MutatedAfterBuild),
+                "{}",
+                position
+            );
+        }
+        owned.index.data_mut().swap(0, 1563);
+        assert!(owned.revalidate().is_err());
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "data mutated after index build")]
+    fn mutated() {
+        let mut owned = build();
+        owned.index.data_mut()[1563] = 1;
+        owned.search(300);
+    }
+
+    #[test]
+    fn build_owned() {
+        use rand::seq::SliceRandom;
+
+        let mut data: Vec<u64> = (0..100000).map(|i| i * 3).collect();
+        data.shuffle(&mut rand::thread_rng());
+
+        let owned = RadixSpline::build_owned(data, RadixBits::new(18), MaxError::new(32));
+
+        for i in 0..100000 {
+            assert_eq!(owned.search(i * 3), Some(i as usize));
+            assert_eq!(owned.search(i * 3 + 1), None);
+        }
+    }
+
+    #[test]
+    fn from_runs() {
+        let a: Vec<u64> = (0..1000).map(|i| i * 3).collect();
+        let b: Vec<u64> = (500..2000).map(|i| i * 2).collect();
+        let c: Vec<u64> = (0..300).map(|i| i * 7 + 1).collect();
+        let runs = [&a[..], &b[..], &c[..]];
+
+        let owned = RadixSpline::from_runs(&runs, RadixBits::new(8), MaxError::new(4));
+
+        let mut expected: Vec<u64> = runs.concat();
+        expected.sort_unstable();
+        assert_eq!(owned.data(), &expected[..]);
+        for key in runs.concat() {
+            let pos = expected.partition_point(|&k| k < key);
+            assert_eq!(owned.search(key), Some(pos));
+        }
+        assert_eq!(owned.search(5), None);
+    }
+}
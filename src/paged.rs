@@ -0,0 +1,257 @@
+//! # Paged lookups
+//! `PositionEstimator` is the minimal interface of a model that maps a key to a range of positions without the keys,
+//! e.g., for a storage engine which keeps the sorted keys in fixed-size pages on disk. `PagedLookup` turns
+//! the positions into page ids, so an engine can swap models without depending on any of them.
+
+use std::ops::Range;
+
+use crate::mini::MiniModel;
+use crate::model::RadixSplineModel;
+use crate::radix::{RadixSpline, SearchBound};
+use crate::spline_corridor::GreedySplineCorridor;
+
+/// A model of sorted (possibly repeated) `u64` keys, which bounds the position of a key.
+pub trait PositionEstimator {
+    /// The positions `start..stop` that hold the first occurrence of `key` if it is present.
+    /// A key outside `[min_key, max_key]` gets an empty bound at either end, i.e., at 0 or at `num_keys()`.
+    fn estimate(&self, key: u64) -> SearchBound;
+
+    /// The number of keys of the data of the model.
+    fn num_keys(&self) -> usize;
+}
+
+impl<E: PositionEstimator + ?Sized> PositionEstimator for &E {
+    fn estimate(&self, key: u64) -> SearchBound {
+        (**self).estimate(key)
+    }
+
+    fn num_keys(&self) -> usize {
+        (**self).num_keys()
+    }
+}
+
+impl<'a> PositionEstimator for RadixSpline<'a> {
+    fn estimate(&self, key: u64) -> SearchBound {
+        self.search_bound(key)
+    }
+
+    fn num_keys(&self) -> usize {
+        self.data().len()
+    }
+}
+
+impl PositionEstimator for RadixSplineModel {
+    fn estimate(&self, key: u64) -> SearchBound {
+        RadixSplineModel::estimate(self, key)
+    }
+
+    fn num_keys(&self) -> usize {
+        RadixSplineModel::num_keys(self)
+    }
+}
+
+impl PositionEstimator for MiniModel {
+    fn estimate(&self, key: u64) -> SearchBound {
+        MiniModel::estimate(self, key)
+    }
+
+    fn num_keys(&self) -> usize {
+        self.len()
+    }
+}
+
+impl<'a> PositionEstimator for GreedySplineCorridor<'a> {
+    /// The window of `search`: around the prediction, or from the first key to the window of the first spline point
+    /// for a key before it.
+    fn estimate(&self, key: u64) -> SearchBound {
+        let data = self.data();
+        let len = data.len();
+        if data.first().is_none_or(|&min_key| key < min_key) {
+            return SearchBound { start: 0, stop: 0 };
+        }
+        if key > data[len - 1] {
+            return SearchBound {
+                start: len,
+                stop: len,
+            };
+        }
+        let max_error = self.max_error();
+        match self.predict(key) {
+            Some(predicted) => SearchBound {
+                start: predicted.saturating_sub(max_error),
+                stop: predicted.saturating_add(max_error).min(len - 1) + 1,
+            },
+            None => SearchBound {
+                start: 0,
+                stop: self
+                    .first_point_position()
+                    .saturating_add(max_error)
+                    .min(len),
+            },
+        }
+    }
+
+    fn num_keys(&self) -> usize {
+        self.data().len()
+    }
+}
+
+/// Page ids for the keys of a `PositionEstimator`, where page `i` holds the positions
+/// `i * page_size..(i + 1) * page_size`, and the last page may be partial.
+pub struct PagedLookup<E> {
+    estimator: E,
+    page_size: usize,
+}
+
+impl<E: PositionEstimator> PagedLookup<E> {
+    /// `page_size` is the number of keys per page, at least 1.
+    pub fn new(estimator: E, page_size: usize) -> Self {
+        assert!(page_size > 0, "a page holds at least one key");
+        PagedLookup {
+            estimator,
+            page_size,
+        }
+    }
+
+    pub fn estimator(&self) -> &E {
+        &self.estimator
+    }
+
+    pub fn page_size(&self) -> usize {
+        self.page_size
+    }
+
+    pub fn num_pages(&self) -> usize {
+        self.estimator.num_keys().div_ceil(self.page_size)
+    }
+
+    /// The pages of the positions `start..stop`, which are empty (at the page of `start`) if the positions are.
+    fn pages(&self, start: usize, stop: usize) -> Range<usize> {
+        let first = start / self.page_size;
+        if start >= stop {
+            return first..first;
+        }
+        first..(stop - 1) / self.page_size + 1
+    }
+
+    /// The pages holding the bound of `key`, so the first occurrence of `key` if it is present.
+    /// A key outside the keys gets no pages, at the first page or after the last one.
+    pub fn pages_for_key(&self, key: u64) -> Range<usize> {
+        let bound = self.estimator.estimate(key);
+        self.pages(bound.start, bound.stop)
+    }
+
+    /// The pages from the bound of `lo` to that of `hi`, so from the first occurrence of `lo`
+    /// to that of `hi` if they are present. A run of `hi` may continue on the pages after them.
+    /// There are no pages if `lo > hi`.
+    pub fn pages_for_range(&self, lo: u64, hi: u64) -> Range<usize> {
+        let (lo_bound, hi_bound) = (self.estimator.estimate(lo), self.estimator.estimate(hi));
+        if lo > hi {
+            return self.pages(lo_bound.start, lo_bound.start);
+        }
+        self.pages(lo_bound.start, hi_bound.stop)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{MaxError, RadixBits};
+    use rand::Rng;
+
+    /// The pages of the positions `start..stop`, one position at a time.
+    fn brute_force_pages(start: usize, stop: usize, page_size: usize) -> Range<usize> {
+        let pages: Vec<usize> = (start..stop).map(|p| p / page_size).collect();
+        match (pages.first(), pages.last()) {
+            (Some(&first), Some(&last)) => first..last + 1,
+            _ => start / page_size..start / page_size,
+        }
+    }
+
+    fn check_pages<E: PositionEstimator>(estimator: E, data: &[u64], probes: &[u64]) {
+        for page_size in [1, 3, 256, data.len() - 1, data.len(), usize::MAX / 2] {
+            let paged = PagedLookup::new(&estimator, page_size);
+            assert_eq!(paged.num_pages(), data.len().div_ceil(page_size));
+            for &key in probes {
+                let bound = estimator.estimate(key);
+                let pages = paged.pages_for_key(key);
+                assert_eq!(pages, brute_force_pages(bound.start, bound.stop, page_size));
+                assert!(pages.end <= paged.num_pages());
+                let first = data.partition_point(|&x| x < key);
+                if data.get(first) == Some(&key) {
+                    assert!(
+                        pages.contains(&(first / page_size)),
+                        "{} in {:?}",
+                        key,
+                        pages
+                    );
+                } else if key < data[0] {
+                    assert_eq!(pages, 0..0);
+                } else if key > data[data.len() - 1] {
+                    assert!(pages.is_empty() && pages.start >= paged.num_pages() - 1);
+                }
+            }
+            for w in probes.windows(2) {
+                let (lo, hi) = (w[0].min(w[1]), w[0].max(w[1]));
+                let (lo_bound, hi_bound) = (estimator.estimate(lo), estimator.estimate(hi));
+                let pages = paged.pages_for_range(lo, hi);
+                assert_eq!(
+                    pages,
+                    brute_force_pages(lo_bound.start, hi_bound.stop, page_size)
+                );
+                // with both ends present, every key of the range has its first occurrence on the pages
+                let first = |key: u64| {
+                    data.binary_search(&key)
+                        .map(|_| data.partition_point(|&x| x < key))
+                };
+                if let (Ok(start), Ok(end)) = (first(lo), first(hi)) {
+                    for position in start..=end {
+                        assert!(pages.contains(&(position / page_size)), "{}", position);
+                    }
+                }
+                if lo < hi {
+                    assert!(paged.pages_for_range(hi, lo).is_empty());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn paged_lookup() {
+        let mut rng = rand::thread_rng();
+        let mut data: Vec<u64> = (0..5000).map(|_| rng.gen_range(0..1_000_000)).collect();
+        data.extend([data[0]; 600]);
+        data.sort_unstable();
+        let mut probes: Vec<u64> = (0..300).map(|_| rng.gen_range(0..1_100_000)).collect();
+        probes.extend(data.iter().step_by(37));
+        probes.extend([0, data[0], data[data.len() - 1], u64::MAX]);
+
+        let radix_spline = RadixSpline::new(&data, RadixBits::new(10), MaxError::new(32));
+        check_pages(&radix_spline, &data, &probes);
+        check_pages(radix_spline.to_model(), &data, &probes);
+        check_pages(radix_spline.downsample(16), &data, &probes);
+        check_pages(
+            GreedySplineCorridor::new(&data, MaxError::new(32)),
+            &data,
+            &probes,
+        );
+    }
+
+    /// Bounds that straddle a page boundary take both pages, and bounds within a page take one.
+    #[test]
+    fn straddling() {
+        let data: Vec<u64> = (0..1000).collect();
+        let radix_spline = RadixSpline::new(&data, RadixBits::new(4), MaxError::new(0));
+        let paged = PagedLookup::new(radix_spline.to_model(), 256);
+        assert_eq!(paged.pages_for_key(255), 0..1);
+        assert_eq!(paged.pages_for_key(256), 1..2);
+        assert_eq!(paged.pages_for_range(255, 256), 0..2);
+        assert_eq!(paged.pages_for_range(0, 999), 0..4);
+        assert_eq!(paged.pages_for_range(10, 5), 0..0);
+        assert_eq!(paged.pages_for_key(1000), 3..3);
+        assert_eq!(paged.num_pages(), 4);
+        let paged = PagedLookup::new(&radix_spline, 1);
+        assert_eq!(paged.pages_for_key(500), 500..501);
+        assert_eq!(paged.pages_for_range(100, 199), 100..200);
+    }
+}
@@ -0,0 +1,167 @@
+//! # Build parameters
+//! Distinct types for the number of radix bits and the max error, so the two cannot be swapped silently,
+//! e.g., `RadixSpline::new(&data, RadixBits::new(18), MaxError::new(32))`.
+//! The constructors are `const`, so an out-of-range constant fails at compile time.
+
+use std::fmt;
+
+use crate::error::ConfigError;
+
+/// The number of radix bits, i.e., the radix table has at most `2^bits + 1` entries.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct RadixBits(u32);
+
+/// The max error of the spline, i.e., the half width of the window a search scans.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct MaxError(usize);
+
+impl RadixBits {
+    /// A table of `2^32 + 1` entries is already beyond the largest radix table.
+    pub const MAX: u32 = 32;
+    pub const DEFAULT: RadixBits = RadixBits(18);
+
+    /// Panics if `bits` exceeds `RadixBits::MAX`.
+    pub const fn new(bits: u32) -> Self {
+        assert!(bits <= Self::MAX, "the number of radix bits is at most 32");
+        RadixBits(bits)
+    }
+
+    pub const fn get(self) -> u32 {
+        self.0
+    }
+}
+
+impl MaxError {
+    /// Positions of the `compact-table` feature are 32-bit, so a larger error is meaningless.
+    pub const MAX: usize = u32::MAX as usize;
+    pub const DEFAULT: MaxError = MaxError(32);
+
+    /// Panics if `max_error` exceeds `MaxError::MAX`.
+    pub const fn new(max_error: usize) -> Self {
+        assert!(max_error <= Self::MAX, "the max error is at most u32::MAX");
+        MaxError(max_error)
+    }
+
+    pub const fn get(self) -> usize {
+        self.0
+    }
+}
+
+impl Default for RadixBits {
+    fn default() -> Self {
+        RadixBits::DEFAULT
+    }
+}
+
+impl Default for MaxError {
+    fn default() -> Self {
+        MaxError::DEFAULT
+    }
+}
+
+impl TryFrom<u32> for RadixBits {
+    type Error = ConfigError;
+
+    fn try_from(bits: u32) -> Result<Self, Self::Error> {
+        if bits <= RadixBits::MAX {
+            Ok(RadixBits(bits))
+        } else {
+            Err(ConfigError::RadixBitsOutOfRange {
+                num_radix_bits: bits,
+            })
+        }
+    }
+}
+
+impl TryFrom<usize> for MaxError {
+    type Error = ConfigError;
+
+    fn try_from(max_error: usize) -> Result<Self, Self::Error> {
+        if max_error <= MaxError::MAX {
+            Ok(MaxError(max_error))
+        } else {
+            Err(ConfigError::MaxErrorOutOfRange { max_error })
+        }
+    }
+}
+
+impl fmt::Display for RadixBits {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "radix_bits={}", self.0)
+    }
+}
+
+impl fmt::Display for MaxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "max_error={}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::error::BuildError;
+    use crate::RadixSpline;
+
+    const BITS: RadixBits = RadixBits::new(32);
+    const ERROR: MaxError = MaxError::new(MaxError::MAX);
+
+    #[test]
+    fn ranges() {
+        assert_eq!(BITS.get(), 32);
+        assert_eq!(ERROR.get(), u32::MAX as usize);
+        assert_eq!(RadixBits::try_from(0), Ok(RadixBits::new(0)));
+        assert_eq!(RadixBits::try_from(32), Ok(BITS));
+        assert_eq!(
+            RadixBits::try_from(33),
+            Err(ConfigError::RadixBitsOutOfRange { num_radix_bits: 33 })
+        );
+        assert_eq!(MaxError::try_from(0), Ok(MaxError::new(0)));
+        assert_eq!(MaxError::try_from(MaxError::MAX), Ok(ERROR));
+        assert_eq!(
+            MaxError::try_from(MaxError::MAX + 1),
+            Err(ConfigError::MaxErrorOutOfRange {
+                max_error: MaxError::MAX + 1
+            })
+        );
+        assert!(
+            BuildError::from(RadixBits::try_from(64).unwrap_err()).is_retryable_with_new_config()
+        );
+        assert_eq!(RadixBits::default(), RadixBits::new(18));
+        assert_eq!(MaxError::default(), MaxError::new(32));
+    }
+
+    #[test]
+    #[should_panic(expected = "the number of radix bits is at most 32")]
+    fn out_of_range() {
+        RadixBits::new(33);
+    }
+
+    /// The positional form still builds the same index.
+    #[test]
+    #[allow(deprecated)]
+    fn positional() {
+        let data: Vec<u64> = (0..1000).map(|x| x * x).collect();
+        let typed = RadixSpline::new(&data, RadixBits::new(12), MaxError::new(4));
+        let positional = RadixSpline::new_positional(&data, 12, 4);
+        assert_eq!(positional.points(), typed.points());
+        assert_eq!(positional.table(), typed.table());
+        assert_eq!(positional.to_string(), typed.to_string());
+    }
+
+    #[test]
+    fn display() {
+        let data: Vec<u64> = (0..1000).map(|x| x * x).collect();
+        let radix_spline = RadixSpline::new(&data, RadixBits::new(8), MaxError::new(4));
+        assert_eq!(
+            radix_spline.to_string(),
+            format!(
+                "RadixSpline(keys=1000, radix_bits=8, max_error=4, points={}, table_entries={})",
+                radix_spline.points().len(),
+                radix_spline.table().len()
+            )
+        );
+        assert_eq!(RadixBits::new(8).to_string(), "radix_bits=8");
+        assert_eq!(MaxError::new(4).to_string(), "max_error=4");
+    }
+}
@@ -0,0 +1,124 @@
+//! # Bucket partitions
+//! Split the data into ranges of positions along radix-bucket boundaries, e.g., the work of a parallel scan.
+//! A boundary never splits the keys of one radix prefix, so each part can be scanned with its own buckets.
+
+use std::ops::Range;
+
+use crate::radix::RadixSpline;
+
+impl<'a> RadixSpline<'a> {
+    /// Up to `target_parts` ranges of positions which exactly cover `0..data.len()` in order, without overlap,
+    /// each starting at the first key of a radix bucket. The cut for each of the evenly spaced targets is
+    /// the nearest bucket boundary, found by a bisection over the prefixes, so a bucket larger than a part
+    /// is kept whole and empty parts are dropped. A single bucket gives a single range.
+    pub fn bucket_partitions(&self, target_parts: usize) -> Vec<Range<usize>> {
+        assert!(target_parts > 0, "at least one part is needed");
+        let len = self.data().len();
+        let shift = self.shift_radix_bits();
+        let max_prefix = (self.max_key() - self.min_key()) >> shift;
+        // the position of the first key of `prefix`, which is at most `max_prefix`
+        let bucket_start =
+            |prefix: u64| self.lower_bound_of_boundary(self.min_key() + (prefix << shift));
+
+        let mut cuts = vec![0];
+        for part in 1..target_parts {
+            let target = (part as u128 * len as u128 / target_parts as u128) as usize;
+            // the first bucket starting at or after `target`, or `max_prefix + 1` for none
+            let (mut low, mut high) = (1, max_prefix + 1);
+            while low < high {
+                let mid = low + (high - low) / 2;
+                if bucket_start(mid) >= target {
+                    high = mid;
+                } else {
+                    low = mid + 1;
+                }
+            }
+            let after = if low > max_prefix {
+                len
+            } else {
+                bucket_start(low)
+            };
+            let before = if low > 1 { bucket_start(low - 1) } else { 0 };
+            let cut = if target - before <= after - target {
+                before
+            } else {
+                after
+            };
+            if cut > cuts[cuts.len() - 1] && cut < len {
+                cuts.push(cut);
+            }
+        }
+        cuts.push(len);
+        cuts.windows(2).map(|w| w[0]..w[1]).collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{MaxError, RadixBits, RadixSpline};
+    use rand::Rng;
+
+    /// The parts cover the data in order, and no prefix is split between two parts.
+    fn check(radix_spline: &RadixSpline, target_parts: usize) -> Vec<usize> {
+        let data = radix_spline.data();
+        let parts = radix_spline.bucket_partitions(target_parts);
+        assert!(!parts.is_empty() && parts.len() <= target_parts);
+        assert_eq!(parts[0].start, 0);
+        assert_eq!(parts[parts.len() - 1].end, data.len());
+        for (i, part) in parts.iter().enumerate() {
+            assert!(!part.is_empty());
+            if i > 0 {
+                assert_eq!(parts[i - 1].end, part.start);
+                assert_ne!(
+                    radix_spline.prefix_of(data[part.start - 1]),
+                    radix_spline.prefix_of(data[part.start])
+                );
+            }
+        }
+        parts.iter().map(|part| part.len()).collect()
+    }
+
+    #[test]
+    fn skewed() {
+        let mut rng = rand::thread_rng();
+        // a dense cluster holding most keys, a sparse spread, and a long run
+        let mut data: Vec<u64> = (0..50_000)
+            .map(|_| rng.gen_range(1 << 30..(1 << 30) + 1000))
+            .collect();
+        data.extend((0..5_000).map(|_| rng.gen_range(0..1 << 40)));
+        data.extend(std::iter::repeat_n(1 << 35, 3_000));
+        data.sort_unstable();
+        for num_radix_bits in [1, 4, 12, 20] {
+            let radix_spline =
+                RadixSpline::new(&data, RadixBits::new(num_radix_bits), MaxError::new(16));
+            for target_parts in [1, 2, 7, 64, 100_000] {
+                check(&radix_spline, target_parts);
+            }
+        }
+    }
+
+    #[test]
+    fn balanced() {
+        let data: Vec<u64> = (0..100_000u64).map(|x| x * 37).collect();
+        let radix_spline = RadixSpline::new(&data, RadixBits::new(16), MaxError::new(32));
+        let sizes = check(&radix_spline, 8);
+        assert_eq!(sizes.len(), 8);
+        assert!(
+            sizes.iter().all(|&size| size.abs_diff(100_000 / 8) < 100),
+            "{:?}",
+            sizes
+        );
+    }
+
+    #[test]
+    fn single_bucket() {
+        for data in [vec![7; 100], vec![1, 2, 3], vec![5]] {
+            let radix_spline = RadixSpline::new(&data, RadixBits::new(0), MaxError::new(4));
+            assert_eq!(radix_spline.bucket_partitions(10), vec![0..data.len()]);
+        }
+        let data: Vec<u64> = (0..1000).collect();
+        let radix_spline = RadixSpline::new(&data, RadixBits::new(1), MaxError::new(4));
+        assert_eq!(radix_spline.bucket_partitions(1), vec![0..1000]);
+        assert_eq!(check(&radix_spline, 4), vec![512, 488]);
+    }
+}
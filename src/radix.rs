@@ -1,211 +1,2866 @@
 //! # A builder for radix spline index
-//! Building the `spline points` and `radix table` in **one-pass**.
+//! Building the `spline points` in **one-pass** over the data, and then the `radix table` in one sweep over the points.
+//!
+//! ## Duplicates
+//! The builder skips every repeated key, so a run of duplicates is seen once, at its first occurrence.
+//! Hence the spline point keys are strictly increasing, and each point sits at the first occurrence of its key,
+//! wherever a run starts or ends relative to the points the corridor emits. `search` relies on both, and `verify` checks them.
 
-use std::process::id;
+use std::alloc::GlobalAlloc;
+use std::borrow::Cow;
+use std::fmt;
+use std::ops::{Bound, Deref, Range, RangeBounds};
+use std::sync::{Arc, OnceLock};
 
+#[cfg(feature = "accuracy-study")]
+use crate::accuracy::AccuracyStats;
+use crate::block::BlockIndex;
+use crate::buffer::{Buffer, RawBuffer, SharedAlloc};
 use crate::common::Line;
 use crate::common::Point;
+use crate::common::Key;
+use crate::common::{widen, Points, Position, SegmentBiases, SegmentErrors, Table};
+use crate::error::{BuildError, ConfigError, DataError};
+use crate::owned::OwnedRadixSpline;
+use crate::params::{MaxError, RadixBits};
+use crate::rle::RleRadixSpline;
+use crate::spline_corridor::GreedySplineCorridor;
+use crate::table::{RadixTable, TableLayout};
 
-/// `RadixSpline` builds an index for sorted data (assuming `u64`).
+/// `RadixSpline` builds an index for sorted data of `u64` keys, or of another unsigned `Key` type.
 /// Given a `key`, we compute it by `shift_radix_bits` -> the index of `table`. And the value of `table` is a pointer, indicting the position of `points`. `points` is an error-bounded spline by interpolating, and it can be used to predict the position of `key`.
-pub struct RadixSpline<'a> {
-    data: &'a Vec<u64>, // sorted data
-    min_key: u64,
+pub struct RadixSpline<'a, K: Key = u64> {
+    data: Cow<'a, [K]>, // sorted data
+    min_key: K,
     shift_radix_bits: u32, // it is computed from `num_radix_bits`
     max_error: usize,      // max error bound
-    points: Vec<Point>,    // spline points
-    table: Vec<usize>,     // radix table
+    points: Buffer<Points<K>, Point<K>>, // spline points
+    table: RadixTable,                   // radix table
+    // the actual max error of each segment, indexed by the segment's end point, see `segment_error`
+    segment_errors: Buffer<SegmentErrors, u8>,
+    // the mean signed error of each segment, indexed as `segment_errors`, see `SearchStrategy::BiasedProbe`
+    segment_biases: Buffer<SegmentBiases, i8>,
+    // runs of duplicates longer than the `max_run_hint`, sorted by key
+    long_runs: Vec<LongRun<K>>,
+    uniform_timing: bool, // always take the full search path
+    direct_table: bool,   // each prefix is a single key, see `get_spline_segment`
+    linear: bool,         // `data` is an arithmetic progression of distinct keys, so `max_error` is 0
+    search_strategy: SearchStrategy,
+    // `estimated_search_comparisons`, computed on first use
+    search_comparisons: OnceLock<f64>,
+    #[cfg(feature = "accuracy-study")]
+    accuracy: AccuracyStats,
 }
 
-fn get_num_shift_bits(diff: u64, num_radix_bits: u32) -> u32 {
+/// The positions `start..stop` that hold the first occurrence of a present key, see `search_bound`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SearchBound {
+    pub start: usize,
+    pub stop: usize,
+}
+
+/// How `search` looks for a key in the window around its prediction, see `with_search_strategy`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SearchStrategy {
+    /// a binary search over the whole window
+    #[default]
+    Window,
+    /// probe the prediction, and then the prediction shifted by the mean signed error of its segment,
+    /// each probe either resolving the first occurrence or narrowing the window for the binary search
+    BiasedProbe,
+}
+
+/// A run of one key spanning `data[start..start + len]`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LongRun<K = u64> {
+    pub key: K,
+    pub start: usize,
+    pub len: usize,
+}
+
+/// A `max_error` of at least half the data length is degenerate: the corridor bounds reach past the data,
+/// and the window covers nearly all keys. So it is clamped below `data.len() / 2`, which `max_error()` reports.
+pub(crate) fn effective_max_error(max_error: usize, len: usize) -> usize {
+    max_error.min((len / 2).saturating_sub(1))
+}
+
+/// The largest radix table, which is already 32 GB with 64-bit positions.
+const MAX_TABLE_ENTRIES: usize = u32::MAX as usize;
+
+/// The first of `points[start..]` whose key is not less than `key`, one point at a time.
+/// Such a point must exist, e.g., the end of the radix bucket of `key`.
+#[inline]
+pub(crate) fn scan_points<K: Key>(points: &[Point<K>], start: usize, key: K) -> usize {
+    let mut current = start;
+    while points[current].key() < key {
+        current += 1;
+    }
+    current
+}
+
+/// The keys that `scan_points_chunked` compares at a time.
+#[cfg(any(feature = "simd", feature = "testing", test))]
+const SCAN_LANES: usize = 8;
+
+/// `scan_points`, comparing `SCAN_LANES` keys at a time. The keys are sorted, so the number of keys less than `key`
+/// in a chunk is the offset of the first one not less, and the branch-free count compiles to vector comparisons.
+#[cfg(any(feature = "simd", feature = "testing", test))]
+#[inline]
+pub(crate) fn scan_points_chunked<K: Key>(points: &[Point<K>], start: usize, key: K) -> usize {
+    let rest = &points[start..];
+    let mut chunks = rest.chunks_exact(SCAN_LANES);
+    for (i, chunk) in chunks.by_ref().enumerate() {
+        let chunk: &[Point<K>; SCAN_LANES] = chunk.try_into().expect("an exact chunk");
+        let less: usize = chunk.iter().map(|p| usize::from(p.key() < key)).sum();
+        if less < SCAN_LANES {
+            return start + i * SCAN_LANES + less;
+        }
+    }
+    scan_points(points, points.len() - chunks.remainder().len(), key)
+}
+
+pub(crate) fn get_num_shift_bits<K: Key>(diff: K, num_radix_bits: u32) -> u32 {
     let zeros = diff.leading_zeros();
-    // note all keys here `u64`.
-    if 64 - zeros < num_radix_bits {
+    // the bits of the key type, e.g., 64 for `u64`
+    if K::BITS - zeros < num_radix_bits {
         0
     } else {
-        64 - num_radix_bits - zeros
+        // shifting a key by its width overflows, so keep at least one prefix bit
+        (K::BITS - zeros - num_radix_bits).min(K::BITS - 1)
     }
 }
 
-impl<'a> RadixSpline<'a> {
-    /// `data` is sorted, whose size is at least 3.
-    pub fn new(data: &'a Vec<u64>, num_radix_bits: u32, max_error: usize) -> Self {
-        assert!(data.len() >= 3);
-        let min_key = data[0];
-        let max_key = data[data.len() - 1];
+impl<'a, K: Key> RadixSpline<'a, K> {
+    /// Build over sorted, non-empty keys of any `Key` type, e.g., `u32` ids or `u128` composite keys, as `new`.
+    pub fn from_keys(data: &'a [K], num_radix_bits: RadixBits, max_error: MaxError) -> Self {
+        RadixSpline::from_cow(Cow::Borrowed(data), num_radix_bits.get(), max_error.get())
+    }
+
+    /// The same as `from_keys`, but checking that `data` is sorted, as `try_new`.
+    pub fn try_from_keys(
+        data: &'a [K],
+        num_radix_bits: RadixBits,
+        max_error: MaxError,
+    ) -> Result<Self, BuildError> {
+        check_sorted(data)?;
+        RadixSpline::try_from_cow(Cow::Borrowed(data), num_radix_bits.get(), max_error.get())
+    }
+
+    /// The owned data, to corrupt it in tests.
+    #[cfg(test)]
+    pub(crate) fn data_mut(&mut self) -> &mut [K] {
+        self.data.to_mut()
+    }
+
+    /// Build an index which owns its sorted `data`.
+    pub(crate) fn from_vec(
+        data: Vec<K>,
+        num_radix_bits: u32,
+        max_error: usize,
+    ) -> RadixSpline<'static, K> {
+        RadixSpline::from_cow(Cow::Owned(data), num_radix_bits, max_error)
+    }
+
+    /// Build an index over a borrowed sorted `data`.
+    pub(crate) fn from_slice(data: &'a [K], num_radix_bits: u32, max_error: usize) -> Self {
+        RadixSpline::from_cow(Cow::Borrowed(data), num_radix_bits, max_error)
+    }
+
+    fn from_cow(data: Cow<'a, [K]>, num_radix_bits: u32, max_error: usize) -> Self {
+        RadixSpline::try_from_cow(data, num_radix_bits, max_error)
+            .unwrap_or_else(|error| panic!("{}", error))
+    }
 
-        let shift_radix_bits = get_num_shift_bits(max_key - min_key, num_radix_bits);
+    pub(crate) fn try_from_cow(
+        data: Cow<'a, [K]>,
+        num_radix_bits: u32,
+        max_error: usize,
+    ) -> Result<Self, BuildError> {
+        let max_error = effective_max_error(max_error, data.len());
+        let mut points = Points::<K>::new();
+        let mut table = Table::new();
+        let shift_radix_bits =
+            RadixSpline::build_into(&data, num_radix_bits, max_error, &mut points, &mut table)?;
+        let table = RadixTable::auto(table, data.len());
+        Ok(RadixSpline::assemble(data, shift_radix_bits, max_error, points, table))
+    }
 
-        let max_prefix = (max_key - min_key) >> shift_radix_bits;
-        let mut table = vec![0; (max_prefix + 2) as usize];
+    /// The same as `try_from_cow`, with the points, table and segment errors in `alloc`.
+    /// The table stays flat, as the run-length layout is built on the heap.
+    pub(crate) fn try_from_cow_in(
+        data: Cow<'a, [K]>,
+        num_radix_bits: u32,
+        max_error: usize,
+        alloc: SharedAlloc,
+    ) -> Result<Self, BuildError> {
+        check_len(&data)?;
+        let max_error = effective_max_error(max_error, data.len());
+        let (min_key, max_key) = (data[0], data[data.len() - 1]);
+        let (shift_radix_bits, table_len) = table_shape(min_key, max_key, num_radix_bits)?;
+        let mut table = RawBuffer::try_filled_in(alloc.clone(), table_len, 0)
+            .map_err(|_| ConfigError::TableTooLarge { num_radix_bits })?;
+        let mut points = RawBuffer::new_in(alloc);
+        RadixSpline::build_points(&mut points, &data, max_error);
+        // the `parallel` fill collects the prefixes of the points on the heap
+        build_table_sequential(&mut table, &points, min_key, shift_radix_bits);
+        let table = RadixTable::Flat(Buffer::Raw(table));
+        Ok(RadixSpline::assemble(data, shift_radix_bits, max_error, Buffer::Raw(points), table))
+    }
 
-        let mut points: Vec<Point> = vec![];
+    /// Build `points` and `table` over `data` into the given (empty) buffers, returning `shift_radix_bits`.
+    /// `data` is assumed to be sorted.
+    pub(crate) fn build_into(
+        data: &[K],
+        num_radix_bits: u32,
+        max_error: usize,
+        points: &mut Points<K>,
+        table: &mut Table,
+    ) -> Result<u32, BuildError> {
+        check_len(data)?;
+        let min_key = data[0];
+        let max_key = data[data.len() - 1];
+        let shift_radix_bits = allocate_table(table, min_key, max_key, num_radix_bits)?;
 
         // build `points` and `table`
         RadixSpline::build(
-            &mut points,
-            &mut table,
+            points,
+            table,
             data,
             min_key,
             shift_radix_bits,
             max_error,
         );
+        Ok(shift_radix_bits)
+    }
+
+    /// Build an index over sorted `data` from the `points` a `Corridor` built over it with `max_error`,
+    /// which must already be the effective one, so only the radix table is left.
+    pub(crate) fn from_points(
+        data: Cow<'a, [K]>,
+        points: Points<K>,
+        num_radix_bits: u32,
+        max_error: usize,
+    ) -> Result<Self, BuildError> {
+        #[cfg(feature = "compact-table")]
+        if data.len() > u32::MAX as usize {
+            return Err(DataError::TooManyKeys { len: data.len() }.into());
+        }
+        let (min_key, max_key) = (data[0], data[data.len() - 1]);
+        let mut table = Table::new();
+        let shift_radix_bits = allocate_table(&mut table, min_key, max_key, num_radix_bits)?;
+        build_table(&mut table, &points, min_key, shift_radix_bits);
+        let table = RadixTable::auto(table, data.len());
+        Ok(RadixSpline::assemble(data, shift_radix_bits, max_error, points, table))
+    }
+
+    /// Rebuild an index from stored parts, e.g., a persisted blob, checking that `points` and `table` describe `data`.
+    /// The segment errors are taken from `segments`, as of `segment_words`, and measured over `data` without them.
+    pub(crate) fn from_parts(
+        data: Cow<'a, [K]>,
+        shift_radix_bits: u32,
+        max_error: usize,
+        points: Points<K>,
+        table: Table,
+        segments: Option<&[u64]>,
+    ) -> Result<Self, &'static str> {
+        if data.is_empty() || shift_radix_bits >= K::BITS {
+            return Err("bad header");
+        }
+        let (min_key, max_key) = (data[0], data[data.len() - 1]);
+        match (points.first(), points.last()) {
+            (Some(first), Some(last))
+                if first.key() == min_key && first.position() == 0 && last.key() == max_key => {}
+            _ => return Err("spline points do not span the data"),
+        }
+        // each point is at the first occurrence of its key
+        let on_data = |p: &Point<K>| {
+            let position = p.position();
+            position < data.len()
+                && data[position] == p.key()
+                && (position == 0 || data[position - 1] < p.key())
+        };
+        if !points.iter().all(on_data) {
+            return Err("spline points are not on the data");
+        }
+
+        let max_prefix = max_key.prefix(min_key, shift_radix_bits);
+        if usize::try_from(max_prefix).ok().and_then(|p| p.checked_add(2)) != Some(table.len()) {
+            return Err("bad radix table length");
+        }
+        let mut expected: Table = std::iter::repeat_n(0, table.len()).collect();
+        build_table(&mut expected, &points, min_key, shift_radix_bits);
+        if expected[..] != table[..] {
+            return Err("radix table does not match the spline points");
+        }
+
+        let segments = match segments {
+            Some(words) if words.len() == points.len() && words.iter().all(|&w| w >> 16 == 0) => {
+                let errors = words.iter().map(|&w| w as u8).collect();
+                let biases = words.iter().map(|&w| (w >> 8) as u8 as i8).collect();
+                Some((errors, biases))
+            }
+            Some(_) => return Err("bad segment errors"),
+            None => None,
+        };
+
+        let table = RadixTable::auto(table, data.len());
+        let radix_spline =
+            RadixSpline::assemble_with(data, shift_radix_bits, max_error, points, table, segments);
+        radix_spline.validate_points().map_err(|_| "spline points are not sorted")?;
+        Ok(radix_spline)
+    }
+
+    /// The segment errors are kept in the allocator of `points`.
+    pub(crate) fn assemble(
+        data: Cow<'a, [K]>,
+        shift_radix_bits: u32,
+        max_error: usize,
+        points: impl Into<Buffer<Points<K>, Point<K>>>,
+        table: RadixTable,
+    ) -> Self {
+        RadixSpline::assemble_with(data, shift_radix_bits, max_error, points, table, None)
+    }
+
+    /// The same as `assemble`, with the segment errors and biases of a persisted index if there are any,
+    /// which spares the pass over the data that measures them.
+    fn assemble_with(
+        data: Cow<'a, [K]>,
+        shift_radix_bits: u32,
+        max_error: usize,
+        points: impl Into<Buffer<Points<K>, Point<K>>>,
+        table: RadixTable,
+        segments: Option<(SegmentErrors, SegmentBiases)>,
+    ) -> Self {
+        let min_key = data[0];
+        let points = points.into();
+
+        let (segment_errors, segment_biases, linear) = match segments {
+            // a linear index persists a `max_error` of 0, and an index of at most two points
+            // whose keys are all predicted exactly searches the same as a linear one
+            Some((errors, biases)) => {
+                let linear = points.len() <= 2 && max_error == 0;
+                (errors.into(), biases.into(), linear)
+            }
+            None => {
+                let mut segment_errors = points.filled_like(points.len(), 0);
+                let mut segment_biases = points.filled_like(points.len(), 0);
+                // the greedy pass yields two points for an arithmetic progression, where the prediction is exact
+                let linear = points.len() <= 2 && is_arithmetic(&data);
+                if !linear {
+                    record_segment_errors(&data, &points, &mut segment_errors, &mut segment_biases);
+                }
+                (segment_errors, segment_biases, linear)
+            }
+        };
 
         RadixSpline {
             data,
             min_key,
             shift_radix_bits,
-            max_error,
+            max_error: if linear { 0 } else { max_error },
             points,
             table,
+            segment_errors,
+            segment_biases,
+            long_runs: vec![],
+            uniform_timing: false,
+            search_strategy: SearchStrategy::default(),
+            direct_table: shift_radix_bits == 0,
+            linear,
+            search_comparisons: OnceLock::new(),
+            #[cfg(feature = "accuracy-study")]
+            accuracy: AccuracyStats::default(),
         }
     }
 
-    fn build(
-        points: &mut Vec<Point>,
-        table: &mut [usize],
-        data: &Vec<u64>,
-        min_key: u64,
-        shift_radix_bits: u32,
-        max_error: usize,
-    ) {
-        points.push(Point::new(data[0], 0));
+    /// By default, `search` returns directly when `key` hits a spline point, which makes such queries much faster than others.
+    /// With `uniform_timing`, every query takes the full interpolate-and-search path, trading that shortcut for a lower variance of latency, e.g., when benchmarking percentiles.
+    pub fn with_uniform_timing(mut self, uniform_timing: bool) -> Self {
+        self.uniform_timing = uniform_timing;
+        self
+    }
 
-        let mut c_base = Point::new(data[0], 0);
+    /// Look for a key in the window around its prediction with `strategy`, by default a binary search over the window.
+    /// Both find the same positions, so only the number of keys compared differs, see `search_counted`.
+    pub fn with_search_strategy(mut self, strategy: SearchStrategy) -> Self {
+        self.search_strategy = strategy;
+        self
+    }
 
-        // error corridor bounds
-        let mut upper = Point::new(data[1], 1 + max_error);
-        let mut lower = Point::new(data[1], 1usize.saturating_sub(max_error));
+    /// Store the radix table in `layout`. By default, the run-length layout is chosen when it takes less than a quarter of the flat table's memory,
+    /// e.g., for clustered keys leaving most prefixes empty. It costs a binary search over the runs per lookup instead of one read.
+    pub fn with_table_layout(mut self, layout: TableLayout) -> Self {
+        if layout != self.table.layout() {
+            self.table = RadixTable::with_layout(&self.table.to_flat(), layout);
+        }
+        self
+    }
 
-        let mut last_prefix = 0usize;
-        // note `i` starts from `0`
-        for (i, &key) in data[2..].iter().enumerate() {
-            let i = i + 2;
-            let point_c = Point::new(key, i);
-
-            // line BC (base -> point_c)
-            let bc = Line::new(c_base, point_c);
-            // line BU (base -> upper)
-            let bu = Line::new(c_base, upper);
-            // line BL (base -> lower)
-            let bl = Line::new(c_base, lower);
-
-            // continue if `bc` or `bu` or `bl`'s `dx` is 0
-            // skip the repeated values
-            if bc.is_vertical() || bu.is_vertical() || bl.is_vertical() {
-                upper = Point::new(point_c.key(), i + max_error);
-                lower = Point::new(point_c.key(), i.saturating_sub(max_error));
-                continue;
-            }
-
-            if bc.is_left(&bu) || bc.is_right(&bl) {
-                c_base = Point::new(data[i - 1], i - 1);
-                points.push(c_base);
-                
-                // update table
-                let current_prefix = ((data[i - 1] - min_key) >> shift_radix_bits) as usize;
-                if current_prefix > last_prefix {
-                    table[last_prefix+1..=current_prefix].fill(points.len() - 1);
-                    last_prefix = current_prefix;
-                }
-                // end updating table
-
-                upper = Point::new(point_c.key(), i + max_error);
-                lower = Point::new(point_c.key(), i.saturating_sub(max_error));
-            } else {
-                let _upper = Point::new(point_c.key(), i + max_error);
-                let _lower = Point::new(point_c.key(), i.saturating_sub(max_error));
+    /// The same index with `num_radix_bits`, e.g., to tune it: the spline depends only on `max_error`,
+    /// so the points and segment errors are reused and only the radix table is rebuilt, in one sweep over the points.
+    /// Borrowed data is shared, while owned data is cloned. The table layout is chosen automatically again.
+    pub fn with_radix_bits(&self, num_radix_bits: RadixBits) -> Result<Self, BuildError> {
+        let max_key = self.data[self.data.len() - 1];
+        let mut table = Table::new();
+        let shift_radix_bits =
+            allocate_table(&mut table, self.min_key, max_key, num_radix_bits.get())?;
+        build_table(&mut table, &self.points, self.min_key, shift_radix_bits);
+        Ok(RadixSpline {
+            data: self.data.clone(),
+            min_key: self.min_key,
+            shift_radix_bits,
+            max_error: self.max_error,
+            points: self.points.clone(),
+            table: RadixTable::auto(table, self.data.len()),
+            segment_errors: self.segment_errors.clone(),
+            segment_biases: self.segment_biases.clone(),
+            long_runs: self.long_runs.clone(),
+            uniform_timing: self.uniform_timing,
+            search_strategy: self.search_strategy,
+            direct_table: shift_radix_bits == 0,
+            linear: self.linear,
+            search_comparisons: OnceLock::new(),
+            #[cfg(feature = "accuracy-study")]
+            accuracy: AccuracyStats::default(),
+        })
+    }
 
-                // line BU' (base -> _upper)
-                let _bu = Line::new(c_base, _upper);
-                // line BL' (base -> _lower)
-                let _bl = Line::new(c_base, _lower);
-                if bu.is_left(&_bu) {
-                    upper = _upper;
-                }
-                if bl.is_right(&_bl) {
-                    lower = _lower;
-                }
+    /// Record every run of duplicates longer than `max_run_hint` in a side table, so that `search` and `equal_range` resolve those keys without scanning the run.
+    /// The spline already treats a run as a single point at its first occurrence.
+    pub fn with_max_run_hint(mut self, max_run_hint: usize) -> Self {
+        let mut long_runs = vec![];
+        let mut start = 0;
+        while start < self.data.len() {
+            let key = self.data[start];
+            let len = self.data[start..].partition_point(|&x| x == key);
+            if len > max_run_hint {
+                long_runs.push(LongRun { key, start, len });
             }
-        } // end of for
+            start += len;
+        }
+        self.long_runs = long_runs;
+        self
+    }
 
-        let n = data.len();
-        points.push(Point::new(data[n - 1], n - 1));
+    /// The runs recorded by `with_max_run_hint`, sorted by key.
+    pub fn long_runs(&self) -> &[LongRun<K>] {
+        &self.long_runs
+    }
 
-        // update table
-        let current_prefix = ((data[n - 1] - min_key) >> shift_radix_bits) as usize;
-        if current_prefix > last_prefix {
-            table[last_prefix + 1..=current_prefix].fill(points.len() - 1);
-            last_prefix = current_prefix;
+    fn long_run(&self, key: K) -> Option<&LongRun<K>> {
+        if self.long_runs.is_empty() {
+            return None;
         }
-        table[last_prefix + 1..].fill(points.len());
+        let idx = self.long_runs.binary_search_by_key(&key, |run| run.key).ok()?;
+        Some(&self.long_runs[idx])
     }
 
-    /// default `max_radix_bits` is 18, and default `max_error` is 32
-    pub fn default(data: &'a Vec<u64>) -> Self {
-        RadixSpline::new(data, 18, 32)
+    /// Build the spline `points` in one pass over `data`, then fill the radix `table` in one sweep over the points.
+    fn build(
+        points: &mut Points<K>,
+        table: &mut [Position],
+        data: &[K],
+        min_key: K,
+        shift_radix_bits: u32,
+        max_error: usize,
+    ) {
+        RadixSpline::build_points(points, data, max_error);
+        build_table(table, points, min_key, shift_radix_bits);
+    }
+
+    fn build_points(points: &mut impl PointSink<K>, data: &[K], max_error: usize) {
+        let mut corridor = Corridor::new(points, data[0], max_error);
+        for (i, &key) in data.iter().enumerate().skip(1) {
+            corridor.push(points, key, i);
+        }
+        corridor.finish(points);
     }
 
-    fn get_spline_segment(&self, key: u64) -> usize {
-        let c_prefix = ((key - self.min_key) >> self.shift_radix_bits) as usize;
+    pub(crate) fn get_spline_segment(&self, key: K) -> usize {
+        let c_prefix = key.prefix(self.min_key, self.shift_radix_bits) as usize;
 
-        let _start = self.table[c_prefix];
-        let _end = self.table[c_prefix + 1];
+        // The fast path is active if `shift_radix_bits` is 0, i.e., `max_key - min_key` fits in `num_radix_bits` bits (e.g., dense integer keys).
+        // Then the prefix of `key` is `key - min_key` itself, so `table[c_prefix]` is already the first spline point not less than `key`.
+        let (start, end) = self.table.bucket(c_prefix);
+        if self.direct_table {
+            return start;
+        }
 
-        if _end - _start < 32 {
+        if end - start < 32 {
             // linear search
-            let mut _current = _start;
-            while self.points[_current].key() < key {
-                _current += 1;
-            }
-            return _current;
+            #[cfg(feature = "simd")]
+            return scan_points_chunked(&self.points, start, key);
+            #[cfg(not(feature = "simd"))]
+            return scan_points(&self.points, start, key);
         }
 
         // a binary search
         let key_point = Point::new(key, 0);
-        match self.points[_start.._end].binary_search(&key_point) {
-             Ok(idx) => _start + idx,
-             Err(idx) => _start + idx,
+        match self.points[start..end].binary_search(&key_point) {
+             Ok(idx) => start + idx,
+             Err(idx) => start + idx,
         }
     } 
-    /// search a given `key`
-    pub fn search(&self, key: u64) -> Option<usize> {
 
+    /// The segment errors and biases, one word per spline point with the error in the low byte and the bias
+    /// in the next, which the blob and model formats persist, see `from_parts`.
+    pub(crate) fn segment_words(&self) -> impl ExactSizeIterator<Item = u64> + '_ {
+        let biases = self.segment_biases.iter();
+        self.segment_errors
+            .iter()
+            .zip(biases)
+            .map(|(&error, &bias)| u64::from(error) | u64::from(bias as u8) << 8)
+    }
+
+    /// The error bound of the spline segment ending at `point_location`, i.e., its recorded error,
+    /// or `max_error` if the recorded error saturated.
+    pub(crate) fn segment_error(&self, point_location: usize) -> usize {
+        match self.segment_errors[point_location] {
+            u8::MAX => self.max_error,
+            error => usize::from(error).min(self.max_error),
+        }
+    }
+
+    /// predict the position of `key`, which must be within `[min_key, max_key]`.
+    pub(crate) fn predict(&self, key: K) -> usize {
+        self.predict_in_segment(key, self.get_spline_segment(key))
+    }
+
+    /// predict the position of `key` in the spline segment ending at `point_location`, see `get_spline_segment`.
+    pub(crate) fn predict_in_segment(&self, key: K, point_location: usize) -> usize {
+        let end = self.points[point_location];
+        if end.key() == key || point_location == 0 {
+            return end.position();
+        }
+        interpolate(self.points[point_location - 1], end, key)
+    }
+
+    /// The indexed data, so positions can be mapped back to keys without carrying the data separately.
+    pub fn data(&self) -> &[K] {
+        &self.data
+    }
+
+    /// The indexed data, dropping the index, e.g., to get owned data back without a copy.
+    pub(crate) fn into_data(self) -> Cow<'a, [K]> {
+        self.data
+    }
+
+    /// The key at `pos`, or `None` if `pos` is out of bounds.
+    pub fn key_at_pos(&self, pos: usize) -> Option<K> {
+        self.data.get(pos).copied()
+    }
+
+    /// The keys at a range of positions, e.g., from `search` to a later position.
+    /// Positions are clamped to the data bounds, so a range past the end is empty.
+    pub fn resolve_range(&self, positions: Range<usize>) -> &[K] {
+        let end = positions.end.min(self.data.len());
+        let start = positions.start.min(end);
+        &self.data[start..end]
+    }
+
+    pub(crate) fn points(&self) -> &[Point<K>] {
+        &self.points
+    }
+
+    /// The flat radix table, which is expanded if it is stored run-length encoded.
+    pub(crate) fn table(&self) -> Cow<'_, [Position]> {
+        self.table.to_flat()
+    }
+
+    /// The layout of the radix table, chosen when building, see `with_table_layout`.
+    pub fn table_layout(&self) -> TableLayout {
+        self.table.layout()
+    }
+
+    /// The memory of the radix table in bytes.
+    pub fn table_bytes(&self) -> usize {
+        self.table.bytes()
+    }
+
+    /// The memory of the model in bytes, on the heap or in the allocator of `new_in`: the spline points, their segment errors and biases, the long runs
+    /// and the radix table. The keys are excluded, even if the index owns them, e.g., to compare with a B-tree over the same keys.
+    pub fn size_in_bytes(&self) -> usize {
+        size_of_val(self.points())
+            + size_of_val(&self.segment_errors[..])
+            + size_of_val(&self.segment_biases[..])
+            + size_of_val(self.long_runs())
+            + self.table_bytes()
+    }
+
+    pub fn num_spline_points(&self) -> usize {
+        self.points.len()
+    }
+
+    /// The number of radix table entries whatever the layout: one per prefix up to that of `max_key`, and an end entry.
+    pub fn table_len(&self) -> usize {
+        self.table.len()
+    }
+
+    #[cfg(feature = "accuracy-study")]
+    pub(crate) fn accuracy_stats(&self) -> &AccuracyStats {
+        &self.accuracy
+    }
+
+    pub(crate) fn shift_radix_bits(&self) -> u32 {
+        self.shift_radix_bits
+    }
+
+    /// The radix bits of the table, i.e., of the largest prefix, e.g., the number chosen by `auto`.
+    /// It is the requested number, or fewer if the key range `max_key - min_key` has fewer bits.
+    pub fn num_radix_bits(&self) -> u32 {
+        let diff = self.max_key().checked_sub(self.min_key).expect("the keys are sorted");
+        (K::BITS - diff.leading_zeros()).saturating_sub(self.shift_radix_bits)
+    }
+
+    /// The radix prefix (the index of `table`) of `key`, if `key` is within `[min_key, max_key]`.
+    pub fn prefix_of(&self, key: K) -> Option<usize> {
+        if key < self.min_key || key > self.max_key() {
+            return None;
+        }
+        Some(key.prefix(self.min_key, self.shift_radix_bits) as usize)
+    }
+
+    /// The smallest indexed key, i.e., `data[0]`.
+    pub fn min_key(&self) -> K {
+        self.min_key
+    }
+
+    /// The largest indexed key, i.e., `data[data.len() - 1]`. Keys outside `[min_key, max_key]` are never found.
+    pub fn max_key(&self) -> K {
+        self.points[self.points.len() - 1].key()
+    }
+
+    pub(crate) fn max_error(&self) -> usize {
+        self.max_error
+    }
+
+    /// Check that every spline point is within the data bounds, and that points are sorted by both key and position.
+    /// It returns the index of the first bad point, which protects `search` from externally constructed (e.g., deserialized) points.
+    pub fn validate_points(&self) -> Result<(), usize> {
+        for (i, point) in self.points.iter().enumerate() {
+            if point.position() >= self.data.len() {
+                return Err(i);
+            }
+            if i > 0 {
+                let prev = self.points[i - 1];
+                if point.key() <= prev.key() || point.position() < prev.position() {
+                    return Err(i);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The key range `(data[start], data[end - 1])` covered by a range of positions, e.g., a page.
+    /// Positions are clamped to the data bounds, so a page past the end covers the last key.
+    pub fn key_range_of(&self, positions: Range<usize>) -> (K, K) {
+        let end = positions.end.clamp(1, self.data.len());
+        let start = positions.start.min(end - 1);
+        (self.data[start], self.data[end - 1])
+    }
+
+    /// A model-based estimate of the comparisons per `search`, without running a benchmark:
+    /// the comparisons to resolve the spline segment within a radix bucket (averaged over buckets),
+    /// plus a binary search over the final window of `2 * max_error + 1` positions.
+    /// It walks the radix table once and is then cached.
+    pub fn estimated_search_comparisons(&self) -> f64 {
+        *self
+            .search_comparisons
+            .get_or_init(|| self.compute_search_comparisons())
+    }
+
+    fn compute_search_comparisons(&self) -> f64 {
+        let buckets = self.table.len() - 1;
+        let segment_comparisons: f64 = self
+            .table()
+            .windows(2)
+            .map(|w| {
+                let span = widen(w[1] - w[0]) as f64;
+                // the same threshold as `get_spline_segment`
+                if span < 32.0 {
+                    span / 2.0 + 1.0
+                } else {
+                    span.log2() + 1.0
+                }
+            })
+            .sum();
+        let window = 2.0 * self.max_error as f64 + 1.0;
+        segment_comparisons / buckets as f64 + window.log2()
+    }
+
+    /// search a given `key`, returning the position of its first occurrence
+    ///
+    /// A key equal to a spline point resolves in the segment ending at that point, and spline points are placed at the first occurrence of their keys.
+    /// So hitting a spline point returns the same position as the window search, and `GreedySplineCorridor::search` follows the same contract.
+    pub fn search(&self, key: K) -> Option<usize> {
+        self.search_in_window(key, None, &mut ())
+    }
+
+    /// The smallest position holding `key`, i.e., its first occurrence, which `search` already returns.
+    /// The window search is a `partition_point`, and a run of `key` beyond the window is followed to its start.
+    pub fn search_first(&self, key: K) -> Option<usize> {
+        self.search(key)
+    }
+
+    /// The same as `search`, also returning the number of data keys compared with `key`,
+    /// e.g., to compare search strategies without the noise of timing.
+    pub fn search_counted(&self, key: K) -> (Option<usize>, usize) {
+        let mut comparisons = 0;
+        let found = self.search_in_window(key, None, &mut comparisons);
+        (found, comparisons)
+    }
+
+    /// The window that `search` scans for `key`, without touching the keys, so the last-mile search is left to the caller.
+    /// If `key` is present, its first occurrence is within the bound; a key outside `[min_key, max_key]` gets an empty bound at either end.
+    pub fn search_bound(&self, key: K) -> SearchBound {
+        if key < self.min_key {
+            return SearchBound { start: 0, stop: 0 };
+        }
+        if key > self.max_key() {
+            let len = self.data.len();
+            return SearchBound { start: len, stop: len };
+        }
+        if let Some(run) = self.long_run(key) {
+            return SearchBound {
+                start: run.start,
+                stop: run.start + 1,
+            };
+        }
         let point_location = self.get_spline_segment(key);
-        if self.points[point_location].key() == key {
-            return Some(self.points[point_location].position())
+        let predicted = self.predict(key);
+        let max_error = self.segment_error(point_location);
+        SearchBound {
+            start: predicted.saturating_sub(max_error),
+            stop: predicted.saturating_add(max_error).min(self.data.len() - 1) + 1,
+        }
+    }
+
+    /// search a given `key` in data which is logically sorted but physically stored unsorted, returning its physical row.
+    /// `perm[i]` is the physical row of the `i`-th smallest key, and its length must equal the data length.
+    pub fn search_physical(&self, key: K, perm: &[usize]) -> Option<usize> {
+        assert_eq!(perm.len(), self.data.len(), "the permutation must cover the data");
+        self.search(key).map(|pos| perm[pos])
+    }
+
+    /// search a given `key` within `window` positions around the prediction, instead of `max_error`.
+    /// A `window` smaller than `max_error` may miss present keys, while a larger one is always correct but slower.
+    pub fn search_with_window(&self, key: K, window: usize) -> Option<usize> {
+        self.search_in_window(key, Some(window), &mut ())
+    }
+
+    /// Try a narrow `first_window` first, and fall back to the `max_error` window on a miss.
+    pub fn search_speculative(&self, key: K, first_window: usize) -> Option<usize> {
+        let found = self.search_with_window(key, first_window);
+        if found.is_some() || first_window >= self.max_error {
+            return found;
         }
-        if point_location == 0 {
-            return None
+        self.search(key)
+    }
+
+    fn search_in_window(
+        &self,
+        key: K,
+        window: Option<usize>,
+        comparisons: &mut impl Comparisons,
+    ) -> Option<usize> {
+        if key < self.min_key || key > self.max_key() {
+            return None;
         }
-        let start = self.points[point_location - 1];
 
-        let end = self.points[point_location];
-        // no need to use `f64` as `usize` is faster.
-        // it is fine to always lose the precision.
-        let predicted = start.position()
-            + (key as usize - start.key() as usize) * (end.position() - start.position())
-                / (end.key() as usize - start.key() as usize);
+        if let Some(run) = self.long_run(key) {
+            return Some(run.start);
+        }
 
+        if self.linear {
+            // the interpolation is exact, so there is nothing to search
+            let predicted = self.predict(key);
+            comparisons.add(1);
+            return (self.data[predicted] == key).then_some(predicted);
+        }
 
-        let from = predicted.saturating_sub(self.max_error);
-        let to = if predicted + self.max_error > self.data.len() - 1 {
-            self.data.len() - 1
+        let point_location = self.get_spline_segment(key);
+        self.search_segment(key, point_location, window, comparisons)
+    }
+
+    /// search `key` in the spline segment ending at `point_location`, see `get_spline_segment`,
+    /// adding the data keys compared with `key` to `comparisons`.
+    pub(crate) fn search_segment(
+        &self,
+        key: K,
+        point_location: usize,
+        window: Option<usize>,
+        comparisons: &mut impl Comparisons,
+    ) -> Option<usize> {
+        let end = self.points[point_location];
+        if !self.uniform_timing && end.key() == key {
+            return Some(end.position());
+        }
+        // `point_location` is 0 only if `key` is `min_key`
+        let predicted = if point_location == 0 {
+            end.position()
         } else {
-            predicted + self.max_error
+            interpolate(self.points[point_location - 1], end, key)
         };
 
-        // binary search `from` `to` in `data`
-        match self.data[from..=to].binary_search(&key) {
-            Ok(p) => Some(p + from),
-            _ => None,
+        let max_error = window.unwrap_or_else(|| self.segment_error(point_location));
+        #[cfg(feature = "accuracy-study")]
+        if point_location > 0 {
+            let start = self.points[point_location - 1];
+            self.record_prediction(start, end, key, predicted, max_error);
+        }
+        let mut from = predicted.saturating_sub(max_error);
+        let mut to = predicted.saturating_add(max_error).min(self.data.len() - 1);
+        if self.search_strategy == SearchStrategy::BiasedProbe {
+            let bias = self.segment_biases[point_location];
+            let shifted = (bias != 0).then(|| predicted.saturating_add_signed(bias.into()));
+            for probe in std::iter::once(predicted).chain(shifted) {
+                if probe < from || probe > to {
+                    continue;
+                }
+                comparisons.add(1);
+                if self.data[probe] < key {
+                    from = probe + 1;
+                    continue;
+                }
+                to = probe;
+                if self.data[probe] == key {
+                    // the first occurrence, unless the key before is equal too
+                    if probe == 0 {
+                        return Some(probe);
+                    }
+                    comparisons.add(1);
+                    if self.data[probe - 1] < key {
+                        return Some(probe);
+                    }
+                }
+            }
+        }
+
+        // lower bound of `key` in `data[from..=to]`, so the leftmost occurrence is returned
+        let idx = from
+            + self.data[from..=to].partition_point(|&x| {
+                comparisons.add(1);
+                x < key
+            });
+        if idx > to {
+            return None;
+        }
+        comparisons.add(1);
+        if self.data[idx] != key {
+            return None;
+        }
+        // a window narrower than `max_error` may cut a run of `key`
+        if idx == from && from > 0 {
+            comparisons.add(1);
+            if self.data[from - 1] == key {
+                return Some(self.data[..from].partition_point(|&x| {
+                    comparisons.add(1);
+                    x < key
+                }));
+            }
+        }
+        Some(idx)
+    }
+
+
+    /// The index of the first element not less than `key`, or `data.len()` if there is none.
+    /// The corridor window holds the answer for present keys; otherwise it is extended beyond the window.
+    pub(crate) fn lower_bound_position(&self, key: K) -> usize {
+        if key <= self.min_key {
+            return 0;
+        }
+        if key > self.max_key() {
+            return self.data.len();
+        }
+        self.lower_bound_in_segment(key, self.get_spline_segment(key))
+    }
+
+    /// `lower_bound_position` of a `key` within `(min_key, max_key]` in the spline segment ending at `point_location`.
+    fn lower_bound_in_segment(&self, key: K, point_location: usize) -> usize {
+        let predicted = self.predict_in_segment(key, point_location);
+        let from = predicted.saturating_sub(self.max_error);
+        let to = predicted.saturating_add(self.max_error).min(self.data.len() - 1);
+
+        let idx = from + self.data[from..=to].partition_point(|&x| x < key);
+        if idx == from && from > 0 && self.data[from - 1] >= key {
+            // e.g., prediction of an absent key right after a long run of duplicates
+            return self.data[..from].partition_point(|&x| x < key);
+        }
+        if idx > to && to + 1 < self.data.len() {
+            return to + 1 + self.data[to + 1..].partition_point(|&x| x < key);
+        }
+        idx
+    }
+
+    /// The index of the first element not less than `key`, i.e., where `key` would be inserted before its duplicates,
+    /// or `data.len()` if `key` exceeds all elements. The same as `data.partition_point(|&x| x < key)`.
+    pub fn lower_bound(&self, key: K) -> usize {
+        self.lower_bound_position(key)
+    }
+
+    /// The index of the first element greater than `key`, i.e., where `key` would be inserted after its duplicates,
+    /// or `data.len()` if there is none. The same as `data.partition_point(|&x| x <= key)`.
+    pub fn upper_bound(&self, key: K) -> usize {
+        key.successor()
+            .map_or(self.data.len(), |next| self.lower_bound_position(next))
+    }
+
+    /// The positions holding `key`, which is empty (at the lower bound of `key`) if it is absent.
+    pub fn equal_range(&self, key: K) -> Range<usize> {
+        if let Some(run) = self.long_run(key) {
+            return run.start..run.start + run.len;
+        }
+        let start = self.lower_bound_position(key);
+        start..start + self.data[start..].partition_point(|&x| x == key)
+    }
+
+    /// The positions of the keys within `[range.0, range.1]`, which is empty (at the lower bound of `range.0`) if there is none.
+    pub fn positions_of(&self, range: (K, K)) -> Range<usize> {
+        let (lo, hi) = range;
+        let start = self.lower_bound_position(lo);
+        if lo > hi {
+            return start..start;
         }
+        let end = hi
+            .successor()
+            .map_or(self.data.len(), |end| self.lower_bound_position(end));
+        start..end
+    }
+
+    /// The positions of the keys within `range`, e.g., `lo..=hi` for a `BETWEEN` predicate, with two corridor lookups.
+    /// It is empty (at the position of its start) if no key is within it, including a reversed range.
+    pub fn range<R: RangeBounds<K>>(&self, range: R) -> Range<usize> {
+        let start = match range.start_bound() {
+            Bound::Included(&lo) => self.lower_bound(lo),
+            Bound::Excluded(&lo) => self.upper_bound(lo),
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&hi) => self.upper_bound(hi),
+            Bound::Excluded(&hi) => self.lower_bound(hi),
+            Bound::Unbounded => self.data.len(),
+        };
+        start..end.max(start)
     }
+
 }
 
-#[cfg(test)]
-mod test {
-    use super::*;
+impl<'a> RadixSpline<'a> {
+    /// `data` is sorted and non-empty. A `max_error` of at least `data.len() / 2` is clamped below it.
+    pub fn new(data: &'a [u64], num_radix_bits: RadixBits, max_error: MaxError) -> Self {
+        RadixSpline::from_cow(Cow::Borrowed(data), num_radix_bits.get(), max_error.get())
+    }
+
+    /// The positional form of `new`, which cannot tell swapped parameters apart.
+    /// Panics if a parameter is out of the range of `RadixBits` or `MaxError`.
+    #[deprecated(since = "0.1.0", note = "use `new` with `RadixBits` and `MaxError`")]
+    pub fn new_positional(data: &'a [u64], num_radix_bits: u32, max_error: usize) -> Self {
+        let num_radix_bits = RadixBits::try_from(num_radix_bits)
+            .unwrap_or_else(|error| panic!("{}", BuildError::from(error)));
+        let max_error = MaxError::try_from(max_error)
+            .unwrap_or_else(|error| panic!("{}", BuildError::from(error)));
+        RadixSpline::new(data, num_radix_bits, max_error)
+    }
+
+    /// The same as `new`, but checking that `data` is sorted, and returning an error instead of panicking.
+    pub fn try_new(
+        data: &'a [u64],
+        num_radix_bits: RadixBits,
+        max_error: MaxError,
+    ) -> Result<Self, BuildError> {
+        RadixSpline::try_from_keys(data, num_radix_bits, max_error)
+    }
+
+    /// The same as `new`, but the spline points, the radix table and the segment errors are allocated in `alloc`,
+    /// e.g., an arena of a memory budget, which gets them back when the index is dropped.
+    /// `Vec` takes no allocator on stable Rust, so `alloc` is a `GlobalAlloc`, and the storage a `RawBuffer` over it.
+    /// The table stays flat, and what is built later, e.g., the table of `with_radix_bits` or `with_table_layout`,
+    /// or the side table of `with_max_run_hint`, is on the heap.
+    pub fn new_in<A: GlobalAlloc + Send + Sync + 'static>(
+        data: &'a [u64],
+        num_radix_bits: RadixBits,
+        max_error: MaxError,
+        alloc: Arc<A>,
+    ) -> Self {
+        RadixSpline::try_from_cow_in(
+            Cow::Borrowed(data),
+            num_radix_bits.get(),
+            max_error.get(),
+            alloc,
+        )
+        .unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    /// The same as `new_in`, but checking that `data` is sorted, and returning an error instead of panicking,
+    /// e.g., for a table `alloc` cannot hold.
+    pub fn try_new_in<A: GlobalAlloc + Send + Sync + 'static>(
+        data: &'a [u64],
+        num_radix_bits: RadixBits,
+        max_error: MaxError,
+        alloc: Arc<A>,
+    ) -> Result<Self, BuildError> {
+        check_sorted(data)?;
+        RadixSpline::try_from_cow_in(
+            Cow::Borrowed(data),
+            num_radix_bits.get(),
+            max_error.get(),
+            alloc,
+        )
+    }
+
+    /// The same as `try_new`, retrying with fewer radix bits while the error is retryable with a new configuration.
+    /// A retry uses at most one more radix bit than the bits of `data.len()`, as a larger table is mostly empty.
+    /// A data error is returned as is.
+    pub fn try_new_tuned(
+        data: &'a [u64],
+        num_radix_bits: RadixBits,
+        max_error: MaxError,
+    ) -> Result<Self, BuildError> {
+        let mut num_radix_bits = num_radix_bits;
+        loop {
+            match RadixSpline::try_new(data, num_radix_bits, max_error) {
+                Err(error) if error.is_retryable_with_new_config() && num_radix_bits.get() > 0 => {
+                    let bits = (num_radix_bits.get() - 1).min(data.len().ilog2() + 1);
+                    num_radix_bits = RadixBits::new(bits);
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Build with the radix bits chosen for the distribution of `data`, see `num_radix_bits`.
+    /// The spline points are built once, and the fewest radix bits with at most 4 points per non-empty bucket
+    /// on average are chosen, so a lookup scans few points to find its segment. The table is kept within
+    /// 8 entries per point, e.g., for clustered keys, where the scans are the shortest the budget allows instead.
+    pub fn auto(data: &'a [u64], max_error: MaxError) -> Self {
+        assert!(!data.is_empty(), "{}", BuildError::from(DataError::TooFewKeys { len: 0 }));
+        let max_error = effective_max_error(max_error.get(), data.len());
+        let mut points = Points::new();
+        let mut corridor = Corridor::new(&mut points, data[0], max_error);
+        for (i, &key) in data.iter().enumerate().skip(1) {
+            corridor.push(&mut points, key, i);
+        }
+        corridor.finish(&mut points);
+        let num_radix_bits = auto_radix_bits(&points, data[0], data[data.len() - 1]);
+        RadixSpline::from_points(Cow::Borrowed(data), points, num_radix_bits, max_error)
+            .unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    /// default `max_radix_bits` is 18, and default `max_error` is 32
+    #[deprecated(
+        since = "0.1.0",
+        note = "use `RadixSpline::new(data, RadixBits::DEFAULT, MaxError::DEFAULT)`"
+    )]
+    pub fn default(data: &'a [u64]) -> Self {
+        RadixSpline::new(data, RadixBits::DEFAULT, MaxError::DEFAULT)
+    }
+
+    /// Build an index over run-length encoded data, i.e., `(distinct_key, run_length)` pairs sorted by `distinct_key`.
+    /// The spline is built over the distinct keys only, while positions refer to the logical expanded array.
+    pub fn from_rle(
+        pairs: &[(u64, usize)],
+        num_radix_bits: RadixBits,
+        max_error: MaxError,
+    ) -> RleRadixSpline {
+        RleRadixSpline::new(pairs, num_radix_bits, max_error)
+    }
+
+    /// Build an index over a column of distinct keys and a column of their counts, e.g., of a columnar engine.
+    /// Positions refer to the logical expanded array, where each key is repeated `counts[i]` times.
+    pub fn from_distinct(
+        keys: &[u64],
+        counts: &[usize],
+        num_radix_bits: RadixBits,
+        max_error: MaxError,
+    ) -> RleRadixSpline {
+        RleRadixSpline::from_distinct(keys, counts, num_radix_bits, max_error)
+    }
+
+    /// Build an index mapping a key to the block of `block_size` keys containing it, for block-based storage.
+    pub fn block_index(
+        data: &[u64],
+        block_size: usize,
+        num_radix_bits: RadixBits,
+        max_error: MaxError,
+    ) -> BlockIndex<'_> {
+        BlockIndex::new(data, block_size, num_radix_bits, max_error)
+    }
+
+    /// Sort `data`, and build an index which owns it.
+    pub fn build_owned(
+        data: Vec<u64>,
+        num_radix_bits: RadixBits,
+        max_error: MaxError,
+    ) -> OwnedRadixSpline {
+        OwnedRadixSpline::from_unsorted(data, num_radix_bits, max_error)
+    }
+
+    /// Merge independently sorted `runs` into one sorted sequence, and build an index which owns it.
+    /// Positions returned by `search` refer to the merged array.
+    pub fn from_runs(
+        runs: &[&[u64]],
+        num_radix_bits: RadixBits,
+        max_error: MaxError,
+    ) -> OwnedRadixSpline {
+        OwnedRadixSpline::from_runs(runs, num_radix_bits, max_error)
+    }
+
+    /// estimate the key stored at `position` by interpolating the spline inversely.
+    /// Positions beyond the last spline point are clamped to the maximum key.
+    pub fn estimate_key_at(&self, position: usize) -> u64 {
+        let idx = self.points.partition_point(|p| p.position() < position);
+        if idx == self.points.len() {
+            return self.points[idx - 1].key();
+        }
+        let end = self.points[idx];
+        if idx == 0 || end.position() == position {
+            return end.key();
+        }
+        let start = self.points[idx - 1];
+        start.key()
+            + ((position - start.position()) as u128 * (end.key() - start.key()) as u128
+                / (end.position() - start.position()) as u128) as u64
+    }
+
+    /// The positions of the keys whose high `prefix_bits` bits equal `prefix`, e.g., all keys of one tenant,
+    /// the same as `positions_of` over `[prefix << (64 - prefix_bits), ((prefix + 1) << (64 - prefix_bits)) - 1]`.
+    /// A `prefix` of more than `prefix_bits` bits is past every key, and a `prefix_bits` of 0 covers all keys.
+    pub fn prefix_range(&self, prefix: u64, prefix_bits: u32) -> Range<usize> {
+        assert!(prefix_bits <= 64);
+        let len = self.data.len();
+        if prefix.checked_shr(prefix_bits).unwrap_or(0) != 0 {
+            return len..len;
+        }
+        // the shift is 64 (overflowing) when `prefix_bits` is 0
+        let shift = 64 - prefix_bits;
+        let start = self.lower_bound_of_boundary(prefix.checked_shl(shift).unwrap_or(0));
+        // the last prefix ends at the top of the domain, where `(prefix + 1) << shift` overflows
+        let end = match prefix.checked_add(1) {
+            Some(next) if next.checked_shr(prefix_bits).unwrap_or(0) == 0 => {
+                self.lower_bound_of_boundary(next << shift)
+            }
+            _ => len,
+        };
+        start..end
+    }
+
+    /// `lower_bound_position` of a prefix boundary. A boundary which starts a radix bucket takes its spline segment
+    /// from the table without a search, as the first point of the bucket is the first point not less than `key`.
+    pub(crate) fn lower_bound_of_boundary(&self, key: u64) -> usize {
+        if key > self.min_key && key <= self.max_key() {
+            let offset = key - self.min_key;
+            if offset.trailing_zeros() >= self.shift_radix_bits {
+                let prefix = (offset >> self.shift_radix_bits) as usize;
+                let (point_location, _) = self.table.bucket(prefix);
+                return self.lower_bound_in_segment(key, point_location);
+            }
+        }
+        self.lower_bound_position(key)
+    }
+
+    /// The position of the first key whose high `prefix_bits` bits equal `prefix`, if there is one.
+    pub fn first_key_with_prefix(&self, prefix: u64, prefix_bits: u32) -> Option<usize> {
+        assert!(prefix_bits <= 64);
+        // the shift is 64 (overflowing) when `prefix_bits` is 0, and then every key matches
+        let shift = 64 - prefix_bits;
+        if prefix.checked_shr(prefix_bits).unwrap_or(0) != 0 {
+            return None;
+        }
+        let probe = prefix.checked_shl(shift).unwrap_or(0);
+
+        let idx = self.lower_bound_position(probe);
+        if idx < self.data.len() && self.data[idx].checked_shr(shift).unwrap_or(0) == prefix {
+            Some(idx)
+        } else {
+            None
+        }
+    }
+}
+
+/// A one-line summary of the built index, e.g., for logs. The radix bits and max error are the effective ones,
+/// e.g., after clamping, so a swapped configuration shows as a huge table with a tiny error.
+impl<K: Key> fmt::Display for RadixSpline<'_, K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let span = self.max_key().checked_sub(self.min_key).unwrap_or_default();
+        let key_bits = K::BITS - span.leading_zeros();
+        write!(
+            f,
+            "RadixSpline(keys={}, radix_bits={}, max_error={}, points={}, table_entries={})",
+            self.data.len(),
+            key_bits.saturating_sub(self.shift_radix_bits),
+            self.max_error,
+            self.points.len(),
+            self.table.len()
+        )
+    }
+}
+
+/// Whether `data` is `[min_key, min_key + step, min_key + 2 * step, ...]` for a positive `step`, or a single key.
+fn is_arithmetic<K: Key>(data: &[K]) -> bool {
+    if data.len() == 1 {
+        return true;
+    }
+    let span = data[data.len() - 1].offset_from(data[0]);
+    let gaps = (data.len() - 1) as u128;
+    if span == 0 || !span.is_multiple_of(gaps) {
+        return false;
+    }
+    let step = span / gaps;
+    data.windows(2).all(|w| w[1].offset_from(w[0]) == step)
+}
+
+impl<'a, K: Key> GreedySplineCorridor<'a, K> {
+    /// Upgrade the spline to a `RadixSpline` over the same data, adding a radix table with `num_radix_bits`.
+    /// Both build the same corridor, so the points are reused, and only the segment errors take a pass over the data.
+    pub fn into_radix_spline(
+        self,
+        num_radix_bits: RadixBits,
+    ) -> Result<RadixSpline<'a, K>, BuildError> {
+        let (data, max_error, points) = self.into_parts();
+        #[cfg(feature = "compact-table")]
+        if data.len() > u32::MAX as usize {
+            return Err(DataError::TooManyKeys { len: data.len() }.into());
+        }
+        let points: Points<K> = points
+            .into_iter()
+            .map(|(key, position)| Point::new(key, position))
+            .collect();
+        let (min_key, max_key) = (data[0], data[data.len() - 1]);
+        let mut table = Table::new();
+        let shift_radix_bits =
+            allocate_table(&mut table, min_key, max_key, num_radix_bits.get())?;
+        build_table(&mut table, &points, min_key, shift_radix_bits);
+        let table = RadixTable::auto(table, data.len());
+        Ok(RadixSpline::assemble(
+            Cow::Borrowed(data),
+            shift_radix_bits,
+            max_error,
+            points,
+            table,
+        ))
+    }
+}
+
+/// The buffer a `Corridor` appends the spline points to: the usual `Points`, or a `RawBuffer` of `new_in`.
+pub(crate) trait PointSink<K>: Deref<Target = [Point<K>]> {
+    fn push(&mut self, point: Point<K>);
+}
+
+impl<K: Key> PointSink<K> for Points<K> {
+    #[inline]
+    fn push(&mut self, point: Point<K>) {
+        Points::push(self, point);
+    }
+}
+
+impl<K: Key> PointSink<K> for RawBuffer<Point<K>> {
+    #[inline]
+    fn push(&mut self, point: Point<K>) {
+        RawBuffer::push(self, point);
+    }
+}
+
+/// The state of the greedy spline corridor between two keys, so the points can be built over the keys in chunks.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Corridor<K = u64> {
+    c_base: Point<K>,
+    // the previous distinct key, at the position of its first occurrence
+    prev: Point<K>,
+    // error corridor bounds
+    upper: Point<K>,
+    lower: Point<K>,
+    max_error: usize,
+}
+
+impl<K: Key> Corridor<K> {
+    /// Start the corridor at the first key, which is the first spline point.
+    pub(crate) fn new(points: &mut impl PointSink<K>, first_key: K, max_error: usize) -> Self {
+        let c_base = Point::new(first_key, 0);
+        points.push(c_base);
+        Corridor {
+            c_base,
+            prev: c_base,
+            upper: c_base,
+            lower: c_base,
+            max_error,
+        }
+    }
+
+    /// The key at position `i`, which is not less than the previous one.
+    #[inline]
+    pub(crate) fn push(&mut self, points: &mut impl PointSink<K>, key: K, i: usize) {
+        // skip the repeated values, so a key is always predicted at its first occurrence
+        if key == self.prev.key() {
+            return;
+        }
+        let point_c = Point::new(key, i);
+        let _upper = Point::new(key, i.saturating_add(self.max_error));
+        let _lower = Point::new(key, i.saturating_sub(self.max_error));
+
+        // the first distinct key after the base opens the corridor
+        if self.prev == self.c_base {
+            self.upper = _upper;
+            self.lower = _lower;
+            self.prev = point_c;
+            return;
+        }
+
+        // line BC (base -> point_c)
+        let bc = Line::new(self.c_base, point_c);
+        // line BU (base -> upper)
+        let bu = Line::new(self.c_base, self.upper);
+        // line BL (base -> lower)
+        let bl = Line::new(self.c_base, self.lower);
+
+        if bc.is_left(&bu) || bc.is_right(&bl) {
+            // `point_c` is out of the corridor, so `prev` becomes a spline point
+            self.c_base = self.prev;
+            points.push(self.c_base);
+
+            self.upper = _upper;
+            self.lower = _lower;
+        } else {
+            // line BU' (base -> _upper)
+            let _bu = Line::new(self.c_base, _upper);
+            // line BL' (base -> _lower)
+            let _bl = Line::new(self.c_base, _lower);
+            if bu.is_left(&_bu) {
+                self.upper = _upper;
+            }
+            if bl.is_right(&_bl) {
+                self.lower = _lower;
+            }
+        }
+        self.prev = point_c;
+    }
+
+    /// The last distinct key is the last spline point.
+    pub(crate) fn finish(self, points: &mut impl PointSink<K>) {
+        if self.prev != self.c_base {
+            points.push(self.prev);
+        }
+        debug_assert!(
+            points.windows(2).all(|w| w[0].key() < w[1].key()),
+            "spline point keys must be strictly increasing"
+        );
+    }
+}
+
+fn check_sorted<K: Key>(data: &[K]) -> Result<(), BuildError> {
+    if let Some(position) = data.windows(2).position(|w| w[0] > w[1]) {
+        return Err(DataError::NotSorted {
+            position: position + 1,
+        }
+        .into());
+    }
+    Ok(())
+}
+
+/// A build needs a key, and positions must fit in `Position`.
+fn check_len<K>(data: &[K]) -> Result<(), BuildError> {
+    if data.is_empty() {
+        return Err(DataError::TooFewKeys { len: 0 }.into());
+    }
+    #[cfg(feature = "compact-table")]
+    if data.len() > u32::MAX as usize {
+        return Err(DataError::TooManyKeys { len: data.len() }.into());
+    }
+    Ok(())
+}
+
+/// `(shift_radix_bits, table_len)` of the table for the keys in `[min_key, max_key]` with `num_radix_bits`.
+fn table_shape<K: Key>(
+    min_key: K,
+    max_key: K,
+    num_radix_bits: u32,
+) -> Result<(u32, usize), BuildError> {
+    let diff = max_key.checked_sub(min_key).expect("the keys are sorted");
+    let shift_radix_bits = get_num_shift_bits(diff, num_radix_bits);
+
+    let max_prefix = max_key.prefix(min_key, shift_radix_bits);
+    let table_len = usize::try_from(max_prefix)
+        .ok()
+        .and_then(|max_prefix| max_prefix.checked_add(2))
+        .filter(|&table_len| table_len <= MAX_TABLE_ENTRIES)
+        .ok_or(ConfigError::TableTooLarge { num_radix_bits })?;
+    Ok((shift_radix_bits, table_len))
+}
+
+/// Size the (empty) `table` for the keys in `[min_key, max_key]` with `num_radix_bits`, returning `shift_radix_bits`.
+fn allocate_table<K: Key>(
+    table: &mut Table,
+    min_key: K,
+    max_key: K,
+    num_radix_bits: u32,
+) -> Result<u32, BuildError> {
+    let (shift_radix_bits, table_len) = table_shape(min_key, max_key, num_radix_bits)?;
+    // an allocation failure is reported rather than aborting, e.g., for a table larger than the memory
+    table
+        .try_reserve_exact(table_len)
+        .map_err(|_| ConfigError::TableTooLarge { num_radix_bits })?;
+    table.resize(table_len, 0);
+    Ok(shift_radix_bits)
+}
+
+/// The target of `RadixSpline::auto`: the mean number of spline points per non-empty radix bucket.
+const AUTO_POINTS_PER_BUCKET: usize = 4;
+
+/// The memory budget of `RadixSpline::auto`: the radix table entries per spline point,
+/// i.e., a table of at most 4 times the memory of the points.
+const AUTO_TABLE_ENTRIES_PER_POINT: usize = 8;
+
+/// The fewest radix bits with at most `AUTO_POINTS_PER_BUCKET` points per non-empty bucket on average,
+/// or the most within the budget of `AUTO_TABLE_ENTRIES_PER_POINT`, which gives the fewest points per bucket.
+/// The non-empty buckets of each candidate are counted in one pass over the points.
+fn auto_radix_bits<K: Key>(points: &[Point<K>], min_key: K, max_key: K) -> u32 {
+    let diff = max_key.checked_sub(min_key).expect("the keys are sorted");
+    let max_bits = (K::BITS - diff.leading_zeros()).min(RadixBits::MAX);
+    let budget = points.len().saturating_mul(AUTO_TABLE_ENTRIES_PER_POINT) as u128;
+    let mut chosen = 0;
+    for num_radix_bits in 0..=max_bits {
+        let shift_radix_bits = get_num_shift_bits(diff, num_radix_bits);
+        if max_key.prefix(min_key, shift_radix_bits) + 2 > budget {
+            break;
+        }
+        chosen = num_radix_bits;
+        let mut buckets = 0;
+        let mut last_prefix = None;
+        for point in points {
+            let prefix = point.key().prefix(min_key, shift_radix_bits);
+            if last_prefix != Some(prefix) {
+                buckets += 1;
+                last_prefix = Some(prefix);
+            }
+        }
+        if points.len() <= buckets * AUTO_POINTS_PER_BUCKET {
+            break;
+        }
+    }
+    chosen
+}
+
+/// The table entries below which `build_table` fills sequentially under the `parallel` feature.
+#[cfg(feature = "parallel")]
+const PARALLEL_TABLE_MIN: usize = 1 << 16;
+
+/// Fill `table` so that `table[p]` is the index of the first spline point whose prefix is not less than `p`, with `table[0] == 0`.
+/// Prefixes of points are monotone, so each entry is written exactly once, however sparse the prefixes are.
+/// Under the `parallel` feature, large tables are filled in chunks on the rayon pool, with the same entries.
+fn build_table<K: Key>(
+    table: &mut [Position],
+    points: &[Point<K>],
+    min_key: K,
+    shift_radix_bits: u32,
+) {
+    #[cfg(feature = "parallel")]
+    if table.len() >= PARALLEL_TABLE_MIN {
+        let prefixes: Vec<usize> = points
+            .iter()
+            .map(|point| point.key().prefix(min_key, shift_radix_bits) as usize)
+            .collect();
+        par_build_table(table, &prefixes);
+        return;
+    }
+    build_table_sequential(table, points, min_key, shift_radix_bits);
+}
+
+fn build_table_sequential<K: Key>(
+    table: &mut [Position],
+    points: &[Point<K>],
+    min_key: K,
+    shift_radix_bits: u32,
+) {
+    let mut next_prefix = 0usize; // the first prefix not filled yet
+    for (i, point) in points.iter().enumerate() {
+        let prefix = point.key().prefix(min_key, shift_radix_bits) as usize;
+        if prefix >= next_prefix {
+            table[next_prefix..=prefix].fill(i as Position);
+            next_prefix = prefix + 1;
+        }
+    }
+    table[next_prefix..].fill(points.len() as Position);
+}
+
+/// `build_table` from the (non-decreasing) prefixes of the points. Each chunk of the prefix range starts
+/// at the first point whose prefix is in or past it, found by binary search, so the chunks are independent.
+#[cfg(feature = "parallel")]
+fn par_build_table(table: &mut [Position], prefixes: &[usize]) {
+    use rayon::prelude::*;
+
+    // about 4 tasks per thread
+    let chunk_len = table.len().div_ceil(rayon::current_num_threads() * 4).max(1 << 12);
+    table
+        .par_chunks_mut(chunk_len)
+        .enumerate()
+        .for_each(|(chunk, entries)| {
+            let first_prefix = chunk * chunk_len;
+            let end_prefix = first_prefix + entries.len();
+            let mut next_prefix = first_prefix;
+            let start = prefixes.partition_point(|&prefix| prefix < first_prefix);
+            for (i, &prefix) in prefixes.iter().enumerate().skip(start) {
+                let last = prefix.min(end_prefix - 1);
+                entries[next_prefix - first_prefix..=last - first_prefix].fill(i as Position);
+                next_prefix = last + 1;
+                if next_prefix == end_prefix {
+                    return;
+                }
+            }
+            entries[next_prefix - first_prefix..].fill(prefixes.len() as Position);
+        });
+}
+
+/// Record into the zeroed `segment_errors` the actual max error of each spline segment, indexed by the segment's end point, saturating at `u8::MAX`.
+/// It is the distance from the prediction of a key to its first occurrence, which the corridor bounds by `max_error`,
+/// so a window of that radius holds the first occurrence for both `search` and `search_bound`.
+/// The keys are visited in order, so the segment of each key is found by advancing over the points instead of a table lookup.
+///
+/// In the same pass, into `segment_biases`, the mean signed error (first occurrence minus prediction) of the distinct keys of each segment,
+/// rounded and saturating at the bounds of `i8`.
+fn record_segment_errors<K: Key>(
+    data: &[K],
+    points: &[Point<K>],
+    segment_errors: &mut [u8],
+    segment_biases: &mut [i8],
+) {
+    // the sum of the signed errors and the number of keys of the segment at `point_location`
+    let (mut sum, mut count) = (0i64, 0i64);
+    let mean = |sum: i64, count: i64| {
+        let mean = (sum as f64 / count.max(1) as f64).round();
+        mean.clamp(i8::MIN as f64, i8::MAX as f64) as i8
+    };
+    let mut point_location = 0;
+    let mut start = 0;
+    while start < data.len() {
+        let key = data[start];
+        let mut end = start + 1;
+        while end < data.len() && data[end] == key {
+            end += 1;
+        }
+        // the first point not less than `key`, as `get_spline_segment`
+        while point_location + 1 < points.len() && points[point_location].key() < key {
+            segment_biases[point_location] = mean(sum, count);
+            (sum, count) = (0, 0);
+            point_location += 1;
+        }
+        let point = points[point_location];
+        let predicted = if point.key() <= key || point_location == 0 {
+            point.position()
+        } else {
+            interpolate(points[point_location - 1], point, key)
+        };
+        let error = u8::try_from(predicted.abs_diff(start)).unwrap_or(u8::MAX);
+        segment_errors[point_location] = segment_errors[point_location].max(error);
+        sum += start as i64 - predicted as i64;
+        count += 1;
+        start = end;
+    }
+    segment_biases[point_location] = mean(sum, count);
+}
+
+/// A counter of the data keys a search compares with its key, see `search_counted`.
+/// `()` counts nothing, so the uncounted searches compile to the same code as without a counter.
+pub(crate) trait Comparisons {
+    fn add(&mut self, count: usize);
+}
+
+impl Comparisons for usize {
+    #[inline]
+    fn add(&mut self, count: usize) {
+        *self += count;
+    }
+}
+
+impl Comparisons for () {
+    #[inline]
+    fn add(&mut self, _count: usize) {}
+}
+
+/// interpolate the position of `key` on the line from `start` to `end`.
+pub(crate) fn interpolate<K: Key>(start: Point<K>, end: Point<K>, key: K) -> usize {
+    // no need to use `f64` as `usize` is faster.
+    // the floor is at most one position below the exact prediction, and the segment errors are measured with it,
+    // so no present key is missed (see the `accuracy-study` feature).
+    let (dx, span) = (key.offset_from(start.key()), end.key().offset_from(start.key()));
+    let dy = end.position() - start.position();
+    let (Ok(dx), Ok(span)) = (usize::try_from(dx), usize::try_from(span)) else {
+        // gaps beyond 64 bits, i.e., of `u128` keys, drop their low bits, so the product fits in `u128`
+        let shift = (128 - span.leading_zeros()).saturating_sub(64);
+        return start.position() + ((dx >> shift) * dy as u128 / (span >> shift)) as usize;
+    };
+    match dx.checked_mul(dy) {
+        Some(product) => start.position() + product / span,
+        // only for huge key gaps
+        None => start.position() + (dx as u128 * dy as u128 / span as u128) as usize,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// The parallel table fill is bit-identical to the sequential one, including empty prefixes,
+    /// prefixes shared by several points, and chunks without points.
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn par_build_table() {
+        use rand::Rng;
+
+        let mut rng = rand::thread_rng();
+        let uniform: Vec<u64> = (0..1_000_000).map(|_| rng.gen()).collect();
+        // dense clusters far apart, so most prefixes are empty
+        let clustered: Vec<u64> = (0..1_000_000u64)
+            .map(|i| (i / 100_000) << 50 | rng.gen_range(0..1 << 20))
+            .collect();
+        for mut data in [uniform, clustered] {
+            data.sort_unstable();
+            for num_radix_bits in [18, 20, 24] {
+                let radix_spline =
+                    RadixSpline::new(&data, RadixBits::new(num_radix_bits), MaxError::new(32));
+                let table = radix_spline.table();
+                assert!(table.len() >= PARALLEL_TABLE_MIN);
+                let mut sequential: Table = std::iter::repeat_n(0, table.len()).collect();
+                build_table_sequential(
+                    &mut sequential,
+                    &radix_spline.points,
+                    radix_spline.min_key,
+                    radix_spline.shift_radix_bits,
+                );
+                assert_eq!(table[..], sequential[..], "{} bits", num_radix_bits);
+            }
+        }
+    }
+
+    /// `interpolate` is the floor of the exact prediction, including huge key gaps.
+    #[test]
+    fn interpolate_floors() {
+        let exact = |start: Point, end: Point, key: u64| {
+            let dx = (key - start.key()) as u128;
+            let dy = (end.position() - start.position()) as u128;
+            let span = (end.key() - start.key()) as u128;
+            start.position() + (dx * dy / span) as usize
+        };
+        for kind in crate::testing::Adversarial::ALL {
+            let data = crate::testing::adversarial(kind);
+            let radix_spline = RadixSpline::new(&data, RadixBits::new(18), MaxError::new(8));
+            for &key in &data {
+                let point_location = radix_spline.get_spline_segment(key);
+                if point_location > 0 {
+                    let start = radix_spline.points[point_location - 1];
+                    let end = radix_spline.points[point_location];
+                    assert_eq!(interpolate(start, end, key), exact(start, end, key), "{:?}", kind);
+                }
+            }
+        }
+        let (start, end) = (Point::new(0, 0), Point::new(u64::MAX, usize::MAX / 2));
+        for key in [1, u64::MAX / 3, u64::MAX - 1] {
+            assert_eq!(interpolate(start, end, key), exact(start, end, key));
+        }
+    }
+
+    #[test]
+    fn shift_bits_never_overflow() {
+        assert_eq!(get_num_shift_bits(1u64 << 63, 0), 63);
+        assert_eq!(get_num_shift_bits(u64::MAX, 0), 63);
+        assert_eq!(get_num_shift_bits(u64::MAX, 1), 63);
+        assert_eq!(get_num_shift_bits(u64::MAX, 18), 46);
+        assert_eq!(get_num_shift_bits(15u64, 18), 0);
+    }
+
+    #[test]
+    fn zero_radix_bits_with_top_bit_set() {
+        let data: Vec<u64> = vec![0, 1, 2, 3, (1 << 63) + 5];
+
+        let radix_spline = RadixSpline::new(&data, RadixBits::new(0), MaxError::new(1));
+
+        assert_eq!(radix_spline.shift_radix_bits, 63);
+        // only the last point has prefix 1
+        let n = radix_spline.points.len() as Position;
+        assert_eq!(radix_spline.table()[..], [0, n - 1, n]);
+        for (i, &key) in data.iter().enumerate() {
+            assert_eq!(radix_spline.search(key), Some(i));
+        }
+    }
+
+    #[test]
+    fn segment_errors() {
+        use rand::{distributions::Uniform, Rng};
+
+        let range = Uniform::from(0..10000000);
+        let mut data: Vec<u64> = rand::thread_rng()
+            .sample_iter(&range)
+            .take(100000)
+            .collect();
+        data.sort_unstable();
+
+        let radix_spline = RadixSpline::new(&data, RadixBits::DEFAULT, MaxError::DEFAULT);
+        let segment_errors: Vec<usize> = (0..radix_spline.points.len())
+            .map(|point_location| radix_spline.segment_error(point_location))
+            .collect();
+
+        assert!(segment_errors.iter().all(|&e| e <= radix_spline.max_error));
+        // most segments are far more accurate than the global bound
+        let mean = segment_errors.iter().sum::<usize>() as f64 / segment_errors.len() as f64;
+        assert!(mean < radix_spline.max_error as f64);
+        for &key in &data {
+            // the segment-local window covers the true residual
+            let first = data.partition_point(|&x| x < key);
+            let last = data.partition_point(|&x| x <= key) - 1;
+            let predicted = radix_spline.predict(key);
+            let residual = if predicted < first {
+                first - predicted
+            } else {
+                predicted.saturating_sub(last)
+            };
+            let point_location = radix_spline.get_spline_segment(key);
+            assert!(residual <= segment_errors[point_location]);
+
+            match radix_spline.search(key) {
+                Some(idx) => assert_eq!(data[idx], key),
+                None => panic!("Error when searching!"),
+            }
+        }
+    }
+
+    #[test]
+    fn estimated_search_comparisons() {
+        use rand::{distributions::Uniform, Rng};
+
+        let range = Uniform::from(0..10000000);
+        let mut data: Vec<u64> = rand::thread_rng()
+            .sample_iter(&range)
+            .take(1000000)
+            .collect();
+        data.sort_unstable();
+
+        let estimate = RadixSpline::new(&data, RadixBits::new(18), MaxError::new(32))
+            .estimated_search_comparisons();
+        let doubled = RadixSpline::new(&data, RadixBits::new(18), MaxError::new(64))
+            .estimated_search_comparisons();
+
+        assert!(estimate > 6.0);
+        assert!((doubled - estimate - 1.0).abs() < 0.2);
+    }
+
+    #[test]
+    fn key_range_of() {
+        let data: Vec<u64> = (0..1000).map(|i| i * 2).collect();
+        let radix_spline = RadixSpline::new(&data, RadixBits::DEFAULT, MaxError::DEFAULT);
+
+        // pages of 256 positions
+        assert_eq!(radix_spline.key_range_of(256..512), (512, 1022));
+        assert_eq!(radix_spline.key_range_of(768..1024), (1536, 1998));
+        assert_eq!(radix_spline.key_range_of(1024..1280), (1998, 1998));
+        assert_eq!(radix_spline.key_range_of(0..0), (0, 0));
+    }
+
+    #[test]
+    fn min_max_key() {
+        let data: Vec<u64> = vec![5, 5, 9, 1 << 40, u64::MAX, u64::MAX];
+        let radix_spline = RadixSpline::new(&data, RadixBits::new(8), MaxError::new(1));
+        assert_eq!(radix_spline.min_key(), 5);
+        assert_eq!(radix_spline.max_key(), u64::MAX);
+
+        let data = vec![5];
+        let radix_spline = RadixSpline::new(&data, RadixBits::new(8), MaxError::new(1));
+        assert_eq!(radix_spline.min_key(), 5);
+        assert_eq!(radix_spline.max_key(), 5);
+    }
+
+    #[test]
+    fn search_physical() {
+        use rand::seq::SliceRandom;
+
+        // the physical table, and the sort order over it
+        let mut rows: Vec<u64> = (0..10000).map(|i| i * 5).collect();
+        rows.shuffle(&mut rand::thread_rng());
+        let mut perm: Vec<usize> = (0..rows.len()).collect();
+        perm.sort_unstable_by_key(|&row| rows[row]);
+        let sorted: Vec<u64> = perm.iter().map(|&row| rows[row]).collect();
+
+        let radix_spline = RadixSpline::new(&sorted, RadixBits::new(18), MaxError::new(8));
+        for (row, &key) in rows.iter().enumerate() {
+            assert_eq!(radix_spline.search_physical(key, &perm), Some(row));
+        }
+        assert_eq!(radix_spline.search_physical(3, &perm), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "the permutation must cover the data")]
+    fn search_physical_short_perm() {
+        let data: Vec<u64> = vec![1, 2, 3];
+        RadixSpline::new(&data, RadixBits::new(18), MaxError::new(8)).search_physical(2, &[0, 1]);
+    }
+
+    /// Repeat the keys at, before and after each spline point, i.e., where the corridor decided to emit a point.
+    #[test]
+    fn duplicates_at_spline_points() {
+        use crate::testing::assert_conformance;
+        use rand::{distributions::Uniform, Rng};
+
+        let mut data: Vec<u64> = rand::thread_rng()
+            .sample_iter(&Uniform::from(0..10000000))
+            .take(4000)
+            .collect();
+        data.sort_unstable();
+        for max_error in [0, 1, 4, 32] {
+            let points = RadixSpline::new(&data, RadixBits::new(12), MaxError::new(max_error))
+                .points()
+                .to_vec();
+            for offset in [-1, 0, 1] {
+                for repeats in [2, max_error + 1, 2 * max_error + 3] {
+                    let mut emitted = points
+                        .iter()
+                        .map(|p| p.position().saturating_add_signed(offset))
+                        .peekable();
+                    let mut duplicated = vec![];
+                    for (i, &key) in data.iter().enumerate() {
+                        let times = if emitted.next_if_eq(&i).is_some() { repeats } else { 1 };
+                        duplicated.extend(std::iter::repeat_n(key, times));
+                    }
+                    let radix_spline =
+                        RadixSpline::new(&duplicated, RadixBits::new(12), MaxError::new(max_error));
+                    assert!(radix_spline
+                        .points()
+                        .windows(2)
+                        .all(|w| w[0].key() < w[1].key()));
+                    let report = radix_spline.verify();
+                    assert!(report.is_ok(), "{:?}", report);
+                    assert_conformance(&radix_spline, &duplicated);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn max_run_hint() {
+        // one key spans 40% of the positions
+        let mut data: Vec<u64> = (0..3000).collect();
+        data.extend(std::iter::repeat_n(5000, 4000));
+        data.extend(6000..9000);
+
+        let plain = RadixSpline::new(&data, RadixBits::new(18), MaxError::new(8));
+        let radix_spline = RadixSpline::new(&data, RadixBits::new(18), MaxError::new(8))
+            .with_max_run_hint(100);
+        assert_eq!(
+            radix_spline.long_runs(),
+            &[LongRun {
+                key: 5000,
+                start: 3000,
+                len: 4000
+            }]
+        );
+        assert_eq!(radix_spline.search(5000), Some(3000));
+        assert_eq!(radix_spline.equal_range(5000), 3000..7000);
+        for key in [0, 2999, 3000, 4999, 5001, 6000, 8999, 9000] {
+            assert_eq!(radix_spline.search(key), plain.search(key), "{}", key);
+            assert_eq!(radix_spline.equal_range(key), plain.equal_range(key), "{}", key);
+            let first = data.partition_point(|&x| x < key);
+            let end = data.partition_point(|&x| x <= key);
+            assert_eq!(radix_spline.equal_range(key), first..end, "{}", key);
+        }
+        assert!(RadixSpline::new(&data, RadixBits::new(18), MaxError::new(8))
+            .with_max_run_hint(4000)
+            .long_runs()
+            .is_empty());
+    }
+
+    #[test]
+    fn key_at_pos() {
+        let data: Vec<u64> = (0..1000).map(|i| i * 2).collect();
+        let radix_spline = RadixSpline::new(&data, RadixBits::DEFAULT, MaxError::DEFAULT);
+
+        assert_eq!(radix_spline.data(), &data[..]);
+        assert_eq!(radix_spline.key_at_pos(0), Some(0));
+        assert_eq!(radix_spline.key_at_pos(999), Some(1998));
+        assert_eq!(radix_spline.key_at_pos(1000), None);
+        assert_eq!(radix_spline.resolve_range(10..13), &[20, 22, 24]);
+        assert_eq!(radix_spline.resolve_range(998..1200), &[1996, 1998]);
+        assert!(radix_spline.resolve_range(1000..1200).is_empty());
+    }
+
+    #[test]
+    fn search_bound() {
+        let mut data: Vec<u64> = vec![];
+        for key in 0..5000u64 {
+            data.extend(std::iter::repeat_n(key * key, 1 + (key as usize * 7) % 13));
+        }
+        data.extend(std::iter::repeat_n(u64::MAX - 1, 500));
+        for max_error in [0, 4, 32] {
+            for radix_spline in [
+                RadixSpline::new(&data, RadixBits::new(18), MaxError::new(max_error)),
+                RadixSpline::new(&data, RadixBits::new(18), MaxError::new(max_error))
+                    .with_max_run_hint(100),
+            ] {
+                for key in (0..5000u64).map(|key| key * key).chain([u64::MAX - 1]) {
+                    let bound = radix_spline.search_bound(key);
+                    let first = data.partition_point(|&x| x < key);
+                    assert!((bound.start..bound.stop).contains(&first), "{}", key);
+                    assert!(bound.stop - bound.start <= 2 * max_error + 1);
+                }
+                let end = SearchBound {
+                    start: data.len(),
+                    stop: data.len(),
+                };
+                assert_eq!(radix_spline.search_bound(u64::MAX), end);
+            }
+        }
+        let data: Vec<u64> = (1..100).collect();
+        let radix_spline = RadixSpline::new(&data, RadixBits::new(18), MaxError::new(8));
+        assert_eq!(radix_spline.search_bound(0), SearchBound { start: 0, stop: 0 });
+        assert!(radix_spline.size_in_bytes() > radix_spline.table_bytes());
+    }
+
+    #[cfg(feature = "inline-storage")]
+    mod inline_storage {
+        use super::*;
+        use std::alloc::{GlobalAlloc, Layout, System};
+        use std::cell::Cell;
+
+        thread_local! {
+            static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+        }
+
+        /// counts the allocations of the current thread, so that concurrent tests do not interfere
+        struct CountingAllocator;
+
+        unsafe impl GlobalAlloc for CountingAllocator {
+            unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+                let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+                System.alloc(layout)
+            }
+
+            unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+                System.dealloc(ptr, layout)
+            }
+        }
+
+        #[global_allocator]
+        static GLOBAL: CountingAllocator = CountingAllocator;
+
+        fn allocations() -> usize {
+            ALLOCATIONS.with(|count| count.get())
+        }
+
+        #[test]
+        fn micro_shards() {
+            // thousands of shards of a few dozen keys
+            let mut seed = 42u64;
+            let shards: Vec<Vec<u64>> = (0..4000)
+                .map(|_| {
+                    let mut shard: Vec<u64> = (0..40)
+                        .map(|_| {
+                            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1);
+                            seed >> 20
+                        })
+                        .collect();
+                    shard.sort_unstable();
+                    shard
+                })
+                .collect();
+
+            let before = allocations();
+            for shard in &shards {
+                let radix_spline = RadixSpline::new(shard, RadixBits::new(3), MaxError::new(32));
+                match &radix_spline.table {
+                    RadixTable::Flat(Buffer::Heap(table)) => assert!(!table.spilled()),
+                    _ => panic!("a tiny table is flat, on the heap"),
+                }
+                assert!(matches!(&radix_spline.points, Buffer::Heap(points) if !points.spilled()));
+                for &key in shard {
+                    assert_eq!(radix_spline.search(key), Some(shard.partition_point(|&x| x < key)));
+                }
+            }
+            assert_eq!(allocations(), before);
+        }
+
+        #[test]
+        fn spilled() {
+            // too many points and table entries to stay inline, the same as the heap path
+            let data: Vec<u64> = (0..10000).map(|i| i * i * 2).collect();
+            let radix_spline = RadixSpline::new(&data, RadixBits::new(18), MaxError::new(4))
+                .with_table_layout(TableLayout::Flat);
+            assert!(matches!(&radix_spline.points, Buffer::Heap(points) if points.spilled()));
+            match &radix_spline.table {
+                RadixTable::Flat(Buffer::Heap(table)) => assert!(table.spilled()),
+                _ => unreachable!(),
+            }
+            for (i, &key) in data.iter().enumerate() {
+                assert_eq!(radix_spline.search(key), Some(i));
+            }
+        }
+    }
+
+    #[test]
+    fn linear() {
+        for step in [1, 3, 1000, 1 << 40] {
+            let data: Vec<u64> = (0..10000).map(|i| 7 + i * step).collect();
+            let radix_spline = RadixSpline::new(&data, RadixBits::new(18), MaxError::new(32));
+            assert!(radix_spline.linear);
+            assert_eq!(radix_spline.points.len(), 2);
+            assert_eq!(radix_spline.max_error(), 0);
+            for (i, &key) in data.iter().enumerate() {
+                assert_eq!(radix_spline.search(key), Some(i));
+            }
+            if step > 1 {
+                assert_eq!(radix_spline.search(8), None);
+                assert_eq!(radix_spline.search(data[500] + 1), None);
+            }
+        }
+
+        // two points, but not a progression
+        let data: Vec<u64> = (0..100).filter(|&key| key != 50).collect();
+        let radix_spline = RadixSpline::new(&data, RadixBits::new(18), MaxError::new(32));
+        assert_eq!(radix_spline.points.len(), 2);
+        assert!(!radix_spline.linear);
+        assert_eq!(radix_spline.max_error(), 32);
+
+        let data: Vec<u64> = vec![5, 5];
+        assert!(!RadixSpline::new(&data, RadixBits::new(18), MaxError::new(32)).linear);
+    }
+
+    #[test]
+    fn direct_table() {
+        use rand::Rng;
+
+        // non-linear keys under 2^18, so lookups go through the table, with and without duplicates
+        let mut rng = rand::thread_rng();
+        let mut random: Vec<u64> = (0..20_000).map(|_| rng.gen_range(0..1 << 18)).collect();
+        random.sort_unstable();
+        let squares: Vec<u64> = (0..512).map(|i| i * i).collect();
+        for data in [squares, random] {
+            let radix_spline = RadixSpline::new(&data, RadixBits::new(18), MaxError::new(4));
+            assert!(!radix_spline.linear);
+            assert!(radix_spline.direct_table);
+            for key in 0..(1 << 18) + 2 {
+                let first = data.partition_point(|&x| x < key);
+                let expected = (data.get(first) == Some(&key)).then_some(first);
+                assert_eq!(radix_spline.search(key), expected, "{}", key);
+            }
+        }
+
+        let data: Vec<u64> = (0..=4096).collect();
+
+        // the keys span more than `num_radix_bits` bits
+        let radix_spline = RadixSpline::new(&data, RadixBits::new(8), MaxError::new(4));
+        assert!(!radix_spline.direct_table);
+    }
+
+    #[test]
+    fn uniform_timing() {
+        let data: Vec<u64> = (0..10000).map(|i| i * i * 2).collect();
+        let radix_spline = RadixSpline::new(&data, RadixBits::new(18), MaxError::new(4))
+            .with_uniform_timing(true);
+
+        // both spline points and other keys
+        assert!(radix_spline.points.len() > 2);
+        for (i, &key) in data.iter().enumerate() {
+            assert_eq!(radix_spline.search(key), Some(i));
+            assert_eq!(radix_spline.search(key + 1), None);
+        }
+    }
+
+    #[test]
+    fn search_with_window() {
+        use rand::{distributions::Uniform, Rng};
+
+        let range = Uniform::from(0..10000000);
+        let mut data: Vec<u64> = rand::thread_rng()
+            .sample_iter(&range)
+            .take(100000)
+            .collect();
+        data.sort_unstable();
+        data.dedup();
+
+        let radix_spline = RadixSpline::new(&data, RadixBits::DEFAULT, MaxError::DEFAULT);
+
+        let miss_rate = |window: usize| {
+            let misses = data
+                .iter()
+                .filter(|&&key| radix_spline.search_with_window(key, window).is_none())
+                .count();
+            misses as f64 / data.len() as f64
+        };
+        let rates: Vec<f64> = [0, 1, 2, 4, 8, 16, 32, 64].map(miss_rate).to_vec();
+
+        assert!(rates[0] > 0.0);
+        assert!(rates.windows(2).all(|w| w[0] >= w[1]));
+        assert_eq!(rates[6], 0.0);
+        assert_eq!(rates[7], 0.0);
+
+        for (i, &key) in data.iter().enumerate() {
+            assert_eq!(radix_spline.search_speculative(key, 0), Some(i));
+            assert_eq!(radix_spline.search_speculative(key, 4), Some(i));
+        }
+    }
+
+    #[test]
+    fn validate_points() {
+        let data: Vec<u64> = (0..10000).map(|i| i * i).collect();
+        let mut radix_spline = RadixSpline::new(&data, RadixBits::new(18), MaxError::new(4));
+        assert!(radix_spline.points.len() > 4);
+        assert_eq!(radix_spline.validate_points(), Ok(()));
+
+        radix_spline.points[3] = Point::new(radix_spline.points[3].key(), data.len());
+        assert_eq!(radix_spline.validate_points(), Err(3));
+
+        radix_spline.points[2] = radix_spline.points[1];
+        assert_eq!(radix_spline.validate_points(), Err(2));
+    }
+
+    #[test]
+    #[cfg(feature = "compact-table")]
+    fn compact_table() {
+        use rand::{distributions::Uniform, Rng};
+
+        let range = Uniform::from(0..100000000);
+        let mut data: Vec<u64> = rand::thread_rng()
+            .sample_iter(&range)
+            .take(1000000)
+            .collect();
+        data.sort_unstable();
+
+        let radix_spline = RadixSpline::new(&data, RadixBits::DEFAULT, MaxError::DEFAULT);
+
+        for (i, &key) in data.iter().enumerate().step_by(7) {
+            match radix_spline.search(key) {
+                Some(idx) => assert_eq!(data[idx], key),
+                None => panic!("Error when searching {} at {}", key, i),
+            }
+        }
+    }
+
+    #[test]
+    fn leftmost_duplicates() {
+        // runs longer than `max_error` at every offset to the window edges
+        let mut data: Vec<u64> = vec![];
+        for key in 0..300u64 {
+            data.extend(std::iter::repeat_n(key * 2, 1 + (key as usize * 7) % 23));
+        }
+
+        for max_error in [0, 1, 4, 8] {
+            let radix_spline =
+                RadixSpline::new(&data, RadixBits::new(18), MaxError::new(max_error));
+            for key in 0..601 {
+                let first = data.partition_point(|&x| x < key);
+                let expected = if data.get(first) == Some(&key) {
+                    Some(first)
+                } else {
+                    None
+                };
+                assert_eq!(radix_spline.search(key), expected, "{}", key);
+                assert_eq!(radix_spline.lower_bound_position(key), first, "{}", key);
+            }
+        }
+    }
+
+    #[test]
+    fn boundary_ties() {
+        use crate::GreedySplineCorridor;
+
+        // duplicate runs on and around segment boundaries
+        let cases: Vec<Vec<u64>> = vec![
+            vec![1, 1, 1, 2, 2, 2, 2, 3],
+            vec![0, 0, 5, 5, 5, 5, 5, 9, 9],
+            vec![3, 3, 3, 3, 4, 10, 10, 11, 11, 11, 11, 11, 30],
+            vec![0, 1, 1, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 4],
+            vec![7, 7, 8, 100, 100, 100, 101, 101, 1000, 1000],
+        ];
+        for data in &cases {
+            for max_error in [0, 1, 2, 3] {
+                let spline = GreedySplineCorridor::new(data, MaxError::new(max_error));
+                let radix_spline =
+                    RadixSpline::new(data, RadixBits::new(2), MaxError::new(max_error));
+                let uniform = RadixSpline::new(data, RadixBits::new(2), MaxError::new(max_error))
+                    .with_uniform_timing(true);
+                for &key in data {
+                    let first = Some(data.partition_point(|&x| x < key));
+                    assert_eq!(spline.search(key), first, "{:?} {}", data, key);
+                    assert_eq!(radix_spline.search(key), first, "{:?} {}", data, key);
+                    assert_eq!(uniform.search(key), first, "{:?} {}", data, key);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn first_key_with_prefix() {
+        let data: Vec<u64> = vec![
+            1,
+            2,
+            3,
+            (1 << 60) + 5,
+            (1 << 60) + 5,
+            (3 << 60) + 7,
+            u64::MAX - 1,
+            u64::MAX,
+        ];
+        let radix_spline = RadixSpline::new(&data, RadixBits::new(18), MaxError::new(1));
+
+        // the top 4 bits
+        assert_eq!(radix_spline.first_key_with_prefix(0, 4), Some(0));
+        assert_eq!(radix_spline.first_key_with_prefix(1, 4), Some(3));
+        assert_eq!(radix_spline.first_key_with_prefix(2, 4), None);
+        assert_eq!(radix_spline.first_key_with_prefix(3, 4), Some(5));
+        assert_eq!(radix_spline.first_key_with_prefix(15, 4), Some(6));
+        assert_eq!(radix_spline.first_key_with_prefix(16, 4), None);
+
+        assert_eq!(radix_spline.first_key_with_prefix(0, 0), Some(0));
+        assert_eq!(radix_spline.first_key_with_prefix(1, 0), None);
+
+        assert_eq!(radix_spline.first_key_with_prefix(3, 64), Some(2));
+        assert_eq!(radix_spline.first_key_with_prefix(4, 64), None);
+        assert_eq!(radix_spline.first_key_with_prefix(u64::MAX, 64), Some(7));
+    }
+
+    #[test]
+    fn prefix_range() {
+        use rand::Rng;
+
+        // the tenant id in the top 24 bits, with tenants 2 and 3 empty
+        let mut rng = rand::thread_rng();
+        let tenants = [0u64, 1, 4, 5, 1000, (1 << 24) - 2, (1 << 24) - 1];
+        let mut data: Vec<u64> = tenants
+            .iter()
+            .flat_map(|&tenant| {
+                let count = rng.gen_range(1..2000);
+                (0..count).map(move |i| (tenant << 40) + (i / 3) * 17)
+            })
+            .chain([0, u64::MAX, u64::MAX])
+            .collect();
+        data.sort_unstable();
+
+        let expected = |prefix: u64, prefix_bits: u32| {
+            let of = |key: u64| key.checked_shr(64 - prefix_bits).unwrap_or(0);
+            let start = data.partition_point(|&key| of(key) < prefix);
+            start..data.partition_point(|&key| of(key) <= prefix)
+        };
+        for num_radix_bits in [0, 8, 16, 20] {
+            let radix_spline =
+                RadixSpline::new(&data, RadixBits::new(num_radix_bits), MaxError::new(8));
+            for tenant in (0..8).chain([999, 1000, 1001]).chain(tenants) {
+                let range = radix_spline.prefix_range(tenant, 24);
+                assert_eq!(range, expected(tenant, 24), "tenant {}", tenant);
+                let lo = tenant << 40;
+                assert_eq!(radix_spline.positions_of((lo, lo + ((1 << 40) - 1))), range);
+            }
+            // the last tenant ends at the top of the domain
+            assert_eq!(radix_spline.prefix_range((1 << 24) - 1, 24).end, data.len());
+            assert_eq!(radix_spline.prefix_range(1 << 24, 24), data.len()..data.len());
+            for prefix_bits in [1, 4, 16, 40, 63] {
+                for &key in data.iter().step_by(97) {
+                    let prefix = key >> (64 - prefix_bits);
+                    for prefix in [prefix.saturating_sub(1), prefix, prefix + 1] {
+                        let range = radix_spline.prefix_range(prefix, prefix_bits);
+                        let expected = expected(prefix, prefix_bits);
+                        assert_eq!(range, expected, "{} {}", prefix, prefix_bits);
+                    }
+                }
+            }
+            // the whole domain, and single keys
+            assert_eq!(radix_spline.prefix_range(0, 0), 0..data.len());
+            assert_eq!(radix_spline.prefix_range(1, 0), data.len()..data.len());
+            for key in [0, 3, 17, u64::MAX] {
+                assert_eq!(radix_spline.prefix_range(key, 64), expected(key, 64));
+            }
+            assert_eq!(radix_spline.prefix_range(u64::MAX, 64), data.len() - 2..data.len());
+            assert!(radix_spline.prefix_range(3, 64).is_empty());
+            assert!(radix_spline.positions_of((5, 4)).is_empty());
+        }
+    }
+
+    #[test]
+    fn first_occurrence() {
+        let data = crate::testing::adversarial(crate::testing::Adversarial::DuplicateRuns);
+
+        let radix_splines = [
+            RadixSpline::new(&data, RadixBits::DEFAULT, MaxError::DEFAULT),
+            RadixSpline::new(&data, RadixBits::new(18), MaxError::new(2)),
+            RadixSpline::new(&data, RadixBits::new(18), MaxError::new(2)).with_uniform_timing(true),
+        ];
+        for radix_spline in &radix_splines {
+            for (i, &key) in data.iter().enumerate() {
+                if i > 0 && data[i - 1] == key {
+                    continue;
+                }
+                assert_eq!(radix_spline.search(key), Some(i));
+                // also when the window cuts the run
+                for window in [0, 1] {
+                    if let Some(found) = radix_spline.search_with_window(key, window) {
+                        assert_eq!(found, i);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Probe `min_key`, `max_key` and their neighbors through every lookup API, against `partition_point`.
+    fn check_boundaries(radix_spline: &RadixSpline, probes: &[u64]) {
+        let data = radix_spline.data();
+        let (min_key, max_key) = (data[0], data[data.len() - 1]);
+        let expected: Vec<_> = probes
+            .iter()
+            .map(|&key| {
+                let first = data.partition_point(|&x| x < key);
+                (data.get(first) == Some(&key)).then_some(first)
+            })
+            .collect();
+        for (&key, &found) in probes.iter().zip(&expected) {
+            let first = data.partition_point(|&x| x < key);
+            let end = data.partition_point(|&x| x <= key);
+            assert_eq!(radix_spline.search(key), found, "search({})", key);
+            assert_eq!(radix_spline.search_counted(key).0, found, "search({})", key);
+            assert_eq!(radix_spline.lower_bound(key), first, "lower_bound({})", key);
+            assert_eq!(radix_spline.upper_bound(key), end, "upper_bound({})", key);
+            assert_eq!(radix_spline.equal_range(key), first..end, "equal_range({})", key);
+            assert_eq!(radix_spline.range(key..=key), first..end, "range({})", key);
+            assert_eq!(radix_spline.range(..key), 0..first, "range(..{})", key);
+            assert_eq!(radix_spline.range(key..), first..data.len(), "range({}..)", key);
+            assert_eq!(radix_spline.range(..=key), 0..end, "range(..={})", key);
+            let within = (min_key..=max_key).contains(&key);
+            assert_eq!(radix_spline.prefix_of(key).is_some(), within, "prefix_of({})", key);
+            let bound = radix_spline.search_bound(key);
+            if found.is_some() {
+                assert!((bound.start..bound.stop).contains(&first), "search_bound({})", key);
+            } else if !within {
+                assert_eq!(bound.start..bound.stop, first..first, "search_bound({})", key);
+            }
+        }
+        assert_eq!(radix_spline.search_batch(probes), expected);
+    }
+
+    /// `min_key`, `max_key` and their neighbors, present and absent, for every fixture and a grid of configurations.
+    /// They are the first and the last radix prefixes, the first segment, and the clamped end of the last one.
+    #[test]
+    fn boundary_keys() {
+        use crate::testing::{adversarial, Adversarial};
+        use crate::FrozenSet;
+
+        let mut fixtures: Vec<Vec<u64>> = Adversarial::ALL.map(adversarial).to_vec();
+        fixtures.extend([
+            vec![5],
+            vec![5; 3],
+            vec![0, u64::MAX],
+            vec![u64::MAX; 2],
+            (0..1000).collect(),
+            (0..1000).map(|x| u64::MAX - 1000 + x).collect(),
+            (1..1000).map(|x| x * x * x).collect(),
+        ]);
+        let mut datasets = Vec::new();
+        for data in fixtures {
+            let (min_key, max_key) = (data[0], data[data.len() - 1]);
+            let inner = |key: u64| key > min_key && key < max_key;
+            let neighbors = [min_key.wrapping_add(1), max_key.wrapping_sub(1)];
+            // the neighbors within the keys present, repeated at the start and the end, and absent
+            let mut present = data.clone();
+            present.extend(neighbors.into_iter().filter(|&key| inner(key)));
+            present.extend([min_key, max_key]);
+            present.sort_unstable();
+            let mut absent = data.clone();
+            absent.retain(|&key| !(inner(key) && neighbors.contains(&key)));
+            datasets.extend([data, present, absent]);
+        }
+
+        for data in &datasets {
+            let (min_key, max_key) = (data[0], data[data.len() - 1]);
+            let mut probes = vec![min_key, max_key];
+            probes.extend([min_key.checked_sub(1), min_key.checked_add(1)].into_iter().flatten());
+            probes.extend([max_key.checked_sub(1), max_key.checked_add(1)].into_iter().flatten());
+            probes.extend([0, u64::MAX]);
+
+            let set = FrozenSet::from_sorted(data.clone());
+            for &key in &probes {
+                assert_eq!(set.contains(&key), data.binary_search(&key).is_ok(), "{}", key);
+            }
+            for (num_radix_bits, max_error) in
+                [0, 1, 4, 12, 18].into_iter().flat_map(|bits| [0, 1, 4, 32].map(|e| (bits, e)))
+            {
+                let build = || {
+                    RadixSpline::new(data, RadixBits::new(num_radix_bits), MaxError::new(max_error))
+                };
+                let radix_spline = build();
+                let mini = radix_spline.downsample(2);
+                for &key in &probes {
+                    let first = data.partition_point(|&x| x < key);
+                    let bound = mini.estimate(key);
+                    if data.get(first) == Some(&key) {
+                        assert!((bound.start..bound.stop).contains(&first), "estimate({})", key);
+                    }
+                }
+                let retuned = radix_spline.with_radix_bits(RadixBits::new(8)).unwrap();
+                check_boundaries(&retuned, &probes);
+                for layout in [TableLayout::Flat, TableLayout::RunLength] {
+                    for strategy in [SearchStrategy::Window, SearchStrategy::BiasedProbe] {
+                        let radix_spline =
+                            build().with_table_layout(layout).with_search_strategy(strategy);
+                        check_boundaries(&radix_spline, &probes);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Runs of 200 identical keys, far longer than the window, at the start, in the middle and at the end.
+    #[test]
+    fn search_first() {
+        let run = |key| std::iter::repeat_n(key, 200);
+        let mut data: Vec<u64> = run(5).collect();
+        data.extend((0..1000).map(|x| 10 + x * 3));
+        data.extend(run(5000));
+        data.extend((0..1000).map(|x| 6000 + x * 5));
+        data.extend(run(20_000));
+        for max_error in [0, 2, 32] {
+            for strategy in [SearchStrategy::Window, SearchStrategy::BiasedProbe] {
+                let radix_spline =
+                    RadixSpline::new(&data, RadixBits::new(8), MaxError::new(max_error))
+                        .with_search_strategy(strategy);
+                for key in [5, 5000, 20_000] {
+                    let first = data.partition_point(|&x| x < key);
+                    assert_eq!(radix_spline.search_first(key), Some(first), "{}", key);
+                }
+                for (i, &key) in data.iter().enumerate() {
+                    if i == 0 || data[i - 1] != key {
+                        assert_eq!(radix_spline.search_first(key), Some(i), "{}", key);
+                    }
+                }
+                assert_eq!(radix_spline.search_first(6), None);
+                assert_eq!(radix_spline.search_first(5001), None);
+            }
+        }
+    }
+
+    /// the table fill interleaved with emitting points, as the builder used to do
+    fn incremental_table(
+        table_len: usize,
+        points: &[Point],
+        min_key: u64,
+        shift_radix_bits: u32,
+    ) -> Vec<Position> {
+        let mut table = vec![0; table_len];
+        let mut last_prefix = 0usize;
+        for (i, point) in points.iter().enumerate().skip(1) {
+            let current_prefix = ((point.key() - min_key) >> shift_radix_bits) as usize;
+            if current_prefix > last_prefix {
+                table[last_prefix + 1..=current_prefix].fill(i as Position);
+                last_prefix = current_prefix;
+            }
+        }
+        table[last_prefix + 1..].fill(points.len() as Position);
+        table
+    }
+
+    #[test]
+    fn table_sweep() {
+        use crate::testing::{adversarial, Adversarial};
+        use rand::{distributions::Uniform, Rng};
+
+        let mut datasets: Vec<Vec<u64>> = Adversarial::ALL.iter().map(|&k| adversarial(k)).collect();
+        let mut random: Vec<u64> = rand::thread_rng()
+            .sample_iter(&Uniform::from(0..u64::MAX))
+            .take(10000)
+            .collect();
+        random.sort_unstable();
+        datasets.push(random);
+        datasets.push(vec![7]);
+        datasets.push(vec![7, 7, 7]);
+
+        for data in &datasets {
+            for radix_bits in [0, 1, 8, 18, 24] {
+                let radix_spline =
+                    RadixSpline::new(data, RadixBits::new(radix_bits), MaxError::new(8));
+                let expected = incremental_table(
+                    radix_spline.table().len(),
+                    &radix_spline.points,
+                    radix_spline.min_key,
+                    radix_spline.shift_radix_bits,
+                );
+                assert_eq!(radix_spline.table()[..], expected[..]);
+            }
+        }
+    }
+
+    /// Retuning the radix bits gives the index a full build would, without another pass over the data.
+    #[test]
+    fn with_radix_bits() {
+        let data: Vec<u64> = (0..100_000u64).map(|i| i * i / 5 + (i % 7) * 3).collect();
+        let radix_spline = RadixSpline::new(&data, RadixBits::new(10), MaxError::new(16));
+        for num_radix_bits in [0, 4, 12, 20] {
+            let bits = RadixBits::new(num_radix_bits);
+            let retuned = radix_spline.with_radix_bits(bits).unwrap();
+            let built = RadixSpline::new(&data, bits, MaxError::new(16));
+            assert_eq!(retuned.points(), built.points());
+            assert_eq!(retuned.table(), built.table());
+            assert_eq!(retuned.segment_errors[..], built.segment_errors[..]);
+            assert_eq!(retuned.to_string(), built.to_string());
+            assert!(retuned.verify().is_ok());
+
+            let upgraded = GreedySplineCorridor::new(&data, MaxError::new(16))
+                .into_radix_spline(bits)
+                .unwrap();
+            assert_eq!(upgraded.points(), built.points());
+            assert_eq!(upgraded.table(), built.table());
+        }
+        let wide: Vec<u64> = vec![0, u64::MAX];
+        let radix_spline = RadixSpline::new(&wide, RadixBits::new(8), MaxError::new(0));
+        assert_eq!(
+            radix_spline.with_radix_bits(RadixBits::new(32)).err(),
+            Some(BuildError::from(ConfigError::TableTooLarge { num_radix_bits: 32 }))
+        );
+    }
+
+    /// `lower_bound` and `upper_bound` agree with `partition_point` for present and absent keys,
+    /// including long runs of duplicates and keys beyond both ends.
+    #[test]
+    fn lower_and_upper_bound() {
+        use rand::Rng;
+
+        let mut rng = rand::thread_rng();
+        let mut data: Vec<u64> = (0..20_000).map(|_| rng.gen_range(10..1 << 32)).collect();
+        data.extend(std::iter::repeat_n(1 << 20, 500));
+        data.extend([u64::MAX - 1, u64::MAX - 1]);
+        data.sort_unstable();
+        for (num_radix_bits, max_error) in [(0, 0), (8, 4), (18, 32)] {
+            let radix_spline =
+                RadixSpline::new(&data, RadixBits::new(num_radix_bits), MaxError::new(max_error));
+            let keys = data
+                .iter()
+                .step_by(7)
+                .flat_map(|&key| [key - 1, key, key + 1])
+                .chain((0..1000).map(|_| rng.gen()))
+                .chain([0, 9, 10, 1 << 20, u64::MAX]);
+            for key in keys {
+                let lower = data.partition_point(|&x| x < key);
+                let upper = data.partition_point(|&x| x <= key);
+                assert_eq!(radix_spline.lower_bound(key), lower, "lower_bound({})", key);
+                assert_eq!(radix_spline.upper_bound(key), upper, "upper_bound({})", key);
+            }
+        }
+    }
+
+    /// The same on the fixtures and an fb-like dataset (clustered ids with a sparse heavy tail),
+    /// probing every spline knot and its neighbours.
+    #[test]
+    fn bounds_on_fixtures() {
+        use crate::testing::{adversarial, Adversarial};
+        use rand::Rng;
+
+        let mut rng = rand::thread_rng();
+        let mut fb_like: Vec<u64> = (0..50_000)
+            .map(|_| rng.gen_range(1 << 20..1 << 32) >> rng.gen_range(0..20))
+            .collect();
+        fb_like.extend((0..100).map(|_| rng.gen_range(1 << 40..1 << 63)));
+        fb_like.sort_unstable();
+        let datasets = Adversarial::ALL.into_iter().map(adversarial).chain([fb_like]);
+        for data in datasets {
+            for (num_radix_bits, max_error) in [(4, 2), (18, 32)] {
+                let radix_spline = RadixSpline::new(
+                    &data,
+                    RadixBits::new(num_radix_bits),
+                    MaxError::new(max_error),
+                );
+                let knots = radix_spline.points().iter().map(|point| point.key());
+                let keys = knots.chain(data.iter().step_by(13).copied()).chain([0, u64::MAX]);
+                let keys =
+                    keys.flat_map(|key| [key.saturating_sub(1), key, key.saturating_add(1)]);
+                for key in keys {
+                    let lower = data.partition_point(|&x| x < key);
+                    let upper = data.partition_point(|&x| x <= key);
+                    assert_eq!(radix_spline.lower_bound(key), lower, "lower_bound({})", key);
+                    assert_eq!(radix_spline.upper_bound(key), upper, "upper_bound({})", key);
+                }
+            }
+        }
+    }
+
+    /// `range` on duplicate-heavy data, with bounds in the middle of runs, between keys, and outside the domain.
+    #[test]
+    fn range() {
+        let data: Vec<u64> = (1..200u64)
+            .flat_map(|key| std::iter::repeat_n(key * 10, key as usize % 17 + 1))
+            .collect();
+        let radix_spline = RadixSpline::new(&data, RadixBits::new(8), MaxError::new(4));
+        let count = |lo: u64, hi: u64| {
+            let start = data.partition_point(|&x| x < lo);
+            start..data.partition_point(|&x| x <= hi).max(start)
+        };
+        for lo in (0..2010).step_by(5) {
+            for hi in [lo, lo + 5, lo + 10, lo + 333, u64::MAX] {
+                assert_eq!(radix_spline.range(lo..=hi), count(lo, hi), "{}..={}", lo, hi);
+                if let Some(last) = hi.checked_sub(1) {
+                    assert_eq!(radix_spline.range(lo..hi), count(lo, last), "{}..{}", lo, hi);
+                }
+                let excluded = (Bound::Excluded(lo), Bound::Included(hi));
+                assert_eq!(radix_spline.range(excluded), count(lo + 1, hi));
+            }
+            assert_eq!(radix_spline.range(lo..), count(lo, u64::MAX));
+            assert_eq!(radix_spline.range(..=lo), count(0, lo));
+        }
+        assert_eq!(radix_spline.range(..), 0..data.len());
+        let domain = radix_spline.min_key()..=radix_spline.max_key();
+        assert_eq!(radix_spline.range(domain), 0..data.len());
+
+        // empty: reversed, between two keys, and outside `[min_key, max_key]`
+        let start = data.partition_point(|&x| x < 500);
+        #[allow(clippy::reversed_empty_ranges)]
+        let reversed = radix_spline.range(500..=400);
+        assert_eq!(reversed, start..start);
+        let after = data.partition_point(|&x| x <= 500);
+        assert_eq!(radix_spline.range(501..=509), after..after);
+        assert_eq!(radix_spline.range(0..=9), 0..0);
+        assert_eq!(radix_spline.range(1991..), data.len()..data.len());
+    }
+
+    /// `BiasedProbe` finds the same positions as the window search on the fixtures,
+    /// and the counted search agrees with `search`. The probes compare at most a tenth more keys,
+    /// e.g., within runs of duplicates, and fewer where the errors of a segment lean one way,
+    /// e.g., convex keys, or are none, e.g., dense keys.
+    #[test]
+    fn biased_probe() {
+        use crate::testing::{adversarial, Adversarial};
+        use rand::Rng;
+
+        let mut rng = rand::thread_rng();
+        let mut random: Vec<u64> = (0..100_000).map(|_| rng.gen_range(0..1 << 40)).collect();
+        random.sort_unstable();
+        let convex: Vec<u64> = (0..100_000u64).map(|x| x * x * x).collect();
+        let datasets = Adversarial::ALL.into_iter().map(adversarial);
+        for (i, data) in datasets.chain([random, convex]).enumerate() {
+            let build = |strategy| {
+                RadixSpline::new(&data, RadixBits::new(18), MaxError::new(32))
+                    .with_search_strategy(strategy)
+            };
+            let window = build(SearchStrategy::Window);
+            let biased = build(SearchStrategy::BiasedProbe);
+            let keys = data
+                .iter()
+                .step_by(3)
+                .flat_map(|&key| [key.saturating_sub(1), key, key.saturating_add(1)]);
+            let (mut window_comparisons, mut biased_comparisons) = (0, 0);
+            for key in keys {
+                let (expected, comparisons) = window.search_counted(key);
+                window_comparisons += comparisons;
+                assert_eq!(window.search(key), expected, "search({})", key);
+                let (found, comparisons) = biased.search_counted(key);
+                biased_comparisons += comparisons;
+                assert_eq!(found, expected, "search({})", key);
+                assert_eq!(biased.search(key), expected, "search({})", key);
+            }
+            assert!(
+                biased_comparisons <= window_comparisons + window_comparisons / 10,
+                "dataset {}: {} vs {} comparisons",
+                i,
+                biased_comparisons,
+                window_comparisons
+            );
+            let convex = i == Adversarial::ALL.len() + 1;
+            if convex || Adversarial::ALL.get(i) == Some(&Adversarial::HugeOutlier) {
+                assert!(biased_comparisons < window_comparisons * 19 / 20, "dataset {}", i);
+            }
+        }
+    }
+
+    /// Both indexes build over any slice, e.g., a `Box<[u64]>` or a part of a larger buffer, without a copy,
+    /// and agree with the indexes over a `Vec` of the same keys.
+    #[test]
+    fn slices() {
+        use crate::GreedySplineCorridor;
+
+        let data: Vec<u64> = (0..10_000u64).map(|x| x * x / 3).collect();
+        let boxed: Box<[u64]> = data.clone().into_boxed_slice();
+        let mut buffer = vec![0; 100];
+        buffer.extend(&data);
+        buffer.extend([u64::MAX; 100]);
+        let part = &buffer[100..100 + data.len()];
+
+        let radix_spline = RadixSpline::new(&data, RadixBits::new(12), MaxError::new(8));
+        let spline = GreedySplineCorridor::new(&data, MaxError::new(8));
+        for keys in [&boxed[..], part] {
+            let over_slice = RadixSpline::new(keys, RadixBits::new(12), MaxError::new(8));
+            assert_eq!(over_slice.data().as_ptr(), keys.as_ptr());
+            assert_eq!(over_slice.to_bytes(), radix_spline.to_bytes());
+            let spline_over_slice = GreedySplineCorridor::new(keys, MaxError::new(8));
+            assert_eq!(spline_over_slice.num_points(), spline.num_points());
+            for key in (0..data[9999] + 2).step_by(997) {
+                assert_eq!(over_slice.search(key), radix_spline.search(key), "{}", key);
+                assert_eq!(spline_over_slice.search(key), spline.search(key), "{}", key);
+            }
+        }
+    }
+
+    /// The mean number of spline points per non-empty radix bucket with `num_radix_bits`.
+    fn points_per_bucket(radix_spline: &RadixSpline, num_radix_bits: u32) -> f64 {
+        let radix_spline = radix_spline.with_radix_bits(RadixBits::new(num_radix_bits)).unwrap();
+        let mut prefixes: Vec<usize> = radix_spline
+            .points()
+            .iter()
+            .map(|point| radix_spline.prefix_of(point.key()).unwrap())
+            .collect();
+        prefixes.dedup();
+        radix_spline.points().len() as f64 / prefixes.len() as f64
+    }
+
+    /// `auto` chooses the fewest bits with at most 4 points per non-empty bucket, unless the table budget binds.
+    #[test]
+    fn auto() {
+        use rand::Rng;
+
+        let mut rng = rand::thread_rng();
+        let mut data: Vec<u64> = (0..1_000_000).map(|_| rng.gen_range(0..1 << 40)).collect();
+        data.sort_unstable();
+        let radix_spline = RadixSpline::auto(&data, MaxError::new(32));
+        let bits = radix_spline.num_radix_bits();
+        assert!(points_per_bucket(&radix_spline, bits) <= 4.0);
+        assert!(points_per_bucket(&radix_spline, bits - 1) > 4.0);
+        assert!(radix_spline.table().len() <= 8 * radix_spline.points().len());
+        for &key in data.iter().step_by(101) {
+            assert_eq!(radix_spline.search(key), Some(data.partition_point(|&x| x < key)));
+        }
+
+        // narrow clusters far apart, so 4 points per bucket needs a table over the budget
+        let mut data: Vec<u64> = (0..100u64)
+            .flat_map(|cluster| (0..1000).map(move |_| cluster << 56))
+            .map(|base| base + rng.gen_range(0..1 << 20))
+            .collect();
+        data.sort_unstable();
+        let radix_spline = RadixSpline::auto(&data, MaxError::new(4));
+        let bits = radix_spline.num_radix_bits();
+        assert!(points_per_bucket(&radix_spline, bits) > 4.0);
+        assert!(radix_spline.table().len() <= 8 * radix_spline.points().len());
+        let larger = radix_spline.with_radix_bits(RadixBits::new(bits + 1)).unwrap();
+        assert!(larger.table().len() > 8 * radix_spline.points().len());
+
+        for data in [vec![7], vec![7, 7, 7], vec![1, 2, 3]] {
+            let radix_spline = RadixSpline::auto(&data, MaxError::DEFAULT);
+            assert_eq!(radix_spline.num_radix_bits(), 0);
+            assert_eq!(radix_spline.search(data[0]), Some(0));
+        }
+    }
+
+    /// The chunked scan of the `simd` feature finds the same point as the scalar one, from any start,
+    /// within a chunk, at its boundaries and in the remainder.
+    #[test]
+    fn scan_points_chunked() {
+        let points: Vec<Point> = (0..100u64).map(|i| Point::new(i * 10, i as usize)).collect();
+        for start in [0, 1, 7, 8, 9, 60, 95, 99] {
+            for key in points[start].key()..=990 {
+                assert_eq!(
+                    super::scan_points_chunked(&points, start, key),
+                    scan_points(&points, start, key),
+                    "{} from {}",
+                    key,
+                    start
+                );
+            }
+        }
+    }
+
+    /// `num_radix_bits` is the requested number, or the bits of the key range if fewer.
+    #[test]
+    fn num_radix_bits() {
+        let wide: Vec<u64> = (0..1000u64).map(|x| x << 40).collect();
+        let narrow: Vec<u64> = (0..1000).collect();
+        for bits in [0, 1, 10, 18, 24] {
+            let radix_spline = RadixSpline::new(&wide, RadixBits::new(bits), MaxError::DEFAULT);
+            assert_eq!(radix_spline.num_radix_bits(), bits);
+            let radix_spline = RadixSpline::new(&narrow, RadixBits::new(bits), MaxError::DEFAULT);
+            assert_eq!(radix_spline.num_radix_bits(), bits.min(10));
+        }
+    }
+
+    /// `size_in_bytes` counts the points and every table entry in the flat layout, and excludes the keys.
+    #[test]
+    fn size_in_bytes() {
+        use crate::common::Position;
+
+        let data = crate::testing::adversarial(crate::testing::Adversarial::DuplicateRuns);
+        for bits in [4, 12, 18] {
+            let radix_spline = RadixSpline::new(&data, RadixBits::new(bits), MaxError::new(8))
+                .with_table_layout(TableLayout::Flat);
+            assert_eq!(radix_spline.num_spline_points(), radix_spline.points().len());
+            assert_eq!(radix_spline.table_len(), radix_spline.table().len());
+            assert!(radix_spline.table_len() <= (1 << radix_spline.num_radix_bits()) + 1);
+            let model = radix_spline.num_spline_points() * size_of::<Point>()
+                + radix_spline.table_len() * size_of::<Position>();
+            let per_segment = size_of::<u8>() + size_of::<i8>();
+            assert_eq!(
+                radix_spline.size_in_bytes(),
+                model + radix_spline.num_spline_points() * per_segment
+            );
+
+            // the same entries in fewer bytes
+            let (table_len, flat_bytes) = (radix_spline.table_len(), radix_spline.size_in_bytes());
+            let run_length = radix_spline.with_table_layout(TableLayout::RunLength);
+            assert_eq!(run_length.table_len(), table_len);
+            assert_eq!(
+                run_length.size_in_bytes() - run_length.table_bytes(),
+                flat_bytes - table_len * size_of::<Position>()
+            );
+        }
+    }
+
+    /// Check `search` and the bounds of an index over `data` of any key type against `partition_point`.
+    fn check_keys<K: Key>(data: &[K], probes: &[K]) {
+        for (num_radix_bits, max_error) in [(0, 0), (8, 4), (18, 32)] {
+            let radix_spline = RadixSpline::from_keys(
+                data,
+                RadixBits::new(num_radix_bits),
+                MaxError::new(max_error),
+            );
+            for &key in data.iter().chain(probes) {
+                let lower = data.partition_point(|&x| x < key);
+                let upper = data.partition_point(|&x| x <= key);
+                let expected = (data.get(lower) == Some(&key)).then_some(lower);
+                assert_eq!(radix_spline.search(key), expected, "search({:?})", key);
+                assert_eq!(radix_spline.lower_bound(key), lower, "lower_bound({:?})", key);
+                assert_eq!(radix_spline.upper_bound(key), upper, "upper_bound({:?})", key);
+                assert_eq!(radix_spline.range(key..=key), lower..upper, "range({:?})", key);
+            }
+        }
+    }
+
+    /// `u16`, `u32` and `u128` keys, with `u128` gaps beyond `u64`, and the shift of each width.
+    #[test]
+    fn key_types() {
+        use rand::Rng;
+
+        let mut rng = rand::thread_rng();
+        let mut ports: Vec<u16> = (0..5000).map(|_| rng.gen_range(1000..u16::MAX)).collect();
+        ports.extend([0, u16::MAX, u16::MAX]);
+        ports.sort_unstable();
+        check_keys(&ports, &[1, 999, u16::MAX - 1]);
+
+        let mut ids: Vec<u32> = (0..20_000).map(|_| rng.gen_range(0..1 << 30)).collect();
+        ids.extend(std::iter::repeat_n(1 << 29, 300));
+        ids.extend([u32::MAX - 1, u32::MAX]);
+        ids.sort_unstable();
+        let probes: Vec<u32> = (0..1000).map(|_| rng.gen()).chain([0, 1 << 30]).collect();
+        check_keys(&ids, &probes);
+
+        // composite keys: a `u64` tenant in the high half, and a `u64` row id in the low half
+        let mut composite: Vec<u128> = (0..20_000)
+            .map(|_| (rng.gen_range(0..64u128) << 64) | rng.gen::<u64>() as u128)
+            .collect();
+        composite.extend([0, u128::MAX]);
+        composite.sort_unstable();
+        let probes: Vec<u128> = composite
+            .iter()
+            .step_by(11)
+            .flat_map(|&key| [key.saturating_sub(1), key.saturating_add(1)])
+            .chain((0..1000).map(|_| rng.gen()))
+            .collect();
+        check_keys(&composite, &probes);
+
+        assert_eq!(get_num_shift_bits(u32::MAX, 18), 14);
+        assert_eq!(get_num_shift_bits(u16::MAX, 0), 15);
+        assert_eq!(get_num_shift_bits(u128::MAX, 18), 110);
+        assert_eq!(get_num_shift_bits(1u128 << 100, 0), 101);
+        let radix_spline =
+            RadixSpline::from_keys(&composite, RadixBits::new(12), MaxError::new(16));
+        assert_eq!(radix_spline.shift_radix_bits(), 116);
+        assert_eq!(
+            RadixSpline::try_from_keys(&[3u32, 2], RadixBits::DEFAULT, MaxError::DEFAULT).err(),
+            RadixSpline::try_new(&[3, 2], RadixBits::DEFAULT, MaxError::DEFAULT).err()
+        );
+    }
 
     #[test]
     fn search() {
@@ -222,7 +2877,7 @@ mod test {
 
         data.sort_unstable();
 
-        let radix_spline = RadixSpline::default(&data);
+        let radix_spline = RadixSpline::new(&data, RadixBits::DEFAULT, MaxError::DEFAULT);
 
         match radix_spline.search(value) {
             Some(idx) => assert_eq!(data[idx], value),
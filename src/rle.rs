@@ -0,0 +1,237 @@
+//! # Radix spline over run-length encoded data
+//! The spline is built over the distinct keys, and the positions refer to the logical expanded array.
+//! A position in the expanded array is the run offset of a distinct key, so the expanded array is never materialized.
+
+use std::ops::Range;
+
+use crate::params::{MaxError, RadixBits};
+use crate::radix::RadixSpline;
+
+/// `RleRadixSpline` indexes `(distinct_key, run_length)` pairs without expanding them.
+/// `offsets[i]` is the start of the `i`-th run in the expanded array, and `offsets[n]` is the expanded length.
+pub struct RleRadixSpline {
+    index: RadixSpline<'static>, // built over the distinct keys
+    offsets: Vec<usize>,         // cumulative run offsets
+}
+
+impl RleRadixSpline {
+    /// `pairs` is sorted by `distinct_key`, each key appears once, and each run length is positive.
+    pub fn new(pairs: &[(u64, usize)], num_radix_bits: RadixBits, max_error: MaxError) -> Self {
+        assert!(
+            pairs.windows(2).all(|w| w[0].0 < w[1].0),
+            "keys must be sorted and distinct"
+        );
+        assert!(
+            pairs.iter().all(|&(_, run_length)| run_length > 0),
+            "counts must be positive"
+        );
+
+        let keys: Vec<u64> = pairs.iter().map(|&(key, _)| key).collect();
+        let run_lengths = pairs.iter().map(|&(_, run_length)| run_length);
+        RleRadixSpline::build(keys, run_lengths, num_radix_bits, max_error)
+    }
+
+    /// The same as `new`, with the distinct keys and their counts in separate columns.
+    /// `keys` is sorted and distinct, and each count is positive.
+    pub fn from_distinct(
+        keys: &[u64],
+        counts: &[usize],
+        num_radix_bits: RadixBits,
+        max_error: MaxError,
+    ) -> Self {
+        assert_eq!(keys.len(), counts.len(), "each key needs a count");
+        assert!(
+            keys.windows(2).all(|w| w[0] < w[1]),
+            "keys must be sorted and distinct"
+        );
+        assert!(
+            counts.iter().all(|&count| count > 0),
+            "counts must be positive"
+        );
+        RleRadixSpline::build(
+            keys.to_vec(),
+            counts.iter().copied(),
+            num_radix_bits,
+            max_error,
+        )
+    }
+
+    fn build(
+        keys: Vec<u64>,
+        run_lengths: impl Iterator<Item = usize>,
+        num_radix_bits: RadixBits,
+        max_error: MaxError,
+    ) -> Self {
+        let mut offsets = Vec::with_capacity(keys.len() + 1);
+        let mut offset = 0usize;
+        offsets.push(offset);
+        for run_length in run_lengths {
+            offset = offset
+                .checked_add(run_length)
+                .expect("the expanded length overflows `usize`");
+            offsets.push(offset);
+        }
+
+        RleRadixSpline {
+            index: RadixSpline::from_vec(keys, num_radix_bits.get(), max_error.get()),
+            offsets,
+        }
+    }
+
+    /// default `max_radix_bits` is 18, and default `max_error` is 32
+    #[deprecated(
+        since = "0.1.0",
+        note = "use `RleRadixSpline::new(pairs, RadixBits::DEFAULT, MaxError::DEFAULT)`"
+    )]
+    pub fn default(pairs: &[(u64, usize)]) -> Self {
+        RleRadixSpline::new(pairs, RadixBits::DEFAULT, MaxError::DEFAULT)
+    }
+
+    /// search a given `key`, returning the start offset of its run in the expanded array.
+    pub fn search(&self, key: u64) -> Option<usize> {
+        self.index.search(key).map(|idx| self.offsets[idx])
+    }
+
+    /// the run length of a given `key` (0 if it is absent).
+    pub fn count(&self, key: u64) -> usize {
+        match self.index.search(key) {
+            Some(idx) => self.offsets[idx + 1] - self.offsets[idx],
+            None => 0,
+        }
+    }
+
+    /// The position of the first key not less than `key` in the expanded array, which is its length if there is none.
+    pub fn lower_bound(&self, key: u64) -> usize {
+        self.offsets[self.index.lower_bound_position(key)]
+    }
+
+    /// The positions holding `key` in the expanded array, which is empty (at `lower_bound`) if it is absent.
+    pub fn equal_range(&self, key: u64) -> Range<usize> {
+        let idx = self.index.lower_bound_position(key);
+        if self.index.data().get(idx) == Some(&key) {
+            self.offsets[idx]..self.offsets[idx + 1]
+        } else {
+            self.offsets[idx]..self.offsets[idx]
+        }
+    }
+
+    /// the length of the logical expanded array.
+    pub fn len(&self) -> usize {
+        self.offsets[self.offsets.len() - 1]
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn search_and_count() {
+        let pairs = vec![(3, 2), (5, 1), (8, 4), (10, 1), (19, 3), (20, 2)];
+
+        let rle = RadixSpline::from_rle(&pairs, RadixBits::new(18), MaxError::new(1));
+
+        assert_eq!(rle.len(), 13);
+
+        assert_eq!(rle.search(3), Some(0));
+        assert_eq!(rle.search(8), Some(3));
+        assert_eq!(rle.search(19), Some(8));
+        assert_eq!(rle.search(20), Some(11));
+        assert_eq!(rle.search(9), None);
+
+        assert_eq!(rle.count(8), 4);
+        assert_eq!(rle.count(20), 2);
+        assert_eq!(rle.count(9), 0);
+    }
+
+    #[test]
+    fn large_search() {
+        use rand::Rng;
+
+        let mut rng = rand::thread_rng();
+        let mut key = 0u64;
+        let mut pairs = vec![];
+        for _ in 0..100000 {
+            key += rng.gen_range(1..1000);
+            pairs.push((key, rng.gen_range(1..10)));
+        }
+
+        let rle = RleRadixSpline::new(&pairs, RadixBits::DEFAULT, MaxError::DEFAULT);
+
+        let mut offset = 0;
+        for &(key, run_length) in &pairs {
+            assert_eq!(rle.search(key), Some(offset));
+            assert_eq!(rle.count(key), run_length);
+            offset += run_length;
+        }
+        assert_eq!(rle.len(), offset);
+    }
+
+    #[test]
+    fn from_distinct() {
+        use rand::Rng;
+
+        let mut rng = rand::thread_rng();
+        let mut keys = vec![];
+        let mut counts = vec![];
+        let mut key = 0u64;
+        for i in 0..5000 {
+            key += rng.gen_range(1..100);
+            keys.push(key);
+            // mostly heavy runs, with a few huge ones
+            counts.push(if i % 500 == 0 {
+                10000
+            } else {
+                rng.gen_range(1..200)
+            });
+        }
+        let expanded: Vec<u64> = keys
+            .iter()
+            .zip(&counts)
+            .flat_map(|(&key, &count)| std::iter::repeat_n(key, count))
+            .collect();
+
+        for max_error in [0, 4, 32] {
+            let rle = RadixSpline::from_distinct(
+                &keys,
+                &counts,
+                RadixBits::new(18),
+                MaxError::new(max_error),
+            );
+            assert_eq!(rle.len(), expanded.len());
+            let probes = keys
+                .iter()
+                .flat_map(|&key| [key - 1, key, key + 1])
+                .chain([0, u64::MAX]);
+            for key in probes {
+                let start = expanded.partition_point(|&x| x < key);
+                let end = expanded.partition_point(|&x| x <= key);
+                assert_eq!(rle.lower_bound(key), start, "{}", key);
+                assert_eq!(rle.equal_range(key), start..end, "{}", key);
+                assert_eq!(rle.search(key), (start < end).then_some(start), "{}", key);
+                assert_eq!(rle.count(key), end - start);
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "each key needs a count")]
+    fn from_distinct_lengths() {
+        RadixSpline::from_distinct(&[1, 2, 3], &[1, 2], RadixBits::new(18), MaxError::new(32));
+    }
+
+    /// A run of length 0 would put a missing key at the offset of the next key.
+    #[test]
+    #[should_panic(expected = "counts must be positive")]
+    fn empty_run() {
+        RleRadixSpline::new(
+            &[(1, 2), (5, 0), (9, 1)],
+            RadixBits::new(18),
+            MaxError::new(32),
+        );
+    }
+}
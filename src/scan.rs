@@ -0,0 +1,237 @@
+//! # Scan search
+//! Search a stream of increasing keys, e.g., the probe side of a merge join, by advancing from the previous lookup instead of going through the radix table.
+//! `search_batch` searches unsorted keys in the same way, in sorted order, so that neighboring lookups share cache lines
+//! of the points and the data.
+
+use crate::radix::RadixSpline;
+
+/// The state of a scan, which remembers the spline segment of the previous key.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ScanState {
+    last_key: u64,
+    segment: usize,
+}
+
+impl ScanState {
+    pub fn new() -> Self {
+        ScanState::default()
+    }
+}
+
+impl<'a> RadixSpline<'a> {
+    /// search a given `key`, starting from the spline segment found by the previous call with the same `state`.
+    /// For increasing keys, the segment is found by galloping forward, so a whole sorted probe stream costs linear time.
+    /// A key behind the previous one restarts from the first segment.
+    pub fn scan_search(&self, key: u64, state: &mut ScanState) -> Option<usize> {
+        if key < self.min_key() || key > self.max_key() {
+            return None;
+        }
+        if key < state.last_key {
+            *state = ScanState::default();
+        }
+
+        let points = self.points();
+        let mut segment = state.segment;
+        if points[segment].key() < key {
+            // gallop to find `(segment, segment + step]` holding the first point not less than `key`
+            let mut step = 1;
+            while segment + step < points.len() && points[segment + step].key() < key {
+                segment += step;
+                step *= 2;
+            }
+            let end = (segment + step + 1).min(points.len());
+            segment += points[segment..end].partition_point(|p| p.key() < key);
+        }
+
+        state.last_key = key;
+        state.segment = segment;
+        self.search_segment(key, segment, None, &mut ())
+    }
+
+    /// `search` every key of `keys`, in the order of `keys`.
+    pub fn search_batch(&self, keys: &[u64]) -> Vec<Option<usize>> {
+        let mut out = Vec::with_capacity(keys.len());
+        self.search_batch_into(keys, &mut out);
+        out
+    }
+
+    /// `search_batch` into `out`, which is cleared first, so its allocation is reused across batches.
+    /// The keys are looked up by `scan_search` in sorted order, and sorted keys are not permuted.
+    pub fn search_batch_into(&self, keys: &[u64], out: &mut Vec<Option<usize>>) {
+        out.clear();
+        let mut state = ScanState::new();
+        if keys.is_sorted() {
+            out.extend(keys.iter().map(|&key| self.scan_search(key, &mut state)));
+            return;
+        }
+        out.resize(keys.len(), None);
+        let mut order: Vec<usize> = (0..keys.len()).collect();
+        order.sort_by_key(|&i| keys[i]);
+        for i in order {
+            out[i] = self.scan_search(keys[i], &mut state);
+        }
+    }
+}
+
+/// A batch search: the result of `search` for every key, in the order of the keys.
+#[cfg(test)]
+pub(crate) type BatchSearch = fn(&RadixSpline, &[u64]) -> Vec<Option<usize>>;
+
+/// Every batch search, each checked against a loop of `search` by `batch_conformance`.
+/// A new batch variant must be added here.
+#[cfg(test)]
+pub(crate) const BATCH_SEARCHES: &[(&str, BatchSearch)] = &[
+    ("search_batch", |index, keys| index.search_batch(keys)),
+    ("search_batch_into", |index, keys| {
+        let mut out = vec![];
+        index.search_batch_into(keys, &mut out);
+        out
+    }),
+    ("search_batch_into, reused", |index, keys| {
+        // a buffer left over from a longer batch
+        let mut out = vec![Some(usize::MAX); keys.len() + 3];
+        index.search_batch_into(keys, &mut out);
+        out
+    }),
+    ("scan_search", |index, keys| {
+        let mut state = ScanState::new();
+        keys.iter()
+            .map(|&key| index.scan_search(key, &mut state))
+            .collect()
+    }),
+];
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{MaxError, RadixBits};
+    use rand::{distributions::Uniform, Rng};
+
+    #[test]
+    fn sorted_probes() {
+        let range = Uniform::from(0..10000000);
+        let mut data: Vec<u64> = rand::thread_rng()
+            .sample_iter(&range)
+            .take(100000)
+            .collect();
+        data.sort_unstable();
+        let radix_spline = RadixSpline::new(&data, RadixBits::DEFAULT, MaxError::DEFAULT);
+
+        // present and absent keys, including out of domain ones
+        let mut probes: Vec<u64> = rand::thread_rng()
+            .sample_iter(&Uniform::from(0..10000100))
+            .take(50000)
+            .chain(data.iter().step_by(3).copied())
+            .collect();
+        probes.sort_unstable();
+
+        let mut state = ScanState::new();
+        for &key in &probes {
+            assert_eq!(
+                radix_spline.scan_search(key, &mut state),
+                radix_spline.search(key)
+            );
+        }
+    }
+
+    /// The results are those of `search`, in the input order, for shuffled, sorted and repeated keys.
+    #[test]
+    fn search_batch() {
+        use rand::seq::SliceRandom;
+
+        let mut rng = rand::thread_rng();
+        let mut data: Vec<u64> = (&mut rng)
+            .sample_iter(&Uniform::from(0..1000000))
+            .take(100000)
+            .collect();
+        data.sort_unstable();
+        let radix_spline = RadixSpline::new(&data, RadixBits::new(16), MaxError::new(16));
+
+        let mut keys: Vec<u64> = (&mut rng)
+            .sample_iter(&Uniform::from(0..1000100))
+            .take(20000)
+            .chain(data.iter().step_by(7).copied())
+            .chain([0, u64::MAX, data[0], data[data.len() - 1]])
+            .collect();
+        keys.shuffle(&mut rng);
+        let mut sorted = keys.clone();
+        sorted.sort_unstable();
+        let mut out = vec![Some(1); 3];
+        for keys in [&keys[..], &sorted, &[data[5]; 4], &[]] {
+            let expected: Vec<_> = keys.iter().map(|&key| radix_spline.search(key)).collect();
+            assert_eq!(radix_spline.search_batch(keys), expected);
+            radix_spline.search_batch_into(keys, &mut out);
+            assert_eq!(out, expected);
+        }
+    }
+
+    /// Every batch search of `BATCH_SEARCHES` equals a loop of `search`, on every workload, and twice the same.
+    #[test]
+    fn batch_conformance() {
+        use rand::rngs::StdRng;
+        use rand::seq::SliceRandom;
+        use rand::SeedableRng;
+
+        let mut rng = StdRng::seed_from_u64(42);
+        let uniform: Vec<u64> = (0..50_000).map(|_| rng.gen_range(1_000..1 << 32)).collect();
+        // long runs of a few keys
+        let duplicates: Vec<u64> = (0..50_000)
+            .map(|_| rng.gen_range(0..50) * 1_000 + 1_000)
+            .collect();
+        for mut data in [uniform, duplicates] {
+            data.sort_unstable();
+            let (min, max) = (data[0], data[data.len() - 1]);
+            // absent and present keys
+            let mut random: Vec<u64> = (0..5_000).map(|_| rng.gen_range(min..=max)).collect();
+            random.extend(data.choose_multiple(&mut rng, 5_000));
+            random.shuffle(&mut rng);
+            let mut sorted = random.clone();
+            sorted.sort_unstable();
+            let repeated: Vec<u64> = (0..5_000).map(|i| data[i % 7 * 1_000]).collect();
+            let out_of_domain = vec![0, min - 1, max + 1, u64::MAX, min, max, min - 1, 0];
+            let workloads: [(&str, &[u64]); 6] = [
+                ("random", &random),
+                ("sorted", &sorted),
+                ("duplicate-heavy", &repeated),
+                ("out-of-domain", &out_of_domain),
+                ("empty", &[]),
+                ("single", &[data[data.len() / 2]]),
+            ];
+            for radix_spline in [
+                RadixSpline::new(&data, RadixBits::new(16), MaxError::new(16)),
+                RadixSpline::new(&data, RadixBits::new(4), MaxError::new(2))
+                    .with_search_strategy(crate::SearchStrategy::BiasedProbe),
+            ] {
+                for (workload, keys) in workloads {
+                    let expected: Vec<_> =
+                        keys.iter().map(|&key| radix_spline.search(key)).collect();
+                    for (name, batch_search) in BATCH_SEARCHES {
+                        let first = batch_search(&radix_spline, keys);
+                        assert_eq!(first, expected, "{} on {}", name, workload);
+                        assert_eq!(
+                            batch_search(&radix_spline, keys),
+                            first,
+                            "{} on {}",
+                            name,
+                            workload
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn backward_probes() {
+        let data: Vec<u64> = (0..10000).map(|i| i * i).collect();
+        let radix_spline = RadixSpline::new(&data, RadixBits::new(18), MaxError::new(4));
+
+        let mut state = ScanState::new();
+        for key in [9801, 100, 99980001, 4, 0, 250000, 250001] {
+            assert_eq!(
+                radix_spline.scan_search(key, &mut state),
+                radix_spline.search(key)
+            );
+        }
+    }
+}
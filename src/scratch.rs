@@ -0,0 +1,232 @@
+//! # Build scratch
+//! Reuse the temporary buffers of a build across many builds, e.g., when bulk-loading thousands of small shards.
+
+use std::borrow::Cow;
+
+use crate::common::{Point, Points, Position, Table};
+use crate::params::{MaxError, RadixBits};
+use crate::radix::{effective_max_error, RadixSpline};
+use crate::table::RadixTable;
+
+/// The default number of retained entries per buffer, i.e., 8 MB of table with 64-bit positions.
+const DEFAULT_MAX_RETAINED: usize = 1 << 20;
+
+/// Reusable buffers for `RadixSpline::new_with_scratch`.
+///
+/// A build copies its points and table out of the scratch at exact size, so the scratch only keeps the capacity.
+/// A buffer that grew beyond `max_retained` entries is released after the build, so one large build does not pin its memory.
+#[derive(Debug)]
+pub struct BuildScratch {
+    points: Points,
+    table: Table,
+    max_retained: usize,
+}
+
+impl Default for BuildScratch {
+    fn default() -> Self {
+        BuildScratch::new()
+    }
+}
+
+impl BuildScratch {
+    pub fn new() -> Self {
+        BuildScratch {
+            points: Points::new(),
+            table: Table::new(),
+            max_retained: DEFAULT_MAX_RETAINED,
+        }
+    }
+
+    /// Retain at most `max_retained` entries per buffer between builds.
+    pub fn with_max_retained(mut self, max_retained: usize) -> Self {
+        self.max_retained = max_retained;
+        self.shrink();
+        self
+    }
+
+    /// Release all retained capacity.
+    pub fn clear(&mut self) {
+        self.points = Points::new();
+        self.table = Table::new();
+    }
+
+    /// The retained capacity in bytes.
+    pub fn retained_bytes(&self) -> usize {
+        self.points.capacity() * size_of::<Point>() + self.table.capacity() * size_of::<Position>()
+    }
+
+    fn shrink(&mut self) {
+        self.points.clear();
+        self.table.clear();
+        if self.points.capacity() > self.max_retained {
+            self.points = Points::new();
+        }
+        if self.table.capacity() > self.max_retained {
+            self.table = Table::new();
+        }
+    }
+}
+
+impl<'a> RadixSpline<'a> {
+    /// The same as `new`, building in the buffers of `scratch` instead of fresh allocations.
+    pub fn new_with_scratch(
+        data: &'a [u64],
+        num_radix_bits: RadixBits,
+        max_error: MaxError,
+        scratch: &mut BuildScratch,
+    ) -> Self {
+        let (num_radix_bits, max_error) = (num_radix_bits.get(), max_error.get());
+        let max_error = effective_max_error(max_error, data.len());
+        scratch.shrink();
+        let shift_radix_bits = RadixSpline::build_into(
+            data,
+            num_radix_bits,
+            max_error,
+            &mut scratch.points,
+            &mut scratch.table,
+        )
+        .unwrap_or_else(|error| panic!("{}", error));
+        let points = Points::from(&scratch.points[..]);
+        let table = RadixTable::auto_from_slice(&scratch.table, data.len());
+        scratch.shrink();
+        RadixSpline::assemble(
+            Cow::Borrowed(data),
+            shift_radix_bits,
+            max_error,
+            points,
+            table,
+        )
+    }
+
+    /// Build one index per shard on the rayon pool, with one `BuildScratch` per worker.
+    #[cfg(feature = "parallel")]
+    pub fn build_shards(
+        shards: &[&'a [u64]],
+        num_radix_bits: RadixBits,
+        max_error: MaxError,
+    ) -> Vec<RadixSpline<'a>> {
+        use rayon::prelude::*;
+
+        shards
+            .par_iter()
+            .map_init(BuildScratch::new, |scratch, shard| {
+                RadixSpline::new_with_scratch(shard, num_radix_bits, max_error, scratch)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::Rng;
+
+    use super::*;
+    use crate::testing::assert_conformance;
+    use crate::{MaxError, RadixBits};
+
+    fn shards() -> Vec<Vec<u64>> {
+        let mut rng = rand::thread_rng();
+        (0..64)
+            .map(|i| {
+                let len = rng.gen_range(1..(1 << (i % 12 + 1)));
+                let range = if i % 3 == 0 { 1_000 } else { u64::MAX / 2 };
+                let mut shard: Vec<u64> = (0..len).map(|_| rng.gen_range(0..range)).collect();
+                shard.sort();
+                shard
+            })
+            .collect()
+    }
+
+    #[test]
+    fn new_with_scratch() {
+        let mut scratch = BuildScratch::new();
+        for shard in shards() {
+            for (num_radix_bits, max_error) in [(0, 0), (8, 4), (18, 32)] {
+                let pooled = RadixSpline::new_with_scratch(
+                    &shard,
+                    RadixBits::new(num_radix_bits),
+                    MaxError::new(max_error),
+                    &mut scratch,
+                );
+                let fresh = RadixSpline::new(
+                    &shard,
+                    RadixBits::new(num_radix_bits),
+                    MaxError::new(max_error),
+                );
+                assert_eq!(pooled.points(), fresh.points());
+                assert_eq!(pooled.table(), fresh.table());
+                assert_eq!(pooled.table_layout(), fresh.table_layout());
+                assert_conformance(&pooled, &shard);
+            }
+        }
+    }
+
+    #[test]
+    fn shrink_policy() {
+        let large: Vec<u64> = (0..1 << 16).map(|x| x * 3).collect();
+        let small: Vec<u64> = (0..100).collect();
+
+        let mut scratch = BuildScratch::new().with_max_retained(1 << 10);
+        RadixSpline::new_with_scratch(&small, RadixBits::new(8), MaxError::new(4), &mut scratch);
+        let retained = scratch.retained_bytes();
+        assert!(retained > 0);
+
+        // the 2^16 + 1 table entries exceed the bound and are released
+        RadixSpline::new_with_scratch(&large, RadixBits::new(16), MaxError::new(0), &mut scratch);
+        assert!(scratch.retained_bytes() <= retained);
+
+        let mut scratch = BuildScratch::new();
+        RadixSpline::new_with_scratch(&large, RadixBits::new(16), MaxError::new(0), &mut scratch);
+        assert!(scratch.retained_bytes() > retained);
+        scratch.clear();
+        assert!(scratch.retained_bytes() < retained);
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn build_shards() {
+        let shards = shards();
+        let slices: Vec<&[u64]> = shards.iter().map(|shard| &shard[..]).collect();
+        let indexes = RadixSpline::build_shards(&slices, RadixBits::new(12), MaxError::new(8));
+        assert_eq!(indexes.len(), shards.len());
+        for (index, shard) in indexes.iter().zip(&shards) {
+            assert_eq!(index.data(), &shard[..]);
+            assert_conformance(index, shard);
+        }
+    }
+
+    /// The serialized indexes are identical whatever the number of threads building them.
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn reproducible_blobs() {
+        use std::collections::hash_map::DefaultHasher;
+        use std::hash::{Hash, Hasher};
+
+        let shards = shards();
+        let slices: Vec<&[u64]> = shards.iter().map(|shard| &shard[..]).collect();
+        let expected: Vec<Vec<u8>> = shards
+            .iter()
+            .map(|shard| RadixSpline::new(shard, RadixBits::new(12), MaxError::new(8)).to_bytes())
+            .collect();
+        let fingerprint = |blobs: &[Vec<u8>]| {
+            let mut hasher = DefaultHasher::new();
+            blobs.hash(&mut hasher);
+            hasher.finish()
+        };
+        for threads in [1, 2, 8] {
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .unwrap();
+            let blobs: Vec<Vec<u8>> = pool
+                .install(|| {
+                    RadixSpline::build_shards(&slices, RadixBits::new(12), MaxError::new(8))
+                })
+                .iter()
+                .map(RadixSpline::to_bytes)
+                .collect();
+            assert_eq!(blobs, expected);
+            assert_eq!(fingerprint(&blobs), fingerprint(&expected));
+        }
+    }
+}
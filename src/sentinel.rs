@@ -0,0 +1,213 @@
+//! # Radix spline over data padded with sentinels
+//! Some files pad the keys with a low sentinel (e.g., `0`) at the front and a high one (e.g., `u64::MAX`) at the back.
+//! Indexing them as keys stretches the first and last segments, so the spline is built over the interior only,
+//! and the positions are shifted back to the original array.
+
+use std::ops::Range;
+
+use crate::index::LearnedIndex;
+use crate::params::{MaxError, RadixBits};
+use crate::radix::RadixSpline;
+
+/// `TrimmedRadixSpline` indexes `data[interior]`, where the leading run of the low sentinel
+/// and the trailing run of the high sentinel are trimmed. The trimmed runs are searched directly.
+pub struct TrimmedRadixSpline<'a> {
+    data: &'a [u64],
+    interior: Range<usize>,
+    index: Option<RadixSpline<'a>>, // `None` if the data holds nothing but sentinels
+}
+
+impl<'a> TrimmedRadixSpline<'a> {
+    /// `data` is sorted and non-empty. With `trim_sentinels` of `Some((low, high))`, a leading run equal to `low`
+    /// and a trailing run equal to `high` are trimmed; a side whose sentinel is absent is kept as is.
+    pub fn new(
+        data: &'a [u64],
+        trim_sentinels: Option<(u64, u64)>,
+        num_radix_bits: RadixBits,
+        max_error: MaxError,
+    ) -> Self {
+        assert!(!data.is_empty(), "data must be non-empty");
+        let interior = match trim_sentinels {
+            Some((low, high)) => {
+                let start = data.iter().take_while(|&&x| x == low).count();
+                let trailing = data[start..]
+                    .iter()
+                    .rev()
+                    .take_while(|&&x| x == high)
+                    .count();
+                start..data.len() - trailing
+            }
+            None => 0..data.len(),
+        };
+        let index = (!interior.is_empty()).then(|| {
+            RadixSpline::from_slice(
+                &data[interior.clone()],
+                num_radix_bits.get(),
+                max_error.get(),
+            )
+        });
+        TrimmedRadixSpline {
+            data,
+            interior,
+            index,
+        }
+    }
+
+    /// The positions of the indexed keys in the original array.
+    pub fn interior(&self) -> Range<usize> {
+        self.interior.clone()
+    }
+
+    /// The index over the interior, whose positions are relative to `interior().start`.
+    pub fn index(&self) -> Option<&RadixSpline<'a>> {
+        self.index.as_ref()
+    }
+
+    /// search a given `key`, returning the position of its first occurrence in the original array.
+    pub fn search(&self, key: u64) -> Option<usize> {
+        match &self.index {
+            Some(index) if (index.min_key()..=index.max_key()).contains(&key) => index
+                .search(key)
+                .map(|position| self.interior.start + position),
+            _ => {
+                let position = self.lower_bound(key);
+                (self.data.get(position) == Some(&key)).then_some(position)
+            }
+        }
+    }
+
+    /// The position of the first key not less than `key` in the original array.
+    pub fn lower_bound(&self, key: u64) -> usize {
+        let Range { start, end } = self.interior;
+        match &self.index {
+            Some(index) if key <= index.min_key() => {
+                self.data[..start].partition_point(|&x| x < key)
+            }
+            Some(index) if key > index.max_key() => {
+                end + self.data[end..].partition_point(|&x| x < key)
+            }
+            Some(index) => start + index.lower_bound_position(key),
+            None => self.data.partition_point(|&x| x < key),
+        }
+    }
+
+    /// The predicted position of `key`, or `None` outside the original `[min_key, max_key]`.
+    /// Keys outside the interior are predicted exactly.
+    pub fn predict(&self, key: u64) -> Option<usize> {
+        if key < self.data[0] || key > self.data[self.data.len() - 1] {
+            return None;
+        }
+        match &self.index {
+            Some(index) if (index.min_key()..=index.max_key()).contains(&key) => {
+                Some(self.interior.start + index.predict(key))
+            }
+            _ => Some(self.lower_bound(key)),
+        }
+    }
+
+    pub fn max_error(&self) -> usize {
+        self.index.as_ref().map_or(0, |index| index.max_error())
+    }
+}
+
+impl<'a> LearnedIndex for TrimmedRadixSpline<'a> {
+    fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    fn search(&self, key: u64) -> Option<usize> {
+        TrimmedRadixSpline::search(self, key)
+    }
+
+    fn lower_bound(&self, key: u64) -> usize {
+        TrimmedRadixSpline::lower_bound(self, key)
+    }
+
+    fn predict(&self, key: u64) -> Option<usize> {
+        TrimmedRadixSpline::predict(self, key)
+    }
+
+    fn max_error(&self) -> usize {
+        TrimmedRadixSpline::max_error(self)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testing::assert_conformance;
+
+    const SENTINELS: Option<(u64, u64)> = Some((0, u64::MAX));
+
+    fn padded(front: usize, back: usize) -> Vec<u64> {
+        let mut data = vec![0; front];
+        data.extend((1..1000u64).map(|x| x * x));
+        data.extend(vec![u64::MAX; back]);
+        data
+    }
+
+    fn build(data: &[u64], trim_sentinels: Option<(u64, u64)>) -> TrimmedRadixSpline<'_> {
+        TrimmedRadixSpline::new(data, trim_sentinels, RadixBits::new(8), MaxError::new(4))
+    }
+
+    #[test]
+    fn sentinels() {
+        for (front, back) in [(0, 0), (3, 0), (0, 2), (1, 1), (5, 7)] {
+            let data = padded(front, back);
+            let index = build(&data, SENTINELS);
+            assert_eq!(index.interior(), front..data.len() - back);
+            assert_eq!(index.index().unwrap().min_key(), 1);
+            assert_eq!(index.index().unwrap().max_key(), 999 * 999);
+            assert_conformance(&index, &data);
+
+            assert_eq!(index.search(0), (front > 0).then_some(0));
+            assert_eq!(index.equal_range(0), 0..front);
+            assert_eq!(
+                index.search(u64::MAX),
+                (back > 0).then_some(data.len() - back)
+            );
+            assert_eq!(index.equal_range(u64::MAX), data.len() - back..data.len());
+            assert_eq!(index.search(4), Some(front + 1));
+        }
+    }
+
+    /// Without the option, or with sentinels not at the ends, nothing is trimmed.
+    #[test]
+    fn no_sentinels() {
+        let data = padded(2, 2);
+        let index = build(&data, None);
+        assert_eq!(index.interior(), 0..data.len());
+        assert_conformance(&index, &data);
+
+        let data = padded(0, 0);
+        let index = build(&data, Some((4, 9)));
+        assert_eq!(index.interior(), 0..data.len());
+        assert_conformance(&index, &data);
+    }
+
+    /// The sentinel values are also real keys: a leading run of `low` is trimmed either way,
+    /// and `high` is kept where it is not a trailing run.
+    #[test]
+    fn sentinel_keys() {
+        let data: Vec<u64> = vec![5, 5, 5, 6, 7, 9, 9, 10, 12, 20, 20];
+        let index = build(&data, Some((5, 9)));
+        assert_eq!(index.interior(), 3..data.len());
+        assert_eq!(index.search(5), Some(0));
+        assert_eq!(index.search(9), Some(5));
+        assert_conformance(&index, &data);
+
+        let index = build(&data, Some((20, 5)));
+        assert_eq!(index.interior(), 0..data.len());
+        assert_conformance(&index, &data);
+    }
+
+    #[test]
+    fn only_sentinels() {
+        for data in [vec![0, 0, u64::MAX], vec![0], vec![u64::MAX, u64::MAX]] {
+            let index = build(&data, SENTINELS);
+            assert!(index.index().is_none());
+            assert_eq!(index.max_error(), 0);
+            assert_conformance(&index, &data);
+        }
+    }
+}
@@ -0,0 +1,367 @@
+//! # A frozen ordered set
+//! `FrozenSet` is the read-only subset of the `BTreeSet<u64>` API over sorted keys and a radix spline,
+//! e.g., a set built once and then only queried. Its semantics follow `BTreeSet` exactly.
+
+use std::fmt;
+use std::iter::{FusedIterator, Peekable};
+use std::ops::{Bound, RangeBounds};
+use std::slice;
+
+use crate::owned::OwnedRadixSpline;
+use crate::params::{MaxError, RadixBits};
+
+/// With a size ratio of at least this, set operations look up the elements of the smaller side in the larger
+/// one instead of merging both.
+const PROBE_RATIO: usize = 16;
+
+/// An immutable set of `u64`, searched with a radix spline.
+#[derive(Default)]
+pub struct FrozenSet {
+    index: Option<OwnedRadixSpline>, // `None` for the empty set
+}
+
+impl FrozenSet {
+    /// `data` is sorted, and duplicates are removed.
+    pub fn from_sorted(mut data: Vec<u64>) -> Self {
+        assert!(data.windows(2).all(|w| w[0] <= w[1]), "data must be sorted");
+        data.dedup();
+        FrozenSet {
+            index: (!data.is_empty())
+                .then(|| OwnedRadixSpline::new(data, RadixBits::DEFAULT, MaxError::DEFAULT)),
+        }
+    }
+
+    fn data(&self) -> &[u64] {
+        self.index.as_ref().map_or(&[], |index| index.data())
+    }
+
+    /// The position of the first element not less than `key`.
+    fn lower_bound(&self, key: u64) -> usize {
+        self.index
+            .as_ref()
+            .map_or(0, |index| index.lower_bound_position(key))
+    }
+
+    /// The position of the first element greater than `key`.
+    fn upper_bound(&self, key: u64) -> usize {
+        key.checked_add(1)
+            .map_or(self.len(), |next| self.lower_bound(next))
+    }
+
+    pub fn contains(&self, value: &u64) -> bool {
+        self.get(value).is_some()
+    }
+
+    pub fn get(&self, value: &u64) -> Option<&u64> {
+        let index = self.index.as_ref()?;
+        index.search(*value).map(|position| &self.data()[position])
+    }
+
+    /// The elements within `range` in ascending order.
+    /// Panics if the range starts after it ends, or starts and ends at the same excluded value, as `BTreeSet::range`.
+    pub fn range<R: RangeBounds<u64>>(&self, range: R) -> slice::Iter<'_, u64> {
+        match (range.start_bound(), range.end_bound()) {
+            (Bound::Excluded(start), Bound::Excluded(end)) if start == end => {
+                panic!("range start and end are equal and excluded in FrozenSet")
+            }
+            (
+                Bound::Included(start) | Bound::Excluded(start),
+                Bound::Included(end) | Bound::Excluded(end),
+            ) if start > end => {
+                panic!("range start is greater than range end in FrozenSet")
+            }
+            _ => {}
+        }
+        let start = match range.start_bound() {
+            Bound::Included(&start) => self.lower_bound(start),
+            Bound::Excluded(&start) => self.upper_bound(start),
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&end) => self.upper_bound(end),
+            Bound::Excluded(&end) => self.lower_bound(end),
+            Bound::Unbounded => self.len(),
+        };
+        self.data()[start..end.max(start)].iter()
+    }
+
+    pub fn iter(&self) -> slice::Iter<'_, u64> {
+        self.data().iter()
+    }
+
+    pub fn first(&self) -> Option<&u64> {
+        self.data().first()
+    }
+
+    pub fn last(&self) -> Option<&u64> {
+        self.data().last()
+    }
+
+    pub fn len(&self) -> usize {
+        self.data().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.index.is_none()
+    }
+
+    /// The elements in both sets, in ascending order.
+    /// A much smaller side looks up its elements in the other with the index, instead of merging.
+    pub fn intersection<'a>(&'a self, other: &'a FrozenSet) -> Intersection<'a> {
+        let (small, large) = if self.len() <= other.len() {
+            (self, other)
+        } else {
+            (other, self)
+        };
+        Intersection {
+            small: small.iter(),
+            large,
+            cursor: 0,
+            probe: large.len() >= small.len().saturating_mul(PROBE_RATIO),
+        }
+    }
+
+    /// The elements in `self` but not in `other`, in ascending order.
+    /// A much larger `other` is searched with the index, instead of merging.
+    pub fn difference<'a>(&'a self, other: &'a FrozenSet) -> Difference<'a> {
+        Difference {
+            iter: self.iter(),
+            other,
+            cursor: 0,
+            probe: other.len() >= self.len().saturating_mul(PROBE_RATIO),
+        }
+    }
+
+    /// The elements in either set, in ascending order.
+    pub fn union<'a>(&'a self, other: &'a FrozenSet) -> Union<'a> {
+        Union {
+            a: self.iter().peekable(),
+            b: other.iter().peekable(),
+        }
+    }
+}
+
+impl FromIterator<u64> for FrozenSet {
+    fn from_iter<I: IntoIterator<Item = u64>>(iter: I) -> Self {
+        let mut data: Vec<u64> = iter.into_iter().collect();
+        data.sort_unstable();
+        FrozenSet::from_sorted(data)
+    }
+}
+
+impl<'a> IntoIterator for &'a FrozenSet {
+    type Item = &'a u64;
+    type IntoIter = slice::Iter<'a, u64>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl PartialEq for FrozenSet {
+    fn eq(&self, other: &Self) -> bool {
+        self.data() == other.data()
+    }
+}
+
+impl Eq for FrozenSet {}
+
+impl fmt::Debug for FrozenSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+/// The iterator of `FrozenSet::intersection`.
+pub struct Intersection<'a> {
+    small: slice::Iter<'a, u64>,
+    large: &'a FrozenSet,
+    cursor: usize, // the merge position in `large`
+    probe: bool,
+}
+
+impl<'a> Iterator for Intersection<'a> {
+    type Item = &'a u64;
+
+    fn next(&mut self) -> Option<&'a u64> {
+        let large = self.large.data();
+        for value in self.small.by_ref() {
+            if self.probe {
+                if self.large.contains(value) {
+                    return Some(value);
+                }
+                continue;
+            }
+            while self.cursor < large.len() && large[self.cursor] < *value {
+                self.cursor += 1;
+            }
+            if large.get(self.cursor) == Some(value) {
+                return Some(value);
+            }
+        }
+        None
+    }
+}
+
+impl<'a> FusedIterator for Intersection<'a> {}
+
+/// The iterator of `FrozenSet::difference`.
+pub struct Difference<'a> {
+    iter: slice::Iter<'a, u64>,
+    other: &'a FrozenSet,
+    cursor: usize, // the merge position in `other`
+    probe: bool,
+}
+
+impl<'a> Iterator for Difference<'a> {
+    type Item = &'a u64;
+
+    fn next(&mut self) -> Option<&'a u64> {
+        let other = self.other.data();
+        for value in self.iter.by_ref() {
+            if self.probe {
+                if !self.other.contains(value) {
+                    return Some(value);
+                }
+                continue;
+            }
+            while self.cursor < other.len() && other[self.cursor] < *value {
+                self.cursor += 1;
+            }
+            if other.get(self.cursor) != Some(value) {
+                return Some(value);
+            }
+        }
+        None
+    }
+}
+
+impl<'a> FusedIterator for Difference<'a> {}
+
+/// The iterator of `FrozenSet::union`.
+pub struct Union<'a> {
+    a: Peekable<slice::Iter<'a, u64>>,
+    b: Peekable<slice::Iter<'a, u64>>,
+}
+
+impl<'a> Iterator for Union<'a> {
+    type Item = &'a u64;
+
+    fn next(&mut self) -> Option<&'a u64> {
+        match (self.a.peek(), self.b.peek()) {
+            (Some(a), Some(b)) if a < b => self.a.next(),
+            (Some(a), Some(b)) if a > b => self.b.next(),
+            (Some(_), Some(_)) => {
+                self.b.next();
+                self.a.next()
+            }
+            (Some(_), None) => self.a.next(),
+            (None, _) => self.b.next(),
+        }
+    }
+}
+
+impl<'a> FusedIterator for Union<'a> {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::Rng;
+    use std::collections::BTreeSet;
+
+    fn random_set(rng: &mut impl Rng, len: usize, max: u64) -> Vec<u64> {
+        (0..len).map(|_| rng.gen_range(0..=max)).collect()
+    }
+
+    fn check(set: &FrozenSet, expected: &BTreeSet<u64>, rng: &mut impl Rng, max: u64) {
+        assert_eq!(set.len(), expected.len());
+        assert_eq!(set.is_empty(), expected.is_empty());
+        assert_eq!(set.first(), expected.first());
+        assert_eq!(set.last(), expected.last());
+        assert!(set.iter().eq(expected.iter()));
+        for _ in 0..200 {
+            let value = rng.gen_range(0..=max.saturating_add(1));
+            assert_eq!(set.contains(&value), expected.contains(&value));
+            assert_eq!(set.get(&value), expected.get(&value));
+
+            let (a, b) = (rng.gen_range(0..=max), rng.gen_range(0..=max));
+            let (lo, hi) = (a.min(b), a.max(b));
+            assert!(set.range(lo..=hi).eq(expected.range(lo..=hi)));
+            assert!(set.range(lo..).eq(expected.range(lo..)));
+            assert!(set.range(..hi).eq(expected.range(..hi)));
+            let excluded = (Bound::Excluded(lo), Bound::Included(hi));
+            assert!(set.range(excluded).eq(expected.range(excluded)));
+            if lo < hi {
+                let excluded = (Bound::Excluded(lo), Bound::Excluded(hi));
+                assert!(set.range(excluded).eq(expected.range(excluded)));
+            }
+        }
+        assert!(set.range(..).eq(expected.range(..)));
+        assert!(set.range(u64::MAX..).eq(expected.range(u64::MAX..)));
+    }
+
+    /// Random sets of various sizes and densities agree with `BTreeSet`, including both strategies of the set operations.
+    #[test]
+    fn btree_set() {
+        let mut rng = rand::thread_rng();
+        for (len, other_len, max) in [
+            (0, 0, 10),
+            (0, 100, 10),
+            (1, 1, 10),
+            (100, 100, 200),
+            (50, 5000, 10_000),
+            (5000, 50, 10_000),
+            (1000, 1000, u64::MAX),
+            (3, 3000, u64::MAX),
+        ] {
+            let a = random_set(&mut rng, len, max);
+            let b = random_set(&mut rng, other_len, max);
+            let (set_a, set_b): (FrozenSet, FrozenSet) =
+                (a.iter().copied().collect(), b.iter().copied().collect());
+            let (expected_a, expected_b): (BTreeSet<u64>, BTreeSet<u64>) =
+                (a.into_iter().collect(), b.into_iter().collect());
+            check(&set_a, &expected_a, &mut rng, max);
+            check(&set_b, &expected_b, &mut rng, max);
+
+            assert!(set_a
+                .intersection(&set_b)
+                .eq(expected_a.intersection(&expected_b)));
+            assert!(set_b
+                .intersection(&set_a)
+                .eq(expected_b.intersection(&expected_a)));
+            assert!(set_a
+                .difference(&set_b)
+                .eq(expected_a.difference(&expected_b)));
+            assert!(set_b
+                .difference(&set_a)
+                .eq(expected_b.difference(&expected_a)));
+            assert!(set_a.union(&set_b).eq(expected_a.union(&expected_b)));
+            assert_eq!(format!("{:?}", set_a), format!("{:?}", expected_a));
+        }
+    }
+
+    #[test]
+    fn from_sorted() {
+        let set = FrozenSet::from_sorted(vec![1, 1, 2, 5, 5, 5, u64::MAX]);
+        assert_eq!(set.len(), 4);
+        assert_eq!(set.get(&5), Some(&5));
+        assert!(set.contains(&u64::MAX));
+        assert_eq!(set, [u64::MAX, 5, 2, 1].into_iter().collect());
+        assert_eq!(FrozenSet::default(), FrozenSet::from_sorted(vec![]));
+    }
+
+    #[test]
+    #[should_panic(expected = "range start is greater than range end in FrozenSet")]
+    #[allow(clippy::reversed_empty_ranges)]
+    fn range_reversed() {
+        let set = FrozenSet::from_sorted(vec![1, 2, 3]);
+        let _ = set.range(3..1);
+    }
+
+    #[test]
+    #[should_panic(expected = "range start and end are equal and excluded in FrozenSet")]
+    fn range_excluded() {
+        let set = FrozenSet::from_sorted(vec![1, 2, 3]);
+        let _ = set.range((Bound::Excluded(2), Bound::Excluded(2)));
+    }
+}
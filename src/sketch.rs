@@ -0,0 +1,157 @@
+//! # Quantile sketch
+//! A fixed-memory histogram of `usize` values, e.g., prediction errors, which answers percentiles.
+//! The values below `2^SUB_BITS` get a bucket each, and every power of two above is split into `2^SUB_BITS` buckets,
+//! so a bucket is at most `1 / 2^SUB_BITS` of its smallest value wide. The counts are exact, so a percentile falls
+//! in the bucket of the exact one, and the sketch reports the bucket's smallest value: for the exact percentile `q`,
+//! the reported `r` satisfies `r <= q < r * (1 + 1 / 64)`, and `r == q` below 64.
+//! The memory is `BUCKETS` counters whatever the number of values, i.e., about 30 KiB.
+
+/// The buckets per power of two, as bits.
+const SUB_BITS: u32 = 6;
+const SUB_BUCKETS: usize = 1 << SUB_BITS;
+/// The exact buckets below `SUB_BUCKETS`, then `SUB_BUCKETS` per power of two from `2^SUB_BITS` to `2^63`.
+const BUCKETS: usize = SUB_BUCKETS + (u64::BITS - SUB_BITS) as usize * SUB_BUCKETS;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct QuantileSketch {
+    counts: Box<[u64]>,
+    count: u64,
+    sum: u128,
+    max: usize,
+}
+
+impl Default for QuantileSketch {
+    fn default() -> Self {
+        QuantileSketch {
+            counts: vec![0; BUCKETS].into_boxed_slice(),
+            count: 0,
+            sum: 0,
+            max: 0,
+        }
+    }
+}
+
+/// The bucket of `value`.
+fn bucket(value: usize) -> usize {
+    let value = value as u64;
+    if value < SUB_BUCKETS as u64 {
+        return value as usize;
+    }
+    let msb = u64::BITS - 1 - value.leading_zeros();
+    let shift = msb - SUB_BITS;
+    // `value >> shift` is in `SUB_BUCKETS..2 * SUB_BUCKETS`
+    (shift as usize + 1) * SUB_BUCKETS + (value >> shift) as usize - SUB_BUCKETS
+}
+
+/// The smallest value of `bucket`.
+fn bucket_floor(bucket: usize) -> usize {
+    if bucket < SUB_BUCKETS {
+        return bucket;
+    }
+    let shift = bucket / SUB_BUCKETS - 1;
+    (SUB_BUCKETS + bucket % SUB_BUCKETS) << shift
+}
+
+impl QuantileSketch {
+    pub(crate) fn insert(&mut self, value: usize) {
+        self.counts[bucket(value)] += 1;
+        self.count += 1;
+        self.sum += value as u128;
+        self.max = self.max.max(value);
+    }
+
+    /// The exact mean, or 0 with no values.
+    pub(crate) fn mean(&self) -> f64 {
+        self.sum as f64 / self.count.max(1) as f64
+    }
+
+    /// The exact maximum, or 0 with no values.
+    pub(crate) fn max(&self) -> usize {
+        self.max
+    }
+
+    /// The nearest-rank `p`-quantile, i.e., the `ceil(p * count)`-th smallest value (the smallest for `p == 0`),
+    /// within the accuracy of the module documentation. It is 0 with no values.
+    pub(crate) fn quantile(&self, p: f64) -> usize {
+        assert!((0.0..=1.0).contains(&p), "p must be in [0, 1]");
+        if self.count == 0 {
+            return 0;
+        }
+        let rank = ((p * self.count as f64).ceil() as u64).clamp(1, self.count);
+        if rank == self.count {
+            return self.max;
+        }
+        let mut seen = 0;
+        for (bucket, &count) in self.counts.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return bucket_floor(bucket);
+            }
+        }
+        unreachable!("the counts add up to count")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::Rng;
+
+    fn exact_quantile(sorted: &[usize], p: f64) -> usize {
+        let rank = ((p * sorted.len() as f64).ceil() as usize).clamp(1, sorted.len());
+        sorted[rank - 1]
+    }
+
+    fn assert_within(reported: usize, exact: usize) {
+        assert!(reported <= exact, "{} > {}", reported, exact);
+        if exact < SUB_BUCKETS {
+            assert_eq!(reported, exact);
+        } else {
+            assert!((exact - reported) as f64 <= reported as f64 / SUB_BUCKETS as f64);
+        }
+    }
+
+    #[test]
+    fn buckets() {
+        let mut rng = rand::thread_rng();
+        let values = (0..1 << 12)
+            .chain([usize::MAX, usize::MAX - 1, 1 << 63, (1 << 63) - 1])
+            .chain((0..100_000).map(|_| rng.gen::<usize>() >> rng.gen_range(0..64)));
+        for value in values {
+            let b = bucket(value);
+            assert!(b < BUCKETS);
+            assert_within(bucket_floor(b), value);
+            assert_eq!(bucket(bucket_floor(b)), b);
+        }
+        // the buckets are contiguous and increasing
+        for b in 1..BUCKETS {
+            assert_eq!(bucket(bucket_floor(b) - 1), b - 1);
+        }
+    }
+
+    #[test]
+    fn quantiles() {
+        let mut rng = rand::thread_rng();
+        let ps = [0.0, 0.001, 0.25, 0.5, 0.9, 0.99, 0.999, 1.0];
+        for scale in [4, 100, 1 << 20, usize::MAX >> 8] {
+            let len = rng.gen_range(1..20_000);
+            // skewed, with most values small and a long tail
+            let mut values: Vec<usize> = (0..len)
+                .map(|_| rng.gen_range(0..=scale) >> rng.gen_range(0..8))
+                .collect();
+            let mut sketch = QuantileSketch::default();
+            for &value in &values {
+                sketch.insert(value);
+            }
+            values.sort_unstable();
+            for p in ps {
+                assert_within(sketch.quantile(p), exact_quantile(&values, p));
+            }
+            assert_eq!(sketch.count, len as u64);
+            assert_eq!(sketch.max(), *values.last().unwrap());
+            let mean = values.iter().map(|&v| v as f64).sum::<f64>() / len as f64;
+            assert!((sketch.mean() - mean).abs() <= mean * 1e-9);
+        }
+        assert_eq!(QuantileSketch::default().quantile(0.5), 0);
+    }
+}
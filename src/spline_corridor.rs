@@ -2,41 +2,69 @@
 //!
 //! Neumann, Thomas, and Sebastian Michel. "Smooth interpolating histograms with error guarantees." British National Conference on Databases. Springer, Berlin, Heidelberg, 2008.
 //!
-//! For simplicity, only `u64` data type is allowed.
+//! Keys are of any `SplineKey` type, e.g., `u32` timestamps without widening them to `u64`.
+//! The interpolation uses the exact distance between keys, so it works for signed keys and huge gaps alike.
 //!
-//! This file is self-contained.
+//! This file is self-contained, but for the `MaxError` parameter of `crate::params`.
+//!
+//! Duplicates are handled as in `RadixSpline`: repeated keys are skipped while building,
+//! so the spline point keys are strictly increasing, and each point sits at the first occurrence of its key.
+
+use std::ops::Range;
+
+use crate::params::MaxError;
+
+/// A key type of `GreedySplineCorridor`.
+pub trait SplineKey: Copy + Ord {
+    /// The distance from `base` to `self`, which is not less than `base`.
+    /// It is exact, as an `f64` conversion would merge close keys beyond 2^53.
+    fn offset_from(self, base: Self) -> u128;
+}
+
+macro_rules! spline_key {
+    ($($t:ty),*) => {$(
+        impl SplineKey for $t {
+            #[inline]
+            fn offset_from(self, base: Self) -> u128 {
+                self.abs_diff(base) as u128
+            }
+        }
+    )*};
+}
+
+spline_key!(u16, u32, u64, u128, i32, i64);
 
 #[derive(Clone, Copy, Debug)]
-struct Point {
-    pub key: u64,        // x
+struct Point<K> {
+    pub key: K,          // x
     pub position: usize, // y
 }
 
-impl Point {
-    pub fn new(key: u64, position: usize) -> Self {
+impl<K> Point<K> {
+    pub fn new(key: K, position: usize) -> Self {
         Point { key, position }
     }
 }
 
-impl PartialEq for Point {
+impl<K: Ord> PartialEq for Point<K> {
     fn eq(&self, other: &Self) -> bool {
         self.key == other.key
     }
 }
 
-impl PartialOrd for Point {
+impl<K: Ord> PartialOrd for Point<K> {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        self.key.partial_cmp(&other.key)
+        Some(self.cmp(other))
     }
 }
 
-impl Ord for Point {
+impl<K: Ord> Ord for Point<K> {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
         self.key.cmp(&other.key)
     }
 }
 
-impl Eq for Point {}
+impl<K: Ord> Eq for Point<K> {}
 
 enum Direction {
     Left,
@@ -44,31 +72,27 @@ enum Direction {
     Coincide,
 }
 
-struct Line {
-    start: Point,
-    end: Point,
+struct Line<K> {
+    start: Point<K>,
+    end: Point<K>,
 }
 
-impl Line {
-    fn new(start: Point, end: Point) -> Self {
+impl<K: SplineKey> Line<K> {
+    fn new(start: Point<K>, end: Point<K>) -> Self {
         Line { start, end }
     }
 
-    fn is_vertical(&self) -> bool {
-        self.start.key == self.end.key
-    }
-
-    fn get_direction(&self, other: &Line) -> Direction {
+    fn get_direction(&self, other: &Line<K>) -> Direction {
         // dy can be less than 0
         let (dy, dx) = (
             self.end.position as f64 - self.start.position as f64,
-            self.end.key - self.start.key,
+            self.end.key.offset_from(self.start.key),
         );
         assert!(dx > 0);
 
         let (other_dy, other_dx) = (
             other.end.position as f64 - other.start.position as f64,
-            other.end.key - other.start.key,
+            other.end.key.offset_from(other.start.key),
         );
         assert!(other_dx > 0);
 
@@ -83,53 +107,163 @@ impl Line {
         }
     }
 
-    fn is_left(&self, other: &Line) -> bool {
+    fn is_left(&self, other: &Line<K>) -> bool {
         matches!(self.get_direction(other), Direction::Left)
     }
 
-    fn is_right(&self, other: &Line) -> bool {
+    fn is_right(&self, other: &Line<K>) -> bool {
         matches!(self.get_direction(other), Direction::Right)
     }
 }
 
+/// `with_knot_budget` bisects the error on at most this many keys.
+const KNOT_BUDGET_SAMPLE: usize = 1 << 16;
+
 /// A greedy method to get spline points.
 /// Note that the underlying data should be sorted.
-pub struct GreedySplineCorridor<'a> {
-    data: &'a Vec<u64>,
+pub struct GreedySplineCorridor<'a, K = u64> {
+    data: &'a [K],
     max_error: usize,
-    points: Vec<Point>,
+    points: Vec<Point<K>>,
 }
 
-impl<'a> GreedySplineCorridor<'a> {
-    pub fn new(data: &'a Vec<u64>, max_error: usize) -> Self {
+/// The corridor over `u64` keys, as it was before keys became generic.
+pub type GreedySplineCorridorU64<'a> = GreedySplineCorridor<'a, u64>;
+
+impl<'a, K: SplineKey> GreedySplineCorridor<'a, K> {
+    /// `data` is sorted and non-empty.
+    pub fn new(data: &'a [K], max_error: MaxError) -> Self {
+        let max_error = max_error.get();
         GreedySplineCorridor {
             data,
             max_error,
-            points: GreedySplineCorridor::spline_points(data, max_error),
+            points: Self::spline_points(data, max_error),
         }
     }
 
     /// default `max_error` is 32
-    pub fn default(data: &'a Vec<u64>) -> Self {
-        GreedySplineCorridor::new(data, 32)
+    #[deprecated(
+        since = "0.1.0",
+        note = "use `GreedySplineCorridor::new(data, MaxError::DEFAULT)`"
+    )]
+    pub fn default(data: &'a [K]) -> Self {
+        GreedySplineCorridor::new(data, MaxError::DEFAULT)
+    }
+
+    /// Build with at most `max_knots` spline points (at least 2), and the smallest `max_error` found to fit them.
+    /// The error is searched by bisection on a sample of the keys, then the full data is built once,
+    /// or again with a doubled error until it fits. `max_error()` is then the error measured over all keys,
+    /// which may be below the corridor width used to build.
+    pub fn with_knot_budget(data: &'a [K], max_knots: usize) -> Self {
+        assert!(max_knots >= 2, "a spline needs at least 2 knots");
+        let step = data.len().div_ceil(KNOT_BUDGET_SAMPLE).max(1);
+        let sample: Vec<K> = data.iter().step_by(step).copied().collect();
+        let (mut low, mut high) = (0, sample.len());
+        while low < high {
+            let mid = low + (high - low) / 2;
+            if Self::spline_points(&sample, mid).len() <= max_knots {
+                high = mid;
+            } else {
+                low = mid + 1;
+            }
+        }
+
+        let mut max_error = low.saturating_mul(step);
+        let mut points = Self::spline_points(data, max_error);
+        while points.len() > max_knots {
+            max_error = max_error.saturating_mul(2).max(1);
+            points = Self::spline_points(data, max_error);
+        }
+        let mut spline = GreedySplineCorridor {
+            data,
+            max_error,
+            points,
+        };
+        spline.max_error = spline.measured_error();
+        spline
+    }
+
+    /// The number of spline points.
+    pub fn num_points(&self) -> usize {
+        self.points.len()
     }
 
-    fn spline_points(data: &Vec<u64>, max_error: usize) -> Vec<Point> {
-        assert!(data.len() > 3);
+    /// The position of the first spline point, where the keys before it are searched from the start.
+    pub(crate) fn first_point_position(&self) -> usize {
+        self.points[0].position
+    }
+
+    /// The heap memory of the spline points in bytes, excluding the borrowed keys.
+    pub fn size_in_bytes(&self) -> usize {
+        size_of_val(&self.points[..])
+    }
+
+    /// The data, `max_error` and the spline points as `(key, position)` pairs.
+    pub(crate) fn into_parts(self) -> (&'a [K], usize, Vec<(K, usize)>) {
+        let points = self.points.iter().map(|p| (p.key, p.position)).collect();
+        (self.data, self.max_error, points)
+    }
+
+    /// The largest distance from the prediction of a key to its first occurrence.
+    fn measured_error(&self) -> usize {
+        let mut error = 0;
+        for (i, &key) in self.data.iter().enumerate() {
+            if i > 0 && self.data[i - 1] == key {
+                continue;
+            }
+            let predicted = self.predict(key).expect("every key is within the spline");
+            error = error.max(predicted.abs_diff(i));
+        }
+        error
+    }
+
+    /// The indexed data, so positions can be mapped back to keys without carrying the data separately.
+    pub fn data(&self) -> &[K] {
+        self.data
+    }
+
+    /// The key at `pos`, or `None` if `pos` is out of bounds.
+    pub fn key_at_pos(&self, pos: usize) -> Option<K> {
+        self.data.get(pos).copied()
+    }
+
+    /// The keys at a range of positions, clamped to the data bounds, so a range past the end is empty.
+    pub fn resolve_range(&self, positions: Range<usize>) -> &[K] {
+        let end = positions.end.min(self.data.len());
+        let start = positions.start.min(end);
+        &self.data[start..end]
+    }
+
+    fn spline_points(data: &[K], max_error: usize) -> Vec<Point<K>> {
+        assert!(!data.is_empty());
 
         let mut points = vec![];
         points.push(Point::new(data[0], 0));
 
         let mut base = Point::new(data[0], 0);
+        // the previous distinct key, at the position of its first occurrence
+        let mut prev = base;
 
         // error corridor bounds
-        let mut upper = Point::new(data[1], 1 + max_error);
-        let mut lower = Point::new(data[1], 1usize.saturating_sub(max_error));
+        let mut upper = base;
+        let mut lower = base;
 
-        // note `i` starts from `0`.
-        for (i, &key) in data[2..].iter().enumerate() {
-            let i = i + 2;
+        for (i, &key) in data.iter().enumerate().skip(1) {
+            // skip the repeated values, so a key is always predicted at its first occurrence
+            if key == prev.key {
+                continue;
+            }
             let point_c = Point::new(key, i);
+            let _upper = Point::new(key, i.saturating_add(max_error));
+            let _lower = Point::new(key, i.saturating_sub(max_error));
+
+            // the first distinct key after the base opens the corridor
+            if prev == base {
+                upper = _upper;
+                lower = _lower;
+                prev = point_c;
+                continue;
+            }
 
             // line BC (base -> point_c)
             let bc = Line::new(base, point_c);
@@ -138,24 +272,14 @@ impl<'a> GreedySplineCorridor<'a> {
             // line BL (base -> lower)
             let bl = Line::new(base, lower);
 
-            // continue if `bc` or `bu` or `bl`'s `dx` is 0
-            // skip the repeated values
-            if bc.is_vertical() || bu.is_vertical() || bl.is_vertical() {
-                upper = Point::new(point_c.key, i + max_error);
-                lower = Point::new(point_c.key, i.saturating_sub(max_error));
-                continue;
-            }
-
             if bc.is_left(&bu) || bc.is_right(&bl) {
-                base = Point::new(data[i - 1], i - 1);
+                // `point_c` is out of the corridor, so `prev` becomes a spline point
+                base = prev;
                 points.push(base);
 
-                upper = Point::new(point_c.key, i + max_error);
-                lower = Point::new(point_c.key, i.saturating_sub(max_error));
+                upper = _upper;
+                lower = _lower;
             } else {
-                let _upper = Point::new(point_c.key, i + max_error);
-                let _lower = Point::new(point_c.key, i.saturating_sub(max_error));
-
                 // line BU' (base -> _upper)
                 let _bu = Line::new(base, _upper);
                 // line BL' (base -> _lower)
@@ -167,32 +291,38 @@ impl<'a> GreedySplineCorridor<'a> {
                     lower = _lower;
                 }
             }
+            prev = point_c;
+        }
+        // the last distinct key
+        if prev != base {
+            points.push(prev);
         }
-        let n = data.len();
-        points.push(Point::new(data[n - 1], n - 1));
+        debug_assert!(
+            points.windows(2).all(|w| w[0].key < w[1].key),
+            "spline point keys must be strictly increasing"
+        );
         points
     }
 
-    pub fn search(&self, key: u64) -> Option<usize> {
+    /// search a given `key`, returning the position of its first occurrence
+    ///
+    /// A key equal to a spline point resolves at that point, which is placed at the first occurrence of its key, so it agrees with the window search.
+    pub fn search(&self, key: K) -> Option<usize> {
         let key_point = Point::new(key, 0); // the search position can be arbitrary
         match self.points.binary_search(&key_point) {
             Ok(idx) => Some(self.points[idx].position),
-            Err(idx) if idx > 0 => {
-                let start = self.points[idx - 1];
-                let end = self.points[idx];
-                let predicted = start.position
-                    + (key as usize - start.key as usize) * (end.position - start.position)
-                        / (end.key as usize - start.key as usize);
+            Err(idx) if idx > 0 && idx < self.points.len() => {
+                let predicted = interpolate(self.points[idx - 1], self.points[idx], key);
                 let from = predicted.saturating_sub(self.max_error);
-                let to = if predicted + self.max_error > self.data.len() - 1 {
-                    self.data.len() - 1
+                let to = predicted
+                    .saturating_add(self.max_error)
+                    .min(self.data.len() - 1);
+                // lower bound of `key` in `data[from..=to]`, so the leftmost occurrence is returned
+                let p = from + self.data[from..=to].partition_point(|&x| x < key);
+                if p <= to && self.data[p] == key {
+                    Some(p)
                 } else {
-                    predicted + self.max_error
-                };
-                // binary search `from` `to` in `data`
-                match self.data[from..=to].binary_search(&key) {
-                    Ok(p) => Some(p + from),
-                    _ => None,
+                    None
                 }
                 // how about linear search after predicating?
                 // match self.data[from..=to].iter().position(|&x| x == key) {
@@ -200,9 +330,97 @@ impl<'a> GreedySplineCorridor<'a> {
                 //     _ => None,
                 // }
             }
+            Err(0) => {
+                // below the first spline point, which is at `data[0]` unless the spline starts after some keys,
+                // so the keys before it and its window are searched instead of assuming a miss
+                let end = self.points[0]
+                    .position
+                    .saturating_add(self.max_error)
+                    .min(self.data.len());
+                let p = self.data[..end].partition_point(|&x| x < key);
+                (p < end && self.data[p] == key).then_some(p)
+            }
             _ => None,
         }
     }
+
+    /// predict the position of `key`, or `None` if it is outside `[min_key, max_key]`.
+    pub fn predict(&self, key: K) -> Option<usize> {
+        let key_point = Point::new(key, 0);
+        match self.points.binary_search(&key_point) {
+            Ok(idx) => Some(self.points[idx].position),
+            Err(idx) if idx > 0 && idx < self.points.len() => {
+                Some(interpolate(self.points[idx - 1], self.points[idx], key))
+            }
+            _ => None,
+        }
+    }
+
+    /// The index of the first element not less than `key`, or `data.len()` if there is none.
+    pub fn lower_bound(&self, key: K) -> usize {
+        self.partition_near(key, |x| x < key)
+    }
+
+    /// The index of the first element greater than `key`, or `data.len()` if there is none.
+    fn upper_bound(&self, key: K) -> usize {
+        self.partition_near(key, |x| x <= key)
+    }
+
+    /// The partition point of `is_before`, which holds for keys up to around `key`, searched in the window
+    /// predicted for `key` first, and beyond it only if the partition point is outside.
+    fn partition_near(&self, key: K, is_before: impl Fn(K) -> bool) -> usize {
+        let predicted = match self.predict(key) {
+            Some(predicted) => predicted,
+            None if key < self.points[0].key => {
+                return self.data[..self.points[0].position].partition_point(|&x| is_before(x));
+            }
+            None => return self.data.len(),
+        };
+        let from = predicted.saturating_sub(self.max_error);
+        let to = predicted
+            .saturating_add(self.max_error)
+            .min(self.data.len() - 1);
+
+        let idx = from + self.data[from..=to].partition_point(|&x| is_before(x));
+        if idx == from && from > 0 && !is_before(self.data[from - 1]) {
+            // e.g., prediction of an absent key right after a long run of duplicates
+            return self.data[..from].partition_point(|&x| is_before(x));
+        }
+        if idx > to && to + 1 < self.data.len() {
+            // e.g., the end of a long run of duplicates of `key`
+            return to + 1 + self.data[to + 1..].partition_point(|&x| is_before(x));
+        }
+        idx
+    }
+
+    /// The positions of the keys within `[lo, hi]`, without a radix table, which is empty
+    /// (at the lower bound of `lo`) if there is none, including `lo > hi`.
+    pub fn range(&self, lo: K, hi: K) -> Range<usize> {
+        let start = self.lower_bound(lo);
+        if lo > hi {
+            return start..start;
+        }
+        start..self.upper_bound(hi).max(start)
+    }
+
+    /// The `(position, key)` pairs within `[lo, hi]` in order, see `range`.
+    pub fn iter_range(&self, lo: K, hi: K) -> impl Iterator<Item = (usize, K)> + '_ {
+        let positions = self.range(lo, hi);
+        positions.clone().zip(self.data[positions].iter().copied())
+    }
+
+    pub fn max_error(&self) -> usize {
+        self.max_error
+    }
+}
+
+/// interpolate the position of `key` on the line from `start` to `end`, widening to `u128` so huge key gaps cannot overflow.
+/// Gaps beyond 64 bits, i.e., of `u128` keys, drop their low bits first, so the product still fits.
+fn interpolate<K: SplineKey>(start: Point<K>, end: Point<K>, key: K) -> usize {
+    let (dx, span) = (key.offset_from(start.key), end.key.offset_from(start.key));
+    let shift = (128 - span.leading_zeros()).saturating_sub(64);
+    start.position
+        + ((dx >> shift) * (end.position - start.position) as u128 / (span >> shift)) as usize
 }
 
 #[cfg(test)]
@@ -225,7 +443,7 @@ mod test {
     fn spline_points() {
         let data: Vec<u64> = vec![3, 4, 8, 10, 19, 20];
 
-        let spline = GreedySplineCorridor::new(&data, 1);
+        let spline = GreedySplineCorridor::new(&data, MaxError::new(1));
 
         assert_eq!(
             vec![Point::new(3, 0), Point::new(10, 3), Point::new(20, 5)],
@@ -237,7 +455,7 @@ mod test {
     fn spline_repeated_points() {
         let data: Vec<u64> = vec![3, 4, 8, 8, 10, 10, 19, 20];
 
-        let spline = GreedySplineCorridor::new(&data, 1);
+        let spline = GreedySplineCorridor::new(&data, MaxError::new(1));
 
         assert_eq!(
             vec![Point::new(3, 0), Point::new(10, 5), Point::new(20, 7)],
@@ -245,11 +463,91 @@ mod test {
         );
     }
 
+    #[test]
+    fn duplicates_at_spline_points() {
+        // `spline_points` emits 10 at position 3; repeat it and its neighbours
+        for data in [
+            vec![3, 4, 8, 10, 10, 10, 19, 20],
+            vec![3, 4, 8, 8, 8, 10, 19, 20],
+            vec![3, 4, 8, 10, 19, 19, 19, 20],
+            vec![3, 3, 3, 4, 8, 10, 19, 20, 20, 20],
+        ] {
+            for max_error in [0, 1, 2] {
+                let spline = GreedySplineCorridor::new(&data, MaxError::new(max_error));
+                assert!(spline.points.windows(2).all(|w| w[0].key < w[1].key));
+                for p in &spline.points {
+                    assert_eq!(p.position, data.partition_point(|&x| x < p.key));
+                }
+                for &key in &data {
+                    assert_eq!(spline.search(key), Some(data.partition_point(|&x| x < key)));
+                }
+            }
+        }
+    }
+
+    /// Heavy duplication never builds a line between two points of the same key.
+    #[test]
+    fn heavy_duplicates() {
+        let mut surrounded: Vec<u64> = (0..100).collect();
+        surrounded.extend(std::iter::repeat_n(100, 1000));
+        surrounded.extend(101..200);
+        let mut runs: Vec<u64> = (0..50u64)
+            .flat_map(|key| std::iter::repeat_n(key * 3, 40))
+            .collect();
+        runs.push(1000);
+        for data in [vec![5, 5, 5, 6, 6, 6], vec![5; 1000], surrounded, runs] {
+            for max_error in [0, 1, 4, 32] {
+                let spline = GreedySplineCorridor::new(&data, MaxError::new(max_error));
+                assert!(spline.points.windows(2).all(|w| w[0].key < w[1].key));
+                for key in data[0].saturating_sub(1)..=data[data.len() - 1] + 1 {
+                    let first = data.partition_point(|&x| x < key);
+                    let expected = (data.get(first) == Some(&key)).then_some(first);
+                    assert_eq!(spline.search(key), expected, "key {}", key);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn knot_budget() {
+        use rand::{distributions::Uniform, Rng};
+
+        let mut random: Vec<u64> = rand::thread_rng()
+            .sample_iter(&Uniform::from(0..1_000_000_000))
+            .take(200_000)
+            .collect();
+        random.sort_unstable();
+        let repeated: Vec<u64> = (0..20_000u64).flat_map(|key| [key * key; 3]).collect();
+        for data in [random, repeated, vec![5, 5, 7]] {
+            for max_knots in [2, 3, 10, 100, 1000] {
+                let spline = GreedySplineCorridor::with_knot_budget(&data, max_knots);
+                assert!(spline.num_points() <= max_knots);
+                assert_eq!(
+                    spline.size_in_bytes(),
+                    spline.num_points() * 2 * size_of::<u64>()
+                );
+
+                // the distance from the prediction to the first occurrence of every key
+                let error = data
+                    .iter()
+                    .map(|&key| {
+                        let first = data.partition_point(|&x| x < key);
+                        spline.predict(key).unwrap().abs_diff(first)
+                    })
+                    .max();
+                assert_eq!(Some(spline.max_error()), error);
+                for &key in data.iter().step_by(97) {
+                    assert_eq!(spline.search(key), Some(data.partition_point(|&x| x < key)));
+                }
+            }
+        }
+    }
+
     #[test]
     fn search() {
         let data: Vec<u64> = vec![3, 4, 6, 8, 10, 19, 20];
 
-        let spline = GreedySplineCorridor::new(&data, 1);
+        let spline = GreedySplineCorridor::new(&data, MaxError::new(1));
 
         assert_eq!(spline.search(8), Some(3));
 
@@ -260,6 +558,205 @@ mod test {
         assert_eq!(spline.search(5), None);
     }
 
+    #[test]
+    fn key_at_pos() {
+        let data: Vec<u64> = vec![3, 4, 6, 8, 10, 19, 20];
+        let spline = GreedySplineCorridor::new(&data, MaxError::new(1));
+
+        assert_eq!(spline.data(), &data[..]);
+        assert_eq!(spline.key_at_pos(spline.search(10).unwrap()), Some(10));
+        assert_eq!(spline.key_at_pos(7), None);
+        assert_eq!(spline.resolve_range(2..4), &[6, 8]);
+        assert!(spline.resolve_range(7..9).is_empty());
+    }
+
+    #[test]
+    fn leftmost_duplicates() {
+        let mut data: Vec<u64> = vec![];
+        for key in 0..300u64 {
+            data.extend(std::iter::repeat_n(key * 2, 1 + (key as usize * 7) % 23));
+        }
+        data.push(u64::MAX);
+
+        for max_error in [0, 1, 4, 8] {
+            let spline = GreedySplineCorridor::new(&data, MaxError::new(max_error));
+            for key in (0..601).chain([u64::MAX - 1, u64::MAX]) {
+                let first = data.partition_point(|&x| x < key);
+                let expected = if data.get(first) == Some(&key) {
+                    Some(first)
+                } else {
+                    None
+                };
+                assert_eq!(spline.search(key), expected, "{}", key);
+            }
+        }
+    }
+
+    /// `lower_bound` agrees with `partition_point` on random keys with runs of duplicates,
+    /// for present keys, absent keys within runs' gaps, and keys beyond both ends.
+    #[test]
+    fn lower_bound() {
+        use rand::Rng;
+
+        let mut rng = rand::thread_rng();
+        for (len, max) in [(1, 10), (50, 10), (5000, 1000), (20_000, 1 << 40)] {
+            let mut data: Vec<u64> = (0..len).map(|_| rng.gen_range(5..=max)).collect();
+            // long runs of duplicates
+            data.extend(std::iter::repeat_n(max / 2, len / 4));
+            data.sort_unstable();
+
+            for max_error in [0, 1, 8, 32] {
+                let spline = GreedySplineCorridor::new(&data, MaxError::new(max_error));
+                let keys = data
+                    .iter()
+                    .flat_map(|&key| [key - 1, key, key + 1])
+                    .chain((0..200).map(|_| rng.gen_range(0..=max + 5)))
+                    .chain([0, 4, u64::MAX]);
+                for key in keys {
+                    assert_eq!(
+                        spline.lower_bound(key),
+                        data.partition_point(|&x| x < key),
+                        "key {} (len {}, max_error {})",
+                        key,
+                        len,
+                        max_error
+                    );
+                }
+            }
+        }
+    }
+
+    /// `range` agrees with `partition_point` for ranges within one segment and across many,
+    /// and for bounds beyond both ends or reversed.
+    #[test]
+    fn range() {
+        use rand::Rng;
+
+        let mut rng = rand::thread_rng();
+        let mut data: Vec<u64> = (0..20_000).map(|_| rng.gen_range(10..1 << 30)).collect();
+        data.extend(std::iter::repeat_n(1 << 29, 3000));
+        data.sort_unstable();
+        let expected = |lo: u64, hi: u64| {
+            let start = data.partition_point(|&x| x < lo);
+            start..data.partition_point(|&x| x <= hi).max(start)
+        };
+        for max_error in [0, 4, 32] {
+            let spline = GreedySplineCorridor::new(&data, MaxError::new(max_error));
+            let points = &spline.points;
+            // within one segment, between two consecutive spline points
+            let i = points.len() / 2;
+            let (lo, hi) = (points[i].key + 1, points[i + 1].key - 1);
+            assert_eq!(spline.range(lo, hi), expected(lo, hi));
+            // across many segments
+            let (lo, hi) = (points[1].key, points[points.len() - 2].key);
+            let positions = spline.range(lo, hi);
+            assert_eq!(positions, expected(lo, hi));
+            assert!(positions.len() > data.len() / 2);
+            // the run of duplicates, and beyond both ends
+            for (lo, hi) in [
+                (1 << 29, 1 << 29),
+                (0, 9),
+                (0, u64::MAX),
+                (u64::MAX, u64::MAX),
+                (100, 99),
+                (u64::MAX, 0),
+            ] {
+                assert_eq!(spline.range(lo, hi), expected(lo, hi), "[{}, {}]", lo, hi);
+            }
+            for _ in 0..1000 {
+                let (a, b) = (rng.gen_range(0..1 << 30), rng.gen_range(0..1 << 30));
+                let (lo, hi) = (a.min(b), a.max(b));
+                assert_eq!(spline.range(lo, hi), expected(lo, hi), "[{}, {}]", lo, hi);
+            }
+        }
+
+        let spline = GreedySplineCorridor::new(&data, MaxError::new(8));
+        let (lo, hi) = (data[100], data[120]);
+        let pairs: Vec<(usize, u64)> = spline.iter_range(lo, hi).collect();
+        let positions = expected(lo, hi);
+        assert_eq!(pairs.len(), positions.len());
+        assert!(pairs
+            .iter()
+            .all(|&(i, key)| data[i] == key && (lo..=hi).contains(&key)));
+        assert_eq!(spline.iter_range(hi, lo).count(), 0);
+    }
+
+    /// A spline over `data[start..]` only, with positions in `data`, as if the leading keys were left out of the index.
+    fn starting_at(data: &Vec<u64>, start: usize, max_error: usize) -> GreedySplineCorridor<'_> {
+        let mut points = GreedySplineCorridor::spline_points(&data[start..], max_error);
+        for point in &mut points {
+            point.position += start;
+        }
+        GreedySplineCorridor {
+            data,
+            max_error,
+            points,
+        }
+    }
+
+    /// Keys below a first spline point which is not at position 0 are still found.
+    #[test]
+    fn first_point_after_start() {
+        let mut data: Vec<u64> = vec![1, 3, 3, 7, 10, 11];
+        data.extend((12..2000u64).map(|x| x * x));
+        for start in [0, 1, 3, 5] {
+            for max_error in [0, 1, 4, 32] {
+                let spline = starting_at(&data, start, max_error);
+                assert_eq!(spline.points[0].position, start);
+                for key in (0..20).chain(data.iter().copied()) {
+                    let first = data.partition_point(|&x| x < key);
+                    let expected = (data.get(first) == Some(&key)).then_some(first);
+                    assert_eq!(spline.search(key), expected, "start {} key {}", start, key);
+                    assert_eq!(
+                        spline.lower_bound(key),
+                        first,
+                        "start {} key {}",
+                        start,
+                        key
+                    );
+                }
+            }
+        }
+    }
+
+    fn check_keys<K: SplineKey + std::fmt::Debug>(data: &[K], probes: &[K]) {
+        for max_error in [0, 4, 32] {
+            let spline = GreedySplineCorridor::new(data, MaxError::new(max_error));
+            for &key in data.iter().chain(probes) {
+                let first = data.partition_point(|&x| x < key);
+                let expected = (data.get(first) == Some(&key)).then_some(first);
+                assert_eq!(spline.search(key), expected, "{:?}", key);
+                assert_eq!(spline.lower_bound(key), first, "{:?}", key);
+            }
+        }
+    }
+
+    /// `u32` keys are indexed without widening, signed keys span zero and both extremes, and `u128` gaps exceed `u64`.
+    #[test]
+    fn key_types() {
+        use rand::Rng;
+
+        let mut rng = rand::thread_rng();
+        let mut timestamps: Vec<u32> = (0..5000).map(|_| rng.gen_range(0..1 << 30)).collect();
+        timestamps.extend([0, u32::MAX, u32::MAX]);
+        timestamps.sort_unstable();
+        check_keys(&timestamps, &[1, 1 << 29, u32::MAX - 1]);
+
+        let mut signed: Vec<i64> = (0..5000).map(|_| rng.gen_range(-1000..1000)).collect();
+        signed.extend([i64::MIN, i64::MIN + 1, -1, 0, 1, i64::MAX]);
+        signed.sort_unstable();
+        check_keys(&signed, &[-1001, 1001, i64::MIN + 2, i64::MAX - 1]);
+
+        let mut wide: Vec<u128> = (0..5000).map(|_| rng.gen::<u128>() >> 8).collect();
+        wide.extend([0, u128::MAX]);
+        wide.sort_unstable();
+        check_keys(&wide, &[1, 1 << 100, u128::MAX - 1]);
+
+        let data: Vec<u64> = (0..1000).map(|x| x * x).collect();
+        let spline: GreedySplineCorridorU64 = GreedySplineCorridor::new(&data, MaxError::new(8));
+        assert_eq!(spline.search(81), Some(9));
+    }
+
     #[test]
     fn large_search() {
         use rand::{distributions::Uniform, Rng};
@@ -276,7 +773,7 @@ mod test {
 
         data.sort_unstable();
 
-        let spline = GreedySplineCorridor::new(&data, 32);
+        let spline = GreedySplineCorridor::new(&data, MaxError::new(32));
 
         let start = Instant::now();
         if let Some(idx) = spline.search(value) {
@@ -0,0 +1,237 @@
+//! # Radix table layouts
+//! The flat table spends one entry per prefix. Clustered keys leave long runs of entries pointing at the same spline point,
+//! which the run-length layout stores once per run, at the cost of a binary search per lookup instead of one read.
+
+use std::borrow::Cow;
+
+use crate::buffer::Buffer;
+use crate::common::{widen, Position, Table};
+
+/// The layout of the radix table.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TableLayout {
+    /// one entry per prefix
+    Flat,
+    /// one `(prefix, point index)` pair per run of equal entries
+    RunLength,
+}
+
+/// The run-length layout is chosen automatically for tables of at least this many entries.
+const MIN_RUN_LENGTH_ENTRIES: usize = 1024;
+
+pub(crate) enum RadixTable {
+    Flat(Buffer<Table, Position>),
+    RunLength {
+        // the first prefix of each run, starting at 0
+        prefixes: Vec<usize>,
+        // the entry of each run
+        entries: Vec<Position>,
+        len: usize,
+    },
+}
+
+impl RadixTable {
+    /// Keep `table` flat, unless the run-length layout takes less than a quarter of its memory,
+    /// and the flat table is large next to the `num_keys` keys (more than a sixteenth of them).
+    /// A table much smaller than the data costs little memory, so its single-read lookup is kept, e.g., for large uniform data.
+    pub(crate) fn auto(table: Table, num_keys: usize) -> Self {
+        if prefers_run_length(&table, num_keys) {
+            RadixTable::with_layout(&table, TableLayout::RunLength)
+        } else {
+            RadixTable::Flat(table.into())
+        }
+    }
+
+    /// The same as `auto`, copying a borrowed `table`.
+    pub(crate) fn auto_from_slice(table: &[Position], num_keys: usize) -> Self {
+        if prefers_run_length(table, num_keys) {
+            RadixTable::with_layout(table, TableLayout::RunLength)
+        } else {
+            RadixTable::with_layout(table, TableLayout::Flat)
+        }
+    }
+
+    pub(crate) fn with_layout(table: &[Position], layout: TableLayout) -> Self {
+        match layout {
+            TableLayout::Flat => RadixTable::Flat(Table::from(table).into()),
+            TableLayout::RunLength => {
+                let (mut prefixes, mut entries) = (vec![], vec![]);
+                for (prefix, &entry) in table.iter().enumerate() {
+                    if entries.last() != Some(&entry) {
+                        prefixes.push(prefix);
+                        entries.push(entry);
+                    }
+                }
+                RadixTable::RunLength {
+                    prefixes,
+                    entries,
+                    len: table.len(),
+                }
+            }
+        }
+    }
+
+    pub(crate) fn layout(&self) -> TableLayout {
+        match self {
+            RadixTable::Flat(_) => TableLayout::Flat,
+            RadixTable::RunLength { .. } => TableLayout::RunLength,
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        match self {
+            RadixTable::Flat(table) => table.len(),
+            RadixTable::RunLength { len, .. } => *len,
+        }
+    }
+
+    /// The heap memory of the table in bytes.
+    pub(crate) fn bytes(&self) -> usize {
+        match self {
+            RadixTable::Flat(table) => table.len() * size_of::<Position>(),
+            RadixTable::RunLength {
+                prefixes, entries, ..
+            } => prefixes.len() * size_of::<usize>() + entries.len() * size_of::<Position>(),
+        }
+    }
+
+    /// `(table[prefix], table[prefix + 1])`, i.e., the spline points of the bucket `prefix`.
+    #[inline]
+    pub(crate) fn bucket(&self, prefix: usize) -> (usize, usize) {
+        match self {
+            RadixTable::Flat(table) => (widen(table[prefix]), widen(table[prefix + 1])),
+            RadixTable::RunLength {
+                prefixes, entries, ..
+            } => {
+                let run = last_not_greater(prefixes, prefix);
+                let next = if prefixes.get(run + 1) == Some(&(prefix + 1)) {
+                    entries[run + 1]
+                } else {
+                    entries[run]
+                };
+                (widen(entries[run]), widen(next))
+            }
+        }
+    }
+
+    /// The flat table, which is expanded for the run-length layout.
+    pub(crate) fn to_flat(&self) -> Cow<'_, [Position]> {
+        match self {
+            RadixTable::Flat(table) => Cow::Borrowed(&table[..]),
+            RadixTable::RunLength {
+                prefixes,
+                entries,
+                len,
+            } => {
+                let mut table = Vec::with_capacity(*len);
+                for (run, &entry) in entries.iter().enumerate() {
+                    let end = prefixes.get(run + 1).copied().unwrap_or(*len);
+                    table.resize(end, entry);
+                }
+                Cow::Owned(table)
+            }
+        }
+    }
+}
+
+fn prefers_run_length(table: &[Position], num_keys: usize) -> bool {
+    let flat_bytes = size_of_val(table);
+    if table.len() < MIN_RUN_LENGTH_ENTRIES || flat_bytes * 16 <= num_keys * size_of::<u64>() {
+        return false;
+    }
+    let runs = 1 + table.windows(2).filter(|w| w[0] != w[1]).count();
+    let run_length_bytes = runs * (size_of::<usize>() + size_of::<Position>());
+    run_length_bytes * 4 < flat_bytes
+}
+
+/// The index of the last element not greater than `prefix` in `prefixes`, whose first element is 0.
+/// The loop has a fixed trip count for a given length, and the comparison compiles to a conditional move.
+#[inline]
+fn last_not_greater(prefixes: &[usize], prefix: usize) -> usize {
+    let mut base = 0;
+    let mut len = prefixes.len();
+    while len > 1 {
+        let half = len / 2;
+        base = if prefixes[base + half] <= prefix {
+            base + half
+        } else {
+            base
+        };
+        len -= half;
+    }
+    base
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testing::{adversarial, Adversarial};
+    use crate::RadixSpline;
+    use crate::{MaxError, RadixBits};
+
+    #[test]
+    fn run_length_equivalence() {
+        let mut datasets: Vec<Vec<u64>> =
+            Adversarial::ALL.iter().map(|&k| adversarial(k)).collect();
+        // clustered keys
+        datasets.push((0..5000).map(|i| (i % 50) + (i / 50) * (1 << 40)).collect());
+        datasets.push(vec![42]);
+
+        for data in &datasets {
+            for num_radix_bits in [0, 4, 18] {
+                let flat = RadixSpline::new(data, RadixBits::new(num_radix_bits), MaxError::new(8))
+                    .with_table_layout(TableLayout::Flat);
+                let run_length =
+                    RadixSpline::new(data, RadixBits::new(num_radix_bits), MaxError::new(8))
+                        .with_table_layout(TableLayout::RunLength);
+                assert_eq!(run_length.table_layout(), TableLayout::RunLength);
+                assert_eq!(flat.table()[..], run_length.table()[..]);
+
+                let table = flat.table();
+                let layout = RadixTable::with_layout(&table, TableLayout::RunLength);
+                for prefix in 0..table.len() - 1 {
+                    assert_eq!(
+                        layout.bucket(prefix),
+                        (widen(table[prefix]), widen(table[prefix + 1]))
+                    );
+                }
+                for &key in data.iter().step_by(3) {
+                    assert_eq!(flat.search(key), run_length.search(key));
+                    assert_eq!(
+                        flat.search(key.wrapping_add(1)),
+                        run_length.search(key.wrapping_add(1))
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn auto_layout() {
+        // clustered keys leave most of a large table to a few runs
+        let data: Vec<u64> = (0..5000).map(|i| (i % 50) + (i / 50) * (1 << 40)).collect();
+        let radix_spline = RadixSpline::new(&data, RadixBits::new(18), MaxError::new(8));
+        assert_eq!(radix_spline.table_layout(), TableLayout::RunLength);
+        assert!(
+            radix_spline.table_bytes() * 4 < radix_spline.table().len() * size_of::<Position>()
+        );
+
+        // dense keys spread points over all prefixes
+        let data: Vec<u64> = (0..100000).map(|i| i * i).collect();
+        let radix_spline = RadixSpline::new(&data, RadixBits::new(12), MaxError::new(0));
+        assert_eq!(radix_spline.table_layout(), TableLayout::Flat);
+
+        // few points, but the table is small next to the data
+        let data: Vec<u64> = (0..1000000).map(|i| i * 3 + i % 2).collect();
+        let radix_spline = RadixSpline::new(&data, RadixBits::new(12), MaxError::new(32));
+        assert_eq!(radix_spline.table_layout(), TableLayout::Flat);
+        assert!(
+            RadixSpline::new(&data, RadixBits::new(18), MaxError::new(32)).table_layout()
+                == TableLayout::RunLength
+        );
+
+        // a small table is always flat
+        let radix_spline = RadixSpline::new(&data, RadixBits::new(8), MaxError::new(32));
+        assert_eq!(radix_spline.table_layout(), TableLayout::Flat);
+    }
+}
@@ -0,0 +1,207 @@
+//! # Appended keys
+//! When keys are appended to the data after the index was built, the index still answers for the old keys,
+//! while keys past its `max_key` are beyond the last spline point. `TailPolicy` decides how they are answered
+//! until the index is rebuilt.
+
+use std::ops::Range;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::index::LearnedIndex;
+use crate::radix::RadixSpline;
+
+/// How keys greater than the `max_key` of the index are answered.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TailPolicy {
+    /// they are absent, as if nothing was appended
+    #[default]
+    Reject,
+    /// they are searched with a binary search over the appended keys
+    BinarySearchBeyondLast,
+}
+
+/// `AppendedRadixSpline` is an index attached to its data with keys appended.
+/// The first `num_keys_at_build` keys are the ones the index was built over, and the rest is the tail.
+pub struct AppendedRadixSpline<'a> {
+    index: RadixSpline<'a>,
+    data: &'a [u64],
+    policy: TailPolicy,
+    tail_lookups: AtomicU64,
+}
+
+impl<'a> RadixSpline<'a> {
+    /// Attach the index to `data`, which starts with the keys the index was built over, followed by sorted appended keys
+    /// not less than `max_key`. Only the keys around the boundary are checked.
+    pub fn attach_appended(self, data: &'a [u64], policy: TailPolicy) -> AppendedRadixSpline<'a> {
+        let num_keys_at_build = self.data().len();
+        assert!(
+            data.len() >= num_keys_at_build
+                && data[0] == self.min_key()
+                && data[num_keys_at_build - 1] == self.max_key(),
+            "data must start with the indexed keys"
+        );
+        assert!(
+            data.get(num_keys_at_build)
+                .is_none_or(|&first| first >= self.max_key()),
+            "appended keys must not be less than `max_key`"
+        );
+        AppendedRadixSpline {
+            index: self,
+            data,
+            policy,
+            tail_lookups: AtomicU64::new(0),
+        }
+    }
+}
+
+impl<'a> AppendedRadixSpline<'a> {
+    pub fn index(&self) -> &RadixSpline<'a> {
+        &self.index
+    }
+
+    pub fn num_keys_at_build(&self) -> usize {
+        self.index.data().len()
+    }
+
+    /// The appended keys.
+    pub fn tail(&self) -> &[u64] {
+        &self.data[self.num_keys_at_build()..]
+    }
+
+    /// The number of binary searches over the tail so far, e.g., to decide when to rebuild.
+    pub fn tail_lookups(&self) -> u64 {
+        self.tail_lookups.load(Ordering::Relaxed)
+    }
+
+    /// search a given `key`, returning the position of its first occurrence.
+    pub fn search(&self, key: u64) -> Option<usize> {
+        if key <= self.index.max_key() {
+            return self.index.search(key);
+        }
+        let position = self.lower_bound(key);
+        (position < self.len() && self.data[position] == key).then_some(position)
+    }
+
+    /// The position of the first key not less than `key`, within the keys visible under the policy.
+    pub fn lower_bound(&self, key: u64) -> usize {
+        if key <= self.index.max_key() {
+            return self.index.lower_bound_position(key);
+        }
+        let num_keys_at_build = self.num_keys_at_build();
+        match self.policy {
+            TailPolicy::Reject => num_keys_at_build,
+            TailPolicy::BinarySearchBeyondLast => {
+                self.tail_lookups.fetch_add(1, Ordering::Relaxed);
+                num_keys_at_build + self.tail().partition_point(|&x| x < key)
+            }
+        }
+    }
+
+    /// The number of keys visible under the policy.
+    pub fn len(&self) -> usize {
+        match self.policy {
+            TailPolicy::Reject => self.num_keys_at_build(),
+            TailPolicy::BinarySearchBeyondLast => self.data.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<'a> LearnedIndex for AppendedRadixSpline<'a> {
+    fn len(&self) -> usize {
+        AppendedRadixSpline::len(self)
+    }
+
+    fn search(&self, key: u64) -> Option<usize> {
+        AppendedRadixSpline::search(self, key)
+    }
+
+    fn lower_bound(&self, key: u64) -> usize {
+        AppendedRadixSpline::lower_bound(self, key)
+    }
+
+    /// Keys in the visible tail are predicted exactly, with a binary search.
+    fn predict(&self, key: u64) -> Option<usize> {
+        if key <= self.index.max_key() {
+            return LearnedIndex::predict(&self.index, key);
+        }
+        let visible = &self.data[..self.len()];
+        (key <= visible[visible.len() - 1]).then(|| self.lower_bound(key))
+    }
+
+    fn max_error(&self) -> usize {
+        self.index.max_error()
+    }
+
+    fn equal_range(&self, key: u64) -> Range<usize> {
+        let start = self.lower_bound(key);
+        let end = match key.checked_add(1) {
+            Some(next) => self.lower_bound(next),
+            None => self.len(),
+        };
+        start..end
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{MaxError, RadixBits};
+
+    fn grown(tail: usize) -> Vec<u64> {
+        let mut data: Vec<u64> = (0..10_000u64).map(|x| x * x / 7).collect();
+        let max_key = *data.last().unwrap();
+        // the tail repeats `max_key`, then grows with gaps
+        data.extend((0..tail as u64).map(|i| max_key + i / 3 * 5));
+        data
+    }
+
+    #[test]
+    fn binary_search_beyond_last() {
+        for tail in [0, 1, 10, 1000, 5000] {
+            let data = grown(tail);
+            let num_keys = data.len() - tail;
+            let index =
+                RadixSpline::try_new(&data[..num_keys], RadixBits::new(12), MaxError::new(8))
+                    .unwrap()
+                    .attach_appended(&data, TailPolicy::BinarySearchBeyondLast);
+            assert_eq!(index.tail().len(), tail);
+            let max_key = index.index().max_key();
+
+            for (position, &key) in data.iter().enumerate().step_by(7) {
+                let first = data.partition_point(|&x| x < key);
+                assert_eq!(index.search(key), Some(first), "position {}", position);
+            }
+            assert_eq!(
+                index.equal_range(max_key).end,
+                data.partition_point(|&x| x <= max_key)
+            );
+            assert_eq!(index.search(max_key + 1), None);
+
+            let before = index.tail_lookups();
+            let beyond = data.iter().filter(|&&key| key > max_key).count() as u64;
+            for &key in &data[num_keys..] {
+                index.search(key);
+            }
+            assert_eq!(index.tail_lookups() - before, beyond);
+            index.search(max_key);
+            assert_eq!(index.tail_lookups() - before, beyond);
+            crate::testing::assert_conformance(&index, &data);
+        }
+    }
+
+    /// The default policy behaves like the index over the keys at build time.
+    #[test]
+    fn reject() {
+        let data = grown(100);
+        let num_keys = data.len() - 100;
+        let index = RadixSpline::try_new(&data[..num_keys], RadixBits::new(12), MaxError::new(8))
+            .unwrap()
+            .attach_appended(&data, TailPolicy::default());
+        assert_eq!(index.search(*data.last().unwrap()), None);
+        assert_eq!(index.tail_lookups(), 0);
+        crate::testing::assert_conformance(&index, &data[..num_keys]);
+    }
+}
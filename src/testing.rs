@@ -0,0 +1,432 @@
+//! # Test utilities
+//! Pathological datasets for stress testing, and the counted lookup checks of the benchmarks,
+//! available behind the `testing` feature.
+
+use std::hint::black_box;
+use std::time::Instant;
+
+use crate::{GreedySplineCorridor, LearnedIndex, MaxError, RadixBits, RadixSpline};
+
+/// The kinds of adversarial datasets.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Adversarial {
+    /// long runs of repeated keys
+    DuplicateRuns,
+    /// dense keys followed by a single huge outlier
+    HugeOutlier,
+    /// all keys but the last share one radix prefix
+    SinglePrefix,
+    /// keys ending exactly at `u64::MAX`
+    MaxKey,
+    /// a staircase with long flat treads
+    Staircase,
+}
+
+impl Adversarial {
+    pub const ALL: [Adversarial; 5] = [
+        Adversarial::DuplicateRuns,
+        Adversarial::HugeOutlier,
+        Adversarial::SinglePrefix,
+        Adversarial::MaxKey,
+        Adversarial::Staircase,
+    ];
+}
+
+/// Generate a sorted adversarial dataset. The output is deterministic.
+pub fn adversarial(kind: Adversarial) -> Vec<u64> {
+    match kind {
+        Adversarial::DuplicateRuns => (0..200u64)
+            .flat_map(|key| std::iter::repeat_n(key * 7, 1 + (key as usize * 37) % 300))
+            .collect(),
+        Adversarial::HugeOutlier => {
+            let mut data: Vec<u64> = (0..10000).collect();
+            data.push(u64::MAX / 2);
+            data
+        }
+        Adversarial::SinglePrefix => {
+            let mut data: Vec<u64> = (0..10000).map(|i| i * 3).collect();
+            data.push(1 << 62);
+            data
+        }
+        Adversarial::MaxKey => (0..10000).rev().map(|i| u64::MAX - i * 1000).collect(),
+        Adversarial::Staircase => (0..20u64)
+            .flat_map(|step| {
+                // a steep riser of distinct keys, then a flat tread
+                (0..50)
+                    .map(move |i| step * 1000000 + i)
+                    .chain(std::iter::repeat_n(step * 1000000 + 500000, 500))
+            })
+            .collect(),
+    }
+}
+
+/// Build a `GreedySplineCorridor` and a `RadixSpline` over `data`, and assert that `search` agrees for all `keys`.
+/// It panics with the key and both results on the first mismatch.
+pub fn assert_indexes_agree(
+    data: &[u64],
+    keys: &[u64],
+    num_radix_bits: RadixBits,
+    max_error: MaxError,
+) {
+    let spline = GreedySplineCorridor::new(data, max_error);
+    let radix_spline = RadixSpline::new(data, num_radix_bits, max_error);
+    for &key in keys {
+        let (expected, found) = (spline.search(key), radix_spline.search(key));
+        assert_eq!(
+            expected, found,
+            "key {}: GreedySplineCorridor found {:?}, RadixSpline found {:?} (num_radix_bits {}, max_error {}, {} keys)",
+            key, expected, found, num_radix_bits.get(), max_error.get(), data.len()
+        );
+    }
+}
+
+/// Assert the lookup contract of `LearnedIndex` for `index` built over the sorted, non-empty `data`:
+/// - `search` returns the first occurrence of a present key, and `None` for a miss.
+/// - `lower_bound` is the first position not less than the key, and `equal_range` is half-open.
+/// - keys outside `[min_key, max_key]` are misses, with `lower_bound` at `0` or `len()`, and no prediction.
+/// - every present key is predicted within `max_error` of one of its occurrences.
+/// - the same query gives the same answer.
+///
+/// It probes every key, its neighbours, and the extreme keys, so it is meant for test-sized data.
+pub fn assert_conformance(index: &impl LearnedIndex, data: &[u64]) {
+    assert!(
+        !data.is_empty() && data.windows(2).all(|w| w[0] <= w[1]),
+        "data must be sorted and non-empty"
+    );
+    assert_eq!(index.len(), data.len(), "len");
+    let (min_key, max_key) = (data[0], data[data.len() - 1]);
+
+    let mut probes = vec![0, 1, u64::MAX - 1, u64::MAX];
+    for &key in data {
+        probes.extend([key.wrapping_sub(1), key, key.wrapping_add(1)]);
+    }
+    for key in probes {
+        let first = data.partition_point(|&x| x < key);
+        let end = data.partition_point(|&x| x <= key);
+        let present = first < end;
+
+        let found = index.search(key);
+        assert_eq!(found, present.then_some(first), "search({})", key);
+        assert_eq!(index.lower_bound(key), first, "lower_bound({})", key);
+        assert_eq!(index.equal_range(key), first..end, "equal_range({})", key);
+        assert_eq!(
+            index.search(key),
+            found,
+            "search({}) is not deterministic",
+            key
+        );
+
+        let predicted = index.predict(key);
+        if key < min_key || key > max_key {
+            assert_eq!(predicted, None, "predict({}) outside the domain", key);
+        } else if present {
+            let predicted =
+                predicted.unwrap_or_else(|| panic!("predict({}) within the domain", key));
+            // the distance to the nearest occurrence
+            let error = if predicted < first {
+                first - predicted
+            } else {
+                predicted.saturating_sub(end - 1)
+            };
+            assert!(
+                error <= index.max_error(),
+                "predict({}) = {} is {} away, beyond max_error {}",
+                key,
+                predicted,
+                error,
+                index.max_error()
+            );
+        }
+    }
+}
+
+/// The outcome of `time_lookups`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LookupRun {
+    /// the mean latency of `search`
+    pub nanos_per_lookup: u128,
+    /// the keys `search` resolved wrongly, or `None` if the lookups were not verified
+    pub mismatches: Option<usize>,
+}
+
+/// Count the keys for which `index.search` is not the first occurrence in the sorted `data`, or not a miss for an absent key.
+/// It is a plain comparison rather than an assertion, so a broken index is reported whatever the build settings,
+/// e.g., with `panic = "abort"` or without debug assertions.
+pub fn count_mismatches(index: &impl LearnedIndex, data: &[u64], keys: &[u64]) -> usize {
+    keys.iter()
+        .filter(|&&key| {
+            let first = data.partition_point(|&x| x < key);
+            let expected = (data.get(first) == Some(&key)).then_some(first);
+            index.search(key) != expected
+        })
+        .count()
+}
+
+/// Time `index.search` over `keys`, and then, if `verify`, count the mismatches against `data` with `count_mismatches`.
+/// The check runs after the timed loop, so it only adds to the total running time.
+pub fn time_lookups(
+    index: &impl LearnedIndex,
+    data: &[u64],
+    keys: &[u64],
+    verify: bool,
+) -> LookupRun {
+    let start = Instant::now();
+    for &key in keys {
+        black_box(index.search(black_box(key)));
+    }
+    let nanos_per_lookup = start.elapsed().as_nanos() / keys.len().max(1) as u128;
+    LookupRun {
+        nanos_per_lookup,
+        mismatches: verify.then(|| count_mismatches(index, data, keys)),
+    }
+}
+
+/// The mean nanoseconds of a scan for the first spline point not less than a key, over a radix bucket of `num_points`
+/// points, as `(one point at a time, a chunk of points at a time)`. The second is the scan of the `simd` feature.
+pub fn time_point_scans(num_points: usize, scans: usize) -> (f64, f64) {
+    use crate::common::Point;
+    use crate::radix::{scan_points, scan_points_chunked};
+
+    let points: Vec<Point> = (0..num_points)
+        .map(|i| Point::new(2 * i as u64, i))
+        .collect();
+    // keys spread over the bucket, present and absent
+    let keys: Vec<u64> = (0..scans as u64)
+        .map(|i| i.wrapping_mul(0x9E37_79B9_7F4A_7C15) % (2 * num_points as u64 - 1))
+        .collect();
+    let time = |scan: fn(&[Point], usize, u64) -> usize| {
+        let start = Instant::now();
+        for &key in &keys {
+            black_box(scan(black_box(&points), 0, black_box(key)));
+        }
+        start.elapsed().as_nanos() as f64 / scans.max(1) as f64
+    };
+    (time(scan_points), time(scan_points_chunked))
+}
+
+#[cfg(test)]
+mod test {
+    use std::ops::Range;
+
+    use super::*;
+    use crate::OwnedRadixSpline;
+
+    #[test]
+    fn sorted() {
+        for kind in Adversarial::ALL {
+            let data = adversarial(kind);
+            assert!(data.windows(2).all(|w| w[0] <= w[1]), "{:?}", kind);
+        }
+    }
+
+    #[test]
+    fn search_adversarial() {
+        for kind in Adversarial::ALL {
+            let data = adversarial(kind);
+            let spline = GreedySplineCorridor::new(&data, MaxError::DEFAULT);
+            let radix_spline = RadixSpline::new(&data, RadixBits::DEFAULT, MaxError::DEFAULT);
+            for &key in &data {
+                match spline.search(key) {
+                    Some(idx) => assert_eq!(data[idx], key, "{:?}", kind),
+                    None => panic!("{:?}: spline missed {}", kind, key),
+                }
+                match radix_spline.search(key) {
+                    Some(idx) => assert_eq!(data[idx], key, "{:?}", kind),
+                    None => panic!("{:?}: radix spline missed {}", kind, key),
+                }
+            }
+        }
+    }
+
+    /// Every probe is checked against the first occurrence, so wrapping arithmetic in release builds cannot go unnoticed.
+    #[test]
+    fn indexes_agree() {
+        for kind in Adversarial::ALL {
+            let data = adversarial(kind);
+            let keys: Vec<u64> = data
+                .iter()
+                .flat_map(|&key| [key.wrapping_sub(1), key, key.wrapping_add(1)])
+                .collect();
+            for (num_radix_bits, max_error) in [(0, 0), (8, 4), (18, 32)] {
+                assert_indexes_agree(
+                    &data,
+                    &keys,
+                    RadixBits::new(num_radix_bits),
+                    MaxError::new(max_error),
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn conformance() {
+        let mut datasets: Vec<Vec<u64>> =
+            Adversarial::ALL.iter().map(|&k| adversarial(k)).collect();
+        datasets.extend([
+            vec![0],
+            vec![u64::MAX],
+            vec![3, 3, 3],
+            vec![1, 2, 4, 8, 16],
+            (0..1000).map(|i| i * 9).collect(),
+        ]);
+
+        for data in &datasets {
+            for max_error in [0, 32] {
+                assert_conformance(
+                    &GreedySplineCorridor::new(data, MaxError::new(max_error)),
+                    data,
+                );
+                for num_radix_bits in [0, 18] {
+                    assert_conformance(
+                        &RadixSpline::new(
+                            data,
+                            RadixBits::new(num_radix_bits),
+                            MaxError::new(max_error),
+                        ),
+                        data,
+                    );
+                    assert_conformance(
+                        &RadixSpline::new(
+                            data,
+                            RadixBits::new(num_radix_bits),
+                            MaxError::new(max_error),
+                        )
+                        .with_max_run_hint(16),
+                        data,
+                    );
+                    let bits = RadixBits::new(num_radix_bits);
+                    let retuned =
+                        RadixSpline::new(data, RadixBits::new(8), MaxError::new(max_error))
+                            .with_radix_bits(bits)
+                            .unwrap();
+                    assert_conformance(&retuned, data);
+                    let upgraded = GreedySplineCorridor::new(data, MaxError::new(max_error))
+                        .into_radix_spline(bits)
+                        .unwrap();
+                    assert!(upgraded.verify().is_ok());
+                    assert_conformance(&upgraded, data);
+                }
+                assert_conformance(
+                    &OwnedRadixSpline::new(
+                        data.clone(),
+                        RadixBits::new(18),
+                        MaxError::new(max_error),
+                    ),
+                    data,
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn extreme_probes() {
+        let mut datasets: Vec<Vec<u64>> =
+            Adversarial::ALL.iter().map(|&k| adversarial(k)).collect();
+        datasets.extend([
+            vec![0],
+            vec![u64::MAX],
+            vec![1, 1],
+            vec![0, u64::MAX],
+            vec![0, 0, u64::MAX, u64::MAX],
+            vec![u64::MAX - 2, u64::MAX - 1, u64::MAX, u64::MAX],
+        ]);
+
+        for data in &datasets {
+            let mut probes = vec![0, 1, u64::MAX - 1, u64::MAX];
+            for &key in data.iter().step_by(7) {
+                probes.extend([key.wrapping_sub(1), key, key.wrapping_add(1)]);
+            }
+            for (num_radix_bits, max_error) in [(0, 0), (1, 1), (18, 32), (18, MaxError::MAX)] {
+                let spline = GreedySplineCorridor::new(data, MaxError::new(max_error));
+                let radix_spline = RadixSpline::new(
+                    data,
+                    RadixBits::new(num_radix_bits),
+                    MaxError::new(max_error),
+                );
+                for &key in &probes {
+                    let first = data.partition_point(|&x| x < key);
+                    let expected = (data.get(first) == Some(&key)).then_some(first);
+                    assert_eq!(spline.search(key), expected, "{} {}", key, max_error);
+                    assert_eq!(radix_spline.search(key), expected, "{} {}", key, max_error);
+                    assert_eq!(radix_spline.search_with_window(key, usize::MAX), expected);
+                    assert_eq!(radix_spline.lower_bound_position(key), first);
+                }
+            }
+        }
+
+        // more radix bits than key bits
+        let data = vec![5, 6, 9];
+        let radix_spline = RadixSpline::new(&data, RadixBits::new(32), MaxError::new(0));
+        assert_eq!(radix_spline.search(9), Some(2));
+        assert_eq!(radix_spline.search(u64::MAX), None);
+    }
+
+    /// A `RadixSpline` that answers every key divisible by `every` wrongly.
+    struct Corrupted<'a> {
+        inner: RadixSpline<'a>,
+        every: u64,
+    }
+
+    impl LearnedIndex for Corrupted<'_> {
+        fn len(&self) -> usize {
+            LearnedIndex::len(&self.inner)
+        }
+
+        fn search(&self, key: u64) -> Option<usize> {
+            let found = self.inner.search(key);
+            if !key.is_multiple_of(self.every) {
+                return found;
+            }
+            match found {
+                Some(idx) => Some(idx + 1),
+                None => Some(0),
+            }
+        }
+
+        fn lower_bound(&self, key: u64) -> usize {
+            LearnedIndex::lower_bound(&self.inner, key)
+        }
+
+        fn predict(&self, key: u64) -> Option<usize> {
+            LearnedIndex::predict(&self.inner, key)
+        }
+
+        fn max_error(&self) -> usize {
+            LearnedIndex::max_error(&self.inner)
+        }
+
+        fn equal_range(&self, key: u64) -> Range<usize> {
+            LearnedIndex::equal_range(&self.inner, key)
+        }
+    }
+
+    #[test]
+    fn mismatch_accounting() {
+        let data: Vec<u64> = (0..10000).map(|i| i * 3).collect();
+        // 10000 present keys and 10000 absent ones
+        let keys: Vec<u64> = (0..20000).map(|i| i * 3 / 2 + i % 2).collect();
+        let radix_spline = RadixSpline::new(&data, RadixBits::DEFAULT, MaxError::DEFAULT);
+        assert_eq!(count_mismatches(&radix_spline, &data, &keys), 0);
+        let run = time_lookups(&radix_spline, &data, &keys, true);
+        assert_eq!(run.mismatches, Some(0));
+
+        for every in [1, 7, 1000] {
+            let corrupted = Corrupted {
+                inner: RadixSpline::new(&data, RadixBits::DEFAULT, MaxError::DEFAULT),
+                every,
+            };
+            let expected = keys
+                .iter()
+                .filter(|&&key| key.is_multiple_of(every))
+                .count();
+            assert_eq!(count_mismatches(&corrupted, &data, &keys), expected);
+            let run = time_lookups(&corrupted, &data, &keys, true);
+            assert_eq!(run.mismatches, Some(expected));
+            // skipping the check is reported as such, rather than as no mismatch
+            assert_eq!(
+                time_lookups(&corrupted, &data, &keys, false).mismatches,
+                None
+            );
+        }
+    }
+}
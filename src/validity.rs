@@ -0,0 +1,326 @@
+//! # Deletions through a validity bitmap
+//! A sorted array with tombstones keeps its positions stable: a deleted key stays in place, and a bitmap marks
+//! the positions that are still valid. `ValidityView` answers the queries of the index over the valid positions,
+//! while the bitmap belongs to the caller, who may delete and restore positions between queries.
+//!
+//! Bit `i % 64` of word `i / 64` is set if position `i` is valid. The words are atomics, so the caller keeps them
+//! mutable while a view reads them. The reads are relaxed and a query reads every word when it gets to it,
+//! so a change made during a query may or may not be visible to it, and a query does not see the bitmap at a single
+//! point in time. Changes that complete before a query starts are visible to it.
+//!
+//! `count_range` skips whole blocks of `BLOCK_BITS` positions through a count of their valid positions, which
+//! `delete` and `restore` keep up to date. Words written directly need a `recount` before the next count.
+
+use std::ops::RangeBounds;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+
+use crate::common::Key;
+use crate::radix::RadixSpline;
+
+/// The words per block of `count_range`.
+const BLOCK_WORDS: usize = 8;
+const BLOCK_BITS: usize = BLOCK_WORDS * 64;
+
+/// The positions `0..len` as valid, i.e., the bitmap of data without deletions.
+pub fn all_valid_bitmap(len: usize) -> Vec<AtomicU64> {
+    (0..len.div_ceil(64))
+        .map(|word| {
+            let bits = (len - word * 64).min(64);
+            AtomicU64::new(u64::MAX >> (64 - bits))
+        })
+        .collect()
+}
+
+/// The index restricted to the positions whose bit is set in a bitmap of the caller (bit `i % 64` of word `i / 64`).
+/// The bits are read with relaxed atomics and not under a lock: changes completed before a query are visible to it,
+/// while a change made during a query may or may not be. Change bits through `delete` and `restore`, or `recount`
+/// after writing the words directly, so `count_range` stays exact.
+pub struct ValidityView<'v, 'a, K: Key = u64> {
+    index: &'v RadixSpline<'a, K>,
+    words: &'v [AtomicU64],
+    block_counts: Box<[AtomicU32]>,
+}
+
+impl<'a, K: Key> RadixSpline<'a, K> {
+    /// A view that skips the positions whose bit is clear in `words`, which holds a bit per key
+    /// (`data().len().div_ceil(64)` words). The bits past the last key are ignored.
+    pub fn with_validity<'v>(&'v self, words: &'v [AtomicU64]) -> ValidityView<'v, 'a, K> {
+        assert_eq!(
+            words.len(),
+            self.data().len().div_ceil(64),
+            "the validity bitmap must hold a bit per key"
+        );
+        let num_blocks = words.len().div_ceil(BLOCK_WORDS);
+        let view = ValidityView {
+            index: self,
+            words,
+            block_counts: (0..num_blocks).map(|_| AtomicU32::new(0)).collect(),
+        };
+        view.recount();
+        view
+    }
+}
+
+impl<'v, 'a, K: Key> ValidityView<'v, 'a, K> {
+    pub fn index(&self) -> &'v RadixSpline<'a, K> {
+        self.index
+    }
+
+    pub fn words(&self) -> &'v [AtomicU64] {
+        self.words
+    }
+
+    /// The bits of word `word` that belong to keys.
+    fn load(&self, word: usize) -> u64 {
+        let bits = self.words[word].load(Ordering::Relaxed);
+        let len = self.index.data().len();
+        if (word + 1) * 64 > len {
+            bits & (u64::MAX >> ((word + 1) * 64 - len))
+        } else {
+            bits
+        }
+    }
+
+    /// Recomputes the block counts of `count_range` after the words were written directly.
+    pub fn recount(&self) {
+        for (block, count) in self.block_counts.iter().enumerate() {
+            let end = ((block + 1) * BLOCK_WORDS).min(self.words.len());
+            let valid: u32 = (block * BLOCK_WORDS..end)
+                .map(|word| self.load(word).count_ones())
+                .sum();
+            count.store(valid, Ordering::Relaxed);
+        }
+    }
+
+    pub fn is_valid(&self, position: usize) -> bool {
+        assert!(position < self.index.data().len(), "position out of bounds");
+        self.load(position / 64) >> (position % 64) & 1 == 1
+    }
+
+    /// Marks `position` deleted, and returns whether it was valid.
+    pub fn delete(&self, position: usize) -> bool {
+        assert!(position < self.index.data().len(), "position out of bounds");
+        let mask = 1 << (position % 64);
+        let old = self.words[position / 64].fetch_and(!mask, Ordering::Relaxed);
+        let was_valid = old & mask != 0;
+        if was_valid {
+            self.block_counts[position / BLOCK_BITS].fetch_sub(1, Ordering::Relaxed);
+        }
+        was_valid
+    }
+
+    /// Marks `position` valid again, and returns whether it was deleted.
+    pub fn restore(&self, position: usize) -> bool {
+        assert!(position < self.index.data().len(), "position out of bounds");
+        let mask = 1 << (position % 64);
+        let old = self.words[position / 64].fetch_or(mask, Ordering::Relaxed);
+        let was_deleted = old & mask == 0;
+        if was_deleted {
+            self.block_counts[position / BLOCK_BITS].fetch_add(1, Ordering::Relaxed);
+        }
+        was_deleted
+    }
+
+    /// The valid positions in `start..end`, in order.
+    fn valid_positions(&self, start: usize, end: usize) -> impl Iterator<Item = usize> + '_ {
+        let mut next = start;
+        std::iter::from_fn(move || {
+            while next < end {
+                let bits = self.load(next / 64) >> (next % 64);
+                if bits == 0 {
+                    next = (next / 64 + 1) * 64;
+                    continue;
+                }
+                let position = next + bits.trailing_zeros() as usize;
+                if position >= end {
+                    break;
+                }
+                next = position + 1;
+                return Some(position);
+            }
+            next = end;
+            None
+        })
+    }
+
+    /// The number of valid positions in `start..end`.
+    fn count(&self, start: usize, end: usize) -> usize {
+        let mut count = 0;
+        let mut position = start;
+        while position < end {
+            if position.is_multiple_of(BLOCK_BITS) && position + BLOCK_BITS <= end {
+                count += self.block_counts[position / BLOCK_BITS].load(Ordering::Relaxed) as usize;
+                position += BLOCK_BITS;
+                continue;
+            }
+            let offset = position % 64;
+            let take = (64 - offset).min(end - position);
+            let bits = self.load(position / 64) >> offset;
+            count += (bits & (u64::MAX >> (64 - take))).count_ones() as usize;
+            position += take;
+        }
+        count
+    }
+
+    /// The first valid position of `key`, so a deleted occurrence in a run falls through to the next valid one.
+    pub fn search(&self, key: K) -> Option<usize> {
+        let run = self.index.equal_range(key);
+        self.valid_positions(run.start, run.end).next()
+    }
+
+    /// The valid positions of the keys in `range` and their keys, in order.
+    pub fn iter_range<R: RangeBounds<K>>(&self, range: R) -> impl Iterator<Item = (usize, K)> + '_ {
+        let positions = self.index.range(range);
+        let data = self.index.data();
+        self.valid_positions(positions.start, positions.end)
+            .map(move |position| (position, data[position]))
+    }
+
+    /// The number of valid positions of the keys in `range`.
+    pub fn count_range<R: RangeBounds<K>>(&self, range: R) -> usize {
+        let positions = self.index.range(range);
+        self.count(positions.start, positions.end)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{MaxError, RadixBits};
+    use rand::Rng;
+
+    fn brute_force_count(data: &[u64], valid: &[bool], lo: u64, hi: u64) -> usize {
+        (0..data.len())
+            .filter(|&i| valid[i] && (lo..=hi).contains(&data[i]))
+            .count()
+    }
+
+    #[test]
+    fn all_valid() {
+        for len in [1, 63, 64, 65, 1000] {
+            let words = all_valid_bitmap(len);
+            assert_eq!(words.len(), len.div_ceil(64));
+            let ones: u32 = words
+                .iter()
+                .map(|w| w.load(Ordering::Relaxed).count_ones())
+                .sum();
+            assert_eq!(ones as usize, len);
+        }
+    }
+
+    #[test]
+    fn duplicate_run() {
+        let data = vec![1, 3, 3, 3, 3, 7, 9];
+        let index = RadixSpline::new(&data, RadixBits::new(4), MaxError::new(1));
+        let words = all_valid_bitmap(data.len());
+        let view = index.with_validity(&words);
+        assert_eq!(view.search(3), Some(1));
+        assert!(view.delete(1));
+        assert!(!view.delete(1));
+        assert_eq!(view.search(3), Some(2));
+        assert!(view.delete(3));
+        assert_eq!(view.search(3), Some(2));
+        assert_eq!(view.count_range(3..=3), 2);
+        let run: Vec<(usize, u64)> = view.iter_range(3..=3).collect();
+        assert_eq!(run, vec![(2, 3), (4, 3)]);
+        view.delete(2);
+        view.delete(4);
+        assert_eq!(view.search(3), None);
+        assert_eq!(view.count_range(3..=3), 0);
+        assert_eq!(view.search(1), Some(0));
+        assert_eq!(view.search(7), Some(5));
+        assert!(view.restore(4));
+        assert!(!view.restore(4));
+        assert_eq!(view.search(3), Some(4));
+        assert_eq!(view.count_range(..), 4);
+    }
+
+    #[test]
+    fn deleted_ranges() {
+        let data: Vec<u64> = (0..5000).map(|i| i / 3 * 10).collect();
+        let index = RadixSpline::new(&data, RadixBits::new(8), MaxError::new(16));
+        let words = all_valid_bitmap(data.len());
+        let view = index.with_validity(&words);
+        for position in index.range(1000..=9000) {
+            view.delete(position);
+        }
+        assert_eq!(view.count_range(1000..=9000), 0);
+        assert_eq!(view.iter_range(1000..=9000).next(), None);
+        assert_eq!(view.search(1000), None);
+        assert_eq!(view.search(9000), None);
+        assert_eq!(view.search(990), Some(297));
+        assert_eq!(view.search(9010), Some(2703));
+        let around: Vec<u64> = view.iter_range(980..9030).map(|(_, key)| key).collect();
+        assert_eq!(
+            around,
+            vec![980, 980, 980, 990, 990, 990, 9010, 9010, 9010, 9020, 9020, 9020]
+        );
+        assert_eq!(
+            view.count_range(..),
+            data.len() - index.range(1000..=9000).len()
+        );
+        // the whole data
+        for position in 0..data.len() {
+            view.delete(position);
+        }
+        assert_eq!(view.count_range(..), 0);
+        assert_eq!(view.iter_range(..).count(), 0);
+        assert_eq!(view.search(0), None);
+    }
+
+    #[test]
+    fn partially_deleted_blocks() {
+        let mut rng = rand::thread_rng();
+        let mut data: Vec<u64> = (0..20_000).map(|_| rng.gen_range(0..50_000)).collect();
+        data.sort_unstable();
+        let index = RadixSpline::new(&data, RadixBits::new(10), MaxError::new(32));
+        let words = all_valid_bitmap(data.len());
+        let view = index.with_validity(&words);
+        let mut valid = vec![true; data.len()];
+        // a sparse half, a dense block, and a whole block
+        for position in (0..data.len()).filter(|_| rng.gen_bool(0.3)) {
+            view.delete(position);
+            valid[position] = false;
+        }
+        for position in
+            (3 * BLOCK_BITS + 5..4 * BLOCK_BITS - 7).chain(6 * BLOCK_BITS..7 * BLOCK_BITS)
+        {
+            view.delete(position);
+            valid[position] = false;
+        }
+        let mut check = |view: &ValidityView, valid: &[bool]| {
+            for _ in 0..500 {
+                let (a, b) = (rng.gen_range(0..51_000), rng.gen_range(0..51_000));
+                let (lo, hi) = (a.min(b), a.max(b));
+                let count = brute_force_count(&data, valid, lo, hi);
+                assert_eq!(view.count_range(lo..=hi), count, "{}..={}", lo, hi);
+                assert_eq!(view.iter_range(lo..=hi).count(), count);
+                let first = (0..data.len()).find(|&i| valid[i] && data[i] == lo);
+                assert_eq!(view.search(lo), first);
+            }
+            assert_eq!(view.count_range(..), valid.iter().filter(|&&v| v).count());
+        };
+        check(&view, &valid);
+        // words written directly need a recount
+        for word in words.iter().step_by(3) {
+            word.store(u64::MAX, Ordering::Relaxed);
+        }
+        for (position, v) in valid.iter_mut().enumerate() {
+            *v |= (position / 64) % 3 == 0;
+        }
+        view.recount();
+        check(&view, &valid);
+        for (position, &v) in valid.iter().enumerate() {
+            assert_eq!(view.is_valid(position), v);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "the validity bitmap must hold a bit per key")]
+    fn short_bitmap() {
+        let data: Vec<u64> = (0..100).collect();
+        let index = RadixSpline::new(&data, RadixBits::new(4), MaxError::new(4));
+        let words = all_valid_bitmap(64);
+        index.with_validity(&words);
+    }
+}
@@ -0,0 +1,487 @@
+//! # Verification
+//! Check that every key is predicted within the error bound that `search` relies on, e.g., in a nightly job over a large dataset.
+
+use std::cmp::Reverse;
+use std::ops::Range;
+
+use crate::common::Point;
+use crate::radix::RadixSpline;
+use crate::sketch::QuantileSketch;
+
+/// The result of `verify`. It does not depend on how the work is split, so sequential and parallel runs give the same report.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct VerifyReport {
+    /// the number of checked distinct keys
+    pub checked: usize,
+    /// the number of keys predicted beyond the error bound of their segment
+    pub violations: usize,
+    /// the worst observed distance from the prediction to the nearest occurrence over the checked keys
+    pub worst_error: usize,
+    /// the key with `worst_error` (the smallest one on ties)
+    pub worst_key: Option<u64>,
+    /// the first spline point whose key is not greater than its predecessor's, or that is not at the first occurrence of its key
+    pub bad_point: Option<usize>,
+}
+
+impl VerifyReport {
+    fn merge(self, other: VerifyReport) -> VerifyReport {
+        // the larger error wins, then the smaller key
+        let worst = match (self.worst_key, other.worst_key) {
+            (_, None) => self,
+            (None, _) => other,
+            (Some(key), Some(other_key)) => {
+                if (other.worst_error, Reverse(other_key)) > (self.worst_error, Reverse(key)) {
+                    other
+                } else {
+                    self
+                }
+            }
+        };
+        VerifyReport {
+            checked: self.checked + other.checked,
+            violations: self.violations + other.violations,
+            worst_error: worst.worst_error,
+            worst_key: worst.worst_key,
+            bad_point: self.bad_point.into_iter().chain(other.bad_point).min(),
+        }
+    }
+
+    /// whether every checked key is within its error bound, and the spline points are valid
+    pub fn is_ok(&self) -> bool {
+        self.violations == 0 && self.bad_point.is_none()
+    }
+}
+
+/// The result of `error_report`: the `verify` report, and the distribution of the errors over the distinct keys.
+/// The percentiles come from a fixed-memory sketch, so a reported percentile `r` of an exact one `q`
+/// satisfies `r <= q < r * (1 + 1 / 64)`, and is exact below 64. The mean and the maximum are exact.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ErrorReport {
+    pub verify: VerifyReport,
+    errors: QuantileSketch,
+}
+
+impl ErrorReport {
+    /// The nearest-rank `p`-percentile of the errors, for `p` in `[0, 100]`, e.g., 99 for the p99.
+    pub fn percentile(&self, p: f64) -> usize {
+        assert!((0.0..=100.0).contains(&p), "p must be in [0, 100]");
+        self.errors.quantile(p / 100.0)
+    }
+
+    pub fn median(&self) -> usize {
+        self.percentile(50.0)
+    }
+
+    pub fn mean(&self) -> f64 {
+        self.errors.mean()
+    }
+
+    /// The worst error, the same as `verify.worst_error`.
+    pub fn max(&self) -> usize {
+        self.errors.max()
+    }
+}
+
+/// The state of `verify_streaming` between runs of equal keys.
+struct Stream {
+    point_location: usize, // the segment of the last run
+    last_key: Option<u64>,
+    report: VerifyReport,
+    errors: Option<QuantileSketch>, // for `error_report` only
+}
+
+/// Every `step`-th position is sampled, and `verify_sampled` rounds `1 / rate` to it.
+fn sample_step(rate: f64) -> usize {
+    assert!(rate > 0.0 && rate <= 1.0, "rate must be in (0, 1]");
+    ((1.0 / rate).round() as usize).max(1)
+}
+
+impl<'a> RadixSpline<'a> {
+    /// Predict every distinct key, and compare it with the nearest occurrence of the key.
+    /// Also check that the spline point keys are strictly increasing, each at the first occurrence of its key.
+    pub fn verify(&self) -> VerifyReport {
+        self.check_points()
+            .merge(self.verify_positions(0..self.data().len(), 1))
+    }
+
+    /// Like `verify`, but only check the distinct keys first occurring at every `1 / rate`-th position, for a cheap spot check.
+    /// The spline points are all checked.
+    pub fn verify_sampled(&self, rate: f64) -> VerifyReport {
+        self.check_points()
+            .merge(self.verify_positions(0..self.data().len(), sample_step(rate)))
+    }
+
+    /// The parallel `verify`. The data is split at spline points, so each worker verifies whole segments.
+    #[cfg(feature = "parallel")]
+    pub fn par_verify(&self) -> VerifyReport {
+        self.check_points().merge(self.par_verify_positions(1))
+    }
+
+    /// The parallel `verify_sampled`, which checks the same keys.
+    #[cfg(feature = "parallel")]
+    pub fn par_verify_sampled(&self, rate: f64) -> VerifyReport {
+        self.check_points()
+            .merge(self.par_verify_positions(sample_step(rate)))
+    }
+
+    /// Like `verify`, in one strictly sequential pass over the keys, for data much larger than memory (e.g., memory-mapped).
+    /// The spline segments are walked along with the keys, so no key is read twice or out of order,
+    /// and the extra memory is O(1). `progress` is called with the number of positions done after each segment.
+    pub fn verify_streaming(&self, progress: impl FnMut(usize)) -> VerifyReport {
+        let data = self.data();
+        self.verify_stream(|position| data[position], progress, None).report
+    }
+
+    /// `verify_streaming`, which also sketches the distribution of the errors, in O(1) memory as well.
+    /// The errors are weighted by distinct key, as `VerifyReport::checked` counts them.
+    pub fn error_report(&self, progress: impl FnMut(usize)) -> ErrorReport {
+        let data = self.data();
+        let errors = Some(QuantileSketch::default());
+        let stream = self.verify_stream(|position| data[position], progress, errors);
+        ErrorReport {
+            verify: stream.report,
+            errors: stream.errors.unwrap_or_default(),
+        }
+    }
+
+    /// `verify_streaming` reading the key at each position through `key_at`, which sees increasing positions only.
+    fn verify_stream(
+        &self,
+        mut key_at: impl FnMut(usize) -> u64,
+        mut progress: impl FnMut(usize),
+        errors: Option<QuantileSketch>,
+    ) -> Stream {
+        let len = self.data().len();
+        let mut stream = Stream {
+            point_location: 0,
+            last_key: None,
+            report: VerifyReport {
+                bad_point: self.validate_points().err(),
+                ..VerifyReport::default()
+            },
+            errors,
+        };
+        // the run of equal keys starting at `start`
+        let (mut key, mut start) = (key_at(0), 0);
+        for position in 1..len {
+            let next = key_at(position);
+            if next != key {
+                self.verify_run(&mut stream, key, start..position, &mut progress);
+                (key, start) = (next, position);
+            }
+        }
+        self.verify_run(&mut stream, key, start..len, &mut progress);
+        progress(len);
+        stream
+    }
+
+    /// verify the run of `key` at `run`, moving `stream` to its segment.
+    fn verify_run(
+        &self,
+        stream: &mut Stream,
+        key: u64,
+        run: Range<usize>,
+        progress: &mut impl FnMut(usize),
+    ) {
+        let points = self.points();
+        while stream.point_location + 1 < points.len()
+            && points[stream.point_location].key() < key
+        {
+            // a passed point not at the last run has a key absent from the data
+            if Some(points[stream.point_location].key()) != stream.last_key {
+                stream.report.bad_point.get_or_insert(stream.point_location);
+            }
+            stream.point_location += 1;
+            progress(run.start);
+        }
+        let point = points[stream.point_location];
+        if point.key() == key && point.position() != run.start {
+            stream.report.bad_point.get_or_insert(stream.point_location);
+        }
+        stream.last_key = Some(key);
+
+        let predicted = self.predict_in_segment(key, stream.point_location);
+        let error = if predicted < run.start {
+            run.start - predicted
+        } else {
+            predicted.saturating_sub(run.end - 1)
+        };
+        let bound = self.segment_error(stream.point_location);
+        if let Some(errors) = &mut stream.errors {
+            errors.insert(error);
+        }
+        stream.report = stream.report.merge(VerifyReport {
+            checked: 1,
+            violations: usize::from(error > bound),
+            worst_error: error,
+            worst_key: Some(key),
+            bad_point: None,
+        });
+    }
+
+    fn check_points(&self) -> VerifyReport {
+        let data = self.data();
+        let not_first = |&(_, point): &(usize, &Point)| {
+            data[point.position()] != point.key()
+                || (point.position() > 0 && data[point.position() - 1] == point.key())
+        };
+        let bad_point = match self.validate_points() {
+            Err(i) => Some(i),
+            Ok(()) => self.points().iter().enumerate().find(not_first).map(|(i, _)| i),
+        };
+        VerifyReport {
+            bad_point,
+            ..VerifyReport::default()
+        }
+    }
+
+    #[cfg(feature = "parallel")]
+    fn par_verify_positions(&self, step: usize) -> VerifyReport {
+        use rayon::prelude::*;
+
+        // about 4 tasks per thread, each starting at a spline point
+        let tasks = rayon::current_num_threads() * 4;
+        let segments = self.points().len().div_ceil(tasks);
+        let mut bounds: Vec<usize> = self
+            .points()
+            .iter()
+            .step_by(segments)
+            .map(|point| point.position())
+            .collect();
+        bounds.push(self.data().len());
+
+        bounds
+            .par_windows(2)
+            .map(|w| self.verify_positions(w[0]..w[1], step))
+            .reduce(VerifyReport::default, VerifyReport::merge)
+    }
+
+    /// verify the distinct keys first occurring at the multiples of `step` within `positions`.
+    fn verify_positions(&self, positions: Range<usize>, step: usize) -> VerifyReport {
+        let data = self.data();
+        let mut report = VerifyReport::default();
+        for position in (positions.start.next_multiple_of(step)..positions.end).step_by(step) {
+            let key = data[position];
+            if position > 0 && data[position - 1] == key {
+                continue;
+            }
+            // the search window must reach some occurrence of `key`, and the run is followed leftwards from there
+            let end = position + data[position..].partition_point(|&x| x == key);
+            let predicted = self.predict(key);
+            let error = if predicted < position {
+                position - predicted
+            } else {
+                predicted.saturating_sub(end - 1)
+            };
+            let bound = self.segment_error(self.get_spline_segment(key));
+            report = report.merge(VerifyReport {
+                checked: 1,
+                violations: usize::from(error > bound),
+                worst_error: error,
+                worst_key: Some(key),
+                bad_point: None,
+            });
+        }
+        report
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{MaxError, RadixBits};
+    use rand::{distributions::Uniform, Rng};
+
+    fn random_data() -> Vec<u64> {
+        let mut data: Vec<u64> = rand::thread_rng()
+            .sample_iter(&Uniform::from(0..1000000))
+            .take(200000)
+            .collect();
+        data.sort_unstable();
+        data
+    }
+
+    #[test]
+    fn verify() {
+        let data = random_data();
+        let radix_spline = RadixSpline::new(&data, RadixBits::new(18), MaxError::new(16));
+        let report = radix_spline.verify();
+
+        let mut distinct = data.clone();
+        distinct.dedup();
+        assert_eq!(report.checked, distinct.len());
+        assert!(report.is_ok());
+        assert!(report.worst_error <= 16);
+        let worst_key = report.worst_key.unwrap();
+        let nearest = data
+            .iter()
+            .enumerate()
+            .filter(|&(_, &x)| x == worst_key)
+            .map(|(i, _)| radix_spline.predict(worst_key).abs_diff(i))
+            .min();
+        assert_eq!(nearest, Some(report.worst_error));
+    }
+
+    #[test]
+    fn bad_point() {
+        use std::borrow::Cow;
+
+        use crate::common::{Points, Table};
+        use crate::table::RadixTable;
+
+        let data: Vec<u64> = (0..1000u64).flat_map(|key| [key * key; 2]).collect();
+        let radix_spline = RadixSpline::new(&data, RadixBits::new(8), MaxError::new(4));
+        assert_eq!(radix_spline.verify().bad_point, None);
+
+        let rebuild = |points: Vec<Point>| {
+            let table = Table::from(&radix_spline.table()[..]);
+            RadixSpline::assemble(
+                Cow::Borrowed(&data[..]),
+                radix_spline.shift_radix_bits(),
+                radix_spline.max_error(),
+                points.into_iter().collect::<Points>(),
+                RadixTable::auto(table, data.len()),
+            )
+        };
+        // a point at the second occurrence of its key
+        let mut points = radix_spline.points().to_vec();
+        points[1] = Point::new(points[1].key(), points[1].position() + 1);
+        let report = rebuild(points).verify();
+        assert_eq!(report.bad_point, Some(1));
+        assert!(!report.is_ok());
+        // a repeated point key
+        let mut points = radix_spline.points().to_vec();
+        points[2] = points[1];
+        assert_eq!(rebuild(points).verify_sampled(0.5).bad_point, Some(2));
+    }
+
+    #[test]
+    fn verify_sampled() {
+        let data = random_data();
+        let radix_spline = RadixSpline::new(&data, RadixBits::new(18), MaxError::new(16));
+        let full = radix_spline.verify();
+
+        assert_eq!(radix_spline.verify_sampled(1.0), full);
+        let sampled = radix_spline.verify_sampled(0.01);
+        assert!(sampled.checked > 0 && sampled.checked <= data.len() / 100);
+        assert!(sampled.is_ok() && sampled.worst_error <= full.worst_error);
+    }
+
+    /// The keys are read once each, in order, and the report is the same as `verify`.
+    #[test]
+    fn verify_streaming() {
+        let repeated = (0..1000u64).flat_map(|key| [key * key; 3]).collect();
+        let mut datasets = vec![random_data(), repeated];
+        datasets.extend(crate::testing::Adversarial::ALL.map(crate::testing::adversarial));
+        for data in &datasets {
+            let radix_spline = RadixSpline::new(data, RadixBits::new(12), MaxError::new(8));
+            let mut accesses = Vec::new();
+            let mut done = vec![0];
+            let report = radix_spline.verify_stream(
+                |position| {
+                    accesses.push(position);
+                    data[position]
+                },
+                |position| done.push(position),
+                None,
+            );
+            assert_eq!(accesses, (0..data.len()).collect::<Vec<_>>());
+            assert!(done.windows(2).all(|w| w[0] <= w[1]));
+            assert_eq!(done.last(), Some(&data.len()));
+            let report = report.report;
+            assert_eq!(report, radix_spline.verify());
+            assert_eq!(radix_spline.verify_streaming(|_| ()), report);
+        }
+    }
+
+    /// The sketched percentiles are within the sketch accuracy of the exact ones, on skewed data and wide windows.
+    #[test]
+    fn error_report() {
+        let mut datasets = vec![random_data()];
+        datasets.extend(crate::testing::Adversarial::ALL.map(crate::testing::adversarial));
+        for data in &datasets {
+            for max_error in [8, 1000] {
+                let radix_spline =
+                    RadixSpline::new(data, RadixBits::new(12), MaxError::new(max_error));
+                let report = radix_spline.error_report(|_| ());
+                assert_eq!(report.verify, radix_spline.verify());
+
+                // the exact errors, as `verify_positions` computes them
+                let mut errors = Vec::new();
+                for (start, &key) in data.iter().enumerate() {
+                    if start > 0 && data[start - 1] == key {
+                        continue;
+                    }
+                    let end = start + data[start..].partition_point(|&x| x == key);
+                    let predicted = radix_spline.predict(key);
+                    errors.push(if predicted < start {
+                        start - predicted
+                    } else {
+                        predicted.saturating_sub(end - 1)
+                    });
+                }
+                errors.sort_unstable();
+                for p in [0.0, 50.0, 90.0, 99.0, 99.9, 100.0] {
+                    let rank = ((p / 100.0 * errors.len() as f64).ceil() as usize).max(1);
+                    let exact = errors[rank - 1];
+                    let reported = report.percentile(p);
+                    assert!(reported <= exact && exact - reported <= reported / 64);
+                }
+                assert_eq!(report.max(), report.verify.worst_error);
+                let mean = errors.iter().sum::<usize>() as f64 / errors.len() as f64;
+                assert!((report.mean() - mean).abs() < 1e-6);
+            }
+        }
+    }
+
+    #[test]
+    fn verify_streaming_bad_point() {
+        use std::borrow::Cow;
+
+        use crate::common::{Points, Table};
+        use crate::table::RadixTable;
+
+        let data: Vec<u64> = (0..1000u64).flat_map(|key| [key * key; 2]).collect();
+        let radix_spline = RadixSpline::new(&data, RadixBits::new(8), MaxError::new(4));
+        let rebuild = |points: Vec<Point>| {
+            let table = Table::from(&radix_spline.table()[..]);
+            RadixSpline::assemble(
+                Cow::Borrowed(&data[..]),
+                radix_spline.shift_radix_bits(),
+                radix_spline.max_error(),
+                points.into_iter().collect::<Points>(),
+                RadixTable::auto(table, data.len()),
+            )
+        };
+        // a point at the second occurrence of its key, and a point whose key is absent
+        let mut points = radix_spline.points().to_vec();
+        points[1] = Point::new(points[1].key(), points[1].position() + 1);
+        assert_eq!(rebuild(points).verify_streaming(|_| ()).bad_point, Some(1));
+        let mut points = radix_spline.points().to_vec();
+        points[3] = Point::new(points[3].key() - 1, points[3].position());
+        assert_eq!(rebuild(points).verify_streaming(|_| ()).bad_point, Some(3));
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn par_verify() {
+        let data = random_data();
+        let radix_spline = RadixSpline::new(&data, RadixBits::new(18), MaxError::new(16));
+        let expected = radix_spline.verify();
+        let expected_sampled = radix_spline.verify_sampled(0.1);
+
+        for threads in [1, 2, 3, 8] {
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .unwrap();
+            for _ in 0..3 {
+                assert_eq!(pool.install(|| radix_spline.par_verify()), expected);
+                assert_eq!(
+                    pool.install(|| radix_spline.par_verify_sampled(0.1)),
+                    expected_sampled
+                );
+            }
+        }
+    }
+}
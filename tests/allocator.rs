@@ -0,0 +1,236 @@
+//! `RadixSpline::new_in` places the spline points, the radix table and the segment errors in the given allocator:
+//! a counting arena sees all of their memory, and gets it all back when the index is dropped, across rebuilds,
+//! while the global allocator sees no large allocation during the build.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::ptr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use radix_spline::{
+    BuildError, ConfigError, DataError, MaxError, RadixBits, RadixSpline, TableLayout,
+};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+/// Allocations of at least this many bytes are large, e.g., a table or the points, but not a test harness string.
+const LARGE: usize = 1024;
+
+thread_local! {
+    static LARGE_GLOBAL_ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+/// The global allocator, counting the large allocations of the current thread.
+struct CountingGlobal;
+
+unsafe impl GlobalAlloc for CountingGlobal {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        if layout.size() >= LARGE {
+            LARGE_GLOBAL_ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        }
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        if new_size >= LARGE {
+            LARGE_GLOBAL_ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        }
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingGlobal = CountingGlobal;
+
+fn large_global_allocations() -> usize {
+    LARGE_GLOBAL_ALLOCATIONS.with(|count| count.get())
+}
+
+/// An arena over the system allocator, counting its live bytes and allocations, which fails beyond `budget` bytes.
+struct Arena {
+    budget: usize,
+    live: AtomicUsize,
+    allocations: AtomicUsize,
+    deallocations: AtomicUsize,
+}
+
+impl Arena {
+    fn new(budget: usize) -> Arc<Self> {
+        Arc::new(Arena {
+            budget,
+            live: AtomicUsize::new(0),
+            allocations: AtomicUsize::new(0),
+            deallocations: AtomicUsize::new(0),
+        })
+    }
+
+    fn live(&self) -> usize {
+        self.live.load(Ordering::SeqCst)
+    }
+
+    fn reserve(&self, size: usize) -> bool {
+        let live = self.live.fetch_add(size, Ordering::SeqCst);
+        if live + size > self.budget {
+            self.live.fetch_sub(size, Ordering::SeqCst);
+            return false;
+        }
+        true
+    }
+}
+
+unsafe impl GlobalAlloc for Arena {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        if !self.reserve(layout.size()) {
+            return ptr::null_mut();
+        }
+        self.allocations.fetch_add(1, Ordering::SeqCst);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.live.fetch_sub(layout.size(), Ordering::SeqCst);
+        self.deallocations.fetch_add(1, Ordering::SeqCst);
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        if !self.reserve(new_size) {
+            return ptr::null_mut();
+        }
+        self.live.fetch_sub(layout.size(), Ordering::SeqCst);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+fn generate(rng: &mut StdRng, len: usize) -> Vec<u64> {
+    let mut data: Vec<u64> = (0..len).map(|_| rng.gen_range(0..1 << 40)).collect();
+    // a run of duplicates and a dense cluster
+    data.extend(std::iter::repeat_n(1 << 30, 500));
+    data.extend((0..len as u64 / 10).map(|i| (1 << 35) + i));
+    data.sort_unstable();
+    data
+}
+
+fn assert_searches(radix_spline: &RadixSpline, data: &[u64], rng: &mut StdRng) {
+    for _ in 0..10_000 {
+        let key = data[rng.gen_range(0..data.len())];
+        assert_eq!(
+            radix_spline.search(key),
+            Some(data.partition_point(|&x| x < key))
+        );
+        let absent = rng.gen_range(0..1 << 41);
+        assert_eq!(
+            radix_spline.lower_bound(absent),
+            data.partition_point(|&x| x < absent)
+        );
+    }
+}
+
+#[test]
+fn allocations_flow_through_arena() {
+    let mut rng = StdRng::seed_from_u64(7);
+    let data = generate(&mut rng, 200_000);
+    let arena = Arena::new(usize::MAX);
+    for (num_radix_bits, max_error) in [(18, 32), (8, 4), (20, 1)] {
+        let (bits, error) = (RadixBits::new(num_radix_bits), MaxError::new(max_error));
+        let before = large_global_allocations();
+        let radix_spline = RadixSpline::new_in(&data, bits, error, arena.clone());
+        assert_eq!(
+            large_global_allocations(),
+            before,
+            "{} bits",
+            num_radix_bits
+        );
+
+        // the points may hold spare capacity, as a `Vec` does
+        assert!(arena.live() >= radix_spline.size_in_bytes());
+        let built = RadixSpline::new(&data, bits, error).with_table_layout(TableLayout::Flat);
+        assert_eq!(radix_spline.size_in_bytes(), built.size_in_bytes());
+        assert_eq!(radix_spline.num_spline_points(), built.num_spline_points());
+        assert_eq!(radix_spline.table_len(), built.table_len());
+        assert!(radix_spline.verify().is_ok());
+        assert_searches(&radix_spline, &data, &mut rng);
+
+        drop(radix_spline);
+        assert_eq!(arena.live(), 0);
+    }
+}
+
+#[test]
+fn rebuilds_do_not_leak() {
+    let mut rng = StdRng::seed_from_u64(11);
+    let datasets: Vec<Vec<u64>> = (0..20)
+        .map(|round| generate(&mut rng, 1_000 + round * 5_000))
+        .collect();
+    let arena = Arena::new(usize::MAX);
+    // the index of the previous round, outliving the next build, and its memory in the arena
+    let mut previous: Option<(RadixSpline, usize)> = None;
+    for (round, data) in datasets.iter().enumerate() {
+        let before = arena.live();
+        let radix_spline =
+            RadixSpline::new_in(data, RadixBits::new(16), MaxError::new(16), arena.clone());
+        let bytes = arena.live() - before;
+        assert!(bytes >= radix_spline.size_in_bytes());
+
+        // a retuned index copies the points and segment errors in the arena, with its table on the heap
+        let retuned = radix_spline.with_radix_bits(RadixBits::new(10)).unwrap();
+        assert!(arena.live() > before + bytes);
+        assert_searches(&retuned, data, &mut rng);
+        drop(retuned);
+        assert_eq!(arena.live(), before + bytes, "round {}", round);
+
+        if let Some((previous, previous_bytes)) = previous.replace((radix_spline, bytes)) {
+            assert_eq!(before, previous_bytes);
+            drop(previous);
+            assert_eq!(arena.live(), bytes, "round {}", round);
+        }
+    }
+    drop(previous);
+    assert_eq!(arena.live(), 0);
+    assert_eq!(
+        arena.allocations.load(Ordering::SeqCst),
+        arena.deallocations.load(Ordering::SeqCst)
+    );
+}
+
+#[test]
+fn arena_failures_are_errors() {
+    let data: Vec<u64> = (0..10_000).map(|i| i * i).collect();
+    // room for the points, but not for a table of 2^24 entries
+    let arena = Arena::new(1 << 20);
+    let error =
+        RadixSpline::try_new_in(&data, RadixBits::new(24), MaxError::new(32), arena.clone())
+            .err()
+            .unwrap();
+    assert!(matches!(
+        error,
+        BuildError::Config(ConfigError::TableTooLarge { num_radix_bits: 24 })
+    ));
+    assert_eq!(arena.live(), 0);
+
+    let radix_spline =
+        RadixSpline::try_new_in(&data, RadixBits::new(12), MaxError::new(32), arena.clone())
+            .unwrap();
+    assert_eq!(radix_spline.search(81), Some(9));
+    drop(radix_spline);
+
+    let unsorted = [3, 1, 2];
+    let error = RadixSpline::try_new_in(
+        &unsorted,
+        RadixBits::new(4),
+        MaxError::new(1),
+        arena.clone(),
+    )
+    .err()
+    .unwrap();
+    assert!(matches!(
+        error,
+        BuildError::Data(DataError::NotSorted { position: 1 })
+    ));
+    assert_eq!(arena.live(), 0);
+}
@@ -0,0 +1,12 @@
+//! Old call sites of deprecated constructors still compile, with a deprecation warning naming the replacement,
+//! and the migration snippets of the README compile without any warning.
+//! Each case is compiled as a crate of its own, so `TRYBUILD=overwrite cargo test --test compat` updates
+//! the expected warnings after a change of the notes.
+
+#[test]
+fn compat() {
+    let cases = trybuild::TestCases::new();
+    cases.pass("tests/compat/old_defaults.rs");
+    cases.compile_fail("tests/compat/old_defaults_warn.rs");
+    cases.pass("tests/compat/migrated.rs");
+}
@@ -0,0 +1,19 @@
+//! The migration snippets of the README, which build without warnings.
+#![deny(warnings)]
+
+use radix_spline::{GreedySplineCorridor, MaxError, RadixBits, RadixSpline};
+
+fn main() {
+    let data: Vec<u64> = (0..1000).map(|x| x * x).collect();
+    let value = 81;
+
+    let spline = GreedySplineCorridor::new(&data, MaxError::new(32));
+    if let Some(idx) = spline.search(value) {
+        assert_eq!(data[idx], value);
+    }
+
+    let radix_spline = RadixSpline::new(&data, RadixBits::DEFAULT, MaxError::DEFAULT);
+    if let Some(idx) = radix_spline.search(value) {
+        assert_eq!(data[idx], value);
+    }
+}
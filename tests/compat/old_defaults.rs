@@ -0,0 +1,26 @@
+//! Call sites written against the `default` constructors, which still build the same indexes.
+
+use radix_spline::{
+    GreedySplineCorridor, MaxError, OwnedRadixSpline, RadixBits, RadixSpline, RleRadixSpline,
+};
+
+fn main() {
+    let data: Vec<u64> = (0..1000).map(|x| x * x).collect();
+    let radix_spline = RadixSpline::default(&data);
+    let spline = GreedySplineCorridor::default(&data);
+    assert_eq!(radix_spline.search(81), Some(9));
+    assert_eq!(spline.search(81), Some(9));
+
+    let new = RadixSpline::new(&data, RadixBits::DEFAULT, MaxError::DEFAULT);
+    assert_eq!(radix_spline.to_bytes(), new.to_bytes());
+    assert_eq!(
+        spline.num_points(),
+        GreedySplineCorridor::new(&data, MaxError::new(32)).num_points()
+    );
+
+    let owned = OwnedRadixSpline::default(data.clone());
+    assert_eq!(owned.to_bytes(), OwnedRadixSpline::from(data.clone()).to_bytes());
+    let pairs: Vec<(u64, usize)> = data.iter().map(|&key| (key, 2)).collect();
+    let rle = RleRadixSpline::default(&pairs);
+    assert_eq!(rle.search(81), Some(18));
+}
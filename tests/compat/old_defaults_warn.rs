@@ -0,0 +1,14 @@
+//! The same call sites warn, which is an error here, with a note on the replacement.
+#![deny(deprecated)]
+
+use radix_spline::{GreedySplineCorridor, OwnedRadixSpline, RadixSpline, RleRadixSpline};
+
+fn main() {
+    let data: Vec<u64> = (0..1000).map(|x| x * x).collect();
+    let radix_spline = RadixSpline::default(&data);
+    let spline = GreedySplineCorridor::default(&data);
+    assert_eq!(radix_spline.search(81), spline.search(81));
+    let owned = OwnedRadixSpline::default(data.clone());
+    let rle = RleRadixSpline::default(&[(81, 2)]);
+    assert_eq!(owned.search(81), rle.search(81).map(|_| 9));
+}
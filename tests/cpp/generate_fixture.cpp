@@ -0,0 +1,101 @@
+// Writes the fixture of `tests/cpp_fixture.rs` with the reference C++ RadixSpline (learnedsystems/RadixSpline):
+//
+//   git clone https://github.com/learnedsystems/RadixSpline
+//   g++ -std=c++17 -O2 -I RadixSpline tests/cpp/generate_fixture.cpp -o generate_fixture
+//   ./generate_fixture tests/cpp/fixture
+//
+// into the given directory:
+//   keys.sosd                  the keys in the SOSD format: the number of keys, then the keys, as little-endian u64
+//   model_<bits>_<error>.bin   the bytes of `rs::Serializer<uint64_t>::ToBytes` for each configuration
+//   lookups_<bits>_<error>.bin the number of probes, then `(probe, position)` pairs as little-endian u64,
+//                              where `position` is the lower bound of `probe` found with `GetSearchBound`
+//
+// The keys come from splitmix64, so they do not depend on the standard library.
+
+#include <algorithm>
+#include <cstdint>
+#include <fstream>
+#include <iostream>
+#include <string>
+#include <vector>
+
+#include "include/rs/builder.h"
+#include "include/rs/radix_spline.h"
+#include "include/rs/serializer.h"
+
+namespace {
+
+uint64_t SplitMix64(uint64_t* state) {
+  uint64_t z = (*state += 0x9e3779b97f4a7c15ULL);
+  z = (z ^ (z >> 30)) * 0xbf58476d1ce4e5b9ULL;
+  z = (z ^ (z >> 27)) * 0x94d049bb133111ebULL;
+  return z ^ (z >> 31);
+}
+
+// Uniform keys below 2^40, a dense cluster, runs of duplicates, and a distinct largest key.
+std::vector<uint64_t> Keys() {
+  uint64_t state = 42;
+  std::vector<uint64_t> keys;
+  for (int i = 0; i < 50000; ++i) keys.push_back(SplitMix64(&state) >> 24);
+  for (uint64_t i = 0; i < 10000; ++i) keys.push_back((1ULL << 30) + i);
+  for (uint64_t run = 0; run < 100; ++run) {
+    const uint64_t key = SplitMix64(&state) >> 24;
+    for (int i = 0; i < 20; ++i) keys.push_back(key);
+  }
+  std::sort(keys.begin(), keys.end());
+  keys.push_back(keys.back() + 1000);
+  return keys;
+}
+
+void WriteU64(std::ofstream& out, uint64_t value) {
+  unsigned char bytes[8];
+  for (int i = 0; i < 8; ++i) bytes[i] = static_cast<unsigned char>(value >> (8 * i));
+  out.write(reinterpret_cast<const char*>(bytes), 8);
+}
+
+}  // namespace
+
+int main(int argc, char** argv) {
+  if (argc != 2) {
+    std::cerr << "usage: " << argv[0] << " <output directory>" << std::endl;
+    return 1;
+  }
+  const std::string dir = argv[1];
+  const std::vector<uint64_t> keys = Keys();
+
+  std::ofstream keys_out(dir + "/keys.sosd", std::ios::binary);
+  WriteU64(keys_out, keys.size());
+  for (const uint64_t key : keys) WriteU64(keys_out, key);
+
+  // probes: every 7th key, and keys between them, within [min_key, max_key]
+  std::vector<uint64_t> probes;
+  uint64_t state = 7;
+  for (size_t i = 0; i < keys.size(); i += 7) probes.push_back(keys[i]);
+  for (int i = 0; i < 20000; ++i) {
+    probes.push_back(keys.front() + SplitMix64(&state) % (keys.back() - keys.front() + 1));
+  }
+
+  const size_t configs[][2] = {{18, 32}, {8, 4}};
+  for (const auto& config : configs) {
+    rs::Builder<uint64_t> builder(keys.front(), keys.back(), config[0], config[1]);
+    for (const uint64_t key : keys) builder.AddKey(key);
+    const rs::RadixSpline<uint64_t> index = builder.Finalize();
+
+    const std::string suffix = std::to_string(config[0]) + "_" + std::to_string(config[1]) + ".bin";
+    std::string bytes;
+    rs::Serializer<uint64_t>::ToBytes(index, &bytes);
+    std::ofstream model_out(dir + "/model_" + suffix, std::ios::binary);
+    model_out.write(bytes.data(), bytes.size());
+
+    std::ofstream lookups_out(dir + "/lookups_" + suffix, std::ios::binary);
+    WriteU64(lookups_out, probes.size());
+    for (const uint64_t probe : probes) {
+      const rs::SearchBound bound = index.GetSearchBound(probe);
+      const auto position =
+          std::lower_bound(keys.begin() + bound.begin, keys.begin() + bound.end, probe) - keys.begin();
+      WriteU64(lookups_out, probe);
+      WriteU64(lookups_out, position);
+    }
+  }
+  return 0;
+}
@@ -0,0 +1,76 @@
+//! Models written by the reference C++ RadixSpline are read by `RadixSpline::read_from`, and answer the same queries.
+//! The fixture is generated by `tests/cpp/generate_fixture.cpp`, which builds against a checkout of
+//! learnedsystems/RadixSpline, see its header. The `cpp-compat` CI job generates it and runs this test.
+//! Without the fixture, the test fails rather than passing vacuously, so it is ignored by default.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use radix_spline::RadixSpline;
+
+fn fixture_dir() -> PathBuf {
+    match std::env::var_os("RADIX_SPLINE_CPP_FIXTURE") {
+        Some(dir) => PathBuf::from(dir),
+        None => Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/cpp/fixture"),
+    }
+}
+
+/// Little-endian `u64` words: a count of records, then the records of `width` words each.
+fn read_records(path: &Path, width: usize) -> Vec<u64> {
+    let bytes = fs::read(path).unwrap_or_else(|error| {
+        panic!(
+            "{}: {}, generate it with tests/cpp/generate_fixture.cpp",
+            path.display(),
+            error
+        )
+    });
+    let words: Vec<u64> = bytes
+        .chunks_exact(8)
+        .map(|chunk| u64::from_le_bytes(chunk.try_into().unwrap()))
+        .collect();
+    assert_eq!(bytes.len() % 8, 0, "{}", path.display());
+    assert_eq!(
+        words[0] as usize * width,
+        words.len() - 1,
+        "{}",
+        path.display()
+    );
+    words[1..].to_vec()
+}
+
+#[test]
+#[ignore = "needs the fixture of tests/cpp/generate_fixture.cpp, see the cpp-compat CI job"]
+fn cpp_fixture() {
+    let dir = fixture_dir();
+    let keys = read_records(&dir.join("keys.sosd"), 1);
+    for (num_radix_bits, max_error) in [(18, 32), (8, 4)] {
+        let suffix = format!("{}_{}.bin", num_radix_bits, max_error);
+        let model = fs::read(dir.join(format!("model_{}", suffix))).unwrap();
+        let mut reader = &model[..];
+        let radix_spline = RadixSpline::read_from(&mut reader, &keys)
+            .unwrap_or_else(|error| panic!("model_{}: {}", suffix, error));
+        assert!(reader.is_empty(), "model_{} has trailing bytes", suffix);
+        assert!(radix_spline.verify().is_ok());
+
+        let lookups = read_records(&dir.join(format!("lookups_{}", suffix)), 2);
+        assert!(!lookups.is_empty());
+        for pair in lookups.chunks_exact(2) {
+            let (probe, position) = (pair[0], pair[1] as usize);
+            assert_eq!(
+                position,
+                keys.partition_point(|&x| x < probe),
+                "the C++ answer for {}",
+                probe
+            );
+            assert_eq!(radix_spline.lower_bound(probe), position, "{}", probe);
+            let expected = (keys.get(position) == Some(&probe)).then_some(position);
+            assert_eq!(radix_spline.search(probe), expected, "{}", probe);
+        }
+
+        // a model written back is read by the C++ `Serializer` as well: the same fields, with the shortest table
+        let mut written = vec![];
+        radix_spline.write_to(&mut written).unwrap();
+        let reread = RadixSpline::read_from(&written[..], &keys).unwrap();
+        assert_eq!(reread.serialize(), radix_spline.serialize());
+    }
+}
@@ -0,0 +1,160 @@
+//! The whole pipeline in one place: a dataset is written as a SOSD file and loaded back, indexed with
+//! both index types and two configurations, verified, serialized and reopened, probed against a binary
+//! search, scanned by ranges, and finally rebuilt with `OwnedRadixSpline::from_runs` over the keys merged with
+//! an appended tail. There is no `extend` API, so extending an index in place is left until one lands.
+
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+use radix_spline::{
+    GreedySplineCorridor, LearnedIndex, MaxError, OwnedRadixSpline, RadixBits, RadixSpline,
+};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+const CONFIGS: [(u32, usize); 2] = [(18, 32), (8, 4)];
+
+/// Sorted keys with duplicates, a dense cluster and a sparse tail.
+fn generate(rng: &mut StdRng) -> Vec<u64> {
+    let mut data: Vec<u64> = (0..50_000).map(|_| rng.gen_range(0..1 << 40)).collect();
+    data.extend((0..10_000).map(|_| rng.gen_range(1 << 20..(1 << 20) + 5_000)));
+    data.extend((0..100).map(|_| rng.gen_range(1 << 50..u64::MAX / 2)));
+    data.sort_unstable();
+    data
+}
+
+/// The SOSD format: the number of keys, then the keys, as little-endian `u64`.
+fn write_sosd(path: &Path, data: &[u64]) {
+    let mut writer = BufWriter::new(File::create(path).unwrap());
+    writer
+        .write_all(&(data.len() as u64).to_le_bytes())
+        .unwrap();
+    for key in data {
+        writer.write_all(&key.to_le_bytes()).unwrap();
+    }
+    writer.flush().unwrap();
+}
+
+fn load_sosd(path: &Path) -> Vec<u64> {
+    let mut bytes = vec![];
+    BufReader::new(File::open(path).unwrap())
+        .read_to_end(&mut bytes)
+        .unwrap();
+    let mut words = bytes
+        .chunks_exact(8)
+        .map(|chunk| u64::from_le_bytes(chunk.try_into().unwrap()));
+    let len = words.next().unwrap() as usize;
+    let data: Vec<u64> = words.collect();
+    assert_eq!(data.len(), len);
+    data
+}
+
+fn sosd_path() -> PathBuf {
+    std::env::temp_dir().join(format!("radix-spline-pipeline-{}.sosd", std::process::id()))
+}
+
+/// Present and absent keys, including both ends of the key space.
+fn probes(rng: &mut StdRng, data: &[u64]) -> Vec<u64> {
+    let mut probes = vec![0, 1, u64::MAX - 1, u64::MAX];
+    for _ in 0..20_000 {
+        let key = data[rng.gen_range(0..data.len())];
+        probes.extend([key, key.wrapping_add(1), key.wrapping_sub(1)]);
+        probes.push(rng.gen());
+    }
+    probes
+}
+
+fn check_lookups(index: &impl LearnedIndex, data: &[u64], probes: &[u64]) {
+    assert_eq!(index.len(), data.len());
+    for &key in probes {
+        let lower_bound = data.partition_point(|&x| x < key);
+        let expected = (data.get(lower_bound) == Some(&key)).then_some(lower_bound);
+        assert_eq!(index.search(key), expected, "search({})", key);
+        assert_eq!(index.lower_bound(key), lower_bound, "lower_bound({})", key);
+        let upper_bound = data.partition_point(|&x| x <= key);
+        assert_eq!(
+            index.equal_range(key),
+            lower_bound..upper_bound,
+            "equal_range({})",
+            key
+        );
+    }
+}
+
+fn check_ranges(index: &RadixSpline, data: &[u64], rng: &mut StdRng) {
+    for _ in 0..2_000 {
+        let a = data[rng.gen_range(0..data.len())];
+        let b = a.saturating_add(rng.gen_range(0..1 << 30));
+        let (lo, hi) = if rng.gen() { (a, b) } else { (b, a) };
+        let start = data.partition_point(|&x| x < lo);
+        let expected = if lo > hi {
+            start..start
+        } else {
+            start..data.partition_point(|&x| x <= hi)
+        };
+        let positions = index.positions_of((lo, hi));
+        assert_eq!(positions, expected, "positions_of(({}, {}))", lo, hi);
+        assert!(data[positions].iter().all(|key| (lo..=hi).contains(key)));
+    }
+}
+
+#[test]
+fn pipeline() {
+    let mut rng = StdRng::seed_from_u64(0x5EED);
+    let generated = generate(&mut rng);
+    let path = sosd_path();
+    write_sosd(&path, &generated);
+    let data = load_sosd(&path);
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(data, generated);
+    let probes = probes(&mut rng, &data);
+
+    for (num_radix_bits, max_error) in CONFIGS {
+        let radix_spline = RadixSpline::new(
+            &data,
+            RadixBits::new(num_radix_bits),
+            MaxError::new(max_error),
+        );
+        let report = radix_spline.verify();
+        assert!(report.is_ok(), "{:?}", report);
+        assert!(report.worst_error <= max_error);
+        assert_eq!(radix_spline.verify_streaming(|_| ()), report);
+        check_lookups(&radix_spline, &data, &probes);
+
+        let corridor = GreedySplineCorridor::new(&data, MaxError::new(max_error));
+        check_lookups(&corridor, &data, &probes);
+
+        // serialize, and search the keys in place in the reopened blob
+        let bytes = radix_spline.to_bytes();
+        let words: Vec<u64> = bytes
+            .chunks_exact(8)
+            .map(|chunk| u64::from_le_bytes(chunk.try_into().unwrap()))
+            .collect();
+        let reopened = RadixSpline::from_blob(&words).unwrap();
+        assert_eq!(reopened.data(), &data[..]);
+        assert_eq!(reopened.to_bytes(), bytes);
+        assert_eq!(reopened.verify(), report);
+        check_lookups(&reopened, &data, &probes);
+        check_ranges(&reopened, &data, &mut rng);
+
+        // rebuild over the merged runs of the keys and an appended tail of larger keys
+        let max_key = *data.last().unwrap();
+        let mut tail: Vec<u64> = (0..5_000)
+            .map(|_| rng.gen_range(max_key..u64::MAX))
+            .collect();
+        tail.sort_unstable();
+        let rebuilt = OwnedRadixSpline::from_runs(
+            &[&data, &tail],
+            RadixBits::new(num_radix_bits),
+            MaxError::new(max_error),
+        );
+        let all: Vec<u64> = data.iter().chain(&tail).copied().collect();
+        assert_eq!(rebuilt.data(), &all[..]);
+        assert!(rebuilt.verify().is_ok());
+        let mut tail_probes = probes.clone();
+        tail_probes.extend(&tail);
+        check_lookups(&rebuilt, &all, &tail_probes);
+        check_ranges(&rebuilt, &all, &mut rng);
+    }
+}